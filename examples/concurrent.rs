@@ -0,0 +1,85 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use rand::Rng;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use ordbog::{Code, Dict, Mode};
+
+const ROWS: usize = 1_000_000;
+const CHUNK: usize = 4096;
+const THREADS: usize = 8;
+
+fn main() {
+    let mut rng = rand::thread_rng();
+    // Values drift upwards with row number, as in an append-ordered table, so
+    // that range predicates can rule out whole chunks.
+    let column: Vec<u64> = (0..ROWS)
+        .map(|row| (row / 10) as u64 + rng.gen_range(0..1000))
+        .collect();
+    let sample: Vec<u64> = (0..10_000)
+        .map(|_| column[rng.gen_range(0..ROWS)])
+        .collect();
+
+    // One dictionary, built once and then only ever read.
+    let dict = Dict::new(Mode::Word, sample);
+    println!("built dict with {} exact codes", dict.codes.len());
+
+    // Encode the column in parallel: a fixed pool of workers each takes every
+    // THREADS'th chunk, writing into disjoint output slices.
+    let mut codes = vec![Code(0); ROWS];
+    let mut work: Vec<Vec<(usize, &mut [Code])>> = (0..THREADS).map(|_| Vec::new()).collect();
+    for (i, chunk) in codes.chunks_mut(CHUNK).enumerate() {
+        work[i % THREADS].push((i, chunk));
+    }
+    thread::scope(|s| {
+        for chunks in work {
+            let (dict, column) = (&dict, &column);
+            s.spawn(move || {
+                for (i, out) in chunks {
+                    let base = i * CHUNK;
+                    for (j, c) in out.iter_mut().enumerate() {
+                        *c = dict.encode(&column[base + j]);
+                    }
+                }
+            });
+        }
+    });
+
+    // Scan the codes in parallel for the range predicate `lo <= x <= hi`,
+    // counting chunks that can be skipped entirely because no code in them
+    // could match.
+    let (lo, hi) = (20_000u64, 20_500u64);
+    let (clo, chi) = (dict.encode(&lo), dict.encode(&hi));
+    let skipped = AtomicUsize::new(0);
+    let candidates = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for worker in 0..THREADS {
+            let (codes, skipped, candidates) = (&codes, &skipped, &candidates);
+            s.spawn(move || {
+                for chunk in codes.chunks(CHUNK).skip(worker).step_by(THREADS) {
+                    let n = chunk.iter().filter(|c| clo <= **c && **c <= chi).count();
+                    if n == 0 {
+                        skipped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    candidates.fetch_add(n, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    let actual = column.iter().filter(|x| lo <= **x && **x <= hi).count();
+    let nchunks = ROWS.div_ceil(CHUNK);
+    println!(
+        "predicate {} <= x <= {}: codes [{}, {}], {} candidate rows ({} actual), {}/{} chunks skipped",
+        lo,
+        hi,
+        clo.0,
+        chi.0,
+        candidates.load(Ordering::Relaxed),
+        actual,
+        skipped.load(Ordering::Relaxed),
+        nchunks
+    );
+}
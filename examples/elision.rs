@@ -0,0 +1,86 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal};
+use std::ops::Bound::{Included, Unbounded};
+use std::time::Instant;
+
+use ordbog::workload::{RangePredicate, Replay};
+use ordbog::{Code, Dict, Mode};
+
+const VALUE_BYTES: usize = 8;
+const BLOCKS: [(&str, usize); 2] = [("cache line", 64), ("page", 4096)];
+
+/// Read one integer per line from the file at `path`, or make up a column of
+/// lognormally distributed values if no path is given.
+fn column() -> Vec<u64> {
+    match std::env::args_os().nth(1) {
+        Some(path) => std::fs::read_to_string(path)
+            .expect("reading column")
+            .lines()
+            .filter_map(|l| l.trim().parse().ok())
+            .collect(),
+        None => {
+            let dist = LogNormal::new(10.0, 2.0).unwrap();
+            let mut rng = rand::thread_rng();
+            (0..1_000_000)
+                .map(|_| dist.sample(&mut rng) as u64)
+                .collect()
+        }
+    }
+}
+
+/// Usage: `elision [PATH]`. Encodes a column of integers (one per line in the
+/// file at PATH, or synthetic if none is given) in both modes and reports how
+/// many cache-line and page sized blocks of the underlying values the same
+/// predicates let a scan skip.
+fn main() {
+    let column = column();
+    assert!(!column.is_empty());
+    let mut rng = rand::thread_rng();
+    let sample: Vec<u64> = (0..column.len().min(100_000))
+        .map(|_| column[rng.gen_range(0..column.len())])
+        .collect();
+
+    // A mix of point lookups and narrow and wide ranges, with constants drawn
+    // from the data.
+    let mut predicates: Vec<RangePredicate<u64>> = Vec::new();
+    for _ in 0..100 {
+        let a = column[rng.gen_range(0..column.len())];
+        let b = column[rng.gen_range(0..column.len())];
+        let (lo, hi) = (a.min(b), a.max(b));
+        predicates.push((Included(a), Included(a)));
+        predicates.push((Included(lo), Included(lo + (hi - lo) / 100)));
+        predicates.push((Included(hi), Unbounded));
+    }
+
+    println!(
+        "{} rows, {} predicates, {} byte values",
+        column.len(),
+        predicates.len(),
+        VALUE_BYTES
+    );
+    for mode in [Mode::Byte, Mode::Word] {
+        let t = Instant::now();
+        let dict = Dict::new(mode, sample.clone());
+        let codes: Vec<Code> = column.iter().map(|v| dict.encode(v)).collect();
+        let encode_time = t.elapsed();
+        for (label, bytes) in BLOCKS.iter() {
+            let t = Instant::now();
+            let replay = Replay::new(&dict, &codes, bytes / VALUE_BYTES, *bytes as u64);
+            let report = replay.run(&predicates);
+            println!(
+                "{:?} mode, {:>10} blocks: {:6.2}% elided, {:6.2}% rows to re-check, \
+                 {} MiB saved (built+encoded in {:?}, scanned in {:?})",
+                mode,
+                label,
+                100.0 * report.skip_rate,
+                100.0 * report.candidate_rate,
+                report.bytes_saved >> 20,
+                encode_time,
+                t.elapsed()
+            );
+        }
+    }
+}
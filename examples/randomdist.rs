@@ -3,10 +3,23 @@
 
 use float_ord::FloatOrd;
 use plotlib::{self, page::Page, repr, style::PointStyle, view::ContinuousView};
-use rand;
-use rand_distr::{Distribution, Exp1, Normal, Uniform};
+use rand::Rng;
+use rand_distr::{Distribution, Exp1, LogNormal, Normal, Uniform, Zipf};
 
-use ordbog::{Dict, DictF64, Mode};
+use ordbog::{quality::Metrics, Dict, DictF64, Mode};
+
+/// An even mixture of two normal distributions.
+struct Bimodal(Normal<f64>, Normal<f64>);
+
+impl Distribution<f64> for Bimodal {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        if rng.gen::<bool>() {
+            self.0.sample(rng)
+        } else {
+            self.1.sample(rng)
+        }
+    }
+}
 
 fn plot_dist<D: Distribution<f64>>(name: &str, dist: &D) {
     let mut sample: Vec<DictF64> = Vec::new();
@@ -14,7 +27,7 @@ fn plot_dist<D: Distribution<f64>>(name: &str, dist: &D) {
     for _ in 0..10000 {
         sample.push(DictF64(FloatOrd(dist.sample(&mut rng))));
     }
-    let dict = Dict::new(Mode::Byte, sample);
+    let dict = Dict::new(Mode::Byte, sample.clone());
     let data: Vec<(f64, f64)> = dict
         .codes
         .iter()
@@ -25,6 +38,23 @@ fn plot_dist<D: Distribution<f64>>(name: &str, dist: &D) {
     let view = ContinuousView::new().add(repr);
     let str = Page::single(&view).dimensions(70, 20).to_text().unwrap();
     println!("code assignments of {} data:\n{}", name, str);
+
+    // Measure against a fresh draw from the same distribution, so the numbers
+    // reflect how the dictionary generalizes rather than how it fits its own
+    // sample.
+    let fresh: Vec<DictF64> = (0..10000)
+        .map(|_| DictF64(FloatOrd(dist.sample(&mut rng))))
+        .collect();
+    let m = Metrics::measure(&dict, &fresh);
+    println!(
+        "{}: {} of {} exact codes assigned, max bin mass {:.4} (even spread over intervals {:.4}), exact coverage {:.2}%\n",
+        name,
+        dict.codes.len(),
+        dict.mode.num_exact_codes(),
+        m.max_code_mass,
+        1.0 / (dict.codes.len() + 1) as f64,
+        100.0 * m.exact_hit_rate
+    );
 }
 
 fn main() {
@@ -36,4 +66,17 @@ fn main() {
 
     let exp = Exp1;
     plot_dist("exp1", &exp);
+
+    let lognormal = LogNormal::new(0.0, 1.0).unwrap();
+    plot_dist("lognormal", &lognormal);
+
+    let bimodal = Bimodal(
+        Normal::new(-5.0, 1.0).unwrap(),
+        Normal::new(5.0, 0.5).unwrap(),
+    );
+    plot_dist("bimodal", &bimodal);
+
+    // Zipf produces integral ranks with a heavy head, exercising exact codes.
+    let zipf = Zipf::new(10000, 1.1).unwrap();
+    plot_dist("zipf", &zipf);
 }
@@ -0,0 +1,94 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use rand::Rng;
+use rand_distr::{Distribution, Exp};
+
+use ordbog::{Code, Dict, Mode};
+
+const DAY: u64 = 86400;
+const START: u64 = 1_600_000_000;
+const BLOCK: usize = 2048;
+
+/// Generate a week of log timestamps (epoch seconds) in arrival order, with a
+/// daily cycle in traffic and occasional bursts.
+fn log_timestamps() -> Vec<u64> {
+    let mut rng = rand::thread_rng();
+    let mut ts = Vec::new();
+    let mut now = START as f64;
+    let end = (START + 7 * DAY) as f64;
+    while now < end {
+        let phase = ((now as u64 - START) % DAY) as f64 / DAY as f64;
+        let rate = 2.0 + 1.5 * (phase * std::f64::consts::TAU).sin();
+        if rng.gen_ratio(1, 20000) {
+            // A burst: a few thousand events logged within the same second.
+            for _ in 0..rng.gen_range(1000..5000) {
+                ts.push(now as u64);
+            }
+        }
+        now += Exp::new(rate).unwrap().sample(&mut rng);
+        ts.push(now as u64);
+    }
+    ts
+}
+
+/// The key the dictionary is built over: timestamps truncated to the minute.
+fn key(ts: u64) -> u64 {
+    ts / 60
+}
+
+fn main() {
+    let column = log_timestamps();
+    let mut rng = rand::thread_rng();
+    let sample: Vec<u64> = (0..20000)
+        .map(|_| key(column[rng.gen_range(0..column.len())]))
+        .collect();
+    let dict = Dict::new(Mode::Word, sample);
+    let codes: Vec<Code> = column.iter().map(|t| dict.encode(&key(*t))).collect();
+    println!(
+        "{} log rows, {} exact codes, {} blocks of {} rows",
+        column.len(),
+        dict.codes.len(),
+        codes.len().div_ceil(BLOCK),
+        BLOCK
+    );
+
+    // Evaluate `ts BETWEEN lo AND hi` for windows of various widths. Since
+    // encoding preserves order, the predicate becomes the code range
+    // `[code(key(lo)), code(key(hi))]`: no row outside that range can match,
+    // so blocks with no code in range are skipped, and rows with a code in
+    // range are candidates to re-check against the timestamp itself.
+    for (label, width) in [
+        ("1 minute", 60),
+        ("15 minutes", 900),
+        ("1 hour", 3600),
+        ("6 hours", 6 * 3600),
+    ] {
+        let lo = START + 3 * DAY + 12345;
+        let hi = lo + width;
+        let (clo, chi) = (dict.encode(&key(lo)), dict.encode(&key(hi)));
+        let (mut read, mut candidates, mut actual) = (0, 0, 0);
+        for (cblock, tblock) in codes.chunks(BLOCK).zip(column.chunks(BLOCK)) {
+            let n = cblock.iter().filter(|c| clo <= **c && **c <= chi).count();
+            if n != 0 {
+                read += 1;
+                candidates += n;
+                actual += tblock.iter().filter(|t| lo <= **t && **t <= hi).count();
+            }
+        }
+        let nblocks = codes.len().div_ceil(BLOCK);
+        println!(
+            "BETWEEN over {:>10}: codes [{:#06x}, {:#06x}], read {:>3}/{} blocks ({:.2}% skipped), \
+             {} candidate rows, {} matching ({:.2}% precision)",
+            label,
+            clo.0,
+            chi.0,
+            read,
+            nblocks,
+            100.0 * (nblocks - read) as f64 / nblocks as f64,
+            candidates,
+            actual,
+            100.0 * actual as f64 / candidates.max(1) as f64
+        );
+    }
+}
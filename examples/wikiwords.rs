@@ -1,7 +1,16 @@
 use std::{fs::File, path::Path};
 
 use http_req::{request, response::StatusCode};
-use ordbog::{Dict, Mode};
+use ordbog::{quality::Metrics, Dict, Mode};
+
+fn load_words(txt_path: &Path) -> Vec<String> {
+    println!("loading words from {}", txt_path.display());
+    std::fs::read_to_string(txt_path)
+        .expect("reading txt")
+        .split_ascii_whitespace()
+        .map(String::from)
+        .collect()
+}
 
 fn get_wiki_words() -> Vec<String> {
     let url = "https://www.corpusdata.org/wiki/samples/text.zip";
@@ -23,28 +32,52 @@ fn get_wiki_words() -> Vec<String> {
         let mut writer = File::create(txt_path).expect("writing txt");
         std::io::copy(&mut file, &mut writer).expect("extracting txt");
     }
-    println!("loading words from {}", txt_path_str);
-    std::fs::read_to_string(txt_path)
-        .expect("reading txt")
-        .split_ascii_whitespace()
-        .map(String::from)
-        .collect()
+    load_words(txt_path)
 }
 
-fn main() {
-    let words = get_wiki_words();
-    let dict = Dict::new(Mode::Byte, words);
-    println!("produced dict with {} codes", dict.codes.len());
-    for (i, val) in dict.codes.iter().enumerate() {
-        println!("code 0x{:04x} = {:?}", 2 * (i + 1), val);
+const QUERIES: [&str; 7] = ["", "and", "ape", "the", "thorn", "yolo", "zygote"];
+
+fn report(words: &[String], mode: Mode) {
+    let dict = Dict::new(mode, words.to_vec());
+    println!("produced {:?} dict with {} codes", mode, dict.codes.len());
+    if mode == Mode::Byte {
+        for (i, val) in dict.codes.iter().enumerate() {
+            println!("code 0x{:04x} = {:?}", 2 * (i + 1), val);
+        }
     }
 
-    println!("querying dictionary");
-    for word in vec!["", "and", "ape", "the", "thorn", "yolo", "zygote"] {
+    println!("querying {:?} dictionary", mode);
+    let mut hits = 0;
+    for word in QUERIES.iter() {
+        let code = dict.encode(&String::from(*word));
+        if code.is_exact() {
+            hits += 1;
+        }
         println!(
-            "query: {:?} => code 0x{:04x}",
+            "query: {:?} => code 0x{:04x} ({})",
             word,
-            dict.encode(&String::from(word)).0
+            code.0,
+            if code.is_exact() { "exact" } else { "inexact" }
         );
     }
+    let m = Metrics::measure(&dict, words);
+    println!(
+        "{:?} mode: {}/{} query words exact, {:.2}% of corpus words exact\n",
+        mode,
+        hits,
+        QUERIES.len(),
+        100.0 * m.exact_hit_rate
+    );
+}
+
+/// Usage: `wikiwords [PATH]`. With a path, words are read from that local
+/// text file; otherwise a sample of wikipedia text is downloaded (once) into
+/// the current directory.
+fn main() {
+    let words = match std::env::args_os().nth(1) {
+        Some(path) => load_words(Path::new(&path)),
+        None => get_wiki_words(),
+    };
+    report(&words, Mode::Byte);
+    report(&words, Mode::Word);
 }
@@ -0,0 +1,76 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Differential fuzzing of encoding and predicate evaluation against the
+// reference implementations. Run with
+// `cargo fuzz run differential` from the crate root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ordbog::predicate::CodePredicate;
+use ordbog::reference as oracle;
+use ordbog::scan::{scan_chunks, WordKernel};
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound;
+
+// Decode a bound from a selector byte and a value.
+fn bound(selector: u8, value: u16) -> Bound<u16> {
+    match selector % 3 {
+        0 => Bound::Included(value),
+        1 => Bound::Excluded(value),
+        _ => Bound::Unbounded,
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // A mode byte, two bounds, a block size, then the sample as u16s.
+    if data.len() < 8 {
+        return;
+    }
+    let mode = if data[0] & 1 == 0 {
+        Mode::Byte
+    } else {
+        Mode::Word
+    };
+    let lo = bound(data[1], u16::from_le_bytes([data[2], data[3]]));
+    let hi = bound(data[4], u16::from_le_bytes([data[5], data[6]]));
+    let block_rows = data[7] as usize + 1;
+    let sample: Vec<u16> = data[8..]
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    // The crate can't tell an empty predicate from a narrow one, so only
+    // compare on predicates some value could satisfy.
+    let nonempty = match (&lo, &hi) {
+        (Bound::Included(l), Bound::Included(h)) => l <= h,
+        (Bound::Included(l), Bound::Excluded(h))
+        | (Bound::Excluded(l), Bound::Included(h))
+        | (Bound::Excluded(l), Bound::Excluded(h)) => l < h,
+        _ => true,
+    };
+    if !nonempty {
+        return;
+    }
+    let pred = (lo, hi);
+
+    let dict = Dict::new(mode, sample.clone());
+    let codes: Vec<Code> = sample.iter().map(|v| dict.encode(v)).collect();
+    for (v, c) in sample.iter().zip(codes.iter()) {
+        assert_eq!(*c, oracle::encode(&dict.codes, v));
+    }
+    let compiled = CodePredicate::from_range(&dict, &pred).compile(mode);
+    let expected = oracle::scan(&dict, &pred, &codes);
+    let mut out = vec![0xff; codes.len()];
+    compiled.classify_into(&codes, &mut out);
+    assert_eq!(out, expected);
+    if let Some(kernel) = WordKernel::new(&compiled) {
+        let words: Vec<u16> = codes.iter().map(|c| c.0).collect();
+        kernel.classify_into(&words, &mut out);
+        assert_eq!(out, expected);
+    }
+    assert_eq!(
+        scan_chunks(&dict, &codes, block_rows, &pred),
+        oracle::scan_blocks(&dict, &pred, &codes, block_rows)
+    );
+});
@@ -0,0 +1,55 @@
+/* Copyright 2021 Graydon Hoare <graydon@pobox.com>
+ * Licensed under the MIT and Apache-2.0 licenses.
+ *
+ * C ABI of the ordbog crate, built with its "ffi" feature. See src/ffi.rs
+ * for the ownership and struct-versioning rules. */
+
+#ifndef ORDBOG_H
+#define ORDBOG_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define ORDBOG_ABI_VERSION 1
+
+typedef enum OrdbogStatus {
+    ORDBOG_OK = 0,
+    ORDBOG_NULL_POINTER = 1,
+    ORDBOG_BAD_MODE = 2,
+    ORDBOG_BAD_STRUCT_SIZE = 3
+} OrdbogStatus;
+
+typedef struct OrdbogDictInfo {
+    uint32_t struct_size; /* set to sizeof(OrdbogDictInfo) before calling */
+    uint32_t abi_version;
+    uint32_t mode_bits;
+    uint32_t num_exact_codes;
+    uint32_t max_code;
+} OrdbogDictInfo;
+
+typedef struct OrdbogDictI64 OrdbogDictI64;
+typedef struct OrdbogDictF64 OrdbogDictF64;
+
+uint32_t ordbog_abi_version(void);
+
+OrdbogDictI64 *ordbog_dict_i64_new(uint32_t mode_bits, const int64_t *sample, size_t len);
+void ordbog_dict_i64_free(OrdbogDictI64 *dict);
+OrdbogStatus ordbog_dict_i64_encode(const OrdbogDictI64 *dict, const int64_t *values, size_t len,
+                                    uint16_t *out);
+OrdbogStatus ordbog_dict_i64_info(const OrdbogDictI64 *dict, OrdbogDictInfo *out);
+
+OrdbogDictF64 *ordbog_dict_f64_new(uint32_t mode_bits, const double *sample, size_t len);
+void ordbog_dict_f64_free(OrdbogDictF64 *dict);
+OrdbogStatus ordbog_dict_f64_encode(const OrdbogDictF64 *dict, const double *values, size_t len,
+                                    uint16_t *out);
+OrdbogStatus ordbog_dict_f64_info(const OrdbogDictF64 *dict, OrdbogDictInfo *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ORDBOG_H */
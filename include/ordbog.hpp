@@ -0,0 +1,75 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+//
+// Move-only C++ wrappers over the C ABI in ordbog.h. Each Dict owns its
+// handle and frees it on destruction.
+
+#ifndef ORDBOG_HPP
+#define ORDBOG_HPP
+
+#include "ordbog.h"
+
+#include <cstdint>
+#include <stdexcept>
+#include <utility>
+#include <vector>
+
+namespace ordbog {
+
+enum class Mode : uint32_t { Byte = 8, Word = 16 };
+
+template <typename T, typename Handle,
+          Handle *(*New)(uint32_t, const T *, size_t),
+          void (*Free)(Handle *),
+          OrdbogStatus (*Encode)(const Handle *, const T *, size_t, uint16_t *),
+          OrdbogStatus (*Info)(const Handle *, OrdbogDictInfo *)>
+class BasicDict {
+public:
+    BasicDict(Mode mode, const std::vector<T> &sample)
+        : handle_(New(static_cast<uint32_t>(mode), sample.data(), sample.size())) {
+        if (!handle_) {
+            throw std::invalid_argument("ordbog: could not build dictionary");
+        }
+    }
+    BasicDict(const BasicDict &) = delete;
+    BasicDict &operator=(const BasicDict &) = delete;
+    BasicDict(BasicDict &&other) noexcept : handle_(std::exchange(other.handle_, nullptr)) {}
+    BasicDict &operator=(BasicDict &&other) noexcept {
+        std::swap(handle_, other.handle_);
+        return *this;
+    }
+    ~BasicDict() { Free(handle_); }
+
+    std::vector<uint16_t> encode(const std::vector<T> &values) const {
+        std::vector<uint16_t> out(values.size());
+        check(Encode(handle_, values.data(), values.size(), out.data()));
+        return out;
+    }
+
+    OrdbogDictInfo info() const {
+        OrdbogDictInfo out{};
+        out.struct_size = sizeof(OrdbogDictInfo);
+        check(Info(handle_, &out));
+        return out;
+    }
+
+    const Handle *handle() const { return handle_; }
+
+private:
+    static void check(OrdbogStatus status) {
+        if (status != ORDBOG_OK) {
+            throw std::runtime_error("ordbog: call failed");
+        }
+    }
+
+    Handle *handle_;
+};
+
+using DictI64 = BasicDict<int64_t, OrdbogDictI64, ordbog_dict_i64_new, ordbog_dict_i64_free,
+                          ordbog_dict_i64_encode, ordbog_dict_i64_info>;
+using DictF64 = BasicDict<double, OrdbogDictF64, ordbog_dict_f64_new, ordbog_dict_f64_free,
+                          ordbog_dict_f64_encode, ordbog_dict_f64_info>;
+
+} // namespace ordbog
+
+#endif // ORDBOG_HPP
@@ -0,0 +1,439 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! An order-preserving dictionary that decides bin by bin whether to spend
+//! codes on an exact value or on a finer split of the interval.
+//!
+//! A [Dict](crate::Dict) alternates exact and inexact codes, so half its
+//! codespace goes to exact codes whether or not the column has values worth
+//! them. In a column that is heavily skewed in some ranges and near-uniform
+//! in others, the exact codes in the uniform ranges each stand for a value
+//! scarcely more frequent than its neighbours. An [AdaptiveDict] splits the
+//! sample into equal-depth bins and gives a bin an exact code only if its
+//! most frequent value holds enough of the bin; otherwise the bin just
+//! starts a new interval, costing one code rather than two. Alternatively
+//! [AdaptiveDict::with_exact_fraction] fixes the share of the codespace
+//! spent on exact codes, for workloads known to be equality- or
+//! range-heavy. Both are [AssignmentStrategy]s, and others can be plugged
+//! in with [AdaptiveDict::with_strategy], or [AdaptiveDict::auto] can try
+//! several on the sample and keep whichever measures best.
+//!
+//! The layout is a sorted list of points, each either an exact value (which
+//! gets a code of its own, followed by a code for the interval up to the
+//! next point) or a boundary (which begins a new interval, inclusive).
+//! Codes count up from 1, the interval below the first point; code 0 is
+//! unused. [AdaptiveDict::is_exact] says which codes are exact.
+
+use crate::predicate::CodePredicate;
+use crate::quality::Metrics;
+use crate::workload::RangePredicate;
+use crate::{Code, Mode, ValReq};
+use std::ops::Bound;
+
+/// A point in an [AdaptiveDict]'s layout.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Point<T> {
+    /// A value with an exact code of its own.
+    Exact(T),
+    /// The first value of a new interval.
+    Boundary(T),
+}
+
+impl<T> Point<T> {
+    /// The point's value.
+    pub fn value(&self) -> &T {
+        match self {
+            Point::Exact(v) | Point::Boundary(v) => v,
+        }
+    }
+}
+
+/// A dictionary with a mix of exact codes and interval splits. See the
+/// [module documentation](self).
+pub struct AdaptiveDict<T: ValReq> {
+    /// The mode the dictionary was built in.
+    pub mode: Mode,
+
+    /// The points of the layout, in strictly increasing order of value.
+    pub points: Vec<Point<T>>,
+
+    // The code of each point: its exact code, or the code of the interval it
+    // begins.
+    codes: Vec<Code>,
+
+    // Whether each code, indexed by code, is exact.
+    exact: Vec<bool>,
+}
+
+// One equal-depth bin of the sorted sample's runs of equal values.
+struct Bin<'a, T> {
+    first: &'a T,
+    head: &'a T,
+    head_count: usize,
+    count: usize,
+}
+
+fn bins<T: ValReq>(runs: &[(T, usize)], step: usize) -> Vec<Bin<'_, T>> {
+    let mut out: Vec<Bin<'_, T>> = Vec::new();
+    for (v, n) in runs {
+        match out.last_mut() {
+            Some(b) if b.count < step => {
+                if *n > b.head_count {
+                    b.head = v;
+                    b.head_count = *n;
+                }
+                b.count += n;
+            }
+            _ => out.push(Bin {
+                first: v,
+                head: v,
+                head_count: *n,
+                count: *n,
+            }),
+        }
+    }
+    out
+}
+
+/// A way of laying out codes over a sample, for building an [AdaptiveDict]
+/// with [AdaptiveDict::with_strategy]. Implement this to experiment with
+/// other partitioners.
+pub trait AssignmentStrategy<T: ValReq> {
+    /// Lay out codes over `runs`, the sample's distinct values in increasing
+    /// order with their counts, using no code greater than `budget`. Code 1
+    /// always stands for the interval below the first point, and each point
+    /// after it costs one code if a [Point::Boundary] and two if a
+    /// [Point::Exact], so the points may cost at most `budget - 1`. They
+    /// must be in strictly increasing order of value.
+    fn assign(&self, runs: &[(T, usize)], budget: usize) -> Vec<Point<T>>;
+}
+
+/// The strategy of [Dict::new](crate::Dict::new): every point exact, one in
+/// each equal-depth bin.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Alternating;
+
+impl<T: ValReq> AssignmentStrategy<T> for Alternating {
+    fn assign(&self, runs: &[(T, usize)], budget: usize) -> Vec<Point<T>> {
+        crate::Dict::exact_values(runs, budget.saturating_sub(1) / 2)
+            .into_iter()
+            .map(Point::Exact)
+            .collect()
+    }
+}
+
+/// Give an equal-depth bin an exact code for its most frequent value only
+/// if that value makes up at least this share of the bin. The bins are made
+/// as small as the budget allows.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct HeadShare(pub f64);
+
+impl<T: ValReq> AssignmentStrategy<T> for HeadShare {
+    fn assign(&self, runs: &[(T, usize)], budget: usize) -> Vec<Point<T>> {
+        binned(runs, budget, |bins| {
+            bins.iter().map(|b| share(b) >= self.0).collect()
+        })
+    }
+}
+
+/// Spend about this fraction of the codespace on exact codes and the rest
+/// on intervals, giving the exact codes to the equal-depth bins whose most
+/// frequent values hold the largest shares of them. A fraction of 0.5 or
+/// more gives at most the exact codes of [Alternating], since each needs
+/// an interval after it; a fraction of 0 gives pure equal-depth intervals.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ExactFraction(pub f64);
+
+impl<T: ValReq> AssignmentStrategy<T> for ExactFraction {
+    fn assign(&self, runs: &[(T, usize)], budget: usize) -> Vec<Point<T>> {
+        let k = (self.0.clamp(0.0, 1.0) * budget as f64).round() as usize;
+        let k = k.min(budget.saturating_sub(1) / 2);
+        binned(runs, budget, |bins| {
+            let mut order: Vec<usize> = (0..bins.len()).collect();
+            // Stable, so ties go to the lower bins.
+            order.sort_by(|a, b| share(&bins[*b]).total_cmp(&share(&bins[*a])));
+            let mut exact = vec![false; bins.len()];
+            for i in order.into_iter().take(k) {
+                exact[i] = true;
+            }
+            exact
+        })
+    }
+}
+
+/// Give every distinct value an exact code, as
+/// [Dict::new_exact](crate::Dict::new_exact) does. If the values don't all
+/// fit, falls back to [Alternating].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ExactOnly;
+
+impl<T: ValReq> AssignmentStrategy<T> for ExactOnly {
+    fn assign(&self, runs: &[(T, usize)], budget: usize) -> Vec<Point<T>> {
+        if 1 + 2 * runs.len() > budget {
+            return Alternating.assign(runs, budget);
+        }
+        runs.iter().map(|r| Point::Exact(r.0.clone())).collect()
+    }
+}
+
+/// The strategies [AdaptiveDict::auto] tries.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Choice {
+    /// Pure equal-depth intervals: [ExactFraction] of 0.
+    EquiDepth,
+    /// Exact codes for dominant values only: [HeadShare] of 0.5.
+    SkewAware,
+    /// An exact code for every distinct value: [ExactOnly]. Only tried if
+    /// the values all fit.
+    ExactOnly,
+    /// A quarter of the codespace on exact codes: [ExactFraction] of 0.25.
+    Hybrid,
+}
+
+/// The outcome of [AdaptiveDict::auto]: the strategy it chose, and how each
+/// it tried measured against the sample.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DictStats {
+    /// The strategy the dictionary was built with.
+    pub choice: Choice,
+    /// The chosen dictionary's metrics over the sample.
+    pub metrics: Metrics,
+    /// Every strategy tried, in order, with its metrics and cost.
+    pub candidates: Vec<(Choice, Metrics, f64)>,
+}
+
+/// The cost [AdaptiveDict::auto] minimizes: a blend, weighted by
+/// `equality_weight` between 0 and 1, of the expected false-positive rate
+/// of an equality predicate on a sample value and the largest share of the
+/// sample on any one code (which bounds the false-positive rate of a range
+/// predicate's end codes).
+pub fn cost(metrics: &Metrics, equality_weight: f64) -> f64 {
+    let w = equality_weight.clamp(0.0, 1.0);
+    let equality = (1.0 - metrics.exact_hit_rate) * metrics.inexact_width;
+    w * equality + (1.0 - w) * metrics.max_code_mass
+}
+
+fn share<T>(b: &Bin<'_, T>) -> f64 {
+    b.head_count as f64 / b.count as f64
+}
+
+// Lay out equal-depth bins, as small as fit in `budget`, choosing which get
+// exact codes with `choose`.
+fn binned<T, F>(runs: &[(T, usize)], budget: usize, choose: F) -> Vec<Point<T>>
+where
+    T: ValReq,
+    F: Fn(&[Bin<'_, T>]) -> Vec<bool>,
+{
+    let total: usize = runs.iter().map(|r| r.1).sum();
+    // Code 1 plus one code per boundary and two per exact value.
+    let cost = |exact: &[bool]| {
+        1 + exact
+            .iter()
+            .enumerate()
+            .map(|(i, e)| match (e, i) {
+                (true, _) => 2,
+                (false, 0) => 0,
+                (false, _) => 1,
+            })
+            .sum::<usize>()
+    };
+    // Binary search for the smallest bin size that fits.
+    let (mut lo, mut hi) = (1, total.max(1));
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if cost(&choose(&bins(runs, mid))) <= budget {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    let bins = bins(runs, lo);
+    let mut points = Vec::new();
+    for (i, (b, e)) in bins.iter().zip(choose(&bins)).enumerate() {
+        if e {
+            points.push(Point::Exact(b.head.clone()));
+        } else if i != 0 {
+            // The first bin is already covered by code 1.
+            points.push(Point::Boundary(b.first.clone()));
+        }
+    }
+    points
+}
+
+// Sort the sample into runs of equal values.
+fn runs<T: ValReq>(mut sample: Vec<T>) -> Vec<(T, usize)> {
+    sample.sort_unstable();
+    let mut runs: Vec<(T, usize)> = Vec::new();
+    for v in sample.into_iter() {
+        match runs.last_mut() {
+            Some((last, n)) if *last == v => *n += 1,
+            _ => runs.push((v, 1)),
+        }
+    }
+    runs
+}
+
+impl<T: ValReq> AdaptiveDict<T> {
+    /// Build a dictionary with a given [Mode] over a provided sample with the
+    /// [HeadShare] strategy.
+    pub fn new(mode: Mode, sample: Vec<T>, min_head_share: f64) -> Self {
+        Self::with_strategy(mode, sample, &HeadShare(min_head_share))
+    }
+
+    /// Build a dictionary with a given [Mode] over a provided sample with the
+    /// [ExactFraction] strategy.
+    pub fn with_exact_fraction(mode: Mode, sample: Vec<T>, exact_fraction: f64) -> Self {
+        Self::with_strategy(mode, sample, &ExactFraction(exact_fraction))
+    }
+
+    /// Build a dictionary with a given [Mode] over a provided sample, laid
+    /// out by `strategy`.
+    pub fn with_strategy<S>(mode: Mode, sample: Vec<T>, strategy: &S) -> Self
+    where
+        S: AssignmentStrategy<T> + ?Sized,
+    {
+        let runs = runs(sample);
+        let budget = mode.max_inexact_code().0 as usize;
+        Self::from_points(mode, strategy.assign(&runs, budget))
+    }
+
+    /// Build a dictionary with a given [Mode] over a provided sample with
+    /// each [Choice] of strategy in turn, keeping the one of least [cost]
+    /// over the sample. Ties go to the earlier choice.
+    pub fn auto(mode: Mode, sample: Vec<T>, equality_weight: f64) -> (Self, DictStats) {
+        let runs = runs(sample);
+        let budget = mode.max_inexact_code().0 as usize;
+        let mut tries: Vec<(Choice, &dyn AssignmentStrategy<T>)> = vec![
+            (Choice::EquiDepth, &ExactFraction(0.0)),
+            (Choice::SkewAware, &HeadShare(0.5)),
+        ];
+        if 2 * runs.len() < budget {
+            tries.push((Choice::ExactOnly, &ExactOnly));
+        }
+        tries.push((Choice::Hybrid, &ExactFraction(0.25)));
+        let mut best: Option<(Self, usize)> = None;
+        let mut candidates: Vec<(Choice, Metrics, f64)> = Vec::with_capacity(tries.len());
+        for (choice, strategy) in tries {
+            let dict = Self::from_points(mode, strategy.assign(&runs, budget));
+            let metrics = dict.measure_runs(&runs);
+            let c = cost(&metrics, equality_weight);
+            if best.as_ref().is_none_or(|(_, i)| c < candidates[*i].2) {
+                best = Some((dict, candidates.len()));
+            }
+            candidates.push((choice, metrics, c));
+        }
+        let (dict, i) = best.expect("at least one strategy is always tried");
+        let stats = DictStats {
+            choice: candidates[i].0,
+            metrics: candidates[i].1,
+            candidates,
+        };
+        (dict, stats)
+    }
+
+    /// Make a dictionary from its layout directly. The points must be in
+    /// strictly increasing order of value and fit in the mode's codespace.
+    pub fn from_points(mode: Mode, points: Vec<Point<T>>) -> Self {
+        assert!(points.windows(2).all(|w| w[0].value() < w[1].value()));
+        let mut codes = Vec::with_capacity(points.len());
+        let mut exact = vec![false, false];
+        for p in points.iter() {
+            let c = exact.len() as u16;
+            codes.push(Code(c));
+            match p {
+                Point::Exact(_) => exact.extend_from_slice(&[true, false]),
+                Point::Boundary(_) => exact.push(false),
+            }
+        }
+        assert!(exact.len() <= mode.max_inexact_code().0 as usize + 1);
+        AdaptiveDict {
+            mode,
+            points,
+            codes,
+            exact,
+        }
+    }
+
+    /// Measure the dictionary against `values`. See [Metrics].
+    pub fn metrics(&self, values: &[T]) -> Metrics {
+        Metrics::of_codes(
+            values.iter().map(|v| self.encode(v)),
+            self.max_code(),
+            |c| self.is_exact(c),
+        )
+    }
+
+    fn measure_runs(&self, runs: &[(T, usize)]) -> Metrics {
+        let codes = runs
+            .iter()
+            .flat_map(|(v, n)| std::iter::repeat_n(self.encode(v), *n));
+        Metrics::of_codes(codes, self.max_code(), |c| self.is_exact(c))
+    }
+
+    /// The greatest code the dictionary produces.
+    pub fn max_code(&self) -> Code {
+        Code(self.exact.len() as u16 - 1)
+    }
+
+    /// Whether `code` stands for a single value.
+    pub fn is_exact(&self, code: Code) -> bool {
+        self.exact.get(code.0 as usize).copied().unwrap_or(false)
+    }
+
+    /// Look up the code for a value.
+    pub fn encode(&self, query: &T) -> Code {
+        match self.points.binary_search_by(|p| p.value().cmp(query)) {
+            Ok(i) => self.codes[i],
+            Err(0) => Code(1),
+            Err(i) => match self.points[i - 1] {
+                Point::Exact(_) => Code(self.codes[i - 1].0 + 1),
+                Point::Boundary(_) => self.codes[i - 1],
+            },
+        }
+    }
+
+    // Whether `v` is the first value of `c`'s interval.
+    fn starts_interval(&self, c: Code, v: &T) -> bool {
+        match self.points.binary_search_by(|p| p.value().cmp(v)) {
+            Ok(i) => matches!(self.points[i], Point::Boundary(_)) && self.codes[i] == c,
+            Err(_) => false,
+        }
+    }
+
+    /// Rewrite a range predicate onto codes. Exact codes and intervals
+    /// wholly inside the range are definite; intervals at either end that
+    /// only partly satisfy it are maybe.
+    pub fn rewrite(&self, pred: &RangePredicate<T>) -> CodePredicate {
+        let (lo, lo_definite) = match &pred.0 {
+            Bound::Unbounded => (Code(1), true),
+            Bound::Included(v) => {
+                let c = self.encode(v);
+                (c, self.is_exact(c) || self.starts_interval(c, v))
+            }
+            Bound::Excluded(v) => {
+                let c = self.encode(v);
+                if self.is_exact(c) {
+                    (Code(c.0 + 1), true)
+                } else {
+                    (c, false)
+                }
+            }
+        };
+        let (hi, hi_definite) = match &pred.1 {
+            Bound::Unbounded => (self.max_code(), true),
+            Bound::Included(v) => {
+                let c = self.encode(v);
+                (c, self.is_exact(c))
+            }
+            Bound::Excluded(v) => {
+                let c = self.encode(v);
+                if self.is_exact(c) || self.starts_interval(c, v) {
+                    (Code(c.0 - 1), true)
+                } else {
+                    (c, false)
+                }
+            }
+        };
+        CodePredicate::from_code_range(lo, hi, lo_definite, hi_definite)
+    }
+}
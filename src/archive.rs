@@ -0,0 +1,149 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Dictionaries read in place from their serialized bytes.
+//!
+//! [Dict::from_bytes] copies every value of a serialized dictionary into a
+//! `Vec<T>` before the first lookup. For values of a fixed size that isn't
+//! needed: in the [format](crate::format), each such value is the same
+//! 4-byte length followed by the same number of bytes, so the `i`th value
+//! sits at a known offset. An [ArchivedDict] checks a serialized dictionary
+//! once, without allocating, and then encodes by binary search over the
+//! bytes themselves, decoding only the values the search compares against.
+//! Over a memory-mapped dictionary file, a lookup touches a handful of
+//! pages and nothing is ever copied out of the map.
+
+use crate::format::{sections, BinaryValue, FormatError, Header, HEADER_LEN};
+use crate::{BuildOptions, Code, Dict, DictF32, DictF64, Mode, ValReq};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+/// A [BinaryValue] whose encoding is always [WIDTH](Self::WIDTH) bytes.
+pub trait FixedWidthValue: BinaryValue {
+    /// The number of bytes in every value's encoding.
+    const WIDTH: usize;
+}
+
+macro_rules! fixed_width_value {
+    ($($t:ty),*) => {$(
+        impl FixedWidthValue for $t {
+            const WIDTH: usize = std::mem::size_of::<$t>();
+        }
+    )*};
+}
+
+fixed_width_value!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl FixedWidthValue for DictF32 {
+    const WIDTH: usize = 4;
+}
+
+impl FixedWidthValue for DictF64 {
+    const WIDTH: usize = 8;
+}
+
+/// A dictionary of fixed-width values, read in place from the bytes
+/// [Dict::to_bytes] wrote. See the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct ArchivedDict<'a, T> {
+    mode: Mode,
+    // The reserved band and layout the dictionary was built with.
+    options: BuildOptions,
+    // The values, each a length prefix and WIDTH bytes.
+    values: &'a [u8],
+    len: usize,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: ValReq + FixedWidthValue> ArchivedDict<'a, T> {
+    // Each value's length prefix and encoding.
+    const STRIDE: usize = 4 + T::WIDTH;
+
+    /// Check `bytes`, a dictionary serialized by [Dict::to_bytes], and wrap
+    /// it for lookups. Fails as [Dict::from_bytes] would, and with
+    /// [FormatError::BadValue] if a value isn't [T::WIDTH] bytes long.
+    ///
+    /// [T::WIDTH]: FixedWidthValue::WIDTH
+    pub fn new(bytes: &'a [u8]) -> Result<Self, FormatError> {
+        let header = Header::read(bytes)?;
+        let len = header.num_codes as usize;
+        let width = (T::WIDTH as u32).to_le_bytes();
+        let first = bytes.get(HEADER_LEN..HEADER_LEN + 4);
+        if len != 0 && first.is_some_and(|f| f != width) {
+            // Values of another type, rather than a truncated buffer.
+            return Err(FormatError::BadValue);
+        }
+        let end = HEADER_LEN + len * Self::STRIDE;
+        if bytes.len() < end {
+            return Err(FormatError::Truncated);
+        }
+        let archived = ArchivedDict {
+            mode: header.mode,
+            options: header.options(),
+            values: &bytes[HEADER_LEN..end],
+            len,
+            value: PhantomData,
+        };
+        let mut prev: Option<T> = None;
+        for slot in archived.values.chunks_exact(Self::STRIDE) {
+            if slot[..4] != width {
+                return Err(FormatError::BadValue);
+            }
+            let v = T::read_bytes(&slot[4..]).ok_or(FormatError::BadValue)?;
+            if prev.is_some_and(|p| p >= v) {
+                return Err(FormatError::BadCodes);
+            }
+            prev = Some(v);
+        }
+        sections(bytes)?;
+        Ok(archived)
+    }
+
+    /// The mode the dictionary was built in.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The number of exact codes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no exact codes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The value of the `i`th exact code, code `2 * (i + 1)`.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn value(&self, i: usize) -> T {
+        assert!(i < self.len);
+        let at = i * Self::STRIDE + 4;
+        T::read_bytes(&self.values[at..at + T::WIDTH]).expect("value checked when opened")
+    }
+
+    /// Look up the code for a value, as [Dict::encode] does.
+    pub fn encode<Q: Ord + ?Sized>(&self, query: &Q) -> Code
+    where
+        T: Borrow<Q>,
+    {
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.value(mid).borrow().cmp(query) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return Code((2 * (mid + 1)) as u16),
+            }
+        }
+        Code((2 * lo + 1) as u16)
+    }
+
+    /// Copy the dictionary out of the bytes, as [Dict::from_bytes] would.
+    pub fn to_dict(&self) -> Dict<T> {
+        let codes = (0..self.len).map(|i| self.value(i)).collect();
+        Dict::from_stored_codes(self.mode, codes, &self.options)
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Encoding Arrow arrays.
+//!
+//! [EncodeArray] encodes the values of an Arrow array with a dictionary. For
+//! a `DictionaryArray`, [encode_dictionary] encodes each distinct value of
+//! the array's dictionary once and then maps the keys through the resulting
+//! table, rather than encoding every row. Null slots get code 0, which no
+//! dictionary assigns, and [EncodeArray::encode_column] carries the array's
+//! validity over into a [CodeColumn].
+
+use crate::column::CodeColumn;
+use crate::{Code, Dict, ValReq};
+use arrow_array::types::{ArrowDictionaryKeyType, ArrowPrimitiveType};
+use arrow_array::{
+    Array, DictionaryArray, GenericStringArray, LargeStringArray, OffsetSizeTrait, PrimitiveArray,
+    StringArray,
+};
+
+/// Arrow arrays whose values can be encoded with a `Dict<T>`.
+pub trait EncodeArray<T: ValReq>: Array {
+    /// Encode every slot of the array, giving null slots code 0.
+    fn encode(&self, dict: &Dict<T>) -> Vec<Code>;
+
+    /// Encode every slot of the array into a column with the array's
+    /// logical validity.
+    fn encode_column(&self, dict: &Dict<T>) -> CodeColumn {
+        CodeColumn {
+            codes: self.encode(dict),
+            validity: self.logical_nulls().map(|n| n.iter().collect()),
+        }
+    }
+}
+
+impl<P: ArrowPrimitiveType> EncodeArray<P::Native> for PrimitiveArray<P>
+where
+    P::Native: ValReq,
+{
+    fn encode(&self, dict: &Dict<P::Native>) -> Vec<Code> {
+        self.iter()
+            .map(|v| v.map_or(Code(0), |v| dict.encode(&v)))
+            .collect()
+    }
+}
+
+impl<O: OffsetSizeTrait> EncodeArray<String> for GenericStringArray<O> {
+    fn encode(&self, dict: &Dict<String>) -> Vec<Code> {
+        self.iter()
+            .map(|v| v.map_or(Code(0), |v| dict.encode(v)))
+            .collect()
+    }
+}
+
+/// Encode a dictionary-encoded array whose values are an array of type `V`,
+/// translating each of its distinct values once. Returns None if the
+/// values are not of type `V`.
+pub fn encode_dictionary<K, V, T>(dict: &Dict<T>, array: &DictionaryArray<K>) -> Option<Vec<Code>>
+where
+    K: ArrowDictionaryKeyType,
+    V: EncodeArray<T> + 'static,
+    T: ValReq,
+{
+    let table = array.values().as_any().downcast_ref::<V>()?.encode(dict);
+    Some(
+        array
+            .keys_iter()
+            .map(|k| k.map_or(Code(0), |k| table[k]))
+            .collect(),
+    )
+}
+
+impl<K: ArrowDictionaryKeyType> EncodeArray<String> for DictionaryArray<K> {
+    /// Encode a dictionary-encoded string array through
+    /// [encode_dictionary].
+    ///
+    /// Panics if the dictionary's values are not a `StringArray` or a
+    /// `LargeStringArray`.
+    fn encode(&self, dict: &Dict<String>) -> Vec<Code> {
+        encode_dictionary::<K, StringArray, String>(dict, self)
+            .or_else(|| encode_dictionary::<K, LargeStringArray, String>(dict, self))
+            .expect("dictionary values are not a StringArray or LargeStringArray")
+    }
+}
@@ -0,0 +1,30 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Encoding `arrow2` arrays, with the `arrow2` feature.
+//!
+//! The same adapters as the `arrow` module, for engines built on the arrow2
+//! implementation of Arrow rather than the official one. The features are
+//! independent, so a build can enable both.
+
+encode_arrays!(arrow2);
+
+impl<K: DictionaryKey> EncodeArray<String> for DictionaryArray<K> {
+    /// Encode a dictionary-encoded string array through
+    /// [encode_dictionary].
+    ///
+    /// Panics if the dictionary's values are not a `Utf8Array`.
+    fn encode(&self, dict: &Dict<String>) -> Vec<Code> {
+        encode_dictionary::<K, Utf8Array<i32>, String>(dict, self)
+            .or_else(|| encode_dictionary::<K, Utf8Array<i64>, String>(dict, self))
+            .expect("dictionary values are not a Utf8Array")
+    }
+
+    /// Encode every slot into a column, null wherever the key or the value
+    /// it points to is null.
+    fn encode_column(&self, dict: &Dict<String>) -> CodeColumn {
+        let codes = self.encode(dict);
+        let validity = code_validity(&codes);
+        CodeColumn { codes, validity }
+    }
+}
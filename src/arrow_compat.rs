@@ -0,0 +1,77 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// The adapters of [crate::arrow2] and [crate::polars_arrow]. polars-arrow is
+// a fork of arrow2 and keeps its array API, so the two modules are the same
+// code instantiated over a different crate.
+
+macro_rules! encode_arrays {
+    ($krate:ident) => {
+        use crate::column::CodeColumn;
+        use crate::{Code, Dict, ValReq};
+        use $krate::array::{Array, DictionaryArray, DictionaryKey, PrimitiveArray, Utf8Array};
+        use $krate::types::{NativeType, Offset};
+
+        /// Arrays whose values can be encoded with a `Dict<T>`.
+        pub trait EncodeArray<T: ValReq>: Array {
+            /// Encode every slot of the array, giving null slots code 0.
+            fn encode(&self, dict: &Dict<T>) -> Vec<Code>;
+
+            /// Encode every slot of the array into a column with the
+            /// array's validity.
+            fn encode_column(&self, dict: &Dict<T>) -> CodeColumn {
+                CodeColumn {
+                    codes: self.encode(dict),
+                    validity: self.validity().map(|v| v.iter().collect()),
+                }
+            }
+        }
+
+        impl<N: NativeType + ValReq> EncodeArray<N> for PrimitiveArray<N> {
+            fn encode(&self, dict: &Dict<N>) -> Vec<Code> {
+                self.iter()
+                    .map(|v| v.map_or(Code(0), |v| dict.encode(v)))
+                    .collect()
+            }
+        }
+
+        impl<O: Offset> EncodeArray<String> for Utf8Array<O> {
+            fn encode(&self, dict: &Dict<String>) -> Vec<Code> {
+                self.iter()
+                    .map(|v| v.map_or(Code(0), |v| dict.encode(v)))
+                    .collect()
+            }
+        }
+
+        /// Encode a dictionary-encoded array whose values are an array of
+        /// type `V`, translating each of its distinct values once. Returns
+        /// None if the values are not of type `V`.
+        pub fn encode_dictionary<K, V, T>(
+            dict: &Dict<T>,
+            array: &DictionaryArray<K>,
+        ) -> Option<Vec<Code>>
+        where
+            K: DictionaryKey,
+            V: EncodeArray<T> + 'static,
+            T: ValReq,
+        {
+            let table = array.values().as_any().downcast_ref::<V>()?.encode(dict);
+            Some(
+                array
+                    .keys_iter()
+                    .map(|k| k.map_or(Code(0), |k| table[k]))
+                    .collect(),
+            )
+        }
+
+        // The validity of encoded slots, null wherever a key or the value
+        // it points to is null, and so where the code is 0.
+        fn code_validity(codes: &[Code]) -> Option<Vec<bool>> {
+            if codes.contains(&Code(0)) {
+                Some(codes.iter().map(|c| *c != Code(0)).collect())
+            } else {
+                None
+            }
+        }
+    };
+}
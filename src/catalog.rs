@@ -0,0 +1,264 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! A manifest of the sketches kept for a table.
+//!
+//! A table with many segments and columns has many [sketch
+//! files](crate::sketchfile), and an engine needs to know which columns of
+//! which segments have one, where it is, and whether its dictionary is the
+//! one a query was planned against, without opening every file to find
+//! out. A [Catalog] records this for each sketch, keyed by segment and
+//! column name, and is saved alongside the sketches it describes.
+//!
+//! A sketch is located by a path and the byte range it occupies there, so
+//! several sketches may be packed into one file. Paths are opaque strings
+//! to the catalog: they may be relative to the catalog, or object store
+//! keys.
+//!
+//! The format, under the same compatibility contract as the
+//! [format](crate::format) module, is:
+//!
+//!   - a 16-byte header: the magic bytes `ORDC`, the format version (u16),
+//!     the required-feature flags (u16), the number of entries (u32), and a
+//!     reserved zero u32;
+//!   - the entries in order of segment and then column, each its segment,
+//!     column and path (each a u32 length followed by that many bytes of
+//!     UTF-8), its dictionary's fingerprint, offset, length and row count
+//!     (u64 each) and its mode (u8: its [bits](Mode::bits));
+//!   - anything after the entries is ignored, leaving room for additions.
+//!
+//! All integers are little-endian.
+
+use crate::format::{
+    take_prefixed, take_u64, BinaryValue, Fingerprint, FormatError, KNOWN_FLAGS, READABLE_VERSIONS,
+    WRITE_VERSION,
+};
+use crate::sketchfile::SketchHeader;
+use crate::{Dict, Mode, ValReq};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+/// The magic bytes that begin every serialized [Catalog].
+pub const CATALOG_MAGIC: [u8; 4] = *b"ORDC";
+
+/// The size of the fixed header of a serialized [Catalog].
+pub const CATALOG_HEADER_LEN: usize = 16;
+
+/// What a [Catalog] records about one sketch.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CatalogEntry {
+    /// The segment the sketch covers.
+    pub segment: String,
+    /// The column the sketch covers.
+    pub column: String,
+    /// The file holding the sketch.
+    pub path: String,
+    /// The byte offset of the sketch in the file.
+    pub offset: u64,
+    /// The length of the sketch in bytes.
+    pub len: u64,
+    /// The fingerprint of the sketch's dictionary.
+    pub fingerprint: Fingerprint,
+    /// The sketch's mode.
+    pub mode: Mode,
+    /// The number of rows the sketch covers.
+    pub rows: u64,
+}
+
+impl CatalogEntry {
+    /// Describe the sketch file `sketch`, of `column` in `segment`, stored
+    /// at `offset` in the file at `path`. Reads the mode, row count and
+    /// dictionary fingerprint from the sketch.
+    pub fn for_sketch(
+        segment: &str,
+        column: &str,
+        path: &str,
+        offset: u64,
+        sketch: &[u8],
+    ) -> Result<Self, FormatError> {
+        let header = SketchHeader::read(sketch)?;
+        let fingerprint = crate::format::fingerprint(&header.dict_bytes(sketch)?)?;
+        Ok(CatalogEntry {
+            segment: segment.to_string(),
+            column: column.to_string(),
+            path: path.to_string(),
+            offset,
+            len: sketch.len() as u64,
+            fingerprint,
+            mode: header.mode,
+            rows: header.rows,
+        })
+    }
+}
+
+/// A manifest of sketches by segment and column. See the [module
+/// documentation](self).
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Catalog {
+    entries: BTreeMap<(String, String), CatalogEntry>,
+}
+
+impl Catalog {
+    /// Make an empty catalog.
+    pub fn new() -> Self {
+        Catalog::default()
+    }
+
+    /// The number of sketches recorded.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no sketches are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record a sketch, returning the entry it replaces for the same
+    /// segment and column, if any.
+    pub fn insert(&mut self, entry: CatalogEntry) -> Option<CatalogEntry> {
+        let key = (entry.segment.clone(), entry.column.clone());
+        self.entries.insert(key, entry)
+    }
+
+    /// Forget the sketch of `column` in `segment`, returning its entry.
+    pub fn remove(&mut self, segment: &str, column: &str) -> Option<CatalogEntry> {
+        self.entries
+            .remove(&(segment.to_string(), column.to_string()))
+    }
+
+    /// Look up the sketch of `column` in `segment`.
+    pub fn get(&self, segment: &str, column: &str) -> Option<&CatalogEntry> {
+        self.entries.get(&(segment.to_string(), column.to_string()))
+    }
+
+    /// Iterate over every entry, in order of segment and then column.
+    pub fn entries(&self) -> impl Iterator<Item = &CatalogEntry> {
+        self.entries.values()
+    }
+
+    /// Iterate over the entries of `segment`, in order of column.
+    pub fn segment<'a>(&'a self, segment: &'a str) -> impl Iterator<Item = &'a CatalogEntry> {
+        self.entries().filter(move |e| e.segment == segment)
+    }
+
+    /// Iterate over the entries of `column`, in order of segment.
+    pub fn column<'a>(&'a self, column: &'a str) -> impl Iterator<Item = &'a CatalogEntry> {
+        self.entries().filter(move |e| e.column == column)
+    }
+
+    /// Iterate over the entries of `column` whose sketches weren't encoded
+    /// with `dict`, and so whose codes a plan made against `dict` can't use
+    /// as they are.
+    pub fn stale<'a, T: ValReq + BinaryValue>(
+        &'a self,
+        column: &'a str,
+        dict: &Dict<T>,
+    ) -> impl Iterator<Item = &'a CatalogEntry> {
+        let fingerprint = dict.fingerprint();
+        self.column(column)
+            .filter(move |e| e.fingerprint != fingerprint)
+    }
+
+    /// Serialize the catalog in the current format version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(CATALOG_HEADER_LEN + self.entries.len() * 64);
+        out.extend_from_slice(&CATALOG_MAGIC);
+        out.extend_from_slice(&WRITE_VERSION.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        for e in self.entries() {
+            for s in [&e.segment, &e.column, &e.path].iter() {
+                out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                out.extend_from_slice(s.as_bytes());
+            }
+            out.extend_from_slice(&e.fingerprint.0.to_le_bytes());
+            out.extend_from_slice(&e.offset.to_le_bytes());
+            out.extend_from_slice(&e.len.to_le_bytes());
+            out.extend_from_slice(&e.rows.to_le_bytes());
+            out.push(e.mode.bits());
+        }
+        out
+    }
+
+    /// Deserialize a catalog written by [Catalog::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        if bytes.len() < CATALOG_HEADER_LEN {
+            return Err(if bytes.len() >= 4 && bytes[..4] != CATALOG_MAGIC {
+                FormatError::BadMagic
+            } else {
+                FormatError::Truncated
+            });
+        }
+        if bytes[..4] != CATALOG_MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if !READABLE_VERSIONS.contains(&version) {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let flags = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(FormatError::UnsupportedFlags(flags));
+        }
+        let n = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+        let mut rest = &bytes[CATALOG_HEADER_LEN..];
+        let mut catalog = Catalog::new();
+        let mut prev: Option<(String, String)> = None;
+        for _ in 0..n {
+            let mut strings = Vec::with_capacity(3);
+            for _ in 0..3 {
+                let (s, r) = take_prefixed(rest)?;
+                strings.push(String::read_bytes(s).ok_or(FormatError::BadValue)?);
+                rest = r;
+            }
+            let (fingerprint, r) = take_u64(rest)?;
+            let (offset, r) = take_u64(r)?;
+            let (len, r) = take_u64(r)?;
+            let (rows, r) = take_u64(r)?;
+            let (mode, r) = r.split_first().ok_or(FormatError::Truncated)?;
+            let mode = Mode::from_bits(*mode).ok_or(FormatError::BadMode(*mode))?;
+            rest = r;
+            let path = strings.pop().unwrap();
+            let column = strings.pop().unwrap();
+            let segment = strings.pop().unwrap();
+            let key = (segment.clone(), column.clone());
+            if prev.as_ref().is_some_and(|p| *p >= key) {
+                return Err(FormatError::BadCodes);
+            }
+            prev = Some(key);
+            catalog.insert(CatalogEntry {
+                segment,
+                column,
+                path,
+                offset,
+                len,
+                fingerprint: Fingerprint(fingerprint),
+                mode,
+                rows,
+            });
+        }
+        Ok(catalog)
+    }
+
+    /// Read a catalog from the file at `path`. A file that isn't a readable
+    /// catalog is an [io::ErrorKind::InvalidData] error wrapping the
+    /// [FormatError].
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write the catalog to the file at `path`, replacing it. The catalog is
+    /// written to a temporary file beside it and renamed into place, so
+    /// readers see either the old catalog or the new one.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, self.to_bytes())?;
+        std::fs::rename(&tmp, path)
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Sets of codes, as sorted lists of disjoint ranges.
+//!
+//! Rewriting predicates produces sets of codes (a range for a comparison,
+//! scattered codes for an `IN` list) and combining predicates on one column
+//! combines those sets. A [CodeSet] keeps its codes as the fewest possible
+//! ranges, so the sets of range predicates stay small however many codes
+//! they span, and supports the set algebra predicate combination needs:
+//! union, intersection, difference and complement.
+//!
+//! A [CodePredicate](crate::predicate::CodePredicate) can be built from and
+//! taken apart into a pair of sets, its definite and candidate codes; see
+//! [CodePredicate::from_sets](crate::predicate::CodePredicate::from_sets).
+
+use crate::{Code, CodeRange, Mode};
+use std::ops::RangeInclusive;
+
+/// A set of codes. See the [module documentation](self).
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CodeSet {
+    // Sorted, disjoint and with no two adjacent.
+    ranges: Vec<RangeInclusive<Code>>,
+}
+
+impl CodeSet {
+    /// Make the empty set.
+    pub fn new() -> Self {
+        CodeSet::default()
+    }
+
+    /// Make the set of every code in `mode`'s codespace, from 1 up.
+    pub fn all(mode: Mode) -> Self {
+        Self::from_range(mode.codes().into())
+    }
+
+    /// Make the set of the codes in `range`.
+    pub fn from_range(range: RangeInclusive<Code>) -> Self {
+        Self::from_ranges(std::iter::once(range))
+    }
+
+    /// Make the set of the codes in any of `ranges`, which may overlap and
+    /// come in any order. Empty ranges are ignored.
+    pub fn from_ranges<I: IntoIterator<Item = RangeInclusive<Code>>>(ranges: I) -> Self {
+        let mut all: Vec<RangeInclusive<Code>> =
+            ranges.into_iter().filter(|r| !r.is_empty()).collect();
+        all.sort_by_key(|r| *r.start());
+        let mut out: Vec<RangeInclusive<Code>> = Vec::with_capacity(all.len());
+        for r in all {
+            if let Some(last) = out.last_mut() {
+                if r.start().0 as u32 <= last.end().0 as u32 + 1 {
+                    if r.end() > last.end() {
+                        *last = *last.start()..=*r.end();
+                    }
+                    continue;
+                }
+            }
+            out.push(r);
+        }
+        CodeSet { ranges: out }
+    }
+
+    /// Make the set of `codes`, which may repeat and come in any order.
+    pub fn from_codes(codes: &[Code]) -> Self {
+        Self::from_ranges(codes.iter().map(|c| *c..=*c))
+    }
+
+    /// Return the set's ranges: sorted, disjoint and with no two adjacent.
+    pub fn ranges(&self) -> &[RangeInclusive<Code>] {
+        &self.ranges
+    }
+
+    /// Return true iff the set holds no codes.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Return the number of codes in the set.
+    pub fn len(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|r| CodeRange::from(r.clone()).len())
+            .sum()
+    }
+
+    /// Return true iff `code` is in the set.
+    pub fn contains(&self, code: Code) -> bool {
+        let i = self.ranges.partition_point(|r| *r.end() < code);
+        self.ranges.get(i).is_some_and(|r| *r.start() <= code)
+    }
+
+    /// Iterate over the codes in the set, in order.
+    pub fn iter(&self) -> impl Iterator<Item = Code> + '_ {
+        self.ranges
+            .iter()
+            .flat_map(|r| CodeRange::from(r.clone()).iter())
+    }
+
+    /// Return the codes in either set.
+    pub fn union(&self, other: &CodeSet) -> CodeSet {
+        Self::from_ranges(self.ranges.iter().chain(other.ranges.iter()).cloned())
+    }
+
+    /// Return the codes in both sets.
+    pub fn intersection(&self, other: &CodeSet) -> CodeSet {
+        let mut out = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let lo = *a.start().max(b.start());
+            let hi = *a.end().min(b.end());
+            if lo <= hi {
+                out.push(lo..=hi);
+            }
+            // Move past whichever range ends first.
+            if a.end() < b.end() {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        CodeSet { ranges: out }
+    }
+
+    /// Return the codes of `mode`'s codespace, from 1 up, not in the set.
+    pub fn complement(&self, mode: Mode) -> CodeSet {
+        self.complement_within(1, mode.max_inexact_code().0)
+    }
+
+    // The codes of `lo..=hi` not in the set.
+    fn complement_within(&self, lo: u16, hi: u16) -> CodeSet {
+        let mut out = Vec::new();
+        let mut next = lo as u32;
+        for r in self.ranges.iter() {
+            let (start, end) = (r.start().0 as u32, r.end().0 as u32);
+            if start > next {
+                out.push(Code(next as u16)..=Code((start - 1).min(hi as u32) as u16));
+            }
+            next = next.max(end + 1);
+            if next > hi as u32 {
+                return CodeSet { ranges: out };
+            }
+        }
+        out.push(Code(next as u16)..=Code(hi));
+        CodeSet { ranges: out }
+    }
+
+    /// Return the codes in this set and not in `other`.
+    pub fn difference(&self, other: &CodeSet) -> CodeSet {
+        self.intersection(&other.complement_within(0, u16::MAX))
+    }
+}
+
+impl From<CodeRange> for CodeSet {
+    fn from(range: CodeRange) -> Self {
+        Self::from_range(range.into())
+    }
+}
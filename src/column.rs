@@ -0,0 +1,187 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Code columns with nulls.
+//!
+//! Code 0 is never assigned by a dictionary, so it is free to mark null
+//! slots. A [CodeColumn] carries the codes of a column along with the
+//! validity of each slot, and evaluates code predicates by SQL rules: a
+//! predicate on a null is unknown, so null slots are never selected.
+//!
+//! A column read back from storage can be checked against its dictionary
+//! with [CodeColumn::validate] before it is trusted to prune: a corrupt code
+//! that falls outside a predicate's range silently drops rows.
+
+use crate::predicate::CodePredicate;
+use crate::{Code, Dict, ValReq};
+use std::fmt;
+
+/// Options for [CodeColumn::validate_with_options].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ValidateOptions {
+    /// Whether the column was written sorted by value, so that its non-null
+    /// codes must be non-decreasing and each code must form a single run.
+    pub sorted: bool,
+}
+
+/// The first problem [CodeColumn::validate] found in a column.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ColumnError {
+    /// The validity has a different number of slots from the codes.
+    ValidityLength { codes: usize, validity: usize },
+    /// A null slot holds a code other than 0.
+    NullWithCode { slot: usize, code: Code },
+    /// A valid slot holds code 0, which no dictionary assigns.
+    ZeroCode { slot: usize },
+    /// A valid slot holds a code greater than any the dictionary assigns.
+    OutOfRange { slot: usize, code: Code, max: Code },
+    /// A slot's code is less than the one before it in a sorted column.
+    Unsorted { slot: usize, code: Code },
+}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnError::ValidityLength { codes, validity } => write!(
+                f,
+                "column has {} codes but {} validity slots",
+                codes, validity
+            ),
+            ColumnError::NullWithCode { slot, code } => {
+                write!(f, "null slot {} holds code {}", slot, code.0)
+            }
+            ColumnError::ZeroCode { slot } => write!(f, "valid slot {} holds code 0", slot),
+            ColumnError::OutOfRange { slot, code, max } => write!(
+                f,
+                "slot {} holds code {} but the dictionary's greatest is {}",
+                slot, code.0, max.0
+            ),
+            ColumnError::Unsorted { slot, code } => {
+                write!(f, "slot {} holds code {} out of order", slot, code.0)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ColumnError {}
+
+/// A column of codes, with code 0 in null slots.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CodeColumn {
+    /// One code per slot, 0 where the slot is null.
+    pub codes: Vec<Code>,
+    /// Whether each slot is valid (non-null), or None if all are.
+    pub validity: Option<Vec<bool>>,
+}
+
+impl CodeColumn {
+    /// Make a column from `codes`, treating slots with code 0 as null.
+    pub fn from_codes(codes: Vec<Code>) -> Self {
+        let validity = if codes.iter().any(|c| c.0 == 0) {
+            Some(codes.iter().map(|c| c.0 != 0).collect())
+        } else {
+            None
+        };
+        CodeColumn { codes, validity }
+    }
+
+    /// The number of slots.
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Whether there are no slots.
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Whether slot `i` is null.
+    pub fn is_null(&self, i: usize) -> bool {
+        self.validity.as_ref().is_some_and(|v| !v[i])
+    }
+
+    /// The number of null slots.
+    pub fn null_count(&self) -> usize {
+        self.validity
+            .as_ref()
+            .map_or(0, |v| v.iter().filter(|b| !**b).count())
+    }
+
+    /// The least and greatest codes of the non-null slots, or None if there
+    /// are none.
+    pub fn code_range(&self) -> Option<(Code, Code)> {
+        let mut valid = self
+            .codes
+            .iter()
+            .enumerate()
+            .filter(|(i, c)| !self.is_null(*i) && c.0 != 0)
+            .map(|(_, c)| *c);
+        let first = valid.next()?;
+        Some(valid.fold((first, first), |(lo, hi), c| (lo.min(c), hi.max(c))))
+    }
+
+    /// Check that every slot holds a code `dict` could have assigned: 0 in
+    /// exactly the null slots, and nothing above the dictionary's greatest
+    /// code. Returns the first problem found.
+    pub fn validate<T: ValReq>(&self, dict: &Dict<T>) -> Result<(), ColumnError> {
+        self.validate_with_options(dict, &ValidateOptions::default())
+    }
+
+    /// Like [CodeColumn::validate], with the further checks selected in
+    /// `options`.
+    pub fn validate_with_options<T: ValReq>(
+        &self,
+        dict: &Dict<T>,
+        options: &ValidateOptions,
+    ) -> Result<(), ColumnError> {
+        if let Some(v) = &self.validity {
+            if v.len() != self.codes.len() {
+                return Err(ColumnError::ValidityLength {
+                    codes: self.codes.len(),
+                    validity: v.len(),
+                });
+            }
+        }
+        let max = Code(2 * dict.codes.len() as u16 + 1);
+        let mut prev: Option<Code> = None;
+        for (slot, code) in self.codes.iter().copied().enumerate() {
+            if self.is_null(slot) {
+                if code.0 != 0 {
+                    return Err(ColumnError::NullWithCode { slot, code });
+                }
+                continue;
+            }
+            if code.0 == 0 {
+                return Err(ColumnError::ZeroCode { slot });
+            }
+            if code > max {
+                return Err(ColumnError::OutOfRange { slot, code, max });
+            }
+            if options.sorted && prev.is_some_and(|p| code < p) {
+                return Err(ColumnError::Unsorted { slot, code });
+            }
+            prev = Some(code);
+        }
+        Ok(())
+    }
+
+    /// Flag the slots that may satisfy `pred`, definitely or not. Null slots
+    /// are never flagged.
+    pub fn candidates(&self, pred: &CodePredicate) -> Vec<bool> {
+        self.select(|c| pred.is_definite(c) || pred.is_maybe(c))
+    }
+
+    /// Flag the slots that definitely satisfy `pred`. Null slots are never
+    /// flagged.
+    pub fn definite(&self, pred: &CodePredicate) -> Vec<bool> {
+        self.select(|c| pred.is_definite(c))
+    }
+
+    fn select<F: Fn(Code) -> bool>(&self, f: F) -> Vec<bool> {
+        self.codes
+            .iter()
+            .enumerate()
+            .map(|(i, c)| !self.is_null(i) && c.0 != 0 && f(*c))
+            .collect()
+    }
+}
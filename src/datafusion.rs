@@ -0,0 +1,298 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Rewriting DataFusion physical filter expressions onto code columns.
+//!
+//! A table sketched for DataFusion stores, beside each sketched column, a
+//! column of its codes: `UInt8` for [Mode::Byte](crate::Mode::Byte) and
+//! `UInt16` otherwise, null exactly where the value is null. A
+//! [CodeRewriter] knows which code column sketches which value column, and
+//! with which dictionary, and rewrites each comparison of a sketched column
+//! with a literal (`=`, `<`, `<=`, `>`, `>=`, either way round) and each
+//! `IN` list of literals into
+//!
+//! ```text
+//! candidates AND (definite OR original)
+//! ```
+//!
+//! where `candidates` and `definite` test the code column against the code
+//! sets of the comparison's [CodePredicate]. Rows whose codes rule them out
+//! fail the first, cheap, test; rows whose codes settle them pass the
+//! second; and only the rest evaluate the original comparison on their
+//! values. The rewritten expression is equivalent to the original, nulls
+//! included, so it may stand anywhere in a filter. Other expressions are
+//! left as they are.
+
+use crate::predicate::{CodePredicate, Predicate};
+use crate::{Code, Dict, ValReq};
+use datafusion_common::arrow::datatypes::{DataType, Schema};
+use datafusion_common::tree_node::{Transformed, TreeNode};
+use datafusion_common::{plan_err, Result, ScalarValue};
+use datafusion_expr::Operator;
+use datafusion_physical_expr::expressions::{lit, BinaryExpr, Column, InListExpr, Literal};
+use datafusion_physical_expr::PhysicalExpr;
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// Dictionary values a [CodeRewriter] can read out of DataFusion literals.
+pub trait FromScalar: ValReq + Send + Sync + 'static {
+    /// The value of `scalar`, or None if it is null, of another type, or out
+    /// of range.
+    fn from_scalar(scalar: &ScalarValue) -> Option<Self>;
+}
+
+macro_rules! from_integer_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl FromScalar for $t {
+                fn from_scalar(scalar: &ScalarValue) -> Option<Self> {
+                    let v = match scalar {
+                        ScalarValue::Int8(Some(v)) => i128::from(*v),
+                        ScalarValue::Int16(Some(v)) => i128::from(*v),
+                        ScalarValue::Int32(Some(v)) => i128::from(*v),
+                        ScalarValue::Int64(Some(v)) => i128::from(*v),
+                        ScalarValue::UInt8(Some(v)) => i128::from(*v),
+                        ScalarValue::UInt16(Some(v)) => i128::from(*v),
+                        ScalarValue::UInt32(Some(v)) => i128::from(*v),
+                        ScalarValue::UInt64(Some(v)) => i128::from(*v),
+                        _ => return None,
+                    };
+                    <$t>::try_from(v).ok()
+                }
+            }
+        )*
+    };
+}
+
+from_integer_scalar!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl FromScalar for String {
+    fn from_scalar(scalar: &ScalarValue) -> Option<Self> {
+        match scalar {
+            ScalarValue::Utf8(Some(s))
+            | ScalarValue::LargeUtf8(Some(s))
+            | ScalarValue::Utf8View(Some(s)) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A `Dict<T>` with its value type hidden behind literal conversion.
+trait ScalarDict: Send + Sync {
+    fn rewrite(&self, pred: &Predicate<ScalarValue>) -> Option<CodePredicate>;
+}
+
+impl<T: FromScalar> ScalarDict for Dict<T> {
+    fn rewrite(&self, pred: &Predicate<ScalarValue>) -> Option<CodePredicate> {
+        let pred = match pred {
+            Predicate::Eq(v) => Predicate::Eq(T::from_scalar(v)?),
+            Predicate::Lt(v) => Predicate::Lt(T::from_scalar(v)?),
+            Predicate::Le(v) => Predicate::Le(T::from_scalar(v)?),
+            Predicate::Gt(v) => Predicate::Gt(T::from_scalar(v)?),
+            Predicate::Ge(v) => Predicate::Ge(T::from_scalar(v)?),
+            Predicate::Between(lo, hi) => {
+                Predicate::Between(T::from_scalar(lo)?, T::from_scalar(hi)?)
+            }
+            Predicate::InSet(values) => {
+                Predicate::InSet(values.iter().map(T::from_scalar).collect::<Option<_>>()?)
+            }
+        };
+        Some(Dict::rewrite(self, &pred))
+    }
+}
+
+struct Sketched {
+    value_column: String,
+    code_column: String,
+    dict: Box<dyn ScalarDict>,
+}
+
+/// Rewrites comparisons on sketched columns into comparisons on their codes,
+/// as described in the [module documentation](self).
+#[derive(Default)]
+pub struct CodeRewriter {
+    columns: Vec<Sketched>,
+}
+
+impl CodeRewriter {
+    /// Make a rewriter with no sketched columns.
+    pub fn new() -> Self {
+        CodeRewriter::default()
+    }
+
+    /// Register `code_column` as holding the codes `dict` assigns to the
+    /// values of `value_column`.
+    pub fn add_column<T: FromScalar>(
+        &mut self,
+        value_column: &str,
+        code_column: &str,
+        dict: Dict<T>,
+    ) -> &mut Self {
+        self.columns.push(Sketched {
+            value_column: value_column.to_string(),
+            code_column: code_column.to_string(),
+            dict: Box::new(dict),
+        });
+        self
+    }
+
+    /// Rewrite every supported comparison in `expr`, a predicate over
+    /// batches of `schema`. Fails if a sketched column is compared but its
+    /// code column is missing from `schema` or is neither `UInt8` nor
+    /// `UInt16`, or holds codes too wide for its type.
+    pub fn rewrite(
+        &self,
+        expr: Arc<dyn PhysicalExpr>,
+        schema: &Schema,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        expr.transform_up(|e| self.rewrite_node(e, schema))
+            .map(|t| t.data)
+    }
+
+    fn rewrite_node(
+        &self,
+        expr: Arc<dyn PhysicalExpr>,
+        schema: &Schema,
+    ) -> Result<Transformed<Arc<dyn PhysicalExpr>>> {
+        let (column, pred) = match comparison(&expr) {
+            Some(c) => c,
+            None => return Ok(Transformed::no(expr)),
+        };
+        let sketched = match self.columns.iter().find(|s| s.value_column == column) {
+            Some(s) => s,
+            None => return Ok(Transformed::no(expr)),
+        };
+        let code_pred = match sketched.dict.rewrite(&pred) {
+            Some(p) => p,
+            None => return Ok(Transformed::no(expr)),
+        };
+        let codes = CodeColumn::new(&sketched.code_column, schema)?;
+        let candidates = code_pred.candidate_set();
+        let candidates = codes.in_ranges(candidates.ranges())?;
+        if code_pred.maybe.is_empty() {
+            return Ok(Transformed::yes(candidates));
+        }
+        let rest = if code_pred.definite.is_empty() {
+            expr
+        } else {
+            let definite = codes.in_ranges(code_pred.definite_set().ranges())?;
+            binary(definite, Operator::Or, expr)
+        };
+        Ok(Transformed::yes(binary(candidates, Operator::And, rest)))
+    }
+}
+
+/// The value column and predicate of a comparison of a column with
+/// literals, if `expr` is one.
+fn comparison(expr: &Arc<dyn PhysicalExpr>) -> Option<(String, Predicate<ScalarValue>)> {
+    if let Some(b) = expr.downcast_ref::<BinaryExpr>() {
+        let (column, value, op) = match (column_name(b.left()), literal(b.right())) {
+            (Some(c), Some(v)) => (c, v, *b.op()),
+            _ => (column_name(b.right())?, literal(b.left())?, b.op().swap()?),
+        };
+        let pred = match op {
+            Operator::Eq => Predicate::Eq(value),
+            Operator::Lt => Predicate::Lt(value),
+            Operator::LtEq => Predicate::Le(value),
+            Operator::Gt => Predicate::Gt(value),
+            Operator::GtEq => Predicate::Ge(value),
+            _ => return None,
+        };
+        return Some((column, pred));
+    }
+    let list = expr.downcast_ref::<InListExpr>()?;
+    if list.negated() {
+        return None;
+    }
+    let values = list.list().iter().map(literal).collect::<Option<_>>()?;
+    Some((column_name(list.expr())?, Predicate::InSet(values)))
+}
+
+fn column_name(expr: &Arc<dyn PhysicalExpr>) -> Option<String> {
+    expr.downcast_ref::<Column>().map(|c| c.name().to_string())
+}
+
+fn literal(expr: &Arc<dyn PhysicalExpr>) -> Option<ScalarValue> {
+    let value = expr.downcast_ref::<Literal>()?.value();
+    if value.is_null() {
+        None
+    } else {
+        Some(value.clone())
+    }
+}
+
+fn binary(
+    left: Arc<dyn PhysicalExpr>,
+    op: Operator,
+    right: Arc<dyn PhysicalExpr>,
+) -> Arc<dyn PhysicalExpr> {
+    Arc::new(BinaryExpr::new(left, op, right))
+}
+
+/// A code column of a schema, and the width of its codes.
+struct CodeColumn {
+    column: Arc<dyn PhysicalExpr>,
+    wide: bool,
+}
+
+impl CodeColumn {
+    fn new(name: &str, schema: &Schema) -> Result<Self> {
+        let column = Column::new_with_schema(name, schema)?;
+        let wide = match schema.field(column.index()).data_type() {
+            DataType::UInt8 => false,
+            DataType::UInt16 => true,
+            other => {
+                return plan_err!(
+                    "code column {} has type {}, not UInt8 or UInt16",
+                    name,
+                    other
+                )
+            }
+        };
+        Ok(CodeColumn {
+            column: Arc::new(column),
+            wide,
+        })
+    }
+
+    fn code(&self, code: Code) -> Result<Arc<dyn PhysicalExpr>> {
+        if self.wide {
+            return Ok(lit(ScalarValue::UInt16(Some(code.0))));
+        }
+        match u8::try_from(code.0) {
+            Ok(c) => Ok(lit(ScalarValue::UInt8(Some(c)))),
+            Err(_) => plan_err!("code {} does not fit a UInt8 code column", code.0),
+        }
+    }
+
+    /// An expression testing whether the code lies in any of `ranges`. With
+    /// no ranges it tests for code 0, which no dictionary assigns, to stay
+    /// null on null rows.
+    fn in_ranges(&self, ranges: &[RangeInclusive<Code>]) -> Result<Arc<dyn PhysicalExpr>> {
+        if ranges.is_empty() {
+            return Ok(binary(
+                self.column.clone(),
+                Operator::Eq,
+                self.code(Code(0))?,
+            ));
+        }
+        let mut tests = Vec::with_capacity(ranges.len());
+        for r in ranges {
+            let test = if r.start() == r.end() {
+                binary(self.column.clone(), Operator::Eq, self.code(*r.start())?)
+            } else {
+                binary(
+                    binary(self.column.clone(), Operator::GtEq, self.code(*r.start())?),
+                    Operator::And,
+                    binary(self.column.clone(), Operator::LtEq, self.code(*r.end())?),
+                )
+            };
+            tests.push(test);
+        }
+        Ok(tests
+            .into_iter()
+            .reduce(|a, b| binary(a, Operator::Or, b))
+            .expect("ranges is not empty"))
+    }
+}
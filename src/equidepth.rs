@@ -0,0 +1,101 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! An order-preserving dictionary with no exact codes, only intervals.
+//!
+//! Exact codes pay for themselves when a column has values frequent enough
+//! to be worth answering equality predicates on exactly. A column of
+//! continuous measurements has no such values, and is only ever filtered by
+//! range. An [EquiDepthDict] spends every code on an interval instead,
+//! splitting the sample into equal-depth bins at its quantiles, which
+//! doubles the number of intervals (and so the resolution of range
+//! predicates) of a [Dict](crate::Dict) in the same mode.
+//!
+//! Code `c`, from 1 up, stands for the half-open interval from boundary
+//! `c - 2` (inclusive) to boundary `c - 1` (exclusive), with code 1
+//! unbounded below and the last code unbounded above. Code 0 is unused, as
+//! in a [Dict](crate::Dict). No code is exact, so [Code::is_exact] means
+//! nothing here.
+
+use crate::predicate::CodePredicate;
+use crate::workload::RangePredicate;
+use crate::{Code, Mode, ValReq};
+use std::ops::Bound;
+
+/// A dictionary of equal-depth intervals. See the [module
+/// documentation](self).
+pub struct EquiDepthDict<T: ValReq> {
+    /// The mode the dictionary was built in.
+    pub mode: Mode,
+
+    /// The sorted, distinct values at which each interval after the first
+    /// begins.
+    pub boundaries: Vec<T>,
+}
+
+impl<T: ValReq> EquiDepthDict<T> {
+    /// Build a dictionary with a given [Mode] over a provided sample, with
+    /// boundaries at equally spaced quantiles of the sample. A value more
+    /// frequent than one bin's share may span several quantiles, leaving
+    /// fewer intervals than the mode allows.
+    pub fn new(mode: Mode, mut sample: Vec<T>) -> Self {
+        sample.sort_unstable();
+        let nbins = mode.max_inexact_code().0 as usize;
+        let mut boundaries: Vec<T> = Vec::with_capacity(nbins - 1);
+        if !sample.is_empty() {
+            for i in 1..nbins {
+                let v = &sample[(i * sample.len()) / nbins];
+                // The first value can't begin an interval: nothing would be
+                // left for code 1.
+                if boundaries.last().map_or(*v > sample[0], |b| b < v) {
+                    boundaries.push(v.clone());
+                }
+            }
+        }
+        boundaries.shrink_to_fit();
+        EquiDepthDict { mode, boundaries }
+    }
+
+    /// Look up the code for a value.
+    pub fn encode(&self, query: &T) -> Code {
+        Code(self.boundaries.partition_point(|b| b <= query) as u16 + 1)
+    }
+
+    /// The greatest code the dictionary produces.
+    pub fn max_code(&self) -> Code {
+        Code(self.boundaries.len() as u16 + 1)
+    }
+
+    // Whether `v` is the first value of code `c`'s interval.
+    fn starts_interval(&self, c: Code, v: &T) -> bool {
+        c.0 >= 2 && self.boundaries[c.0 as usize - 2] == *v
+    }
+
+    /// Rewrite a range predicate onto codes. Codes whose whole interval
+    /// satisfies the predicate are definite; those at either end that only
+    /// partly do are maybe.
+    pub fn rewrite(&self, pred: &RangePredicate<T>) -> CodePredicate {
+        let (lo, lo_definite) = match &pred.0 {
+            Bound::Unbounded => (Code(1), true),
+            Bound::Included(v) => {
+                let c = self.encode(v);
+                (c, self.starts_interval(c, v))
+            }
+            Bound::Excluded(v) => (self.encode(v), false),
+        };
+        let (hi, hi_definite) = match &pred.1 {
+            Bound::Unbounded => (self.max_code(), true),
+            Bound::Included(v) => (self.encode(v), false),
+            // Excluding the first value of an interval excludes all of it.
+            Bound::Excluded(v) => {
+                let c = self.encode(v);
+                if self.starts_interval(c, v) {
+                    (Code(c.0 - 1), true)
+                } else {
+                    (c, false)
+                }
+            }
+        };
+        CodePredicate::from_code_range(lo, hi, lo_definite, hi_definite)
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Families of dictionaries at several granularities whose code boundaries
+//! nest, so codes at one level can be mapped exactly to codes at another.
+//!
+//! Each coarser level's exact values are a subset of the next finer level's,
+//! so every boundary between codes at a coarse level is also a boundary at
+//! every finer level. Consequently each fine code lies within exactly one
+//! coarse code, and each coarse code covers a contiguous range of fine codes.
+//! A query planner can evaluate a predicate against coarse codes first and
+//! refine only the coarse codes that remain inconclusive.
+
+use crate::{Code, Dict, Mode, ValReq};
+use std::ops::RangeInclusive;
+
+/// A family of nested dictionaries over the same sample; see the
+/// [module documentation](self).
+pub struct DictFamily<T: ValReq> {
+    /// The dictionaries, coarsest first.
+    pub levels: Vec<Dict<T>>,
+
+    // For each level but the last, the code at that level of each code at the
+    // next finer level, indexed by the finer code.
+    coarsen: Vec<Vec<Code>>,
+}
+
+// The code at `coarse` of the code `c` at `fine`, where the exact values of
+// `coarse` are a subset of those of `fine`.
+fn coarsen_code<T: ValReq>(fine: &Dict<T>, coarse: &Dict<T>, c: Code) -> Code {
+    let i = c.0 as usize / 2;
+    if c.is_exact() {
+        return coarse.encode(&fine.codes[i - 1]);
+    }
+    // Inexact fine code 2i+1 lies just below fine.codes[i], and nothing of
+    // `coarse` lies between it and fine.codes[i-1].
+    match fine.codes.get(i) {
+        None => Code(2 * coarse.codes.len() as u16 + 1),
+        Some(upper) => {
+            let e = coarse.encode(upper);
+            if e.is_exact() {
+                Code(e.0 - 1)
+            } else {
+                e
+            }
+        }
+    }
+}
+
+// Choose at most `n` of `fine`'s exact values: cut the sample mass into `n`
+// equal bins, by the position of each exact code in code order, and take the
+// exact value with the most mass in each bin.
+fn select<T: ValReq>(fine: &Dict<T>, counts: &[usize], n: usize) -> Vec<T> {
+    let total: usize = counts.iter().sum();
+    let mut chosen: Vec<T> = Vec::with_capacity(n);
+    let mut best: Option<(usize, usize)> = None; // (bin, index into fine.codes)
+    let mut before = 0;
+    for (c, count) in counts.iter().enumerate().skip(1) {
+        if c % 2 == 0 {
+            let i = c / 2 - 1;
+            let bin = (before * n)
+                .checked_div(total)
+                .unwrap_or(i * n / fine.codes.len());
+            let bin = bin.min(n - 1);
+            match best {
+                Some((b, j)) if b == bin => {
+                    if counts[2 * (j + 1)] < *count {
+                        best = Some((bin, i));
+                    }
+                }
+                Some((_, j)) => {
+                    chosen.push(fine.codes[j].clone());
+                    best = Some((bin, i));
+                }
+                None => best = Some((bin, i)),
+            }
+        }
+        before += count;
+    }
+    if let Some((_, j)) = best {
+        chosen.push(fine.codes[j].clone());
+    }
+    chosen
+}
+
+impl<T: ValReq> DictFamily<T> {
+    /// Build a family with one level for each entry of `exact_codes`, giving
+    /// the number of exact codes at that level; a level with `n` exact codes
+    /// uses `2n + 1` codes in all, so `[7, 127, 32767]` gives levels of 15,
+    /// 255 and 65535 codes. Levels of up to 127 exact codes are built in
+    /// [Mode::Byte], larger ones in [Mode::Word].
+    ///
+    /// The finest level is built from `sample` as by [Dict::new]; each coarser
+    /// level then picks its exact values from among those of the next finer
+    /// level, spreading them evenly over the sample's mass and preferring the
+    /// most frequent values.
+    ///
+    /// Panics if `exact_codes` is empty or any entry is zero or over 32767.
+    pub fn new(sample: Vec<T>, exact_codes: &[usize]) -> Self {
+        assert!(!exact_codes.is_empty());
+        let mut sizes = exact_codes.to_vec();
+        sizes.sort_unstable();
+        sizes.dedup();
+        assert!(sizes[0] != 0);
+        assert!(sizes[sizes.len() - 1] <= Mode::Word.num_exact_codes());
+        let mode_for = |n: usize| {
+            if n <= Mode::Byte.num_exact_codes() {
+                Mode::Byte
+            } else {
+                Mode::Word
+            }
+        };
+
+        // Count the sample's mass on each of a dictionary's codes.
+        let counts = |d: &Dict<T>| {
+            let mut counts = vec![0usize; 2 * d.codes.len() + 2];
+            for v in sample.iter() {
+                counts[d.encode(v).0 as usize] += 1;
+            }
+            counts
+        };
+
+        // The finest level may ask for fewer codes than its mode holds, in
+        // which case it is thinned out the same way as the coarser levels.
+        let finest = *sizes.last().unwrap();
+        let mut fine = Dict::new(mode_for(finest), sample.clone());
+        if fine.codes.len() > finest {
+            fine = Dict::from_codes(fine.mode, select(&fine, &counts(&fine), finest));
+        }
+        let mut levels = vec![fine];
+        for &n in sizes.iter().rev().skip(1) {
+            let fine = levels.last().unwrap();
+            let mut codes = select(fine, &counts(fine), n);
+            codes.shrink_to_fit();
+            levels.push(Dict::from_codes(mode_for(n), codes));
+        }
+        levels.reverse();
+
+        let coarsen = levels
+            .windows(2)
+            .map(|w| {
+                (0..=2 * w[1].codes.len() + 1)
+                    .map(|c| {
+                        if c == 0 {
+                            Code(0)
+                        } else {
+                            coarsen_code(&w[1], &w[0], Code(c as u16))
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        DictFamily { levels, coarsen }
+    }
+
+    /// Encode `value` at level `level`.
+    pub fn encode(&self, level: usize, value: &T) -> Code {
+        self.levels[level].encode(value)
+    }
+
+    /// Map `code`, a code at level `from`, to the code at the coarser (or
+    /// same) level `to` that contains it.
+    ///
+    /// Panics if `to > from`.
+    pub fn coarsen(&self, from: usize, to: usize, code: Code) -> Code {
+        assert!(to <= from);
+        let mut c = code;
+        for level in (to..from).rev() {
+            c = self.coarsen[level][c.0 as usize];
+        }
+        c
+    }
+
+    /// Map `code`, a code at level `from`, to the inclusive range of codes at
+    /// the finer (or same) level `to` that it contains. The range is a single
+    /// code if `code` is exact.
+    ///
+    /// Panics if `to < from`.
+    pub fn refine(&self, from: usize, to: usize, code: Code) -> RangeInclusive<Code> {
+        assert!(to >= from);
+        refine_code(&self.levels[from], &self.levels[to], code)
+    }
+}
+
+// The inclusive range of codes at `fine` contained in the code `code` at
+// `coarse`, where the exact values of `coarse` are a subset of those of
+// `fine`.
+pub(crate) fn refine_code<T: ValReq>(
+    coarse: &Dict<T>,
+    fine: &Dict<T>,
+    code: Code,
+) -> RangeInclusive<Code> {
+    let i = code.0 as usize / 2;
+    if code.is_exact() {
+        let c = fine.encode(&coarse.codes[i - 1]);
+        return c..=c;
+    }
+    // Inexact coarse code 2i+1 lies strictly between coarse.codes[i-1]
+    // and coarse.codes[i], both of which are exact at the finer level.
+    let lo = match i {
+        0 => Code(1),
+        _ => Code(fine.encode(&coarse.codes[i - 1]).0 + 1),
+    };
+    let hi = match coarse.codes.get(i) {
+        None => Code(2 * fine.codes.len() as u16 + 1),
+        Some(v) => Code(fine.encode(v).0 - 1),
+    };
+    lo..=hi
+}
@@ -0,0 +1,261 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! A C ABI for consuming the crate as a prebuilt shared library.
+//!
+//! Build one with `cargo rustc --release --features ffi --crate-type cdylib`.
+//! The declarations are in `include/ordbog.h`, and `include/ordbog.hpp`
+//! wraps them in move-only C++ classes that free their handles on
+//! destruction.
+//!
+//! Dictionaries are opaque handles, one type per value type. The rules are:
+//!
+//!   - A handle returned by a `_new` function is owned by the caller, who
+//!     must release it exactly once with the matching `_free` function.
+//!     `_free` accepts null.
+//!   - A dictionary is immutable, so a handle may be used from any number of
+//!     threads at once, until it is freed.
+//!   - Arrays passed in are borrowed for the duration of the call only.
+//!   - Functions report failure through an [OrdbogStatus] or a null handle;
+//!     they never unwind into the caller.
+//!
+//! Structs filled in by the library begin with a `struct_size` the caller
+//! sets to the size of its own definition. The library writes only fields
+//! that fit within it and then sets it to the number of bytes written, so
+//! callers built against older or newer headers keep working as fields are
+//! appended. [ORDBOG_ABI_VERSION] changes only on incompatible changes.
+
+use crate::{Dict, DictF64, Mode, ValReq};
+use float_ord::FloatOrd;
+use std::mem::size_of;
+
+/// The version of this ABI, returned by [ordbog_abi_version].
+pub const ORDBOG_ABI_VERSION: u32 = 1;
+
+/// The result of a call.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum OrdbogStatus {
+    Ok = 0,
+    NullPointer = 1,
+    BadMode = 2,
+    BadStructSize = 3,
+}
+
+/// A description of a dictionary, filled in by `ordbog_dict_*_info`.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct OrdbogDictInfo {
+    /// Set by the caller to `sizeof(OrdbogDictInfo)`; set by the library to
+    /// the number of bytes it wrote.
+    pub struct_size: u32,
+    /// The ABI version of the library that filled in the struct.
+    pub abi_version: u32,
+    /// 8 for byte mode, 12 for packed mode, 16 for word mode.
+    pub mode_bits: u32,
+    /// The number of exact codes the dictionary assigned.
+    pub num_exact_codes: u32,
+    /// The greatest code the dictionary produces.
+    pub max_code: u32,
+}
+
+/// An opaque dictionary over signed 64-bit integers.
+pub struct OrdbogDictI64(Dict<i64>);
+
+/// An opaque dictionary over doubles, ordered as by [DictF64].
+pub struct OrdbogDictF64(Dict<DictF64>);
+
+fn mode_of(bits: u32) -> Option<Mode> {
+    std::convert::TryFrom::try_from(bits)
+        .ok()
+        .and_then(Mode::from_bits)
+}
+
+unsafe fn slice<'a, T>(ptr: *const T, len: usize) -> Option<&'a [T]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(std::slice::from_raw_parts(ptr, len))
+    }
+}
+
+unsafe fn encode<T: ValReq, S, F: Fn(&S) -> T>(
+    dict: Option<&Dict<T>>,
+    values: *const S,
+    len: usize,
+    out: *mut u16,
+    key: F,
+) -> OrdbogStatus {
+    let (dict, values) = match (dict, slice(values, len)) {
+        (Some(d), Some(v)) if len == 0 || !out.is_null() => (d, v),
+        _ => return OrdbogStatus::NullPointer,
+    };
+    for (i, v) in values.iter().enumerate() {
+        *out.add(i) = dict.encode(&key(v)).0;
+    }
+    OrdbogStatus::Ok
+}
+
+unsafe fn info<T: ValReq>(dict: Option<&Dict<T>>, out: *mut OrdbogDictInfo) -> OrdbogStatus {
+    let (dict, out) = match (dict, out.as_mut()) {
+        (Some(d), Some(o)) => (d, o),
+        _ => return OrdbogStatus::NullPointer,
+    };
+    // The first field is always present.
+    if (out.struct_size as usize) < size_of::<u32>() {
+        return OrdbogStatus::BadStructSize;
+    }
+    let full = OrdbogDictInfo {
+        struct_size: 0,
+        abi_version: ORDBOG_ABI_VERSION,
+        mode_bits: dict.mode.bits() as u32,
+        num_exact_codes: dict.codes.len() as u32,
+        max_code: 2 * dict.codes.len() as u32 + 1,
+    };
+    let n = (out.struct_size as usize).min(size_of::<OrdbogDictInfo>());
+    // Copy whole fields only, after the size field.
+    let n = n - n % size_of::<u32>();
+    let src = &full as *const OrdbogDictInfo as *const u8;
+    let dst = out as *mut OrdbogDictInfo as *mut u8;
+    let skip = size_of::<u32>();
+    std::ptr::copy_nonoverlapping(src.add(skip), dst.add(skip), n - skip);
+    out.struct_size = n as u32;
+    OrdbogStatus::Ok
+}
+
+/// Return [ORDBOG_ABI_VERSION].
+#[no_mangle]
+pub extern "C" fn ordbog_abi_version() -> u32 {
+    ORDBOG_ABI_VERSION
+}
+
+/// Build a dictionary with `mode_bits` (8, 12 or 16) from the `len` values at
+/// `sample`. Returns null if the mode is invalid or `sample` is null with a
+/// nonzero `len`.
+///
+/// # Safety
+///
+/// `sample` must point to `len` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_i64_new(
+    mode_bits: u32,
+    sample: *const i64,
+    len: usize,
+) -> *mut OrdbogDictI64 {
+    match (mode_of(mode_bits), slice(sample, len)) {
+        (Some(mode), Some(s)) => {
+            Box::into_raw(Box::new(OrdbogDictI64(Dict::new(mode, s.to_vec()))))
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Release a dictionary built by [ordbog_dict_i64_new].
+///
+/// # Safety
+///
+/// `dict` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_i64_free(dict: *mut OrdbogDictI64) {
+    if !dict.is_null() {
+        drop(Box::from_raw(dict));
+    }
+}
+
+/// Encode the `len` values at `values` into the `len` codes at `out`.
+///
+/// # Safety
+///
+/// `dict` must be a live handle, `values` must point to `len` readable
+/// values, and `out` to `len` writable codes.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_i64_encode(
+    dict: *const OrdbogDictI64,
+    values: *const i64,
+    len: usize,
+    out: *mut u16,
+) -> OrdbogStatus {
+    encode(dict.as_ref().map(|d| &d.0), values, len, out, |v| *v)
+}
+
+/// Describe a dictionary; see [OrdbogDictInfo].
+///
+/// # Safety
+///
+/// `dict` must be a live handle and `out` must point to a writable struct of
+/// at least `out->struct_size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_i64_info(
+    dict: *const OrdbogDictI64,
+    out: *mut OrdbogDictInfo,
+) -> OrdbogStatus {
+    info(dict.as_ref().map(|d| &d.0), out)
+}
+
+/// Build a dictionary with `mode_bits` (8, 12 or 16) from the `len` values at
+/// `sample`. Returns null if the mode is invalid or `sample` is null with a
+/// nonzero `len`.
+///
+/// # Safety
+///
+/// `sample` must point to `len` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_f64_new(
+    mode_bits: u32,
+    sample: *const f64,
+    len: usize,
+) -> *mut OrdbogDictF64 {
+    match (mode_of(mode_bits), slice(sample, len)) {
+        (Some(mode), Some(s)) => {
+            let dict = Dict::new_with_key(mode, s, |f| DictF64(FloatOrd(*f)));
+            Box::into_raw(Box::new(OrdbogDictF64(dict)))
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Release a dictionary built by [ordbog_dict_f64_new].
+///
+/// # Safety
+///
+/// `dict` must be null or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_f64_free(dict: *mut OrdbogDictF64) {
+    if !dict.is_null() {
+        drop(Box::from_raw(dict));
+    }
+}
+
+/// Encode the `len` values at `values` into the `len` codes at `out`.
+///
+/// # Safety
+///
+/// `dict` must be a live handle, `values` must point to `len` readable
+/// values, and `out` to `len` writable codes.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_f64_encode(
+    dict: *const OrdbogDictF64,
+    values: *const f64,
+    len: usize,
+    out: *mut u16,
+) -> OrdbogStatus {
+    encode(dict.as_ref().map(|d| &d.0), values, len, out, |f| {
+        DictF64(FloatOrd(*f))
+    })
+}
+
+/// Describe a dictionary; see [OrdbogDictInfo].
+///
+/// # Safety
+///
+/// `dict` must be a live handle and `out` must point to a writable struct of
+/// at least `out->struct_size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ordbog_dict_f64_info(
+    dict: *const OrdbogDictF64,
+    out: *mut OrdbogDictInfo,
+) -> OrdbogStatus {
+    info(dict.as_ref().map(|d| &d.0), out)
+}
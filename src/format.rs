@@ -0,0 +1,568 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! A versioned binary format for dictionaries.
+//!
+//! Dictionaries are kept on disk next to the code columns they encoded, and
+//! those files outlive the library version that wrote them. The format is:
+//!
+//!   - a 16-byte header: the magic bytes `ORDB`, the format version (u16),
+//!     the required-feature flags (u16), the mode (u8: its
+//!     [bits](Mode::bits), 8, 12 or 16), a reserved zero byte, the number
+//!     of exact codes (u32), and the number of
+//!     [reserved codes](crate::BuildOptions::reserved_codes) (u16) if the
+//!     flag [FLAG_RESERVED_CODES] is set, or else zero. The flag
+//!     [FLAG_DENSE_CODES] marks a dictionary built with
+//!     [dense codes](crate::BuildOptions::dense_codes);
+//!   - the exact-code values in order, each a u32 length followed by that
+//!     many bytes of the value's [BinaryValue] encoding;
+//!   - any number of trailing sections, each a u16 tag, a u32 length and
+//!     that many bytes of payload, running to the end of the buffer.
+//!
+//! All integers are little-endian. The compatibility contract is:
+//!
+//!   - A reader accepts any version in [READABLE_VERSIONS], and refuses a
+//!     file with a flag bit it doesn't know. Flags mark changes an older
+//!     reader must not ignore.
+//!   - A reader skips sections with tags it doesn't know. Sections carry
+//!     additions an older reader can safely ignore.
+//!   - The version is only bumped when the layout above changes
+//!     incompatibly.
+//!
+//! The same sample builds a byte-identical serialized dictionary on every
+//! platform, whatever order the sample is given in. The rules that make it
+//! so are:
+//!
+//!   - Construction orders values only by `T`'s [Ord], which must be total
+//!     and distinguish any two values that serialize differently. Counting
+//!     uses integers only.
+//!   - Floats ([DictF32], [DictF64]) order by their [FloatOrd] bit patterns
+//!     and serialize as those bits: `-0.0` and `0.0` are distinct values, as
+//!     are NaNs with different signs or payloads, and none is canonicalized
+//!     to another.
+//!   - Strings order and serialize as their UTF-8 bytes, which is code point
+//!     order. No locale collation or Unicode normalization is applied;
+//!     callers wanting either must apply it before encoding.
+//!   - Integers serialize at their declared width. There is deliberately no
+//!     [BinaryValue] for `usize` or `isize`.
+//!
+//! [SampleSummary]s, which shards of a distributed build exchange in place
+//! of their samples, have a format of their own under the same contract:
+//!
+//!   - a 20-byte header: the magic bytes `ORDS`, the format version (u16),
+//!     the required-feature flags (u16), the number of entries (u32) and the
+//!     summary's `above` mass (u64);
+//!   - the entries in order, each its `count` (u64), its `below` mass (u64)
+//!     and its value as a u32 length followed by that many bytes;
+//!   - anything after the entries is ignored, leaving room for additions.
+
+use crate::hashed::{Fnv1a, FNV_OFFSET};
+use crate::summary::{SampleSummary, SummaryEntry};
+use crate::{BuildOptions, Dict, DictF32, DictF64, Mode, ValReq};
+use float_ord::FloatOrd;
+use std::fmt;
+use std::hash::Hasher;
+use std::ops::RangeInclusive;
+
+/// The magic bytes that begin every serialized dictionary.
+pub const MAGIC: [u8; 4] = *b"ORDB";
+
+/// The format version this crate writes.
+pub const WRITE_VERSION: u16 = 1;
+
+/// The format versions this crate can read.
+pub const READABLE_VERSIONS: RangeInclusive<u16> = 1..=1;
+
+/// The required-feature flags this crate understands in every header. None
+/// are defined yet.
+pub const KNOWN_FLAGS: u16 = 0;
+
+/// The flag marking a dictionary built with a band of
+/// [reserved codes](crate::BuildOptions::reserved_codes), whose size the
+/// header's last u16 holds. It is only set when the band is nonempty, so
+/// dictionaries without one read as they always have.
+pub const FLAG_RESERVED_CODES: u16 = 1;
+
+/// The flag marking a dictionary built with
+/// [dense codes](crate::BuildOptions::dense_codes), whose reserved band
+/// and number of exact codes are counted in the dense layout.
+pub const FLAG_DENSE_CODES: u16 = 2;
+
+/// The flags this crate understands in a dictionary's header.
+pub const DICT_KNOWN_FLAGS: u16 = KNOWN_FLAGS | FLAG_RESERVED_CODES | FLAG_DENSE_CODES;
+
+/// The size of the fixed header.
+pub const HEADER_LEN: usize = 16;
+
+/// The magic bytes that begin every serialized [SampleSummary].
+pub const SUMMARY_MAGIC: [u8; 4] = *b"ORDS";
+
+/// The size of the fixed header of a serialized [SampleSummary].
+pub const SUMMARY_HEADER_LEN: usize = 20;
+
+/// Why a buffer could not be read as a dictionary.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FormatError {
+    /// The buffer doesn't begin with [MAGIC] (or [SUMMARY_MAGIC]).
+    BadMagic,
+    /// The format version is outside [READABLE_VERSIONS].
+    UnsupportedVersion(u16),
+    /// The flags include bits outside [KNOWN_FLAGS] (or, in a dictionary,
+    /// [DICT_KNOWN_FLAGS]).
+    UnsupportedFlags(u16),
+    /// The mode byte is not 8, 12 or 16.
+    BadMode(u8),
+    /// The header claims more exact codes or reserved codes than the mode
+    /// allows, or the values (or summary entries) are out of order.
+    BadCodes,
+    /// A value's bytes don't decode.
+    BadValue,
+    /// The buffer ends in the middle of the header, a value or a section.
+    Truncated,
+    /// A section doesn't match its stored checksum.
+    BadChecksum,
+    /// A compressed section doesn't decompress.
+    BadCompression,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::BadMagic => write!(f, "not a serialized dictionary"),
+            FormatError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            FormatError::UnsupportedFlags(b) => write!(f, "unsupported format flags {:#06x}", b),
+            FormatError::BadMode(m) => write!(f, "bad mode {}", m),
+            FormatError::BadCodes => write!(f, "bad exact codes"),
+            FormatError::BadValue => write!(f, "undecodable value"),
+            FormatError::Truncated => write!(f, "truncated dictionary"),
+            FormatError::BadChecksum => write!(f, "checksum mismatch"),
+            FormatError::BadCompression => write!(f, "undecompressable section"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Types whose values can be stored in the binary format.
+pub trait BinaryValue: Sized {
+    /// Append the value's encoding to `out`.
+    fn write_bytes(&self, out: &mut Vec<u8>);
+    /// Decode a value from exactly the bytes `write_bytes` produced.
+    fn read_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! int_binary_value {
+    ($($t:ty),*) => {$(
+        impl BinaryValue for $t {
+            fn write_bytes(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+            fn read_bytes(bytes: &[u8]) -> Option<Self> {
+                let mut buf = [0u8; std::mem::size_of::<$t>()];
+                if bytes.len() != buf.len() {
+                    return None;
+                }
+                buf.copy_from_slice(bytes);
+                Some(<$t>::from_le_bytes(buf))
+            }
+        }
+    )*};
+}
+
+int_binary_value!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl BinaryValue for DictF32 {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        self.0 .0.to_bits().write_bytes(out)
+    }
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        u32::read_bytes(bytes).map(|b| DictF32(FloatOrd(f32::from_bits(b))))
+    }
+}
+
+impl BinaryValue for DictF64 {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        self.0 .0.to_bits().write_bytes(out)
+    }
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        u64::read_bytes(bytes).map(|b| DictF64(FloatOrd(f64::from_bits(b))))
+    }
+}
+
+impl BinaryValue for String {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes())
+    }
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl BinaryValue for Vec<u8> {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self)
+    }
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+// A tag byte, then the present value's encoding if there is one.
+impl<T: BinaryValue> BinaryValue for Option<T> {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(v) => {
+                out.push(1);
+                v.write_bytes(out)
+            }
+        }
+    }
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first()? {
+            (0, []) => Some(None),
+            (1, rest) => T::read_bytes(rest).map(Some),
+            _ => None,
+        }
+    }
+}
+
+/// The fixed header of a serialized dictionary.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Header {
+    pub version: u16,
+    pub flags: u16,
+    pub mode: Mode,
+    /// The number of exact codes, and so of values following the header.
+    pub num_codes: u32,
+    /// The number of [reserved codes](crate::BuildOptions::reserved_codes).
+    pub reserved_codes: u16,
+    /// Whether the dictionary has [dense codes](crate::BuildOptions::dense_codes).
+    pub dense_codes: bool,
+}
+
+impl Header {
+    /// Read and check the header at the start of `bytes`.
+    pub fn read(bytes: &[u8]) -> Result<Header, FormatError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(if bytes.len() >= 4 && bytes[..4] != MAGIC {
+                FormatError::BadMagic
+            } else {
+                FormatError::Truncated
+            });
+        }
+        if bytes[..4] != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if !READABLE_VERSIONS.contains(&version) {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let flags = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if flags & !DICT_KNOWN_FLAGS != 0 {
+            return Err(FormatError::UnsupportedFlags(flags));
+        }
+        let mode = Mode::from_bits(bytes[8]).ok_or(FormatError::BadMode(bytes[8]))?;
+        let num_codes = u32::from_le_bytes([bytes[10], bytes[11], bytes[12], bytes[13]]);
+        let reserved_codes = if flags & FLAG_RESERVED_CODES != 0 {
+            u16::from_le_bytes([bytes[14], bytes[15]])
+        } else {
+            0
+        };
+        let header = Header {
+            version,
+            flags,
+            mode,
+            num_codes,
+            reserved_codes,
+            dense_codes: flags & FLAG_DENSE_CODES != 0,
+        };
+        if !header
+            .options()
+            .stored_layout_fits(mode, num_codes as usize)
+        {
+            return Err(FormatError::BadCodes);
+        }
+        Ok(header)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.flags.to_le_bytes());
+        out.push(self.mode.bits());
+        out.push(0);
+        out.extend_from_slice(&self.num_codes.to_le_bytes());
+        out.extend_from_slice(&self.reserved_codes.to_le_bytes());
+    }
+
+    // The build options the header records.
+    pub(crate) fn options(&self) -> BuildOptions {
+        BuildOptions {
+            reserved_codes: self.reserved_codes,
+            dense_codes: self.dense_codes,
+            ..BuildOptions::default()
+        }
+    }
+}
+
+// Split a u32-length-prefixed chunk off the front of `bytes`.
+pub(crate) fn take_prefixed(bytes: &[u8]) -> Result<(&[u8], &[u8]), FormatError> {
+    if bytes.len() < 4 {
+        return Err(FormatError::Truncated);
+    }
+    let len = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let rest = &bytes[4..];
+    if rest.len() < len {
+        return Err(FormatError::Truncated);
+    }
+    Ok(rest.split_at(len))
+}
+
+// Split the values off `bytes`, which follows the header, returning the
+// values' encodings and the remaining sections.
+fn split_values(header: Header, mut bytes: &[u8]) -> Result<(Vec<&[u8]>, &[u8]), FormatError> {
+    let mut values = Vec::with_capacity(header.num_codes as usize);
+    for _ in 0..header.num_codes {
+        let (value, rest) = take_prefixed(bytes)?;
+        values.push(value);
+        bytes = rest;
+    }
+    Ok((values, bytes))
+}
+
+/// Return the tag and payload of each trailing section of a serialized
+/// dictionary, in order.
+pub fn sections(bytes: &[u8]) -> Result<Vec<(u16, &[u8])>, FormatError> {
+    let header = Header::read(bytes)?;
+    let (_, mut rest) = split_values(header, &bytes[HEADER_LEN..])?;
+    let mut out = Vec::new();
+    while !rest.is_empty() {
+        if rest.len() < 2 {
+            return Err(FormatError::Truncated);
+        }
+        let tag = u16::from_le_bytes([rest[0], rest[1]]);
+        let (payload, next) = take_prefixed(&rest[2..])?;
+        out.push((tag, payload));
+        rest = next;
+    }
+    Ok(out)
+}
+
+/// Append a section with `tag` and `payload` to a serialized dictionary.
+pub fn append_section(bytes: &mut Vec<u8>, tag: u16, payload: &[u8]) {
+    bytes.extend_from_slice(&tag.to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(payload);
+}
+
+impl<T: ValReq + BinaryValue> Dict<T> {
+    /// Serialize the dictionary in the current format version, with no
+    /// sections.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN + self.codes.len() * 12);
+        let mut flags = 0;
+        if self.reserved != 0 {
+            flags |= FLAG_RESERVED_CODES;
+        }
+        if self.dense {
+            flags |= FLAG_DENSE_CODES;
+        }
+        Header {
+            version: WRITE_VERSION,
+            flags,
+            mode: self.mode,
+            num_codes: self.codes.len() as u32,
+            reserved_codes: self.reserved,
+            dense_codes: self.dense,
+        }
+        .write(&mut out);
+        let mut value = Vec::new();
+        for v in self.codes.iter() {
+            value.clear();
+            v.write_bytes(&mut value);
+            out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            out.extend_from_slice(&value);
+        }
+        out
+    }
+
+    /// Deserialize a dictionary written by [Dict::to_bytes], skipping any
+    /// trailing sections.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        let header = Header::read(bytes)?;
+        let (values, _) = split_values(header, &bytes[HEADER_LEN..])?;
+        let codes = values
+            .into_iter()
+            .map(T::read_bytes)
+            .collect::<Option<Vec<T>>>()
+            .ok_or(FormatError::BadValue)?;
+        if codes.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(FormatError::BadCodes);
+        }
+        sections(bytes)?;
+        Ok(Dict::from_stored_codes(
+            header.mode,
+            codes,
+            &header.options(),
+        ))
+    }
+}
+
+// Read a little-endian u64 from the front of `bytes`, returning the rest.
+pub(crate) fn take_u64(bytes: &[u8]) -> Result<(u64, &[u8]), FormatError> {
+    if bytes.len() < 8 {
+        return Err(FormatError::Truncated);
+    }
+    let (n, rest) = bytes.split_at(8);
+    Ok((u64::read_bytes(n).unwrap(), rest))
+}
+
+impl<T: ValReq + BinaryValue> SampleSummary<T> {
+    /// Serialize the summary in the current format version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(SUMMARY_HEADER_LEN + self.entries.len() * 28);
+        out.extend_from_slice(&SUMMARY_MAGIC);
+        out.extend_from_slice(&WRITE_VERSION.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.above.to_le_bytes());
+        let mut value = Vec::new();
+        for e in self.entries.iter() {
+            out.extend_from_slice(&e.count.to_le_bytes());
+            out.extend_from_slice(&e.below.to_le_bytes());
+            value.clear();
+            e.value.write_bytes(&mut value);
+            out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            out.extend_from_slice(&value);
+        }
+        out
+    }
+
+    /// Deserialize a summary written by [SampleSummary::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        if bytes.len() < SUMMARY_HEADER_LEN {
+            return Err(if bytes.len() >= 4 && bytes[..4] != SUMMARY_MAGIC {
+                FormatError::BadMagic
+            } else {
+                FormatError::Truncated
+            });
+        }
+        if bytes[..4] != SUMMARY_MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if !READABLE_VERSIONS.contains(&version) {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let flags = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(FormatError::UnsupportedFlags(flags));
+        }
+        let n = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+        let (above, mut rest) = take_u64(&bytes[12..])?;
+        // Each entry takes at least 20 bytes, so a corrupt count can't
+        // force a huge allocation.
+        let mut entries = Vec::with_capacity(n.min(rest.len() / 20));
+        for _ in 0..n {
+            let (count, r) = take_u64(rest)?;
+            let (below, r) = take_u64(r)?;
+            let (value, r) = take_prefixed(r)?;
+            let value = T::read_bytes(value).ok_or(FormatError::BadValue)?;
+            entries.push(SummaryEntry {
+                value,
+                count,
+                below,
+            });
+            rest = r;
+        }
+        if entries.windows(2).any(|w| w[0].value >= w[1].value) {
+            return Err(FormatError::BadCodes);
+        }
+        Ok(SampleSummary { entries, above })
+    }
+}
+
+/// A stable 64-bit hash of a dictionary's mode and exact values, for
+/// recording alongside code columns. Computed over the serialized form, so
+/// it is the same on every platform and release writing the same format
+/// version.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Fingerprint(pub u64);
+
+/// What a reader knows about the dictionary that wrote a code column.
+#[derive(Copy, Clone, Debug)]
+pub enum CodeOrigin<'a> {
+    /// Only its fingerprint.
+    Fingerprint(Fingerprint),
+    /// Its serialized form, as written by [Dict::to_bytes].
+    Serialized(&'a [u8]),
+}
+
+/// Whether a dictionary can interpret codes written by another.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Compatibility {
+    /// The dictionaries are the same; codes can be used as they are.
+    Identical,
+    /// Every value with an exact code in the writer has one here too, so
+    /// each of the writer's codes maps onto a contiguous range of this
+    /// dictionary's codes without losing exactness, and codes can be used
+    /// after remapping.
+    Refinable,
+    /// Codes can't be interpreted by this dictionary, or not enough is known
+    /// about the writer to tell.
+    Incompatible,
+}
+
+impl<T: ValReq + BinaryValue> Dict<T> {
+    /// The dictionary's [Fingerprint].
+    pub fn fingerprint(&self) -> Fingerprint {
+        fingerprint_of(&self.to_bytes())
+    }
+
+    /// Check whether codes written by the dictionary described by `origin`
+    /// can be interpreted by this one. A fingerprint alone can only show
+    /// the dictionaries identical; anything else is reported incompatible.
+    /// A serialized dictionary that can't be read is incompatible.
+    pub fn can_interpret_codes_of(&self, origin: CodeOrigin<'_>) -> Compatibility {
+        let other = match origin {
+            CodeOrigin::Fingerprint(f) => {
+                return if f == self.fingerprint() {
+                    Compatibility::Identical
+                } else {
+                    Compatibility::Incompatible
+                };
+            }
+            CodeOrigin::Serialized(bytes) => match Dict::<T>::from_bytes(bytes) {
+                Ok(d) => d,
+                Err(_) => return Compatibility::Incompatible,
+            },
+        };
+        if other.mode == self.mode && other.codes == self.codes {
+            Compatibility::Identical
+        } else if other
+            .codes
+            .iter()
+            .all(|v| self.codes.binary_search(v).is_ok())
+        {
+            Compatibility::Refinable
+        } else {
+            Compatibility::Incompatible
+        }
+    }
+}
+
+/// The [Fingerprint] of a serialized dictionary, without deserializing its
+/// values. Only the header and values count: sections may be added or
+/// dropped without changing which dictionary the bytes describe.
+pub fn fingerprint(bytes: &[u8]) -> Result<Fingerprint, FormatError> {
+    let header = Header::read(bytes)?;
+    let (_, sections) = split_values(header, &bytes[HEADER_LEN..])?;
+    Ok(fingerprint_of(&bytes[..bytes.len() - sections.len()]))
+}
+
+fn fingerprint_of(bytes: &[u8]) -> Fingerprint {
+    let mut h = Fnv1a(FNV_OFFSET);
+    h.write(bytes);
+    Fingerprint(h.finish())
+}
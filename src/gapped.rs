@@ -0,0 +1,100 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! An order-preserving dictionary whose exact codes are spread out over the
+//! codespace, so that values can later be given exact codes of their own
+//! without renumbering any existing ones.
+//!
+//! A [Dict] numbers its exact codes consecutively, so adding one shifts
+//! every code above it and invalidates any column persisted with the old
+//! codes. A [GappedDict] assigns fewer exact codes than the mode allows
+//! and leaves unassigned codes between neighbours; [GappedDict::insert]
+//! takes an exact code from the middle of a gap, leaving every other
+//! assignment alone.
+//!
+//! Inexact codes are the odd code just above the exact code below them (or
+//! code 1, below the first). Inserting into a gap splits the interval its
+//! inexact code stood for: values written before the insertion keep that
+//! code, which then only bounds them to the wider, original interval. Scans
+//! over such older data should treat the split gap's inexact code as
+//! inconclusive for predicates on the inserted value.
+
+use crate::{BuildOptions, Code, Dict, Mode, ValReq};
+
+/// A dictionary with gaps between its exact codes. See the [module
+/// documentation](self).
+pub struct GappedDict<T: ValReq> {
+    /// The mode the dictionary was built in.
+    pub mode: Mode,
+
+    /// The values given exact codes, in increasing order, with their codes.
+    pub entries: Vec<(T, Code)>,
+}
+
+impl<T: ValReq> GappedDict<T> {
+    /// Build a dictionary over a sample as [Dict::new] would, but assigning
+    /// at most one in every `spacing` of the mode's exact codes and spreading
+    /// those evenly over the codespace. A `spacing` of 1 assigns as many
+    /// codes as [Dict::new], leaving gaps only where it would leave codes
+    /// unused.
+    pub fn new(mode: Mode, sample: Vec<T>, spacing: usize) -> Self {
+        let slots = mode.num_exact_codes();
+        let limit = (slots / spacing.max(1)).max(1);
+        // Reserving the top of the codespace is the simplest way to cap the
+        // number of exact codes the underlying dictionary assigns.
+        let options = BuildOptions {
+            reserved_codes: mode.max_inexact_code().0 - (2 * limit as u16 + 1),
+            ..BuildOptions::default()
+        };
+        let dict = Dict::new_with_options(mode, sample, &options);
+        let n = dict.codes.len();
+        // Put each value at the centre of its share of the exact codes, so
+        // there is room at both ends as well as between neighbours.
+        let entries = dict
+            .codes
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let slot = ((2 * i + 1) * slots) / (2 * n);
+                (v, Code(2 * (slot as u16 + 1)))
+            })
+            .collect();
+        GappedDict { mode, entries }
+    }
+
+    /// Look up the code for a value.
+    pub fn encode(&self, query: &T) -> Code {
+        match self.entries.binary_search_by(|(v, _)| v.cmp(query)) {
+            Ok(idx) => self.entries[idx].1,
+            Err(0) => Code(1),
+            Err(idx) => Code(self.entries[idx - 1].1 .0 + 1),
+        }
+    }
+
+    /// Give `value` an exact code of its own, between the codes of its
+    /// neighbours, without changing any other code. Returns the new code,
+    /// or `None` if `value` already has one or its gap has no room left.
+    pub fn insert(&mut self, value: T) -> Option<Code> {
+        let idx = match self.entries.binary_search_by(|(v, _)| v.cmp(&value)) {
+            Ok(_) => return None,
+            Err(idx) => idx,
+        };
+        // The codes bounding the gap, exclusive; the new exact code needs an
+        // odd code free on either side of it.
+        let lo = match idx {
+            0 => 0,
+            _ => self.entries[idx - 1].1 .0 as u32,
+        };
+        let hi = match self.entries.get(idx) {
+            Some((_, c)) => c.0 as u32,
+            None => self.mode.max_inexact_code().0 as u32 + 1,
+        };
+        let code = ((lo + hi) / 2) & !1;
+        if code < lo + 2 || code + 2 > hi {
+            return None;
+        }
+        let code = Code(code as u16);
+        self.entries.insert(idx, (value, code));
+        Some(code)
+    }
+}
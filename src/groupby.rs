@@ -0,0 +1,144 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Pre-aggregating a `GROUP BY` over a code column.
+//!
+//! Grouping by a low-cardinality column whose values all have exact codes
+//! needs nothing but the codes: each exact code is one group. A
+//! [GroupAccumulator] keeps a slot per code of the mode (256, 4096 or
+//! 65536 of them) and folds each row into its code's slot, with no hashing
+//! and no access to the values. The slots of exact codes are finished
+//! groups. The slots of inexact codes may mix several values, so
+//! [GroupAccumulator::groups] reports them as residual work: only their
+//! rows need re-grouping from the base data.
+
+use crate::{Code, Dict, Mode, ValReq};
+
+/// Row counts and partial aggregates per code. See the [module
+/// documentation](self).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GroupAccumulator<A> {
+    mode: Mode,
+    rows: Vec<u64>,
+    aggregates: Vec<A>,
+}
+
+/// A group answered from the codes alone.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ExactGroup<T, A> {
+    /// The group's value.
+    pub value: T,
+    /// The number of rows in the group.
+    pub rows: u64,
+    /// The group's aggregate.
+    pub aggregate: A,
+}
+
+/// The groups of a [GroupAccumulator], split by what the codes can answer.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Groups<T, A> {
+    /// The groups of the exact codes that occur, in value order.
+    pub exact: Vec<ExactGroup<T, A>>,
+    /// The inexact codes that occur, in order. Their rows may hold several
+    /// values each, and must be re-grouped from the base data. Code 0,
+    /// which no dictionary assigns but a column may use for nulls, is
+    /// residual too.
+    pub residual: Vec<Code>,
+    /// The number of rows with residual codes.
+    pub residual_rows: u64,
+}
+
+impl<A: Clone + Default> GroupAccumulator<A> {
+    /// Make an accumulator with an empty slot for each code of `mode`.
+    pub fn new(mode: Mode) -> Self {
+        let slots = mode.max_inexact_code().0 as usize + 1;
+        GroupAccumulator {
+            mode,
+            rows: vec![0; slots],
+            aggregates: vec![A::default(); slots],
+        }
+    }
+
+    /// Count the rows of `codes`, leaving the aggregates alone.
+    ///
+    /// Panics if a code is too wide for the accumulator's mode.
+    pub fn count(&mut self, codes: &[Code]) {
+        for c in codes {
+            self.rows[c.0 as usize] += 1;
+        }
+    }
+
+    /// Count the rows of `codes`, and fold each row's entry of `values`, a
+    /// parallel column, into its code's aggregate with `fold`.
+    ///
+    /// Panics if the columns differ in length or a code is too wide for the
+    /// accumulator's mode.
+    pub fn accumulate<V, F: FnMut(&mut A, &V)>(
+        &mut self,
+        codes: &[Code],
+        values: &[V],
+        mut fold: F,
+    ) {
+        assert_eq!(codes.len(), values.len());
+        for (c, v) in codes.iter().zip(values.iter()) {
+            self.rows[c.0 as usize] += 1;
+            fold(&mut self.aggregates[c.0 as usize], v);
+        }
+    }
+
+    /// Add the counts of `other`, from another part of the column, to this
+    /// accumulator's, and combine its aggregates into this one's with
+    /// `combine`.
+    ///
+    /// Panics if the accumulators' modes differ.
+    pub fn merge<F: FnMut(&mut A, &A)>(&mut self, other: &GroupAccumulator<A>, mut combine: F) {
+        assert!(self.mode == other.mode);
+        for (i, n) in other.rows.iter().enumerate() {
+            if *n != 0 {
+                self.rows[i] += n;
+                combine(&mut self.aggregates[i], &other.aggregates[i]);
+            }
+        }
+    }
+
+    /// The number of rows counted with `code`.
+    pub fn rows(&self, code: Code) -> u64 {
+        self.rows[code.0 as usize]
+    }
+
+    /// The aggregate of the rows counted with `code`.
+    pub fn aggregate(&self, code: Code) -> &A {
+        &self.aggregates[code.0 as usize]
+    }
+
+    /// Split the codes that occur into the groups of exact codes, labelled
+    /// with their values from `dict`, the dictionary the codes were encoded
+    /// with, and the residual inexact codes.
+    ///
+    /// Panics if `dict` was built in a different mode.
+    pub fn groups<T: ValReq>(&self, dict: &Dict<T>) -> Groups<T, A> {
+        assert!(dict.mode == self.mode);
+        let mut groups = Groups {
+            exact: Vec::new(),
+            residual: Vec::new(),
+            residual_rows: 0,
+        };
+        for (c, n) in self.rows.iter().enumerate() {
+            if *n == 0 {
+                continue;
+            }
+            let code = Code(c as u16);
+            if code.is_exact() && c != 0 {
+                groups.exact.push(ExactGroup {
+                    value: dict.codes[c / 2 - 1].clone(),
+                    rows: *n,
+                    aggregate: self.aggregates[c].clone(),
+                });
+            } else {
+                groups.residual.push(code);
+                groups.residual_rows += n;
+            }
+        }
+        groups
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! An equality-only dictionary that assigns codes by hashing, for
+//! high-cardinality columns that are only ever filtered by equality or
+//! membership.
+//!
+//! This is the equality variant of column sketches from the reference paper:
+//! the most frequent sample values each get a unique code, and every other
+//! value is hashed onto one of the remaining codes. Codes preserve equality
+//! but not order, so only `=` and `IN` predicates can be rewritten onto them,
+//! as [CodePredicate]s that compile and scan like those of a [Dict](crate::Dict).
+//! In exchange, the values sharing an inexact code are a pseudo-random
+//! selection of the long tail rather than a whole interval of it, which
+//! spreads hot ranges out and lowers the false-positive rate of equality
+//! lookups.
+//!
+//! The same even/odd convention as [Dict](crate::Dict) applies: unique codes are even (so
+//! [Code::is_exact] is true of them), hash buckets are odd, and code 0 is
+//! unused.
+
+use crate::codeset::CodeSet;
+use crate::predicate::{CodePredicate, Predicate};
+use crate::{Code, Mode, ValReq};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// 64-bit FNV-1a. Codes may be persisted, so this needs to be stable across
+// processes and releases, which std's DefaultHasher does not promise.
+pub(crate) struct Fnv1a(pub(crate) u64);
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// A dictionary mapping values to codes by frequency and hashing rather than
+/// by order. See the [module documentation](self).
+///
+/// Hash bucket assignment depends on `T`'s [Hash] implementation, so codes
+/// are only portable between platforms if that implementation is (for
+/// example, it is not for `usize`).
+pub struct HashDict<T: ValReq + Hash> {
+    /// The mode the dictionary was built in.
+    pub mode: Mode,
+
+    /// The values given unique (even) codes, and their codes.
+    pub unique: HashMap<T, Code>,
+}
+
+impl<T: ValReq + Hash> HashDict<T> {
+    /// Build a dictionary with a given [Mode] over a provided sample. The
+    /// most frequent distinct values in the sample, up to
+    /// [Mode::num_exact_codes] of them, get unique codes; ties in frequency
+    /// are broken in favour of smaller values, so the result does not depend
+    /// on the order of the sample.
+    pub fn new(mode: Mode, mut sample: Vec<T>) -> Self {
+        sample.sort_unstable();
+        let mut clusters: Vec<(usize, T)> = Vec::new();
+        for v in sample.into_iter() {
+            match clusters.last_mut() {
+                Some((n, last)) if *last == v => *n += 1,
+                _ => clusters.push((1, v)),
+            }
+        }
+        // Stable, so equal counts stay in value order.
+        clusters.sort_by_key(|c| std::cmp::Reverse(c.0));
+        clusters.truncate(mode.num_exact_codes());
+        let unique = clusters
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, v))| (v, Code(2 * (i as u16 + 1))))
+            .collect();
+        HashDict { mode, unique }
+    }
+
+    /// Look up the code for a value: its unique code if it has one,
+    /// otherwise the odd code of the hash bucket it falls into.
+    pub fn encode(&self, query: &T) -> Code {
+        if let Some(code) = self.unique.get(query) {
+            return *code;
+        }
+        let mut h = Fnv1a(FNV_OFFSET);
+        query.hash(&mut h);
+        // There is one odd code more than there are even ones.
+        let nbuckets = self.mode.num_exact_codes() as u64 + 1;
+        Code(2 * (h.finish() % nbuckets) as u16 + 1)
+    }
+
+    /// Rewrite the predicate `x = value` onto codes. If `value` has a unique
+    /// code, rows with that code definitely match; otherwise rows with its
+    /// bucket's code may match, and must be re-checked.
+    pub fn rewrite_eq(&self, value: &T) -> CodePredicate {
+        self.rewrite_in(std::slice::from_ref(value))
+    }
+
+    /// Rewrite the predicate `x IN (values...)` onto codes: the unique codes
+    /// of the values are definite, and the buckets of the rest are maybe
+    /// codes.
+    pub fn rewrite_in(&self, values: &[T]) -> CodePredicate {
+        let codes: Vec<Code> = values.iter().map(|v| self.encode(v)).collect();
+        let (exact, inexact): (Vec<Code>, Vec<Code>) = codes.iter().partition(|c| c.is_exact());
+        let definite = CodeSet::from_codes(&exact);
+        CodePredicate::from_sets(&definite, &definite.union(&CodeSet::from_codes(&inexact)))
+    }
+
+    /// Rewrite `pred` onto codes, as [Dict::rewrite](crate::Dict::rewrite)
+    /// does, or return None if it is a range predicate: hashed codes only
+    /// support [Predicate::Eq] and [Predicate::InSet].
+    pub fn rewrite(&self, pred: &Predicate<T>) -> Option<CodePredicate> {
+        match pred {
+            Predicate::Eq(v) => Some(self.rewrite_eq(v)),
+            Predicate::InSet(values) => Some(self.rewrite_in(values)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! A two-level sketch: word codes per row, with byte-code summaries per
+//! block of rows that let scans prune whole blocks before looking at rows.
+//!
+//! The [Mode] documentation describes byte codes as suited to eliding small
+//! blocks and word codes to eliding large ones. This sketch uses both: the
+//! rows are sketched with the finest level of a [DictFamily] (word codes),
+//! and every block of [BLOCK_ROWS] rows is summarized by the least and
+//! greatest coarsest-level (byte) code of its rows. Because the family's
+//! boundaries nest, a range predicate on word codes implies a range on byte
+//! codes, so a scan first discards the blocks whose byte summary is out of
+//! range and only then compares individual word codes.
+
+use crate::family::DictFamily;
+use crate::{Code, Mode, ValReq};
+
+/// The number of rows in each block summarized at the coarse level.
+pub const BLOCK_ROWS: usize = 64;
+
+/// Per-row word codes plus per-block byte-code summaries.
+pub struct HierarchicalSketch {
+    rows: Vec<u16>,
+    // For each block, the least and greatest byte code among its rows.
+    blocks: Vec<(u8, u8)>,
+}
+
+impl HierarchicalSketch {
+    /// Sketch `values` using the finest level of `family` for rows and its
+    /// coarsest level for block summaries.
+    ///
+    /// Panics unless the coarsest level is in [Mode::Byte] and the finest in
+    /// [Mode::Word].
+    pub fn new<T: ValReq>(family: &DictFamily<T>, values: &[T]) -> Self {
+        let (coarse, fine) = (0, family.levels.len() - 1);
+        assert!(family.levels[coarse].mode == Mode::Byte);
+        assert!(family.levels[fine].mode == Mode::Word);
+        let rows: Vec<u16> = values.iter().map(|v| family.encode(fine, v).0).collect();
+        let blocks = rows
+            .chunks(BLOCK_ROWS)
+            .map(|block| {
+                block.iter().fold((u8::MAX, u8::MIN), |(lo, hi), c| {
+                    let b = family.coarsen(fine, coarse, Code(*c)).0 as u8;
+                    (lo.min(b), hi.max(b))
+                })
+            })
+            .collect();
+        HierarchicalSketch { rows, blocks }
+    }
+
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The word codes of all rows.
+    pub fn rows(&self) -> &[u16] {
+        &self.rows
+    }
+
+    fn code_ranges<T: ValReq>(family: &DictFamily<T>, lo: &T, hi: &T) -> ((u16, u16), (u8, u8)) {
+        let fine = family.levels.len() - 1;
+        let (wlo, whi) = (family.encode(fine, lo), family.encode(fine, hi));
+        let blo = family.coarsen(fine, 0, wlo).0 as u8;
+        let bhi = family.coarsen(fine, 0, whi).0 as u8;
+        ((wlo.0, whi.0), (blo, bhi))
+    }
+
+    /// Return the indices of the blocks whose byte summary admits rows with
+    /// values in the inclusive range `[lo, hi]`. Other blocks hold no such
+    /// rows.
+    pub fn candidate_blocks<T: ValReq>(
+        &self,
+        family: &DictFamily<T>,
+        lo: &T,
+        hi: &T,
+    ) -> Vec<usize> {
+        let (_, (blo, bhi)) = Self::code_ranges(family, lo, hi);
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, (min, max))| *min <= bhi && blo <= *max)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Scan for rows that may hold values in the inclusive range `[lo, hi]`,
+    /// returning one flag per row. Blocks ruled out by their byte summary are
+    /// skipped without reading their rows' word codes; flagged rows must be
+    /// re-checked against the underlying values.
+    pub fn scan<T: ValReq>(&self, family: &DictFamily<T>, lo: &T, hi: &T) -> Vec<bool> {
+        let ((wlo, whi), (blo, bhi)) = Self::code_ranges(family, lo, hi);
+        let mut out = vec![false; self.rows.len()];
+        for (i, (min, max)) in self.blocks.iter().enumerate() {
+            if *max < blo || bhi < *min {
+                continue;
+            }
+            let start = i * BLOCK_ROWS;
+            let end = (start + BLOCK_ROWS).min(self.rows.len());
+            for (o, c) in out[start..end].iter_mut().zip(self.rows[start..end].iter()) {
+                *o = wlo <= *c && *c <= whi;
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Hilbert-curve keys for geospatial points, so latitude/longitude data can
+//! be sketched with a [Dict] and pruned by bounding box.
+//!
+//! Points are quantized onto a 2^32 by 2^32 grid over the whole globe
+//! (roughly a centimetre per cell) and keyed by their position along the
+//! Hilbert curve through that grid. The Hilbert curve keeps nearby points
+//! closer together in key order than Z-order does, so a bounding box needs
+//! fewer key ranges, and fewer code ranges, to cover.
+
+use crate::zorder::Rect;
+use crate::{quadtree, Code, Dict};
+use std::ops::RangeInclusive;
+
+/// A point's index along the Hilbert curve through the quantized globe.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct HilbertKey(pub u64);
+
+const GRID: f64 = 4294967296.0; // 2^32
+
+fn quantize(v: f64, min: f64, span: f64) -> u32 {
+    let cell = ((v - min) / span * GRID).floor();
+    cell.clamp(0.0, u32::MAX as f64) as u32
+}
+
+fn dequantize(cell: u32, min: f64, span: f64) -> f64 {
+    min + (cell as f64 + 0.5) / GRID * span
+}
+
+// The classic iterative Hilbert mapping, from "Hacker's Delight" and
+// Wikipedia, over a grid of side n = 2^32.
+fn xy_to_d(x: u32, y: u32) -> u64 {
+    let n: u64 = 1 << 32;
+    let (mut x, mut y) = (x as u64, y as u64);
+    let mut d = 0u64;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = ((x & s) != 0) as u64;
+        let ry = ((y & s) != 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+fn d_to_xy(d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut t = d;
+    let mut s = 1u64;
+    while s < (1 << 32) {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x as u32, y as u32)
+}
+
+impl HilbertKey {
+    /// Key the point at latitude `lat` and longitude `lon`, in degrees.
+    /// Out-of-range coordinates are clamped.
+    pub fn new(lat: f64, lon: f64) -> Self {
+        HilbertKey(xy_to_d(
+            quantize(lon, -180.0, 360.0),
+            quantize(lat, -90.0, 180.0),
+        ))
+    }
+
+    /// The latitude and longitude of the centre of the key's grid cell.
+    pub fn lat_lon(&self) -> (f64, f64) {
+        let (x, y) = d_to_xy(self.0);
+        (dequantize(y, -90.0, 180.0), dequantize(x, -180.0, 360.0))
+    }
+}
+
+/// A latitude/longitude bounding box, in degrees, inclusive on all sides.
+/// Boxes crossing the antimeridian should be split in two by the caller.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl BBox {
+    fn grid_rect(&self) -> Rect {
+        Rect {
+            x: quantize(self.min_lon, -180.0, 360.0)..=quantize(self.max_lon, -180.0, 360.0),
+            y: quantize(self.min_lat, -90.0, 180.0)..=quantize(self.max_lat, -90.0, 180.0),
+        }
+    }
+}
+
+/// Cover the points inside `bbox` with at most `max_ranges` sorted, disjoint,
+/// inclusive ranges of [HilbertKey]s. The cover includes every grid cell the
+/// box touches, and more if `max_ranges` is too small to follow its edges.
+///
+/// Panics if `max_ranges` is zero.
+pub fn bbox_to_ranges(bbox: &BBox, max_ranges: usize) -> Vec<RangeInclusive<HilbertKey>> {
+    quadtree::cover(&bbox.grid_rect(), max_ranges, |x0, y0, level| {
+        // Every point of an aligned square shares the top bits of its index.
+        let span = ((1u128 << (2 * level)) - 1) as u64;
+        xy_to_d(x0, y0) & !span
+    })
+    .into_iter()
+    .map(|r| HilbertKey(*r.start())..=HilbertKey(*r.end()))
+    .collect()
+}
+
+/// Translate the bounding-box predicate `bbox` into a sorted union of
+/// disjoint inclusive code ranges of `dict`, via at most `max_ranges` key
+/// ranges (see [bbox_to_ranges]). A row whose code lies outside all of them
+/// cannot lie in the box.
+pub fn bbox_to_code_ranges(
+    dict: &Dict<HilbertKey>,
+    bbox: &BBox,
+    max_ranges: usize,
+) -> Vec<RangeInclusive<Code>> {
+    quadtree::code_ranges(dict, bbox_to_ranges(bbox, max_ranges))
+}
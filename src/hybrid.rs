@@ -0,0 +1,153 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! A composite sketch pairing a code column with small per-block Bloom
+//! filters, for workloads heavy in point lookups.
+//!
+//! An equality predicate on a value with an exact code is answered exactly by
+//! the codes alone. But a value with an inexact code shares that code with
+//! every other value in its interval, so on a high-cardinality column most
+//! point lookups on long-tail values turn up false-positive blocks. The
+//! [HybridSketch] adds, for each block, a Bloom filter over the values in that
+//! block that got inexact codes, and consults it only for such lookups.
+//! Range predicates still use the codes alone.
+
+use crate::{Code, Dict, ValReq};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+struct Bloom {
+    bits: Vec<u64>,
+    nhashes: u32,
+}
+
+impl Bloom {
+    fn new(nbits: usize, nvalues: usize) -> Bloom {
+        let nwords = nbits.div_ceil(64).max(1);
+        // The optimal number of hash functions is (m/n) ln 2.
+        let per_value = (nwords * 64) as f64 / nvalues.max(1) as f64;
+        let nhashes = (per_value * std::f64::consts::LN_2).round().clamp(1.0, 8.0) as u32;
+        Bloom {
+            bits: vec![0; nwords],
+            nhashes,
+        }
+    }
+
+    // Kirsch-Mitzenmacher double hashing: probe i is h1 + i*h2.
+    fn probes<T: Hash>(&self, v: &T) -> impl Iterator<Item = usize> {
+        let mut h = DefaultHasher::new();
+        v.hash(&mut h);
+        let h = h.finish();
+        let (h1, h2) = (h & 0xffff_ffff, (h >> 32) | 1);
+        let nbits = (self.bits.len() * 64) as u64;
+        (0..self.nhashes as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % nbits) as usize)
+    }
+
+    fn insert<T: Hash>(&mut self, v: &T) {
+        let probes: Vec<usize> = self.probes(v).collect();
+        for bit in probes {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn may_contain<T: Hash>(&self, v: &T) -> bool {
+        self.probes(v)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+/// A code column divided into fixed-size blocks, each with a Bloom filter
+/// over the block's values that were given inexact codes.
+pub struct HybridSketch {
+    codes: Vec<Code>,
+    block_rows: usize,
+    blooms: Vec<Bloom>,
+}
+
+impl HybridSketch {
+    /// Encode `values` with `dict`, dividing the resulting codes into blocks
+    /// of `block_rows` rows, and build a Bloom filter of `bloom_bits` bits
+    /// (rounded up to a multiple of 64) for each block.
+    pub fn new<T: ValReq + Hash>(
+        dict: &Dict<T>,
+        values: &[T],
+        block_rows: usize,
+        bloom_bits: usize,
+    ) -> Self {
+        assert!(block_rows != 0);
+        let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+        let blooms = values
+            .chunks(block_rows)
+            .zip(codes.chunks(block_rows))
+            .map(|(vs, cs)| {
+                let inexact = cs.iter().filter(|c| !c.is_exact()).count();
+                let mut bloom = Bloom::new(bloom_bits, inexact);
+                for (v, c) in vs.iter().zip(cs.iter()) {
+                    if !c.is_exact() {
+                        bloom.insert(v);
+                    }
+                }
+                bloom
+            })
+            .collect();
+        HybridSketch {
+            codes,
+            block_rows,
+            blooms,
+        }
+    }
+
+    /// The encoded column.
+    pub fn codes(&self) -> &[Code] {
+        &self.codes
+    }
+
+    /// The number of blocks.
+    pub fn num_blocks(&self) -> usize {
+        self.blooms.len()
+    }
+
+    /// The codes of block `block`.
+    pub fn block_codes(&self, block: usize) -> &[Code] {
+        let start = block * self.block_rows;
+        let end = (start + self.block_rows).min(self.codes.len());
+        &self.codes[start..end]
+    }
+
+    /// Return false if block `block` definitely holds no row equal to `value`,
+    /// true if it might. For a value with an exact code, true means it
+    /// certainly does.
+    pub fn block_may_contain<T: ValReq + Hash>(
+        &self,
+        dict: &Dict<T>,
+        block: usize,
+        value: &T,
+    ) -> bool {
+        self.block_may_contain_code(block, dict.encode(value), value)
+    }
+
+    fn block_may_contain_code<T: Hash>(&self, block: usize, code: Code, value: &T) -> bool {
+        if !self.block_codes(block).contains(&code) {
+            return false;
+        }
+        code.is_exact() || self.blooms[block].may_contain(value)
+    }
+
+    /// Return the blocks that might hold a row equal to `value`.
+    pub fn candidate_blocks_eq<T: ValReq + Hash>(&self, dict: &Dict<T>, value: &T) -> Vec<usize> {
+        let code = dict.encode(value);
+        (0..self.num_blocks())
+            .filter(|b| self.block_may_contain_code(*b, code, value))
+            .collect()
+    }
+
+    /// Return the blocks that might hold a row whose code lies in the
+    /// inclusive range `[lo, hi]`. Range predicates are answered from codes
+    /// alone.
+    pub fn candidate_blocks_in(&self, lo: Code, hi: Code) -> Vec<usize> {
+        (0..self.num_blocks())
+            .filter(|b| self.block_codes(*b).iter().any(|c| lo <= *c && *c <= hi))
+            .collect()
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Sketches of interval-valued columns, such as the validity ranges of rows
+//! in a temporal table.
+//!
+//! Each interval is a half-open range `[start, end)` and is sketched as a pair
+//! of codes from two coordinated dictionaries, one built over the sample's
+//! start points and one over its end points. Predicates on intervals are
+//! conjunctions of comparisons against those endpoints, so they rewrite onto
+//! code pairs just as scalar comparisons rewrite onto single codes: each
+//! comparison of codes is either conclusive or, when both sides share an
+//! inexact code, inconclusive.
+
+use crate::predicate::Ternary;
+use crate::{Code, Dict, Mode, ValReq};
+use std::cmp::Ordering;
+
+/// A pair of dictionaries for sketching half-open intervals `[start, end)`.
+pub struct IntervalDict<T: ValReq> {
+    /// The dictionary over interval start points.
+    pub starts: Dict<T>,
+
+    /// The dictionary over interval end points.
+    pub ends: Dict<T>,
+}
+
+/// The code pair for one interval: the code of its start in
+/// [IntervalDict::starts] and the code of its end in [IntervalDict::ends].
+pub type IntervalCode = (Code, Code);
+
+// Evaluate `a < b` (or `a <= b`, if `or_equal`) given only the codes of a and
+// b from the same dictionary. Returns Maybe if the codes can't tell.
+fn compare(a: Code, b: Code, or_equal: bool) -> Ternary {
+    match a.cmp(&b) {
+        Ordering::Less => Ternary::True,
+        Ordering::Greater => Ternary::False,
+        Ordering::Equal if a.is_exact() && or_equal => Ternary::True,
+        Ordering::Equal if a.is_exact() => Ternary::False,
+        Ordering::Equal => Ternary::Maybe,
+    }
+}
+
+fn and(a: Ternary, b: Ternary) -> Ternary {
+    match (a, b) {
+        (Ternary::False, _) | (_, Ternary::False) => Ternary::False,
+        (Ternary::True, Ternary::True) => Ternary::True,
+        _ => Ternary::Maybe,
+    }
+}
+
+/// A predicate on intervals, rewritten onto code pairs by
+/// [IntervalDict::contains_point] or [IntervalDict::overlaps].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum IntervalPredicate {
+    /// `start <= p < end`, with the point's codes in each dictionary.
+    ContainsPoint { start: Code, end: Code },
+    /// `start < hi && lo < end`, with `hi` encoded in the starts dictionary
+    /// and `lo` in the ends dictionary.
+    Overlaps { hi: Code, lo: Code },
+}
+
+impl IntervalPredicate {
+    /// Evaluate the predicate on an interval's code pair. Returns
+    /// [Ternary::True] if the interval certainly satisfies the predicate,
+    /// [Ternary::False] if it certainly does not, and [Ternary::Maybe] if the
+    /// underlying interval must be checked.
+    pub fn eval(&self, interval: IntervalCode) -> Ternary {
+        let (s, e) = interval;
+        match *self {
+            IntervalPredicate::ContainsPoint { start, end } => {
+                and(compare(s, start, true), compare(end, e, false))
+            }
+            IntervalPredicate::Overlaps { hi, lo } => {
+                and(compare(s, hi, false), compare(lo, e, false))
+            }
+        }
+    }
+}
+
+impl<T: ValReq> IntervalDict<T> {
+    /// Build the pair of dictionaries in `mode` over a sample of intervals.
+    pub fn new(mode: Mode, sample: Vec<(T, T)>) -> Self {
+        let (starts, ends): (Vec<T>, Vec<T>) = sample.into_iter().unzip();
+        IntervalDict {
+            starts: Dict::new(mode, starts),
+            ends: Dict::new(mode, ends),
+        }
+    }
+
+    /// Encode the interval `[start, end)`.
+    pub fn encode(&self, start: &T, end: &T) -> IntervalCode {
+        (self.starts.encode(start), self.ends.encode(end))
+    }
+
+    /// Rewrite "interval contains `point`", i.e. `start <= point < end`.
+    pub fn contains_point(&self, point: &T) -> IntervalPredicate {
+        IntervalPredicate::ContainsPoint {
+            start: self.starts.encode(point),
+            end: self.ends.encode(point),
+        }
+    }
+
+    /// Rewrite "interval overlaps `[lo, hi)`", i.e. `start < hi && lo < end`.
+    pub fn overlaps(&self, lo: &T, hi: &T) -> IntervalPredicate {
+        IntervalPredicate::Overlaps {
+            hi: self.starts.encode(hi),
+            lo: self.ends.encode(lo),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! JNI bindings, for JVM query engines.
+//!
+//! Build one with `cargo rustc --release --features jni --crate-type
+//! cdylib` and load it with `System.loadLibrary("ordbog")`. It implements
+//! the static native methods of a class `org.ordbog.Native`:
+//!
+//! ```text
+//! package org.ordbog;
+//!
+//! public final class Native {
+//!     public static native long dictI64New(int modeBits, long[] sample);
+//!     public static native void dictI64Free(long dict);
+//!     public static native short[] dictI64Encode(long dict, long[] values);
+//!     public static native int[] dictI64Rewrite(long dict, int op, long[] operands);
+//!     public static native long dictF64New(int modeBits, double[] sample);
+//!     public static native void dictF64Free(long dict);
+//!     public static native short[] dictF64Encode(long dict, double[] values);
+//!     public static native int[] dictF64Rewrite(long dict, int op, double[] operands);
+//! }
+//! ```
+//!
+//! A dictionary is a `long` handle, which the caller owns and must release
+//! exactly once with the matching `Free` method; `Free` accepts 0. A
+//! dictionary is immutable, so a handle may be used from any number of
+//! threads at once, until it is freed.
+//!
+//! Codes come back in a `short[]`, to be read as unsigned with
+//! `Short.toUnsignedInt`. A rewrite takes one of the `OP_*` operators and
+//! its operands (one value, two for [OP_BETWEEN], any number for
+//! [OP_IN]), and returns the [CodePredicate] in the layout of
+//! [code_predicate_to_ints]. Failures throw a `RuntimeException`.
+
+use crate::predicate::{CodePredicate, Predicate};
+use crate::{Dict, DictF64, Mode, ValReq};
+use float_ord::FloatOrd;
+use jni::objects::{
+    JClass, JDoubleArray, JIntArray, JLongArray, JPrimitiveArray, JShortArray, TypeArray,
+};
+use jni::sys::{jint, jlong};
+use jni::{Env, EnvUnowned};
+use std::fmt;
+
+/// `x = v`.
+pub const OP_EQ: jint = 0;
+/// `x < v`.
+pub const OP_LT: jint = 1;
+/// `x <= v`.
+pub const OP_LE: jint = 2;
+/// `x > v`.
+pub const OP_GT: jint = 3;
+/// `x >= v`.
+pub const OP_GE: jint = 4;
+/// `x BETWEEN lo AND hi`, inclusive at both ends.
+pub const OP_BETWEEN: jint = 5;
+/// `x IN (v, ...)`.
+pub const OP_IN: jint = 6;
+
+/// Build the predicate `op` over `operands`, or None if `op` is unknown or
+/// takes a different number of operands.
+pub fn predicate_from_op<T>(op: jint, operands: Vec<T>) -> Option<Predicate<T>> {
+    if op == OP_IN {
+        return Some(Predicate::InSet(operands));
+    }
+    let mut operands = operands.into_iter();
+    let pred = match (op, operands.next(), operands.next()) {
+        (OP_EQ, Some(v), None) => Predicate::Eq(v),
+        (OP_LT, Some(v), None) => Predicate::Lt(v),
+        (OP_LE, Some(v), None) => Predicate::Le(v),
+        (OP_GT, Some(v), None) => Predicate::Gt(v),
+        (OP_GE, Some(v), None) => Predicate::Ge(v),
+        (OP_BETWEEN, Some(lo), Some(hi)) => Predicate::Between(lo, hi),
+        _ => return None,
+    };
+    match operands.next() {
+        None => Some(pred),
+        Some(_) => None,
+    }
+}
+
+/// Flatten `pred` into the number of definite ranges, followed by the
+/// inclusive bounds of each definite range and then of each maybe range.
+pub fn code_predicate_to_ints(pred: &CodePredicate) -> Vec<jint> {
+    let mut ints = Vec::with_capacity(1 + 2 * (pred.definite.len() + pred.maybe.len()));
+    ints.push(pred.definite.len() as jint);
+    for r in pred.definite.iter().chain(pred.maybe.iter()) {
+        ints.push(r.start().0 as jint);
+        ints.push(r.end().0 as jint);
+    }
+    ints
+}
+
+#[derive(Debug)]
+enum BindingError {
+    Jni(jni::errors::Error),
+    BadMode(jint),
+    NullDict,
+    BadPredicate(jint),
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingError::Jni(e) => write!(f, "{}", e),
+            BindingError::BadMode(bits) => {
+                write!(f, "mode must be 8, 12 or 16 bits, not {}", bits)
+            }
+            BindingError::NullDict => write!(f, "null dictionary handle"),
+            BindingError::BadPredicate(op) => {
+                write!(f, "wrong operands for predicate operator {}", op)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+impl From<jni::errors::Error> for BindingError {
+    fn from(e: jni::errors::Error) -> Self {
+        BindingError::Jni(e)
+    }
+}
+
+type Result<T> = std::result::Result<T, BindingError>;
+
+fn mode_of(bits: jint) -> Result<Mode> {
+    std::convert::TryFrom::try_from(bits)
+        .ok()
+        .and_then(Mode::from_bits)
+        .ok_or(BindingError::BadMode(bits))
+}
+
+fn read<T: TypeArray + Default + Clone>(env: &Env, array: &JPrimitiveArray<T>) -> Result<Vec<T>> {
+    let mut buf = vec![T::default(); array.len(env)?];
+    array.get_region(env, 0, &mut buf)?;
+    Ok(buf)
+}
+
+/// # Safety
+///
+/// `dict` must be 0 or a live handle made from a `Box<Dict<T>>`.
+unsafe fn dict_ref<'a, T: ValReq>(dict: jlong) -> Result<&'a Dict<T>> {
+    (dict as *const Dict<T>)
+        .as_ref()
+        .ok_or(BindingError::NullDict)
+}
+
+fn new_dict<T: ValReq>(dict: Dict<T>) -> jlong {
+    Box::into_raw(Box::new(dict)) as jlong
+}
+
+/// # Safety
+///
+/// `dict` must be 0 or a live handle made from a `Box<Dict<T>>`, which is
+/// invalid afterwards.
+unsafe fn free_dict<T: ValReq>(dict: jlong) {
+    if dict != 0 {
+        drop(Box::from_raw(dict as *mut Dict<T>));
+    }
+}
+
+fn encode<'local, T: ValReq>(
+    env: &mut Env<'local>,
+    dict: &Dict<T>,
+    values: impl Iterator<Item = T>,
+) -> Result<JShortArray<'local>> {
+    let codes: Vec<i16> = values.map(|v| dict.encode(&v).0 as i16).collect();
+    let out = JShortArray::new(env, codes.len())?;
+    out.set_region(env, 0, &codes)?;
+    Ok(out)
+}
+
+fn rewrite<'local, T: ValReq>(
+    env: &mut Env<'local>,
+    dict: &Dict<T>,
+    op: jint,
+    operands: Vec<T>,
+) -> Result<JIntArray<'local>> {
+    let pred = predicate_from_op(op, operands).ok_or(BindingError::BadPredicate(op))?;
+    let ints = code_predicate_to_ints(&dict.rewrite(&pred));
+    let out = JIntArray::new(env, ints.len())?;
+    out.set_region(env, 0, &ints)?;
+    Ok(out)
+}
+
+fn f64_key(f: &f64) -> DictF64 {
+    DictF64(FloatOrd(*f))
+}
+
+type Throw = jni::errors::ThrowRuntimeExAndDefault;
+
+/// `long Native.dictI64New(int modeBits, long[] sample)`.
+#[no_mangle]
+pub extern "system" fn Java_org_ordbog_Native_dictI64New<'caller>(
+    mut env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    mode_bits: jint,
+    sample: JLongArray<'caller>,
+) -> jlong {
+    env.with_env(|env| -> Result<jlong> {
+        let mode = mode_of(mode_bits)?;
+        Ok(new_dict(Dict::new(mode, read(env, &sample)?)))
+    })
+    .resolve::<Throw>()
+}
+
+/// `void Native.dictI64Free(long dict)`.
+///
+/// # Safety
+///
+/// `dict` must be 0 or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_ordbog_Native_dictI64Free<'caller>(
+    _env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    dict: jlong,
+) {
+    free_dict::<i64>(dict)
+}
+
+/// `short[] Native.dictI64Encode(long dict, long[] values)`.
+///
+/// # Safety
+///
+/// `dict` must be 0 or a live handle.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_ordbog_Native_dictI64Encode<'caller>(
+    mut env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    dict: jlong,
+    values: JLongArray<'caller>,
+) -> JShortArray<'caller> {
+    env.with_env(|env| -> Result<JShortArray<'caller>> {
+        let dict = dict_ref::<i64>(dict)?;
+        let values = read(env, &values)?;
+        encode(env, dict, values.into_iter())
+    })
+    .resolve::<Throw>()
+}
+
+/// `int[] Native.dictI64Rewrite(long dict, int op, long[] operands)`.
+///
+/// # Safety
+///
+/// `dict` must be 0 or a live handle.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_ordbog_Native_dictI64Rewrite<'caller>(
+    mut env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    dict: jlong,
+    op: jint,
+    operands: JLongArray<'caller>,
+) -> JIntArray<'caller> {
+    env.with_env(|env| -> Result<JIntArray<'caller>> {
+        let dict = dict_ref::<i64>(dict)?;
+        let operands = read(env, &operands)?;
+        rewrite(env, dict, op, operands)
+    })
+    .resolve::<Throw>()
+}
+
+/// `long Native.dictF64New(int modeBits, double[] sample)`.
+#[no_mangle]
+pub extern "system" fn Java_org_ordbog_Native_dictF64New<'caller>(
+    mut env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    mode_bits: jint,
+    sample: JDoubleArray<'caller>,
+) -> jlong {
+    env.with_env(|env| -> Result<jlong> {
+        let mode = mode_of(mode_bits)?;
+        let sample = read(env, &sample)?;
+        Ok(new_dict(Dict::new_with_key(mode, &sample, f64_key)))
+    })
+    .resolve::<Throw>()
+}
+
+/// `void Native.dictF64Free(long dict)`.
+///
+/// # Safety
+///
+/// `dict` must be 0 or a live handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_ordbog_Native_dictF64Free<'caller>(
+    _env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    dict: jlong,
+) {
+    free_dict::<DictF64>(dict)
+}
+
+/// `short[] Native.dictF64Encode(long dict, double[] values)`.
+///
+/// # Safety
+///
+/// `dict` must be 0 or a live handle.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_ordbog_Native_dictF64Encode<'caller>(
+    mut env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    dict: jlong,
+    values: JDoubleArray<'caller>,
+) -> JShortArray<'caller> {
+    env.with_env(|env| -> Result<JShortArray<'caller>> {
+        let dict = dict_ref::<DictF64>(dict)?;
+        let values = read(env, &values)?;
+        encode(env, dict, values.iter().map(f64_key))
+    })
+    .resolve::<Throw>()
+}
+
+/// `int[] Native.dictF64Rewrite(long dict, int op, double[] operands)`.
+///
+/// # Safety
+///
+/// `dict` must be 0 or a live handle.
+#[no_mangle]
+pub unsafe extern "system" fn Java_org_ordbog_Native_dictF64Rewrite<'caller>(
+    mut env: EnvUnowned<'caller>,
+    _class: JClass<'caller>,
+    dict: jlong,
+    op: jint,
+    operands: JDoubleArray<'caller>,
+) -> JIntArray<'caller> {
+    env.with_env(|env| -> Result<JIntArray<'caller>> {
+        let dict = dict_ref::<DictF64>(dict)?;
+        let operands = read(env, &operands)?;
+        rewrite(env, dict, op, operands.iter().map(f64_key).collect())
+    })
+    .resolve::<Throw>()
+}
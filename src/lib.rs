@@ -34,11 +34,12 @@
 //!
 //! The codes it produces have the following characteristics:
 //!
-//!   1. Each code value is logically 8 or 16 bits (depending on the `Mode`
-//!      enum). The user decides whether to operate with 8 or 16 bits: 8 bit
+//!   1. Each code value is logically 8, 12 or 16 bits (depending on the `Mode`
+//!      enum). The user decides whether to operate with 8, 12 or 16 bits: 8 bit
 //!      codes should be used for memory-only scans, to elide 64-byte cache-line
 //!      accesses; 16 bit codes should be used for disk scans, to elide 4k page
-//!      accesses.
+//!      accesses; 12 bit codes, stored two to every three bytes, sit between
+//!      the two.
 //!
 //!   2. Code value 0 is unused, so that subsequent compression can use it as a
 //!      sentinel or missing-value code.
@@ -87,9 +88,89 @@
 //! > Etymology: From ord ("word") +‎ bog ("book"). Compare Swedish ordbok,
 //! > English wordbook, German Wörterbuch.
 
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
+
 use float_ord::FloatOrd;
+use std::borrow::Borrow;
 use std::fmt::Debug;
 
+pub mod adaptive;
+pub mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+// Declared ahead of the modules that use its macro.
+#[cfg(any(feature = "arrow2", feature = "polars-arrow"))]
+#[macro_use]
+mod arrow_compat;
+#[cfg(feature = "arrow2")]
+pub mod arrow2;
+pub mod catalog;
+pub mod codeset;
+pub mod column;
+#[cfg(feature = "datafusion")]
+pub mod datafusion;
+pub mod equidepth;
+pub mod family;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod gapped;
+pub mod groupby;
+pub mod hashed;
+pub mod hierarchical;
+pub mod hilbert;
+pub mod hybrid;
+pub mod interval;
+#[cfg(feature = "jni")]
+pub mod jni;
+pub mod materialize;
+pub mod memo;
+#[cfg(all(feature = "mmap", target_endian = "little"))]
+pub mod mmap;
+#[cfg(feature = "napi")]
+pub mod napi;
+#[cfg(feature = "orc")]
+pub mod orc;
+pub mod parquet;
+pub mod planner;
+#[cfg(feature = "polars-arrow")]
+pub mod polars_arrow;
+pub mod predicate;
+mod quadtree;
+pub mod quality;
+#[cfg(feature = "test-util")]
+pub mod reference;
+pub mod regex;
+#[cfg(feature = "tokio")]
+pub mod remote;
+pub mod sampler;
+pub mod scan;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod sketch;
+pub mod sketchfile;
+#[cfg(feature = "futures")]
+pub mod stream;
+#[cfg(feature = "swap")]
+mod swap;
+#[cfg(feature = "swap")]
+pub use swap::SwappableDict;
+pub mod substrait;
+pub mod summary;
+pub mod tablemetrics;
+pub mod time;
+pub mod topn;
+pub mod verify;
+mod view;
+pub use view::DictView;
+#[cfg(feature = "viz")]
+pub mod viz;
+pub mod window;
+pub mod workload;
+pub mod writer;
+pub mod zonemap;
+pub mod zorder;
+
 /// Wrapper that supplies a Default (1.0) value around [FloatOrd]. This is the
 /// type to use for a [Dict] of underlying [f64] values.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -114,8 +195,9 @@ impl Default for DictF32 {
 
 /// Wrapper for a [Dict] code value. If the [Dict] was
 /// built with [Mode::Byte], this will have values ranging only
-/// over `[1,255]`. If the [Dict] was built with [Mode::Word],
-/// this will have values ranging over `[1,65535]`.
+/// over `[1,255]`. If the [Dict] was built with [Mode::Packed],
+/// this will have values ranging over `[1,4095]`, and if it was
+/// built with [Mode::Word], over `[1,65535]`.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub struct Code(pub u16);
 impl Code {
@@ -126,10 +208,196 @@ impl Code {
     pub fn is_exact(&self) -> bool {
         (self.0 & 1) == 0
     }
+
+    /// Return true iff the code is an _inexact_ code, standing for the open
+    /// interval of values between two exact codes' values. This is true iff
+    /// the code is an odd number.
+    pub fn is_inexact(&self) -> bool {
+        !self.is_exact()
+    }
+
+    /// Return the code below this one, saturating at the least code of
+    /// `mode`, code 1.
+    pub fn predecessor(&self, mode: Mode) -> Code {
+        Code(self.0.saturating_sub(1).clamp(1, mode.max_inexact_code().0))
+    }
+
+    /// Return the code above this one, saturating at the greatest code of
+    /// `mode`.
+    pub fn successor(&self, mode: Mode) -> Code {
+        Code(self.0.saturating_add(1).clamp(1, mode.max_inexact_code().0))
+    }
+
+    /// Return the [DenseCode] for this code, or `None` for code 0.
+    pub fn to_dense(&self) -> Option<DenseCode> {
+        self.0.checked_sub(1).map(DenseCode)
+    }
+}
+
+/// A [Dict] code value in the dense layout, which has no missing-value
+/// sentinel: it is the [Code] less one, so ranges over `[0,254]` in
+/// [Mode::Byte], `[0,4094]` in [Mode::Packed] and `[0,65534]` in
+/// [Mode::Word], and exact codes are _odd_.
+/// Being a distinct type keeps dense and sentinel-bearing codes from being
+/// mixed up in one column. See [BuildOptions::dense_codes].
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct DenseCode(pub u16);
+impl DenseCode {
+    /// Return true iff the code is an _exact_ code. This is true iff the
+    /// dense code is an odd number.
+    pub fn is_exact(&self) -> bool {
+        (self.0 & 1) == 1
+    }
+
+    /// Return the [Code] for this dense code, or `None` for the top value
+    /// of [Mode::Word], which has none.
+    pub fn to_code(&self) -> Option<Code> {
+        self.0.checked_add(1).map(Code)
+    }
+}
+
+/// An inclusive range of [Code]s, `lo..=hi`, that can be iterated, counted
+/// and mapped over. `RangeInclusive<Code>` can't be, since the trait that
+/// would make it so is unstable; the two convert into one another.
+/// Empty if `lo > hi`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CodeRange {
+    pub lo: Code,
+    pub hi: Code,
+}
+
+impl CodeRange {
+    /// Make the range `lo..=hi`.
+    pub fn new(lo: Code, hi: Code) -> Self {
+        CodeRange { lo, hi }
+    }
+
+    /// Return the number of codes in the range.
+    pub fn len(&self) -> usize {
+        (self.hi.0 as usize + 1).saturating_sub(self.lo.0 as usize)
+    }
+
+    /// Return true iff the range holds no codes.
+    pub fn is_empty(&self) -> bool {
+        self.lo > self.hi
+    }
+
+    /// Return true iff `code` is in the range.
+    pub fn contains(&self, code: Code) -> bool {
+        self.lo <= code && code <= self.hi
+    }
+
+    /// Iterate over the codes in the range, in order.
+    pub fn iter(&self) -> CodeIter {
+        CodeIter {
+            next: self.lo.0 as u32,
+            end: self.hi.0 as u32 + 1,
+        }
+    }
+}
+
+impl From<std::ops::RangeInclusive<Code>> for CodeRange {
+    fn from(range: std::ops::RangeInclusive<Code>) -> Self {
+        CodeRange::new(*range.start(), *range.end())
+    }
+}
+
+impl From<CodeRange> for std::ops::RangeInclusive<Code> {
+    fn from(range: CodeRange) -> Self {
+        range.lo..=range.hi
+    }
+}
+
+impl IntoIterator for CodeRange {
+    type Item = Code;
+    type IntoIter = CodeIter;
+    fn into_iter(self) -> CodeIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the codes of a [CodeRange].
+#[derive(Clone, Debug)]
+pub struct CodeIter {
+    // Both as u32, so a range ending at 0xffff can be exhausted.
+    next: u32,
+    end: u32,
+}
+
+impl Iterator for CodeIter {
+    type Item = Code;
+    fn next(&mut self) -> Option<Code> {
+        if self.next >= self.end {
+            return None;
+        }
+        self.next += 1;
+        Some(Code((self.next - 1) as u16))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.end.saturating_sub(self.next) as usize;
+        (n, Some(n))
+    }
+}
+
+impl DoubleEndedIterator for CodeIter {
+    fn next_back(&mut self) -> Option<Code> {
+        if self.next >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(Code(self.end as u16))
+    }
+}
+
+impl ExactSizeIterator for CodeIter {}
+
+impl std::iter::FusedIterator for CodeIter {}
+
+/// The values a [Code] stands for, as given by [Dict::decode].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ValueRange<T> {
+    /// An exact code's single value.
+    Exact(T),
+    /// An inexact code's open interval of values, strictly between `lower`
+    /// and `upper`. A missing bound means the interval is unbounded on that
+    /// side.
+    Between {
+        /// The greatest value below the interval, if any.
+        lower: Option<T>,
+        /// The least value above the interval, if any.
+        upper: Option<T>,
+    },
+}
+
+impl<T: Ord + Clone> ValueRange<T> {
+    /// Return true iff `value` is in the range.
+    pub fn contains(&self, value: &T) -> bool {
+        match self {
+            ValueRange::Exact(v) => v == value,
+            ValueRange::Between { lower, upper } => {
+                lower.as_ref().is_none_or(|l| l < value)
+                    && upper.as_ref().is_none_or(|u| value < u)
+            }
+        }
+    }
+
+    /// Return the range as a predicate on values, holding of exactly the
+    /// values in it.
+    pub fn to_predicate(self) -> workload::RangePredicate<T> {
+        use std::ops::Bound::*;
+        match self {
+            ValueRange::Exact(v) => (Included(v.clone()), Included(v)),
+            ValueRange::Between { lower, upper } => (
+                lower.map_or(Unbounded, Excluded),
+                upper.map_or(Unbounded, Excluded),
+            ),
+        }
+    }
 }
 
-/// Indicates whether to build a small [Dict] of up to 255 values
-/// or a larger one of up to 65535 values.
+/// Indicates whether to build a small [Dict] of up to 255 values, a
+/// middling one of up to 4095 values or a larger one of up to 65535 values.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub enum Mode {
     /// Build a [Dict] with up to 255 codes ranging over `[1,255]`. This mode is
@@ -139,42 +407,271 @@ pub enum Mode {
     /// positive) is offset by the small size of each storage block (and thus
     /// small number of elements).
     Byte,
+    /// Build a [Dict] with up to 4095 codes ranging over `[1,4095]`, each
+    /// stored in 12 bits, two to every three bytes. This mode is a middle
+    /// ground for sketches of blocks too large for 255 codes to separate
+    /// well, but whose sketches can't afford two bytes per element.
+    Packed,
     /// Build a [Dict] with up to 65535 codes ranging over `[1,65535]`. This
     /// mode is most appropriate when building a sketch that elides access to
     /// larger underlying storage blocks like 4096-byte pages, where the larger
     /// number of elements per storage block demands a comparatively low false
     /// positive probability per element.
+    ///
+    /// To check which mode suits a particular column and block size, see
+    /// [quality::simulate_blocks].
     Word,
 }
 impl Mode {
-    /// Returns the count of exact codes in the mode: either `127` for
-    /// [Mode::Byte] or `32767` for [Mode::Word].
+    /// Returns the count of exact codes in the mode: `127` for [Mode::Byte],
+    /// `2047` for [Mode::Packed] or `32767` for [Mode::Word].
     pub fn num_exact_codes(&self) -> usize {
         match self {
             Mode::Byte => 127,
+            Mode::Packed => 2047,
             Mode::Word => 32767,
         }
     }
-    /// Returns the maximum exact code in the mode: either `0xfe` for
-    /// [Mode::Byte] or `0xfffe` for [Mode::Word].
+    /// Returns the maximum exact code in the mode: `0xfe` for [Mode::Byte],
+    /// `0xffe` for [Mode::Packed] or `0xfffe` for [Mode::Word].
     pub fn max_exact_code(&self) -> Code {
         match self {
             Mode::Byte => Code(0xfe),
+            Mode::Packed => Code(0xffe),
             Mode::Word => Code(0xfffe),
         }
     }
-    /// Returns the maximum inexact code in the mode: either `0xff` for
-    /// [Mode::Byte] or `0xffff` for [Mode::Word].
+    /// Returns the maximum inexact code in the mode: `0xff` for
+    /// [Mode::Byte], `0xfff` for [Mode::Packed] or `0xffff` for [Mode::Word].
     pub fn max_inexact_code(&self) -> Code {
         match self {
             Mode::Byte => Code(0xff),
+            Mode::Packed => Code(0xfff),
             Mode::Word => Code(0xffff),
         }
     }
+    /// Returns the number of bits each code of the mode is stored in: 8, 12
+    /// or 16. This is also how files record the mode.
+    pub fn bits(&self) -> u8 {
+        match self {
+            Mode::Byte => 8,
+            Mode::Packed => 12,
+            Mode::Word => 16,
+        }
+    }
+    /// Returns the mode whose codes are stored in `bits` bits, if any.
+    pub fn from_bits(bits: u8) -> Option<Mode> {
+        match bits {
+            8 => Some(Mode::Byte),
+            12 => Some(Mode::Packed),
+            16 => Some(Mode::Word),
+            _ => None,
+        }
+    }
+    /// Returns the number of bytes `rows` codes of the mode take stored
+    /// together: in [Mode::Packed], an odd last code takes two bytes.
+    pub fn stored_len(&self, rows: usize) -> usize {
+        (rows * self.bits() as usize).div_ceil(8)
+    }
+    /// Appends `codes` to `out` as the mode stores them: a byte each in
+    /// [Mode::Byte], a little-endian u16 each in [Mode::Word], and in
+    /// [Mode::Packed] each pair `a, b` as the three bytes of the 24-bit
+    /// little-endian `a | b << 12`, with an odd last code as a u16.
+    ///
+    /// Panics if a code is wider than the mode.
+    pub fn pack_codes(&self, codes: &[Code], out: &mut Vec<u8>) {
+        assert!(codes.iter().all(|c| *c <= self.max_inexact_code()));
+        match self {
+            Mode::Byte => out.extend(codes.iter().map(|c| c.0 as u8)),
+            Mode::Packed => {
+                for pair in codes.chunks(2) {
+                    let (a, b) = (pair[0].0, pair.get(1).map_or(0, |c| c.0));
+                    out.push(a as u8);
+                    out.push((a >> 8) as u8 | (b << 4) as u8);
+                    if pair.len() == 2 {
+                        out.push((b >> 4) as u8);
+                    }
+                }
+            }
+            Mode::Word => {
+                for c in codes {
+                    out.extend_from_slice(&c.0.to_le_bytes());
+                }
+            }
+        }
+    }
+    /// Returns code `i` of `stored`, codes stored as [Mode::pack_codes]
+    /// stores them.
+    pub fn stored_code(&self, stored: &[u8], i: usize) -> Code {
+        match self {
+            Mode::Byte => Code(stored[i] as u16),
+            Mode::Packed => {
+                let at = i / 2 * 3;
+                if i.is_multiple_of(2) {
+                    Code(stored[at] as u16 | (stored[at + 1] as u16 & 0xf) << 8)
+                } else {
+                    Code((stored[at + 1] >> 4) as u16 | (stored[at + 2] as u16) << 4)
+                }
+            }
+            Mode::Word => Code(u16::from_le_bytes([stored[2 * i], stored[2 * i + 1]])),
+        }
+    }
+    /// Appends the codes of `stored`, codes stored as [Mode::pack_codes]
+    /// stores them, to `out`.
+    pub fn unpack_codes(&self, stored: &[u8], out: &mut Vec<Code>) {
+        let n = stored.len() * 8 / self.bits() as usize;
+        out.extend((0..n).map(|i| self.stored_code(stored, i)));
+    }
+    /// Returns the range of every code a [Dict] in the mode can produce:
+    /// `1..=0xff` for [Mode::Byte], `1..=0xfff` for [Mode::Packed] or
+    /// `1..=0xffff` for [Mode::Word].
+    pub fn codes(&self) -> CodeRange {
+        CodeRange::new(Code(1), self.max_inexact_code())
+    }
+    /// Returns a code biased into the signed range of the mode's width,
+    /// preserving order, for engines whose vector compares are signed-only.
+    /// [Mode::Byte] codes map into `[-128,127]` (so fit an [i8]),
+    /// [Mode::Packed] codes into `[-2048,2047]` and [Mode::Word] codes into
+    /// `[-32768,32767]`. Code 0 maps to the least value.
+    pub fn to_signed(&self, code: Code) -> i16 {
+        match self {
+            Mode::Byte => code.0 as i16 - 0x80,
+            Mode::Packed => code.0 as i16 - 0x800,
+            Mode::Word => (code.0 ^ 0x8000) as i16,
+        }
+    }
+    /// Returns the code that [Mode::to_signed] maps to `signed`.
+    pub fn from_signed(&self, signed: i16) -> Code {
+        match self {
+            Mode::Byte => Code((signed + 0x80) as u16),
+            Mode::Packed => Code((signed + 0x800) as u16),
+            Mode::Word => Code(signed as u16 ^ 0x8000),
+        }
+    }
+}
+
+/// Options for building a [Dict] with [Dict::new_with_options]. The
+/// defaults are what [Dict::new] uses.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct BuildOptions {
+    /// Keep, inside the dictionary, the number of sample values each code
+    /// covered, for [Dict::code_weight] and [Dict::code_quantiles]. This
+    /// costs one `u64` per code: up to 2KiB in [Mode::Byte] and 512KiB in
+    /// [Mode::Word].
+    pub retain_weights: bool,
+
+    /// If nonzero, keep inside the dictionary a [summary::SampleSummary] of
+    /// the sample of about this many entries (at most twice as many), for
+    /// [Dict::summary] and later refits with [Dict::from_summary].
+    pub summary_capacity: usize,
+
+    /// Reserve this many of the mode's highest code values, as well as code
+    /// 0, for the caller's own sentinels (a deletion marker, out-of-band
+    /// categories and so on). The dictionary assigns correspondingly fewer
+    /// exact codes so that [Dict::encode] never returns a reserved code; see
+    /// [Dict::reserved_codes]. At most `0xfc` in [Mode::Byte] and `0xfffc`
+    /// in [Mode::Word], leaving room for one exact code.
+    pub reserved_codes: u16,
+
+    /// Give up the code-0 sentinel, for callers who store codes as
+    /// [DenseCode]s with [Dict::encode_dense]. This frees the mode's top
+    /// code value, which counts towards [BuildOptions::reserved_codes]: when
+    /// an odd number of codes is reserved, a dense dictionary gets one more
+    /// exact code than a sparse one.
+    pub dense_codes: bool,
+
+    /// If larger than the number of exact codes assigned, the capacity of
+    /// the dictionary's [Dict::codes] vector, so that later growth with
+    /// [Dict::extend_max] up to this many codes doesn't reallocate. Zero,
+    /// the default, leaves the vector exactly sized.
+    pub code_capacity: usize,
+}
+
+impl BuildOptions {
+    /// Returns these options with [BuildOptions::code_capacity] set to
+    /// `capacity`.
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        BuildOptions {
+            code_capacity: capacity,
+            ..self
+        }
+    }
+
+    /// Returns the count of exact codes a dictionary built in `mode` with
+    /// these options may assign: [Mode::num_exact_codes] less those given
+    /// up to [BuildOptions::reserved_codes].
+    pub fn num_exact_codes(&self, mode: Mode) -> usize {
+        // The number of code values available, not counting code 0 unless
+        // codes are dense.
+        let values = mode.max_inexact_code().0 as usize + self.dense_codes as usize;
+        let reserved = self.reserved_codes as usize;
+        assert!(reserved + 3 <= values, "too many reserved codes for mode");
+        // The 2*n+1 codes used must fall below the reserved band.
+        (values - reserved - 1) / 2
+    }
+
+    // Whether a stored dictionary with these options' reserved band and
+    // layout, and `num_codes` exact codes, could have been built in `mode`.
+    pub(crate) fn stored_layout_fits(&self, mode: Mode, num_codes: usize) -> bool {
+        let values = mode.max_inexact_code().0 as usize + self.dense_codes as usize;
+        self.reserved_codes as usize + 3 <= values && num_codes <= self.num_exact_codes(mode)
+    }
+}
+
+/// Why [Dict::new_exact] could not build a dictionary: the sample has more
+/// distinct values than the mode has exact codes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CardinalityError {
+    /// The number of distinct values in the sample.
+    pub distinct: usize,
+    /// The number of exact codes available.
+    pub limit: usize,
+}
+
+impl std::fmt::Display for CardinalityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sample has {} distinct values but only {} exact codes",
+            self.distinct, self.limit
+        )
+    }
+}
+
+impl std::error::Error for CardinalityError {}
+
+/// Why [Dict::encode_into_u8] or [Dict::encode_into_u16] refused to encode:
+/// the dictionary's mode stores codes at a different width from the buffer.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct WidthError {
+    /// The dictionary's mode.
+    pub mode: Mode,
+    /// The width of the buffer's elements, in bits.
+    pub bits: u8,
+}
+
+impl std::fmt::Display for WidthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} mode stores {}-bit codes, not {}-bit",
+            self.mode,
+            self.mode.bits(),
+            self.bits
+        )
+    }
 }
 
+impl std::error::Error for WidthError {}
+
 /// Trait expressing requirements for the types of underlying values
 /// that can be encoded in a [Dict].
+///
+/// `Option<T>` meets them whenever `T` does, for columns whose values are
+/// genuinely optional rather than marked missing with code 0. `None` sorts,
+/// and so codes, below every present value; see
+/// [predicate::CodePredicate::from_optional_range] for rewriting
+/// comparisons on such a column.
 pub trait ValReq : Ord + Clone + Default /*+ Debug*/ {}
 impl<T> ValReq for T where T : Ord + Clone + Default /*+ Debug*/ {}
 
@@ -185,6 +682,15 @@ struct Cluster<T: ValReq> {
 /// A dictionary over an underlying type `T` conforming to [ValReq]. The
 /// dictionary maps underlying values to [Code]s to use in a sketch, using
 /// [Dict::encode].
+///
+/// Once built, a dictionary is only modified through `&mut self` methods
+/// such as [Dict::extend_max]: encoding takes `&self` and has no interior
+/// mutability, caching or lazy state. A `Dict<T>`
+/// is therefore `Send` and `Sync` whenever `T` is, and a single dictionary can
+/// be shared (for example behind an [std::sync::Arc] or a scoped borrow)
+/// between any number of threads encoding and scanning concurrently. Callers
+/// that mutate the public fields directly take on the job of synchronizing
+/// those mutations themselves.
 pub struct Dict<T: ValReq> {
 
     /// The mode the dictionary was built in.
@@ -194,10 +700,26 @@ pub struct Dict<T: ValReq> {
     /// Implicitly defines both exact and inexact code values based on the
     /// positions of exact codes in the vector.
     pub codes: Vec<T>,
+
+    // The number of sample values each code covered, indexed by code - 1,
+    // when known.
+    weights: Option<Vec<u64>>,
+
+    // A summary of the construction sample, when kept.
+    summary: Option<summary::SampleSummary<T>>,
+
+    // The number of top code values reserved for the caller.
+    reserved: u16,
+
+    // Whether the reserved band is counted in dense codes.
+    dense: bool,
+
+    // Whether every distinct sample value got an exact code.
+    complete: bool,
 }
 
 impl<T: ValReq> Dict<T> {
-    fn clusters(sorted_sample: &Vec<T>) -> Vec<Cluster<T>> {
+    fn clusters(sorted_sample: &[T]) -> Vec<Cluster<T>> {
         let mut clu = Vec::with_capacity(sorted_sample.len());
         if !sorted_sample.is_empty() {
             let mut curr = &sorted_sample[0];
@@ -218,24 +740,162 @@ impl<T: ValReq> Dict<T> {
         clu
     }
 
-    /// Look up the code for a value of the underlying value type `T`.
-    pub fn encode(&self, query: &T) -> Code {
-        // The `self.code` array stores the input values assigned to "exact"
-        // codes, counting upwards from code 2. Thus a successful binary search
-        // landing at `idx` returns exact code `2*(idx+1)`. An unsuccessful
-        // binary search lands on the _next_ exact code greater than the query
-        // value, so we subtract 1 from that code to denote the inexact code
-        // covering the range below that next exact code.
-        let code = match self.codes.binary_search(query) {
-            Ok(idx) => 2 * (idx + 1),
-            Err(idx) => (2 * (idx + 1)) - 1,
-        };
-        assert!(code <= 0xffff);
-        Code(code as u16)
+    /// Look up the code for a value, biased into the signed range with
+    /// [Mode::to_signed].
+    pub fn encode_signed<Q: Ord + ?Sized>(&self, query: &Q) -> i16
+    where
+        T: Borrow<Q>,
+    {
+        self.mode.to_signed(self.encode(query))
     }
 
-    fn assign_codes_with_step(codestep: usize, clu: &Vec<Cluster<T>>) -> Vec<T> {
-        let mut codes = Vec::new();
+    /// Look up the code for a value in the dense layout, which has no
+    /// missing-value sentinel. Together with [BuildOptions::dense_codes] this
+    /// frees the mode's top code value.
+    pub fn encode_dense<Q: Ord + ?Sized>(&self, query: &Q) -> DenseCode
+    where
+        T: Borrow<Q>,
+    {
+        DenseCode(self.encode(query).0 - 1)
+    }
+
+    /// Look up the code for a value of the underlying value type `T`, or
+    /// of any type `T` borrows as, as with [BTreeMap::get]: a `Dict<String>`
+    /// encodes a `&str` without allocating.
+    ///
+    /// [BTreeMap::get]: std::collections::BTreeMap::get
+    pub fn encode<Q: Ord + ?Sized>(&self, query: &Q) -> Code
+    where
+        T: Borrow<Q>,
+    {
+        self.view().encode(query)
+    }
+
+    /// Borrow the dictionary as a [DictView].
+    pub fn view(&self) -> DictView<'_, T> {
+        DictView::new(self.mode, &self.codes)
+    }
+
+    /// Look up the codes of each of `values`, in order.
+    pub fn encode_slice(&self, values: &[T]) -> Vec<Code> {
+        let mut out = Vec::with_capacity(values.len());
+        self.encode_extend(values, &mut out);
+        out
+    }
+
+    /// Look up the codes of each of `values`, appending them to `out`. Runs
+    /// of values sharing a code, as in sorted or clustered columns, are
+    /// encoded with a comparison or two per value rather than a search.
+    pub fn encode_extend(&self, values: &[T], out: &mut Vec<Code>) {
+        out.reserve(values.len());
+        self.encode_runs(values, |c| out.push(c));
+    }
+
+    /// Look up the codes of each of `values`, writing them as bytes to the
+    /// same positions of `out`, as a [Mode::Byte] sketch stores them. Fails
+    /// unless the dictionary is in [Mode::Byte].
+    ///
+    /// Panics if `out` is shorter than `values`.
+    pub fn encode_into_u8(&self, values: &[T], out: &mut [u8]) -> Result<(), WidthError> {
+        self.check_width(8)?;
+        let mut slots = out[..values.len()].iter_mut();
+        self.encode_runs(values, |c| *slots.next().unwrap() = c.0 as u8);
+        Ok(())
+    }
+
+    /// Look up the codes of each of `values`, writing them as u16s to the
+    /// same positions of `out`, as a [Mode::Word] sketch stores them. Fails
+    /// unless the dictionary is in [Mode::Word]; for [Mode::Packed], see
+    /// [Mode::pack_codes].
+    ///
+    /// Panics if `out` is shorter than `values`.
+    pub fn encode_into_u16(&self, values: &[T], out: &mut [u16]) -> Result<(), WidthError> {
+        self.check_width(16)?;
+        let mut slots = out[..values.len()].iter_mut();
+        self.encode_runs(values, |c| *slots.next().unwrap() = c.0);
+        Ok(())
+    }
+
+    fn check_width(&self, bits: u8) -> Result<(), WidthError> {
+        if self.mode.bits() == bits {
+            Ok(())
+        } else {
+            Err(WidthError {
+                mode: self.mode,
+                bits,
+            })
+        }
+    }
+
+    // Pass the code of each of `values` to `emit`, in order, reusing the
+    // previous value's code while it still holds.
+    pub(crate) fn encode_runs<F: FnMut(Code)>(&self, values: &[T], mut emit: F) {
+        let mut last = None;
+        for v in values {
+            let code = match last {
+                Some(code) if self.code_holds(code, v) => code,
+                _ => self.encode(v),
+            };
+            last = Some(code);
+            emit(code);
+        }
+    }
+
+    // Whether `code`, one that `encode` returned, is the code of `query`.
+    fn code_holds(&self, code: Code, query: &T) -> bool {
+        match self.code_bounds(code) {
+            (Some(v), _) if code.is_exact() => v == query,
+            (lower, upper) => {
+                lower.is_none_or(|l| l < query) && upper.is_none_or(|u| query < u)
+            }
+        }
+    }
+
+    /// Return the values bracketing `code`: for an inexact code, the values
+    /// of the exact codes either side of it, None on a side where it is
+    /// unbounded; for an exact code, its value twice. Code 0 and codes
+    /// above the dictionary's last, which [Dict::encode] never returns,
+    /// give `(None, None)`.
+    pub fn code_bounds(&self, code: Code) -> (Option<&T>, Option<&T>) {
+        self.view().code_bounds(code)
+    }
+
+    /// Return the values `code` stands for: the value of an exact code, or
+    /// the open interval between the neighbouring exact codes' values of an
+    /// inexact one. Returns None for code 0 and for codes above the
+    /// dictionary's last, which [Dict::encode] never returns.
+    pub fn decode(&self, code: Code) -> Option<ValueRange<T>> {
+        self.view().decode(code)
+    }
+
+    /// Rewrite `pred`, a predicate on this dictionary's values, onto its
+    /// codes: the codes all of whose values satisfy it are definite, and
+    /// those only some of whose values do are maybe codes. See
+    /// [predicate::CodePredicate::from_range] and
+    /// [predicate::CodePredicate::from_in_list].
+    pub fn rewrite(&self, pred: &predicate::Predicate<T>) -> predicate::CodePredicate {
+        match pred {
+            predicate::Predicate::InSet(values) => {
+                predicate::CodePredicate::from_in_list(self, values)
+            }
+            _ => match pred.to_range() {
+                Some(range) => predicate::CodePredicate::from_range(self, &range),
+                None => predicate::CodePredicate::default(),
+            },
+        }
+    }
+
+    /// Break `sample` down by code, giving each code's share of the sample
+    /// along with the bounds of the values it covers. The result is a plain
+    /// data structure meant for charting dictionary health in an external
+    /// monitoring system; see [quality::Histogram].
+    pub fn export_histogram(&self, sample: &[T]) -> quality::Histogram<T> {
+        quality::Histogram::of(self, sample)
+    }
+
+    // Call `pick` with the index of the cluster given each exact code, when
+    // each code covers about `codestep` of the sample.
+    fn step_through_clusters(codestep: usize, clu: &[Cluster<T>], mut pick: impl FnMut(usize)) {
         let mut first_idx = 0;
         while first_idx < clu.len() {
             let mut last_idx = first_idx;
@@ -248,20 +908,63 @@ impl<T: ValReq> Dict<T> {
                 cluster_count_sum += clu[last_idx].count;
                 last_idx += 1;
             }
-            codes.push(clu[idx_with_max_val].value.clone());
+            pick(idx_with_max_val);
             // FIXME: boundary condition might be wrong here, I think?
             // does this skip the end of each cluster? Why is life full
             // of boundary errors? *Sobs* I am such a fool.
             first_idx = last_idx + 1;
         }
+    }
+
+    fn count_codes_with_step(codestep: usize, clu: &[Cluster<T>]) -> usize {
+        let mut count = 0;
+        Self::step_through_clusters(codestep, clu, |_| count += 1);
+        count
+    }
+
+    // Push the first `ncodes` exact codes at `codestep` onto `codes`.
+    fn assign_codes_with_step(
+        codestep: usize,
+        clu: &[Cluster<T>],
+        codes: &mut Vec<T>,
+        ncodes: usize,
+    ) {
+        Self::step_through_clusters(codestep, clu, |i| {
+            if codes.len() < ncodes {
+                codes.push(clu[i].value.clone())
+            }
+        });
+    }
+
+    // The values this crate's algorithm gives exact codes, given the runs of
+    // equal values in a sorted sample and the number of exact codes
+    // available.
+    pub(crate) fn exact_values(runs: &[(T, usize)], ncodes: usize) -> Vec<T> {
+        let clu: Vec<Cluster<T>> = runs
+            .iter()
+            .map(|(value, count)| Cluster {
+                value: value.clone(),
+                count: *count,
+            })
+            .collect();
+        if clu.len() <= ncodes || ncodes == 0 {
+            return clu.into_iter().take(ncodes).map(|c| c.value).collect();
+        }
+        let samplesize = clu.iter().map(|c| c.count).sum();
+        let mut codes = Vec::new();
+        Self::assign_codes_with_minimal_step(samplesize, ncodes, &clu, &mut codes, 0);
         codes
     }
 
+    // Write the exact codes for `clu` into `codes`, which is empty, having
+    // first made room for them or for `capacity` codes, whichever is more.
     fn assign_codes_with_minimal_step(
         samplesize: usize,
         ncodes: usize,
-        clu: &Vec<Cluster<T>>,
-    ) -> Vec<T> {
+        clu: &[Cluster<T>],
+        codes: &mut Vec<T>,
+        capacity: usize,
+    ) {
         assert!(samplesize != 0);
         assert!(ncodes != 0);
         assert!(ncodes < samplesize);
@@ -278,7 +981,9 @@ impl<T: ValReq> Dict<T> {
 
         // We start with a basic dictionary with each code covering `codestep`
         // sample vaules, calculated by taking elements from the cluster list.
-        let mut codes = Self::assign_codes_with_step(codestep, &clu);
+        // Only the number of codes each step gives matters until the step is
+        // settled, so the codes themselves are written out once, at the end.
+        let mut ncodes_at_step = Self::count_codes_with_step(codestep, clu);
 
         // Unfortunately it's possible some of those clusters overshoot the
         // `codestep`, giving us codes that cover too many sample values and
@@ -288,39 +993,40 @@ impl<T: ValReq> Dict<T> {
         // to get as close as possible (without going over) the target number of
         // codes.
         for _ in 0..=8 {
-            assert!(!codes.is_empty());
+            assert!(ncodes_at_step != 0);
 
-            // If we hit the target we're done.
-            if codes.len() == ncodes {
-                break;
-            }
-
-            // If we overshot the target, truncate the best attempt and return.
-            if codes.len() > ncodes {
-                codes.resize(ncodes, <T as Default>::default());
+            // If we hit the target we're done. If we overshot the target, only
+            // the best attempt's first `ncodes` codes are written below.
+            if ncodes_at_step >= ncodes {
                 break;
             }
 
             // Otherwise estimate, reduce, and (if it's an improvement) accept.
-            assert!(codes.len() < ncodes);
-            let bias = (codes.len() * 10000) / ncodes;
-            codestep *= bias;
-            codestep /= 10000;
+            let bias = (ncodes_at_step * 10000) / ncodes;
+            let next_codestep = codestep * bias / 10000;
             /*
             println!(
                 "wrong number of codes ({}), adjusting step to {}",
-                codes.len(),
-                codestep
+                ncodes_at_step,
+                next_codestep
             );
             */
-            let next_codes = Self::assign_codes_with_step(codestep, &clu);
-            if next_codes.len() <= ncodes {
-                codes = next_codes;
+            let next_ncodes = Self::count_codes_with_step(next_codestep, clu);
+            if next_ncodes <= ncodes {
+                codestep = next_codestep;
+                ncodes_at_step = next_ncodes;
             } else {
                 break;
             }
         }
-        codes
+        Self::reserve_codes(codes, ncodes_at_step.min(ncodes), capacity);
+        Self::assign_codes_with_step(codestep, clu, codes, ncodes);
+    }
+
+    // Make room in `codes`, which is empty, for `len` codes or `capacity`,
+    // whichever is more, and no more than that if it must grow.
+    fn reserve_codes(codes: &mut Vec<T>, len: usize, capacity: usize) {
+        codes.reserve_exact(len.max(capacity));
     }
 
     /// Build a dictionary with a given [Mode] over a provided sample of the
@@ -335,7 +1041,36 @@ impl<T: ValReq> Dict<T> {
     /// This function will sort the sample, so the sample should be small enough
     /// that the caller can tolerate the running time of sorting it. Otherwise
     /// the larger the sample, the more accurate the codes.
-    pub fn new(mode: Mode, mut sample: Vec<T>) -> Self {
+    ///
+    /// The returned dictionary's [Dict::codes] vector is exactly sized: its
+    /// capacity equals its length, so the memory it retains is precisely
+    /// `codes.len() * size_of::<T>()` plus whatever heap storage the values
+    /// themselves own.
+    pub fn new(mode: Mode, sample: Vec<T>) -> Self {
+        Self::new_with_options(mode, sample, &BuildOptions::default())
+    }
+
+    /// Build a dictionary as [Dict::new] does, with non-default
+    /// [BuildOptions].
+    pub fn new_with_options(mode: Mode, sample: Vec<T>, options: &BuildOptions) -> Self {
+        Self::new_with_storage(mode, sample, options, Vec::new())
+    }
+
+    /// Build a dictionary as [Dict::new_with_options] does, writing its
+    /// codes directly into `storage`, a vector the caller allocated (from a
+    /// pool or arena, say) and may get back with [Dict::into_codes].
+    /// `storage` is cleared first, and only grows if it is too small for
+    /// the codes or [BuildOptions::code_capacity]; it is never shrunk.
+    /// Buffers used only while building, such as the sorted sample, are
+    /// still allocated as usual.
+    pub fn new_with_storage(
+        mode: Mode,
+        mut sample: Vec<T>,
+        options: &BuildOptions,
+        mut storage: Vec<T>,
+    ) -> Self {
+        storage.clear();
+
         // println!("beginning building dictionary from {} samples", sample.len());
 
         // For an empty sample we haven't much to work with; assign exact code 2
@@ -343,8 +1078,9 @@ impl<T: ValReq> Dict<T> {
         // will code as 1, any value greater as 3. That's it.
         if sample.is_empty() {
             // println!("empty sample, using 1-element default");
-            let codes = vec![<T as Default>::default()];
-            return Self { mode, codes };
+            Self::reserve_codes(&mut storage, 1, options.code_capacity);
+            storage.push(<T as Default>::default());
+            return Self::finish(mode, storage, &[], options);
         }
 
         // If we have a real sample, we want to sort it both to assign
@@ -353,6 +1089,41 @@ impl<T: ValReq> Dict<T> {
 
         // Do the frequency analysis.
         let clu = Self::clusters(&sample);
+        Self::from_clusters(mode, sample.len(), clu, options, storage)
+    }
+
+    /// Build a dictionary from a [summary::SampleSummary] rather than a
+    /// sample, for example to refit one in a different mode. Each retained
+    /// value stands in for itself and the values between it and the previous
+    /// one, so the result approximates what [Dict::new_with_options] would
+    /// build from the original sample, more closely the larger the summary.
+    pub fn from_summary(
+        mode: Mode,
+        summary: &summary::SampleSummary<T>,
+        options: &BuildOptions,
+    ) -> Self {
+        if summary.entries.is_empty() {
+            return Self::new_with_options(mode, Vec::new(), options);
+        }
+        let clu: Vec<Cluster<T>> = summary
+            .entries
+            .iter()
+            .map(|e| Cluster {
+                value: e.value.clone(),
+                count: (e.count + e.below) as usize,
+            })
+            .collect();
+        let total = clu.iter().map(|c| c.count).sum();
+        Self::from_clusters(mode, total, clu, options, Vec::new())
+    }
+
+    fn from_clusters(
+        mode: Mode,
+        samplesize: usize,
+        clu: Vec<Cluster<T>>,
+        options: &BuildOptions,
+        mut codes: Vec<T>,
+    ) -> Self {
         assert!(!clu.is_empty());
 
         /*
@@ -366,7 +1137,7 @@ impl<T: ValReq> Dict<T> {
         }
         */
 
-        let ncodes = mode.num_exact_codes();
+        let ncodes = options.num_exact_codes(mode);
 
         // If there are the same or fewer clusters than the codespace, we can
         // just assign one code per cluster, there's no need for anything
@@ -377,11 +1148,290 @@ impl<T: ValReq> Dict<T> {
                 "fewer clusters ({}) than target codes {}, using clusters",
                 clu.len(), ncodes);
             */
-            let codes = clu.into_iter().map(|c| c.value).collect();
-            return Self { mode, codes };
+            Self::reserve_codes(&mut codes, clu.len(), options.code_capacity);
+            codes.extend(clu.iter().map(|c| c.value.clone()));
+            return Self::finish(mode, codes, &clu, options);
         }
-        let codes = Self::assign_codes_with_minimal_step(sample.len(), ncodes, &clu);
+        Self::assign_codes_with_minimal_step(
+            samplesize,
+            ncodes,
+            &clu,
+            &mut codes,
+            options.code_capacity,
+        );
         // println!("finished building dictionary with {} exact codes", codes.len());
-        Self { mode, codes }
+        Self::finish(mode, codes, &clu, options)
+    }
+
+    // Finish a dictionary, counting how many of the sample's clusters'
+    // values fall under each code if asked to.
+    fn finish(mode: Mode, codes: Vec<T>, clu: &[Cluster<T>], options: &BuildOptions) -> Self {
+        let mut dict = Self {
+            mode,
+            codes,
+            weights: None,
+            summary: None,
+            reserved: options.reserved_codes,
+            dense: options.dense_codes,
+            complete: false,
+        };
+        if options.summary_capacity != 0 {
+            let runs = clu.iter().map(|c| (&c.value, c.count as u64, 0));
+            dict.summary = Some(summary::SampleSummary::of_runs(
+                runs,
+                0,
+                options.summary_capacity,
+            ));
+        }
+        if !options.retain_weights {
+            return dict;
+        }
+        let mut weights = vec![0u64; 2 * dict.codes.len() + 1];
+        for c in clu.iter() {
+            weights[dict.encode(&c.value).0 as usize - 1] += c.count as u64;
+        }
+        dict.weights = Some(weights);
+        dict
+    }
+
+    /// Make a dictionary directly from its mode and sorted exact-code
+    /// values, as for example when reading one back from storage. It knows
+    /// nothing of the sample it was built from.
+    pub fn from_codes(mode: Mode, codes: Vec<T>) -> Self {
+        Self {
+            mode,
+            codes,
+            weights: None,
+            summary: None,
+            reserved: 0,
+            dense: false,
+            complete: false,
+        }
+    }
+
+    // Make a dictionary read back from storage, keeping the reserved band
+    // and dense layout it was built with. The caller has checked them with
+    // [BuildOptions::stored_layout_fits].
+    pub(crate) fn from_stored_codes(mode: Mode, codes: Vec<T>, options: &BuildOptions) -> Self {
+        Self {
+            reserved: options.reserved_codes,
+            dense: options.dense_codes,
+            ..Self::from_codes(mode, codes)
+        }
+    }
+
+    /// Build a dictionary giving every distinct value in `sample` an exact
+    /// code, or fail if there are more than [Mode::num_exact_codes] of them.
+    /// The result is marked [exact-complete](Dict::is_exact_complete).
+    pub fn new_exact(mode: Mode, mut sample: Vec<T>) -> Result<Self, CardinalityError> {
+        sample.sort_unstable();
+        sample.dedup();
+        let limit = mode.num_exact_codes();
+        if sample.len() > limit {
+            return Err(CardinalityError {
+                distinct: sample.len(),
+                limit,
+            });
+        }
+        let mut dict = if sample.is_empty() {
+            Self::new(mode, sample)
+        } else {
+            sample.shrink_to_fit();
+            Self::from_codes(mode, sample)
+        };
+        dict.complete = true;
+        Ok(dict)
+    }
+
+    /// Return true if the dictionary was built by [Dict::new_exact], so that
+    /// every sample value has an exact code. If the sample was the column's
+    /// whole domain, the column holds no inexact codes at all, and
+    /// [predicate::CodePredicate::from_range] relies on this to rewrite
+    /// predicates with no codes to re-check. Only call [Dict::new_exact]
+    /// with a sample of the whole domain. The mark isn't serialized, and
+    /// [Dict::extend_max] clears it.
+    pub fn is_exact_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Make a dictionary for a known, closed set of categories (the states
+    /// of an enum column, say), giving each an exact code in order without
+    /// any sample. Any other value encodes to the inexact code between the
+    /// categories it falls between, or at either end.
+    ///
+    /// The categories must be given in strictly increasing order, which for a
+    /// `#[derive(Ord)]` enum is its declared order, and there must be no
+    /// more of them than [Mode::num_exact_codes].
+    pub fn from_categories(mode: Mode, categories: Vec<T>) -> Self {
+        assert!(
+            categories.windows(2).all(|w| w[0] < w[1]),
+            "categories must be in strictly increasing order"
+        );
+        assert!(
+            categories.len() <= mode.num_exact_codes(),
+            "too many categories for mode"
+        );
+        if categories.is_empty() {
+            return Self::new(mode, categories);
+        }
+        let mut codes = categories;
+        codes.shrink_to_fit();
+        Self::from_codes(mode, codes)
+    }
+
+    /// Returns the count of exact codes this dictionary could have assigned:
+    /// [Mode::num_exact_codes] less any given up to reserved codes.
+    pub fn num_exact_codes(&self) -> usize {
+        let options = BuildOptions {
+            reserved_codes: self.reserved,
+            dense_codes: self.dense,
+            ..BuildOptions::default()
+        };
+        options.num_exact_codes(self.mode)
+    }
+
+    /// Returns the first and last codes of the band reserved with
+    /// [BuildOptions::reserved_codes], if any. [Dict::encode] never returns
+    /// these, nor code 0. For a dictionary built with
+    /// [BuildOptions::dense_codes] this is `None`; see
+    /// [Dict::reserved_dense_codes].
+    pub fn reserved_codes(&self) -> Option<(Code, Code)> {
+        if self.reserved == 0 || self.dense {
+            return None;
+        }
+        let max = self.mode.max_inexact_code();
+        Some((Code(max.0 - self.reserved + 1), max))
+    }
+
+    /// Returns the first and last [DenseCode]s of the band reserved with
+    /// [BuildOptions::reserved_codes] in a dictionary built with
+    /// [BuildOptions::dense_codes], if any. [Dict::encode_dense] never
+    /// returns these.
+    pub fn reserved_dense_codes(&self) -> Option<(DenseCode, DenseCode)> {
+        if self.reserved == 0 || !self.dense {
+            return None;
+        }
+        let max = self.mode.max_inexact_code();
+        Some((DenseCode(max.0 - self.reserved + 1), DenseCode(max.0)))
+    }
+
+    /// Append exact codes for `new_boundaries` above the current maximum
+    /// value, for domains such as timestamps or sequence numbers where new
+    /// data lands above everything seen so far. Existing codes keep their
+    /// assignments; boundaries not above the current maximum (or each
+    /// other) are skipped, as are any beyond the dictionary's
+    /// [Dict::num_exact_codes]. Returns the number appended.
+    ///
+    /// The old top inexact code, which stood for every value above the old
+    /// maximum, now stands only for those below the first new boundary, so
+    /// codes written before the extension that used it only bound their
+    /// values to the wider, original interval. Retained per-code weights are
+    /// dropped, since the sample mass above the old maximum can't be split.
+    pub fn extend_max(&mut self, new_boundaries: &[T]) -> usize {
+        let limit = self.num_exact_codes();
+        let before = self.codes.len();
+        let spare = self.codes.capacity() - before;
+        for v in new_boundaries {
+            if self.codes.len() >= limit {
+                break;
+            }
+            if self.codes.last().is_none_or(|max| max < v) {
+                self.codes.push(v.clone());
+            }
+        }
+        let appended = self.codes.len() - before;
+        // Only trim the slack this call's own growth left behind, not
+        // capacity set aside with [BuildOptions::code_capacity].
+        if appended > spare {
+            self.codes.shrink_to_fit();
+        }
+        if appended != 0 {
+            self.weights = None;
+            self.complete = false;
+        }
+        appended
+    }
+
+    /// Return the summary of the construction sample, if the dictionary was
+    /// built with a nonzero [BuildOptions::summary_capacity].
+    pub fn summary(&self) -> Option<&summary::SampleSummary<T>> {
+        self.summary.as_ref()
+    }
+
+    /// Return the number of values in the construction sample that encode
+    /// to `code`, if the dictionary was built with
+    /// [BuildOptions::retain_weights] (and its `codes` not modified since).
+    pub fn code_weight(&self, code: Code) -> Option<u64> {
+        let i = (code.0 as usize).checked_sub(1)?;
+        self.weights()?.get(i).copied()
+    }
+
+    // The per-code weights, if known and still consistent with `codes`.
+    fn weights(&self) -> Option<&[u64]> {
+        self.weights
+            .as_deref()
+            .filter(|w| w.len() == 2 * self.codes.len() + 1)
+    }
+
+    /// Return, for each fraction in `q`, the code whose values hold that
+    /// quantile of the sample the dictionary was built from: the first code
+    /// such that at least that fraction of the sample encodes to it or
+    /// below. Fractions are clamped to `[0,1]`.
+    ///
+    /// This uses the per-code sample mass retained when
+    /// [BuildOptions::retain_weights] is set. A dictionary without it (by
+    /// default, or one made by [Dict::from_codes], or whose `codes` have
+    /// since been modified) falls back on the construction algorithm's rough
+    /// equal spacing of sample mass between exact codes, and returns exact
+    /// codes only.
+    pub fn code_quantiles(&self, q: &[f64]) -> Vec<Code> {
+        let weights = self.weights();
+        let total: u64 = weights.map_or(0, |w| w.iter().sum());
+        q.iter()
+            .map(|q| {
+                let q = if q.is_nan() { 0.0 } else { q.clamp(0.0, 1.0) };
+                match weights {
+                    Some(w) if total != 0 => {
+                        let target = ((q * total as f64).ceil() as u64).max(1);
+                        let mut cumulative = 0;
+                        for (i, n) in w.iter().enumerate() {
+                            cumulative += n;
+                            if cumulative >= target {
+                                return Code(i as u16 + 1);
+                            }
+                        }
+                        Code(w.len() as u16)
+                    }
+                    _ => {
+                        let n = self.codes.len().max(1);
+                        let i = (q * (n - 1) as f64).round() as usize;
+                        Code(2 * (i as u16 + 1))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Build a dictionary with a given [Mode] over keys extracted by `key`
+    /// from each element of a sample of some other type `S`, for example
+    /// timestamps truncated to the hour (see [time::TimeBucket::key]). Values
+    /// to encode with the resulting dictionary must be passed through the same
+    /// `key` function. Otherwise this is the same as [Dict::new].
+    pub fn new_with_key<S, F: Fn(&S) -> T>(mode: Mode, sample: &[S], key: F) -> Self {
+        Self::new(mode, sample.iter().map(key).collect())
+    }
+
+    /// Release any excess capacity held by [Dict::codes]. Dictionaries built
+    /// by [Dict::new] are already exactly sized; this is only useful if the
+    /// caller has modified `codes` directly.
+    pub fn shrink_to_fit(&mut self) {
+        self.codes.shrink_to_fit();
+    }
+
+    /// Return the dictionary's code vector, with whatever capacity it has,
+    /// for example to hand storage passed to [Dict::new_with_storage] back
+    /// to its pool.
+    pub fn into_codes(self) -> Vec<T> {
+        self.codes
     }
 }
@@ -0,0 +1,163 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Late materialization: turning a scan's candidate bitmap back into block
+//! and row coordinates.
+//!
+//! A scan over codes produces one flag per row, set where the underlying
+//! value might satisfy the predicate. Those rows still have to be fetched
+//! from storage and re-checked, and storage is organized in blocks. The
+//! [Candidates] iterator walks a bitmap in block-sized steps and yields, for
+//! each block holding at least one candidate, the block's index and the
+//! offsets of its candidate rows, so a caller can issue one fetch per block.
+//! It reads either one flag per row or the 64-row words of a [ScanBitmap].
+
+use crate::scan::ScanBitmap;
+
+/// The candidate rows of a single block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CandidateBlock {
+    /// The index of the block, counting from zero.
+    pub block: usize,
+    /// The offsets within the block of the candidate rows, in increasing
+    /// order.
+    pub rows: Vec<usize>,
+}
+
+impl CandidateBlock {
+    /// The row numbers, counted across the whole column, of the candidate
+    /// rows, given the same `block_rows` used to produce this block.
+    pub fn absolute_rows(&self, block_rows: usize) -> impl Iterator<Item = usize> + '_ {
+        let base = self.block * block_rows;
+        self.rows.iter().map(move |r| base + r)
+    }
+}
+
+/// An iterator over the blocks of a candidate bitmap that hold candidates.
+/// Blocks with no candidate rows are skipped.
+pub struct Candidates<'a> {
+    bitmap: Bitmap<'a>,
+    rows: usize,
+    block_rows: usize,
+    next_block: usize,
+}
+
+enum Bitmap<'a> {
+    Flags(&'a [bool]),
+    // Row `i` is bit `i % 64` of word `i / 64`, as in a [ScanBitmap].
+    Words(&'a [u64]),
+}
+
+impl<'a> Candidates<'a> {
+    /// Iterate over `bitmap` in blocks of `block_rows` rows. The last block
+    /// may be short.
+    ///
+    /// Panics if `block_rows` is zero.
+    pub fn new(bitmap: &'a [bool], block_rows: usize) -> Self {
+        assert!(block_rows > 0);
+        Candidates {
+            bitmap: Bitmap::Flags(bitmap),
+            rows: bitmap.len(),
+            block_rows,
+            next_block: 0,
+        }
+    }
+
+    /// Iterate over the first `rows` rows of a bitmap of 64 rows per word,
+    /// as returned by [ScanBitmap::candidates], in blocks of `block_rows`
+    /// rows. Runs of empty words are skipped a word at a time.
+    ///
+    /// Panics if `block_rows` is zero or `words` holds fewer than `rows`
+    /// bits.
+    pub fn from_words(words: &'a [u64], rows: usize, block_rows: usize) -> Self {
+        assert!(block_rows > 0);
+        assert!(words.len() * 64 >= rows);
+        Candidates {
+            bitmap: Bitmap::Words(words),
+            rows,
+            block_rows,
+            next_block: 0,
+        }
+    }
+
+    /// Iterate over the candidate rows of `scan` in blocks of `block_rows`
+    /// rows; see [Candidates::from_words].
+    pub fn from_scan(scan: &'a ScanBitmap, block_rows: usize) -> Self {
+        Self::from_words(scan.candidates(), scan.rows(), block_rows)
+    }
+
+    fn next_flags(&mut self, flags: &[bool]) -> Option<CandidateBlock> {
+        loop {
+            let start = self.next_block * self.block_rows;
+            if start >= self.rows {
+                return None;
+            }
+            let end = (start + self.block_rows).min(self.rows);
+            let block = self.next_block;
+            self.next_block += 1;
+            let rows: Vec<usize> = flags[start..end]
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b)
+                .map(|(i, _)| i)
+                .collect();
+            if !rows.is_empty() {
+                return Some(CandidateBlock { block, rows });
+            }
+        }
+    }
+
+    fn next_words(&mut self, words: &[u64]) -> Option<CandidateBlock> {
+        // Find the first set bit at or after the next block, and start the
+        // block holding it.
+        let from = self.next_block * self.block_rows;
+        if from >= self.rows {
+            return None;
+        }
+        let mut w = from / 64;
+        let mut bits = words[w] & (!0u64 << (from % 64));
+        while bits == 0 {
+            w += 1;
+            if w * 64 >= self.rows {
+                return None;
+            }
+            bits = words[w];
+        }
+        let first = w * 64 + bits.trailing_zeros() as usize;
+        if first >= self.rows {
+            return None;
+        }
+        let block = first / self.block_rows;
+        self.next_block = block + 1;
+        let start = block * self.block_rows;
+        let end = (start + self.block_rows).min(self.rows);
+        let mut rows = Vec::new();
+        let first_word = first / 64;
+        for (w, word) in words[first_word..=(end - 1) / 64].iter().enumerate() {
+            let w = first_word + w;
+            let lo = (w * 64).max(start);
+            let hi = ((w + 1) * 64).min(end);
+            // Keep bits lo..hi of the word.
+            let mut bits = word >> (lo % 64);
+            if hi - lo < 64 {
+                bits &= (1u64 << (hi - lo)) - 1;
+            }
+            while bits != 0 {
+                rows.push(lo + bits.trailing_zeros() as usize - start);
+                bits &= bits - 1;
+            }
+        }
+        Some(CandidateBlock { block, rows })
+    }
+}
+
+impl<'a> Iterator for Candidates<'a> {
+    type Item = CandidateBlock;
+
+    fn next(&mut self) -> Option<CandidateBlock> {
+        match self.bitmap {
+            Bitmap::Flags(flags) => self.next_flags(flags),
+            Bitmap::Words(words) => self.next_words(words),
+        }
+    }
+}
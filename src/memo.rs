@@ -0,0 +1,68 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! A small memo of recently encoded query constants.
+//!
+//! A server evaluating the same few predicate constants against many
+//! segments encodes them over and over. [EncodeMemo] remembers the codes of
+//! the last few values encoded with one dictionary, so repeats skip the
+//! binary search, which for string or other costly-to-compare values is
+//! most of the work.
+//!
+//! [Dict] itself stays free of interior mutability (see its documentation);
+//! a memo is a separate, `&mut`-updated object, meant to be kept per thread
+//! or per query alongside a shared dictionary.
+
+use crate::{Code, Dict, ValReq};
+
+/// A least-recently-used memo of the codes of values encoded with one
+/// dictionary.
+pub struct EncodeMemo<'a, T: ValReq> {
+    dict: &'a Dict<T>,
+    capacity: usize,
+    // Most recently used last.
+    entries: Vec<(T, Code)>,
+}
+
+impl<'a, T: ValReq> EncodeMemo<'a, T> {
+    /// Make a memo of up to `capacity` values encoded with `dict`. Lookups
+    /// scan the memo linearly, so it should be small: a handful to a few
+    /// dozen entries.
+    pub fn new(dict: &'a Dict<T>, capacity: usize) -> Self {
+        EncodeMemo {
+            dict,
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// The dictionary the memo encodes with.
+    pub fn dict(&self) -> &'a Dict<T> {
+        self.dict
+    }
+
+    /// Encode `query`, as [Dict::encode] would, from the memo if it is
+    /// there and otherwise by the dictionary, remembering the result and
+    /// evicting the least recently used entry if the memo is full.
+    pub fn encode(&mut self, query: &T) -> Code {
+        if let Some(i) = self.entries.iter().position(|(v, _)| v == query) {
+            let entry = self.entries.remove(i);
+            let code = entry.1;
+            self.entries.push(entry);
+            return code;
+        }
+        let code = self.dict.encode(query);
+        if self.capacity != 0 {
+            if self.entries.len() == self.capacity {
+                self.entries.remove(0);
+            }
+            self.entries.push((query.clone(), code));
+        }
+        code
+    }
+
+    /// Whether `query` is currently in the memo.
+    pub fn contains(&self, query: &T) -> bool {
+        self.entries.iter().any(|(v, _)| v == query)
+    }
+}
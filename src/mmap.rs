@@ -0,0 +1,292 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Scanning [sketch files](crate::sketchfile) in place, through a memory map.
+//!
+//! An engine may hold a sketch for every column of thousands of segments,
+//! and open each only to scan it once. A [MappedSketch] maps the file and
+//! checks its header, and nothing else: the dictionary isn't decoded until
+//! asked for, and the zone map and codes are read straight out of the
+//! mapped pages, so opening a sketch costs a system call and a few dozen
+//! bytes of reading.
+//!
+//! Word codes are read as native `u16`s, so this module is only available on
+//! little-endian targets. Requires the `mmap` feature.
+
+use crate::format::{BinaryValue, FormatError};
+use crate::predicate::{CompiledPredicate, CLASS_NONE};
+use crate::sketch::Sketch;
+use crate::sketchfile::{section, ReadOptions, SketchHeader, ZONE_LEN};
+use crate::zonemap::Zone;
+use crate::{Code, Dict, Mode, ValReq};
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// The codes of a mapped sketch, at their stored width.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MappedCodes<'a> {
+    /// The codes of a [Mode::Byte] sketch.
+    Byte(&'a [u8]),
+    /// The codes of a [Mode::Packed] sketch: the code section, each block
+    /// of `block_rows` rows packed on its own.
+    Packed {
+        /// The code section.
+        bytes: &'a [u8],
+        /// The number of codes.
+        rows: usize,
+        /// The rows in each block but the last.
+        block_rows: usize,
+    },
+    /// The codes of a [Mode::Word] sketch.
+    Word(&'a [u16]),
+}
+
+impl MappedCodes<'_> {
+    /// The number of codes.
+    pub fn len(&self) -> usize {
+        match self {
+            MappedCodes::Byte(c) => c.len(),
+            MappedCodes::Packed { rows, .. } => *rows,
+            MappedCodes::Word(c) => c.len(),
+        }
+    }
+
+    /// Whether there are no codes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The code of row `i`.
+    pub fn get(&self, i: usize) -> Code {
+        match self {
+            MappedCodes::Byte(c) => Code(c[i] as u16),
+            MappedCodes::Packed {
+                bytes,
+                rows,
+                block_rows,
+            } => {
+                assert!(i < *rows);
+                let block = i / block_rows * Mode::Packed.stored_len(*block_rows);
+                Mode::Packed.stored_code(&bytes[block..], i % block_rows)
+            }
+            MappedCodes::Word(c) => Code(c[i]),
+        }
+    }
+}
+
+/// A sketch file mapped into memory. See the [module documentation](self).
+pub struct MappedSketch {
+    map: Mmap,
+    header: SketchHeader,
+}
+
+impl MappedSketch {
+    /// Map the sketch file at `path` and check its header, and its
+    /// dictionary and zone map against their checksums. A file that isn't a
+    /// readable sketch is an [io::ErrorKind::InvalidData] error wrapping the
+    /// [FormatError].
+    ///
+    /// The codes aren't read, so aren't checked: see
+    /// [MappedSketch::verify_block]. The file must not be modified while it
+    /// is mapped: its contents are only checked here, and reads through the
+    /// map trust them from then on.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with_options(path, &ReadOptions::default())
+    }
+
+    /// Like [MappedSketch::open], with checksums only verified if
+    /// `options` says so.
+    pub fn open_with_options<P: AsRef<Path>>(path: P, options: &ReadOptions) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: per the documented contract, the file isn't modified while
+        // mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_mmap(map, options).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Wrap an existing map of a sketch file, checking its header and, if
+    /// `options` says so, its dictionary and zone map checksums.
+    pub fn from_mmap(map: Mmap, options: &ReadOptions) -> Result<Self, FormatError> {
+        let header = SketchHeader::read(&map)?;
+        if options.verify_checksums {
+            header.verify_metadata(&map)?;
+        }
+        Ok(MappedSketch { map, header })
+    }
+
+    /// Check block `i`'s codes against their checksum, before trusting a
+    /// scan of them. Passes if the file has no checksums.
+    pub fn verify_block(&self, i: usize) -> Result<(), FormatError> {
+        self.header.verify_block(&self.map, i)
+    }
+
+    /// Return the file's header.
+    pub fn header(&self) -> &SketchHeader {
+        &self.header
+    }
+
+    /// Return the serialized dictionary, as written by [Dict::to_bytes]:
+    /// borrowed from the map unless the file is compressed. Its
+    /// [fingerprint](crate::format::fingerprint) can be taken without
+    /// decoding it.
+    pub fn dict_bytes(&self) -> Result<Cow<'_, [u8]>, FormatError> {
+        self.header.dict_bytes(&self.map)
+    }
+
+    /// Decode the dictionary.
+    pub fn dict<T: ValReq + BinaryValue>(&self) -> Result<Dict<T>, FormatError> {
+        let dict = Dict::from_bytes(&self.dict_bytes()?)?;
+        if dict.mode != self.header.mode {
+            return Err(FormatError::BadCodes);
+        }
+        Ok(dict)
+    }
+
+    /// The number of rows.
+    pub fn rows(&self) -> usize {
+        self.header.rows as usize
+    }
+
+    /// The number of zone-map blocks.
+    pub fn blocks(&self) -> usize {
+        self.header.blocks()
+    }
+
+    /// Return the zone of block `i`.
+    pub fn zone(&self, i: usize) -> Zone {
+        let z = &section(&self.map, self.header.zones)[i * ZONE_LEN..(i + 1) * ZONE_LEN];
+        Zone {
+            min: Code(u16::from_le_bytes([z[0], z[1]])),
+            max: Code(u16::from_le_bytes([z[2], z[3]])),
+            rows: u32::from_le_bytes([z[4], z[5], z[6], z[7]]) as usize,
+        }
+    }
+
+    /// Return the rows of block `i`.
+    pub fn block_range(&self, i: usize) -> Range<usize> {
+        let n = self.header.block_rows as usize;
+        i * n..((i + 1) * n).min(self.rows())
+    }
+
+    /// Return the codes, without copying them.
+    ///
+    /// Panics if the file is compressed; see
+    /// [MappedSketch::classify_block_into].
+    pub fn codes(&self) -> MappedCodes<'_> {
+        assert!(!self.header.is_compressed());
+        let bytes = section(&self.map, self.header.codes);
+        match self.header.mode {
+            Mode::Byte => MappedCodes::Byte(bytes),
+            Mode::Packed => MappedCodes::Packed {
+                bytes,
+                rows: self.rows(),
+                block_rows: self.header.block_rows as usize,
+            },
+            Mode::Word => {
+                // The section starts at a multiple of 8 bytes into the
+                // page-aligned map, so it is aligned for u16.
+                let (pre, words, post) = unsafe { bytes.align_to::<u16>() };
+                assert!(pre.is_empty() && post.is_empty());
+                MappedCodes::Word(words)
+            }
+        }
+    }
+
+    /// Decode the codes into a [Sketch], decompressing them if the file is
+    /// compressed.
+    pub fn to_sketch(&self) -> Result<Sketch, FormatError> {
+        let mut sketch = Sketch::with_capacity(self.header.mode, self.rows());
+        for i in 0..self.blocks() {
+            sketch.extend_from_stored(&self.header.block_codes(&self.map, i)?);
+        }
+        Ok(sketch)
+    }
+
+    /// Return the blocks whose zones show they may hold a code `pred`
+    /// doesn't rule out.
+    ///
+    /// Panics if `pred` was compiled for a different mode.
+    pub fn candidate_blocks(&self, pred: &CompiledPredicate) -> Vec<usize> {
+        assert!(pred.mode == self.header.mode);
+        (0..self.blocks())
+            .filter(|i| {
+                let z = self.zone(*i);
+                let (lo, hi) = (
+                    z.min.0 as usize,
+                    (z.max.0 as usize).min(pred.table.len() - 1),
+                );
+                lo <= hi && pred.table[lo..=hi].iter().any(|c| *c != CLASS_NONE)
+            })
+            .collect()
+    }
+
+    /// Write the class of each row in `rows` under `pred` to the same
+    /// position of `out`, reading the mapped codes directly.
+    ///
+    /// Panics if `pred` was compiled for a different mode, `out` is shorter
+    /// than `rows`, or the file is compressed.
+    pub fn classify_into(&self, pred: &CompiledPredicate, rows: Range<usize>, out: &mut [u8]) {
+        assert!(pred.mode == self.header.mode);
+        let out = &mut out[..rows.len()];
+        match self.codes() {
+            MappedCodes::Byte(codes) => {
+                for (o, c) in out.iter_mut().zip(codes[rows].iter()) {
+                    *o = pred.table[*c as usize];
+                }
+            }
+            packed @ MappedCodes::Packed { .. } => {
+                for (o, r) in out.iter_mut().zip(rows) {
+                    *o = pred.table[packed.get(r).0 as usize];
+                }
+            }
+            MappedCodes::Word(codes) => {
+                for (o, c) in out.iter_mut().zip(codes[rows].iter()) {
+                    *o = pred.table[*c as usize];
+                }
+            }
+        }
+    }
+
+    /// Write the class of each row of block `i` under `pred` to the same
+    /// position of `out`, decompressing the block first if the file is
+    /// compressed. Only this block is read.
+    ///
+    /// Panics if `pred` was compiled for a different mode or `out` is
+    /// shorter than the block.
+    pub fn classify_block_into(
+        &self,
+        pred: &CompiledPredicate,
+        i: usize,
+        out: &mut [u8],
+    ) -> Result<(), FormatError> {
+        assert!(pred.mode == self.header.mode);
+        if !self.header.is_compressed() {
+            self.classify_into(pred, self.block_range(i), out);
+            return Ok(());
+        }
+        let raw = self.header.block_codes(&self.map, i)?;
+        let out = &mut out[..self.header.rows_in_block(i)];
+        match self.header.mode {
+            Mode::Byte => {
+                for (o, c) in out.iter_mut().zip(raw.iter()) {
+                    *o = pred.table[*c as usize];
+                }
+            }
+            Mode::Packed => {
+                for (j, o) in out.iter_mut().enumerate() {
+                    *o = pred.table[Mode::Packed.stored_code(&raw, j).0 as usize];
+                }
+            }
+            Mode::Word => {
+                for (o, c) in out.iter_mut().zip(raw.chunks_exact(2)) {
+                    *o = pred.table[u16::from_le_bytes([c[0], c[1]]) as usize];
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Node.js bindings, through N-API.
+//!
+//! Build a Node addon with `cargo rustc --release --features napi
+//! --crate-type cdylib` and copy the library to `ordbog.node`. It exports
+//! two classes, `DictI64` and `DictF64`, built from typed arrays:
+//!
+//! ```text
+//! const { DictI64 } = require('./ordbog.node');
+//! const dict = DictI64.fromInt32Array(8, new Int32Array([3, 1, 4, 1, 5]));
+//! const codes = dict.encodeInt32Array(new Int32Array([1, 2, 3])); // Uint8Array
+//! ```
+//!
+//! The first argument of each constructor is the mode, in bits: 8, 12 or 16.
+//! Encoding returns one code per value, in a `Uint8Array` for a dictionary
+//! in byte mode and a `Uint16Array` otherwise.
+
+use crate::{Code, Dict, DictF64, Mode};
+use float_ord::FloatOrd;
+use napi::bindgen_prelude::{
+    BigInt64Array, Either, Float64Array, Int32Array, Uint16Array, Uint8Array,
+};
+use napi::{Error, Result};
+use napi_derive::napi;
+
+fn mode_of(bits: u32) -> Result<Mode> {
+    std::convert::TryFrom::try_from(bits)
+        .ok()
+        .and_then(Mode::from_bits)
+        .ok_or_else(|| Error::from_reason(format!("mode must be 8, 12 or 16 bits, not {}", bits)))
+}
+
+/// Pack `codes` into the narrowest typed array that holds codes of `mode`.
+pub fn codes_to_typed_array(mode: Mode, codes: Vec<Code>) -> Either<Uint8Array, Uint16Array> {
+    match mode {
+        Mode::Byte => Either::A(Uint8Array::new(codes.iter().map(|c| c.0 as u8).collect())),
+        _ => Either::B(Uint16Array::new(codes.iter().map(|c| c.0).collect())),
+    }
+}
+
+/// A dictionary over signed 64-bit integers.
+#[napi(js_name = "DictI64")]
+pub struct NapiDictI64 {
+    dict: Dict<i64>,
+}
+
+#[napi]
+impl NapiDictI64 {
+    /// Build a dictionary from a sample of 64-bit integers.
+    #[napi(factory)]
+    pub fn from_big_int64_array(mode_bits: u32, sample: BigInt64Array) -> Result<Self> {
+        Ok(NapiDictI64 {
+            dict: Dict::new(mode_of(mode_bits)?, sample.to_vec()),
+        })
+    }
+
+    /// Build a dictionary from a sample of 32-bit integers.
+    #[napi(factory)]
+    pub fn from_int32_array(mode_bits: u32, sample: Int32Array) -> Result<Self> {
+        Ok(NapiDictI64 {
+            dict: Dict::new(
+                mode_of(mode_bits)?,
+                sample.iter().map(|v| *v as i64).collect(),
+            ),
+        })
+    }
+
+    /// The dictionary's mode, in bits.
+    #[napi(getter)]
+    pub fn mode_bits(&self) -> u32 {
+        self.dict.mode.bits() as u32
+    }
+
+    /// Encode a batch of 64-bit integers.
+    #[napi]
+    pub fn encode_big_int64_array(&self, values: BigInt64Array) -> Either<Uint8Array, Uint16Array> {
+        let codes = values.iter().map(|v| self.dict.encode(v)).collect();
+        codes_to_typed_array(self.dict.mode, codes)
+    }
+
+    /// Encode a batch of 32-bit integers.
+    #[napi]
+    pub fn encode_int32_array(&self, values: Int32Array) -> Either<Uint8Array, Uint16Array> {
+        let codes = values
+            .iter()
+            .map(|v| self.dict.encode(&(*v as i64)))
+            .collect();
+        codes_to_typed_array(self.dict.mode, codes)
+    }
+}
+
+/// A dictionary over doubles, ordered as by [DictF64].
+#[napi(js_name = "DictF64")]
+pub struct NapiDictF64 {
+    dict: Dict<DictF64>,
+}
+
+#[napi]
+impl NapiDictF64 {
+    /// Build a dictionary from a sample of doubles.
+    #[napi(factory)]
+    pub fn from_float64_array(mode_bits: u32, sample: Float64Array) -> Result<Self> {
+        Ok(NapiDictF64 {
+            dict: Dict::new_with_key(mode_of(mode_bits)?, &sample, |f| DictF64(FloatOrd(*f))),
+        })
+    }
+
+    /// The dictionary's mode, in bits.
+    #[napi(getter)]
+    pub fn mode_bits(&self) -> u32 {
+        self.dict.mode.bits() as u32
+    }
+
+    /// Encode a batch of doubles.
+    #[napi]
+    pub fn encode_float64_array(&self, values: Float64Array) -> Either<Uint8Array, Uint16Array> {
+        let codes = values
+            .iter()
+            .map(|f| self.dict.encode(&DictF64(FloatOrd(*f))))
+            .collect();
+        codes_to_typed_array(self.dict.mode, codes)
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! ORC column statistics derived from code summaries.
+//!
+//! An ORC writer records, for each stripe, a `ColumnStatistics` message per
+//! column, which readers use to skip stripes. When a writer sketches a column
+//! it already has the stripe's codes, and [stripe_statistics] turns those and
+//! the dictionary into the statistics, with the structure and field names of
+//! ORC's protobuf definitions, without a second pass over the values.
+//!
+//! The minimum and maximum are those of the values bracketing the stripe's
+//! codes, so they contain the stripe's values but may be wider than its
+//! actual extremes. The count of rows whose codes are exact is reported
+//! alongside, as an indication of how much of the stripe the sketch alone
+//! answers precisely.
+
+use crate::zonemap::{value_bounds, Zone};
+use crate::{Code, Dict, DictF32, DictF64, ValReq};
+
+/// The type-specific part of ORC column statistics.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TypedStatistics {
+    /// ORC `IntegerStatistics`.
+    Integer { minimum: i64, maximum: i64 },
+    /// ORC `DoubleStatistics`.
+    Double { minimum: f64, maximum: f64 },
+    /// ORC `StringStatistics`.
+    String { minimum: String, maximum: String },
+}
+
+/// Types whose values can be described by ORC column statistics.
+pub trait OrcValue: Sized {
+    /// The statistics for a stripe whose values lie in `[min, max]`.
+    fn statistics(min: Self, max: Self) -> TypedStatistics;
+}
+
+impl OrcValue for i32 {
+    fn statistics(min: Self, max: Self) -> TypedStatistics {
+        TypedStatistics::Integer {
+            minimum: min as i64,
+            maximum: max as i64,
+        }
+    }
+}
+
+impl OrcValue for i64 {
+    fn statistics(min: Self, max: Self) -> TypedStatistics {
+        TypedStatistics::Integer {
+            minimum: min,
+            maximum: max,
+        }
+    }
+}
+
+impl OrcValue for DictF32 {
+    fn statistics(min: Self, max: Self) -> TypedStatistics {
+        TypedStatistics::Double {
+            minimum: min.0 .0 as f64,
+            maximum: max.0 .0 as f64,
+        }
+    }
+}
+
+impl OrcValue for DictF64 {
+    fn statistics(min: Self, max: Self) -> TypedStatistics {
+        TypedStatistics::Double {
+            minimum: min.0 .0,
+            maximum: max.0 .0,
+        }
+    }
+}
+
+impl OrcValue for String {
+    fn statistics(min: Self, max: Self) -> TypedStatistics {
+        TypedStatistics::String {
+            minimum: min,
+            maximum: max,
+        }
+    }
+}
+
+/// The statistics of one column in one stripe, mirroring ORC's
+/// `ColumnStatistics`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ColumnStatistics {
+    pub number_of_values: u64,
+    pub has_null: bool,
+    /// The bounds of the stripe's values, or None if the stripe is empty or
+    /// holds an inexact code unbounded on one side.
+    pub typed: Option<TypedStatistics>,
+    /// The number of rows in the stripe whose codes are exact.
+    pub exact_hits: u64,
+}
+
+/// Compute the statistics of a stripe whose values were encoded by `dict` as
+/// `codes`.
+pub fn stripe_statistics<T: ValReq + OrcValue>(dict: &Dict<T>, codes: &[Code]) -> ColumnStatistics {
+    let typed = match (codes.iter().min(), codes.iter().max()) {
+        (Some(min), Some(max)) => {
+            let zone = Zone {
+                min: *min,
+                max: *max,
+                rows: codes.len(),
+            };
+            value_bounds(dict, &zone).map(|(lo, hi)| T::statistics(lo, hi))
+        }
+        _ => None,
+    };
+    ColumnStatistics {
+        number_of_values: codes.len() as u64,
+        has_null: false,
+        typed,
+        exact_hits: codes.iter().filter(|c| c.is_exact()).count() as u64,
+    }
+}
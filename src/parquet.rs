@@ -0,0 +1,174 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Parquet page-index statistics derived from zone maps.
+//!
+//! Parquet readers that implement page skipping consult a column chunk's
+//! ColumnIndex (per-page min/max statistics) and OffsetIndex (per-page file
+//! locations). When each page of a column corresponds to one zone of a
+//! [ZoneMap](crate::zonemap::ZoneMap), these helpers produce both indexes,
+//! with the structure and field names of the Parquet format's Thrift
+//! definitions, so a writer can store them and unmodified readers can skip
+//! pages with them.
+//!
+//! Statistics can be given in the code domain, for a column storing codes,
+//! or in the value domain, for the underlying column; min/max values are
+//! PLAIN-encoded as Parquet requires.
+
+use crate::zonemap::{value_bounds, Zone};
+use crate::{Dict, DictF32, DictF64, ValReq};
+
+/// Types whose values have a Parquet PLAIN encoding, as used for page-index
+/// min/max values.
+pub trait PlainEncode {
+    /// The PLAIN encoding of the value, without any length prefix.
+    fn plain_bytes(&self) -> Vec<u8>;
+}
+
+impl PlainEncode for i32 {
+    fn plain_bytes(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+}
+
+impl PlainEncode for i64 {
+    fn plain_bytes(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+}
+
+impl PlainEncode for DictF32 {
+    fn plain_bytes(&self) -> Vec<u8> {
+        self.0 .0.to_le_bytes().to_vec()
+    }
+}
+
+impl PlainEncode for DictF64 {
+    fn plain_bytes(&self) -> Vec<u8> {
+        self.0 .0.to_le_bytes().to_vec()
+    }
+}
+
+impl PlainEncode for String {
+    fn plain_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl PlainEncode for Vec<u8> {
+    fn plain_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+/// The order of a ColumnIndex's min/max values across pages.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BoundaryOrder {
+    Unordered,
+    Ascending,
+    Descending,
+}
+
+/// Per-page statistics, mirroring Parquet's `ColumnIndex`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ColumnIndex {
+    /// Whether each page holds only nulls. Zones never do.
+    pub null_pages: Vec<bool>,
+    /// The PLAIN-encoded lower bound of each page.
+    pub min_values: Vec<Vec<u8>>,
+    /// The PLAIN-encoded upper bound of each page.
+    pub max_values: Vec<Vec<u8>>,
+    pub boundary_order: BoundaryOrder,
+    /// The number of nulls in each page, if known.
+    pub null_counts: Option<Vec<i64>>,
+}
+
+/// The location of one page, mirroring Parquet's `PageLocation`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PageLocation {
+    /// The file offset of the page header.
+    pub offset: i64,
+    /// The size of the page, including its header.
+    pub compressed_page_size: i32,
+    /// The row number, within the row group, of the page's first row.
+    pub first_row_index: i64,
+}
+
+/// Per-page locations, mirroring Parquet's `OffsetIndex`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OffsetIndex {
+    pub page_locations: Vec<PageLocation>,
+}
+
+fn boundary_order<B: Ord>(bounds: &[(B, B)]) -> BoundaryOrder {
+    let pairs = || bounds.windows(2).map(|w| (&w[0], &w[1]));
+    if pairs().all(|(a, b)| a.0 <= b.0 && a.1 <= b.1) {
+        BoundaryOrder::Ascending
+    } else if pairs().all(|(a, b)| a.0 >= b.0 && a.1 >= b.1) {
+        BoundaryOrder::Descending
+    } else {
+        BoundaryOrder::Unordered
+    }
+}
+
+fn column_index<B: Ord + PlainEncode>(zones: &[Zone], bounds: Vec<(B, B)>) -> ColumnIndex {
+    ColumnIndex {
+        null_pages: vec![false; bounds.len()],
+        min_values: bounds.iter().map(|b| b.0.plain_bytes()).collect(),
+        max_values: bounds.iter().map(|b| b.1.plain_bytes()).collect(),
+        boundary_order: boundary_order(&bounds),
+        null_counts: Some(vec![0; zones.len()]),
+    }
+}
+
+/// Build a ColumnIndex in the code domain, for a column storing the codes
+/// themselves as Parquet INT32, with one page per zone.
+pub fn code_column_index(zones: &[Zone]) -> ColumnIndex {
+    let bounds = zones
+        .iter()
+        .map(|z| (z.min.0 as i32, z.max.0 as i32))
+        .collect();
+    column_index(zones, bounds)
+}
+
+/// Build a ColumnIndex in the value domain, for the underlying column with
+/// one page per zone, from the zones of its codes under `dict`.
+///
+/// A page's bounds are the values bracketing its least and greatest codes,
+/// which contain every value in the page but may be wider than the page's
+/// actual extremes. Returns None if some page holds the lowest or highest
+/// inexact code, whose intervals are unbounded on one side and cannot be
+/// expressed as Parquet statistics.
+pub fn value_column_index<T: ValReq + PlainEncode>(
+    dict: &Dict<T>,
+    zones: &[Zone],
+) -> Option<ColumnIndex> {
+    let bounds = zones
+        .iter()
+        .map(|z| value_bounds(dict, z))
+        .collect::<Option<Vec<_>>>()?;
+    Some(column_index(zones, bounds))
+}
+
+/// Build an OffsetIndex for pages corresponding to `zones`, given each
+/// page's file offset and size.
+///
+/// Panics if `pages` and `zones` differ in length.
+pub fn offset_index(zones: &[Zone], pages: &[(i64, i32)]) -> OffsetIndex {
+    assert_eq!(zones.len(), pages.len());
+    let mut first_row_index = 0;
+    let page_locations = zones
+        .iter()
+        .zip(pages.iter())
+        .map(|(z, (offset, size))| {
+            let loc = PageLocation {
+                offset: *offset,
+                compressed_page_size: *size,
+                first_row_index,
+            };
+            first_row_index += z.rows as i64;
+            loc
+        })
+        .collect();
+    OffsetIndex { page_locations }
+}
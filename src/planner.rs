@@ -0,0 +1,111 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Choosing how to evaluate a predicate on a sketched column.
+//!
+//! A sketch doesn't always pay for itself. If most rows' codes are only
+//! maybe-matches, rechecking them one at a time costs more than scanning the
+//! underlying values outright; if candidates are spread over every block,
+//! block elision saves nothing. [plan] estimates, from a histogram of the
+//! column's codes, what fraction of rows a predicate's codes admit, prices
+//! each strategy with a [CostModel], and picks the cheapest.
+
+use crate::predicate::CodePredicate;
+use crate::quality::Histogram;
+
+/// The relative costs of the operations a scan strategy is made of, in any
+/// consistent unit.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CostModel {
+    /// Cost of scanning one code.
+    pub code_cost: f64,
+    /// Cost of reading and evaluating one underlying value as part of a
+    /// sequential scan of a block.
+    pub value_cost: f64,
+    /// Cost of fetching and evaluating one underlying value on its own, to
+    /// re-check a single candidate row.
+    pub recheck_cost: f64,
+    /// The number of rows in a block of underlying values.
+    pub block_rows: usize,
+}
+
+impl Default for CostModel {
+    // Roughly a byte code against an 8-byte value in a 4k page, with
+    // row-at-a-time fetches costing a cache miss each.
+    fn default() -> Self {
+        CostModel {
+            code_cost: 0.125,
+            value_cost: 1.0,
+            recheck_cost: 8.0,
+            block_rows: 512,
+        }
+    }
+}
+
+/// A strategy for evaluating a predicate.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    /// Scan the codes for per-row results, then re-check the rows with maybe
+    /// codes against their values.
+    SketchWithRecheck,
+    /// Scan the codes only to decide which blocks to read, then evaluate the
+    /// predicate on the values of those blocks.
+    SketchExistencePerBlock,
+    /// Ignore the sketch and evaluate the predicate on every value.
+    ScanRaw,
+}
+
+/// The strategy [plan] chose and the estimates it was chosen on.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Plan {
+    pub strategy: Strategy,
+    /// The estimated cost per row of the chosen strategy.
+    pub cost: f64,
+    /// The estimated fraction of rows whose codes definitely match.
+    pub definite_fraction: f64,
+    /// The estimated fraction of rows whose codes maybe match.
+    pub maybe_fraction: f64,
+    /// The estimated fraction of blocks holding a candidate row.
+    pub block_fraction: f64,
+}
+
+/// Choose a strategy for evaluating `pred` on a column whose codes are
+/// distributed as in `stats`, priced by `cost`.
+///
+/// Block fractions assume candidate rows are spread independently over
+/// blocks, which overestimates them for clustered columns.
+pub fn plan<T>(pred: &CodePredicate, stats: &Histogram<T>, cost: &CostModel) -> Plan {
+    let mut definite = 0.0;
+    let mut maybe = 0.0;
+    for b in stats.buckets.iter() {
+        if pred.is_definite(b.code) {
+            definite += b.fraction;
+        } else if pred.is_maybe(b.code) {
+            maybe += b.fraction;
+        }
+    }
+    let candidate = (definite + maybe).min(1.0);
+    let block_fraction = 1.0 - (1.0 - candidate).powi(cost.block_rows as i32);
+    let costs = [
+        (
+            Strategy::SketchWithRecheck,
+            cost.code_cost + maybe * cost.recheck_cost,
+        ),
+        (
+            Strategy::SketchExistencePerBlock,
+            cost.code_cost + block_fraction * cost.value_cost,
+        ),
+        (Strategy::ScanRaw, cost.value_cost),
+    ];
+    let (strategy, best) = costs
+        .iter()
+        .cloned()
+        .fold(costs[2], |a, b| if b.1 < a.1 { b } else { a });
+    Plan {
+        strategy,
+        cost: best,
+        definite_fraction: definite,
+        maybe_fraction: maybe,
+        block_fraction,
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Encoding `polars-arrow` arrays, with the `polars-arrow` feature.
+//!
+//! The same adapters as the `arrow2` module, over Polars' fork of arrow2,
+//! plus `Utf8ViewArray`, the string layout Polars uses for its own string
+//! and categorical columns.
+
+use polars_arrow::array::Utf8ViewArray;
+
+encode_arrays!(polars_arrow);
+
+impl EncodeArray<String> for Utf8ViewArray {
+    fn encode(&self, dict: &Dict<String>) -> Vec<Code> {
+        self.iter()
+            .map(|v| v.map_or(Code(0), |v| dict.encode(v)))
+            .collect()
+    }
+}
+
+impl<K: DictionaryKey> EncodeArray<String> for DictionaryArray<K> {
+    /// Encode a dictionary-encoded string array through
+    /// [encode_dictionary].
+    ///
+    /// Panics if the dictionary's values are not a `Utf8ViewArray` or
+    /// `Utf8Array`.
+    fn encode(&self, dict: &Dict<String>) -> Vec<Code> {
+        encode_dictionary::<K, Utf8ViewArray, String>(dict, self)
+            .or_else(|| encode_dictionary::<K, Utf8Array<i32>, String>(dict, self))
+            .or_else(|| encode_dictionary::<K, Utf8Array<i64>, String>(dict, self))
+            .expect("dictionary values are not strings")
+    }
+
+    /// Encode every slot into a column, null wherever the key or the value
+    /// it points to is null.
+    fn encode_column(&self, dict: &Dict<String>) -> CodeColumn {
+        let codes = self.encode(dict);
+        let validity = code_validity(&codes);
+        CodeColumn { codes, validity }
+    }
+}
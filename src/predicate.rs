@@ -0,0 +1,513 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Predicates on codes.
+//!
+//! A predicate on values, rewritten onto codes, splits the codes it admits
+//! into two sets: codes all of whose values satisfy it (the "definite"
+//! codes), and codes only some of whose values do (the "maybe" codes). Rows
+//! with definite codes match without looking at their values; rows with
+//! maybe codes must be re-checked.
+
+use crate::codeset::CodeSet;
+use crate::workload::{code_range, RangePredicate};
+use crate::{Code, Dict, DictF32, DictF64, Mode, ValReq};
+use float_ord::FloatOrd;
+use std::ops::{Bound, RangeInclusive};
+
+/// The class in a [CompiledPredicate] table of codes that never satisfy the
+/// predicate.
+pub const CLASS_NONE: u8 = 0;
+/// The class of codes whose rows might satisfy the predicate.
+pub const CLASS_MAYBE: u8 = 1;
+/// The class of codes whose rows definitely satisfy the predicate.
+pub const CLASS_DEFINITE: u8 = 2;
+
+/// The value of a predicate on a row, as far as its code can tell.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Ternary {
+    /// The row does not satisfy the predicate.
+    False,
+    /// The row may satisfy the predicate; only its value can tell.
+    Maybe,
+    /// The row satisfies the predicate.
+    True,
+}
+
+impl Ternary {
+    /// The value of a row of class `class` in a [CompiledPredicate]: one of
+    /// [CLASS_NONE], [CLASS_MAYBE] or [CLASS_DEFINITE].
+    ///
+    /// Panics on any other class.
+    pub fn from_class(class: u8) -> Self {
+        match class {
+            CLASS_NONE => Ternary::False,
+            CLASS_MAYBE => Ternary::Maybe,
+            CLASS_DEFINITE => Ternary::True,
+            _ => panic!("no predicate class {}", class),
+        }
+    }
+
+    /// Return true iff the value needs no check against the row's value.
+    pub fn is_conclusive(self) -> bool {
+        self != Ternary::Maybe
+    }
+}
+
+/// A pair of inclusive bounds on codes, tested without branching.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Thresholds {
+    pub lo: u16,
+    pub hi: u16,
+}
+
+impl Thresholds {
+    /// Whether `lo <= code <= hi`, computed as a single unsigned compare.
+    #[inline]
+    pub fn contains(&self, code: u16) -> bool {
+        code.wrapping_sub(self.lo) <= self.hi.wrapping_sub(self.lo)
+    }
+
+    /// The bounds biased into the signed range with [Mode::to_signed], for
+    /// comparison against codes biased the same way.
+    pub fn to_signed(&self, mode: Mode) -> (i16, i16) {
+        (mode.to_signed(Code(self.lo)), mode.to_signed(Code(self.hi)))
+    }
+}
+
+/// A code predicate compiled for evaluation without per-row branching.
+///
+/// The table holds one class byte per code the mode can produce, indexed by
+/// code. When the candidate codes form a single range, and the definite
+/// codes at most one, those ranges are also given as [Thresholds], which
+/// SIMD kernels can test with two compares per lane.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CompiledPredicate {
+    pub mode: Mode,
+    /// [CLASS_NONE], [CLASS_MAYBE] or [CLASS_DEFINITE] for each code, with
+    /// 256 entries in [Mode::Byte] and 65536 in [Mode::Word].
+    pub table: Vec<u8>,
+    /// The candidate codes, if they form one range.
+    pub candidates: Option<Thresholds>,
+    /// The definite codes, if they form one range.
+    pub definite: Option<Thresholds>,
+}
+
+impl CompiledPredicate {
+    /// The class of `code`.
+    #[inline]
+    pub fn classify(&self, code: Code) -> u8 {
+        self.table[code.0 as usize]
+    }
+
+    /// Write the class of each of `codes` to the same position of `out`.
+    ///
+    /// Panics if `out` is shorter than `codes`.
+    pub fn classify_into(&self, codes: &[Code], out: &mut [u8]) {
+        for (o, c) in out[..codes.len()].iter_mut().zip(codes.iter()) {
+            *o = self.table[c.0 as usize];
+        }
+    }
+
+    /// The value of the predicate on rows with `code`.
+    #[inline]
+    pub fn evaluate(&self, code: Code) -> Ternary {
+        Ternary::from_class(self.classify(code))
+    }
+
+    /// Write the value of the predicate on each of `codes` to the same
+    /// position of `out`.
+    ///
+    /// Panics if `out` is shorter than `codes`.
+    pub fn evaluate_into(&self, codes: &[Code], out: &mut [Ternary]) {
+        for (o, c) in out[..codes.len()].iter_mut().zip(codes.iter()) {
+            *o = self.evaluate(*c);
+        }
+    }
+
+    /// The codes of class `class` in the table, as a set.
+    pub fn class_set(&self, class: u8) -> CodeSet {
+        CodeSet::from_ranges(
+            self.table
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c == class)
+                .map(|(i, _)| Code(i as u16)..=Code(i as u16)),
+        )
+    }
+
+    /// Flag the codes whose rows may satisfy the predicate.
+    pub fn candidates(&self, codes: &[Code]) -> Vec<bool> {
+        match self.candidates {
+            Some(t) => codes.iter().map(|c| t.contains(c.0)).collect(),
+            None => codes
+                .iter()
+                .map(|c| self.table[c.0 as usize] != CLASS_NONE)
+                .collect(),
+        }
+    }
+}
+
+/// A comparison of a column's values against constants, to be rewritten
+/// onto codes with [Dict::rewrite].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Predicate<T> {
+    /// `x = v`.
+    Eq(T),
+    /// `x < v`.
+    Lt(T),
+    /// `x <= v`.
+    Le(T),
+    /// `x > v`.
+    Gt(T),
+    /// `x >= v`.
+    Ge(T),
+    /// `x BETWEEN lo AND hi`, inclusive at both ends.
+    Between(T, T),
+    /// `x IN (v, ...)`.
+    InSet(Vec<T>),
+}
+
+impl<T: Clone> Predicate<T> {
+    /// Return the predicate as a range predicate, or None for
+    /// [Predicate::InSet], which is not one.
+    pub fn to_range(&self) -> Option<RangePredicate<T>> {
+        use Bound::*;
+        Some(match self {
+            Predicate::Eq(v) => (Included(v.clone()), Included(v.clone())),
+            Predicate::Lt(v) => (Unbounded, Excluded(v.clone())),
+            Predicate::Le(v) => (Unbounded, Included(v.clone())),
+            Predicate::Gt(v) => (Excluded(v.clone()), Unbounded),
+            Predicate::Ge(v) => (Included(v.clone()), Unbounded),
+            Predicate::Between(lo, hi) => (Included(lo.clone()), Included(hi.clone())),
+            Predicate::InSet(_) => return None,
+        })
+    }
+}
+
+/// A code predicate rendered as SQL boolean expressions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SqlFilter {
+    /// Selects rows whose codes may satisfy the predicate.
+    pub candidates: String,
+    /// Selects rows whose codes definitely satisfy the predicate.
+    pub definite: String,
+}
+
+fn render_sql(column: &str, ranges: &[RangeInclusive<Code>]) -> String {
+    let terms: Vec<String> = ranges
+        .iter()
+        .map(|r| {
+            if r.start() == r.end() {
+                format!("{} = {}", column, r.start().0)
+            } else {
+                format!("{} BETWEEN {} AND {}", column, r.start().0, r.end().0)
+            }
+        })
+        .collect();
+    match terms.len() {
+        0 => "FALSE".to_string(),
+        1 => terms[0].clone(),
+        _ => format!("({})", terms.join(" OR ")),
+    }
+}
+
+/// Float values, whose equality predicates can be given a tolerance with
+/// [CodePredicate::from_approx_eq].
+pub trait FloatValue: ValReq {
+    /// The float type wrapped.
+    type Float: Copy;
+
+    /// A range covering every value for which `(x - c).abs() <= eps`, as
+    /// evaluated in the float type, is true, or None if no value satisfies
+    /// it: if `c` is infinite or NaN, or `eps` is negative or NaN. The
+    /// subtraction may round a value a little beyond `c ± eps` onto `eps`,
+    /// so the range reaches a few ulps of `c` and `eps` past either end.
+    /// Both zeros lie in it whenever either does.
+    fn tolerance_bounds(c: Self::Float, eps: Self::Float) -> Option<RangePredicate<Self>>;
+
+    /// The range of values within `eps` of `c` exactly, for each of which
+    /// `(x - c).abs() <= eps` is true however it rounds, or None as for
+    /// [tolerance_bounds](FloatValue::tolerance_bounds). It always holds
+    /// `c`.
+    fn tolerance_interior(c: Self::Float, eps: Self::Float) -> Option<RangePredicate<Self>>;
+}
+
+macro_rules! float_value {
+    ($wrapper:ident, $float:ty) => {
+        impl FloatValue for $wrapper {
+            type Float = $float;
+
+            fn tolerance_bounds(c: $float, eps: $float) -> Option<RangePredicate<Self>> {
+                if !c.is_finite() || eps.is_nan() || eps < 0.0 {
+                    return None;
+                }
+                // The rounded ends are within an ulp of `c.abs().max(eps)`
+                // of the exact ones, and the subtraction under test rounds
+                // by at most half an ulp of `eps`; four ulps of the larger
+                // cover both, and the rounding of the widening itself.
+                let scale = c.abs().max(eps);
+                let margin = 4.0 * (scale.next_up() - scale);
+                Some(Self::zero_inclusive((c - eps) - margin, (c + eps) + margin))
+            }
+
+            fn tolerance_interior(c: $float, eps: $float) -> Option<RangePredicate<Self>> {
+                if !c.is_finite() || eps.is_nan() || eps < 0.0 {
+                    return None;
+                }
+                // The exact ends are `lo + lo_err` and `hi + hi_err`, by
+                // the error-free sums of Knuth's TwoSum; an end rounded
+                // outwards moves in by an ulp.
+                let (lo, lo_err) = Self::two_sum(c, -eps);
+                let (hi, hi_err) = Self::two_sum(c, eps);
+                let lo = if lo_err > 0.0 { lo.next_up() } else { lo };
+                let hi = if hi_err < 0.0 { hi.next_down() } else { hi };
+                Some(Self::zero_inclusive(lo, hi))
+            }
+        }
+
+        impl $wrapper {
+            fn two_sum(a: $float, b: $float) -> ($float, $float) {
+                let s = a + b;
+                let bb = s - a;
+                (s, (a - (s - bb)) + (b - bb))
+            }
+
+            fn zero_inclusive(mut lo: $float, mut hi: $float) -> RangePredicate<Self> {
+                // The wrapper orders -0.0 below 0.0, but they are equal.
+                if lo == 0.0 {
+                    lo = -0.0;
+                }
+                if hi == 0.0 {
+                    hi = 0.0;
+                }
+                (
+                    Bound::Included($wrapper(FloatOrd(lo))),
+                    Bound::Included($wrapper(FloatOrd(hi))),
+                )
+            }
+        }
+    };
+}
+
+float_value!(DictF32, f32);
+float_value!(DictF64, f64);
+
+/// A predicate on codes, as disjoint sorted ranges of definite and maybe
+/// codes. Codes in neither set never satisfy the predicate.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CodePredicate {
+    /// Ranges of codes whose rows definitely satisfy the predicate.
+    pub definite: Vec<RangeInclusive<Code>>,
+    /// Ranges of codes whose rows might satisfy the predicate.
+    pub maybe: Vec<RangeInclusive<Code>>,
+}
+
+impl CodePredicate {
+    /// Rewrite a range predicate on the values of `dict` onto its codes. If
+    /// `dict` is [exact-complete](Dict::is_exact_complete), there are no
+    /// maybe codes.
+    pub fn from_range<T: ValReq>(dict: &Dict<T>, pred: &RangePredicate<T>) -> Self {
+        let mut out = Self::from_range_inexact(dict, pred);
+        if dict.is_exact_complete() {
+            out.maybe.clear();
+        }
+        out
+    }
+
+    /// Rewrite the predicate `|x - c| <= eps` on the float values of `dict`.
+    /// Exact float equality rarely survives arithmetic, so this is usually
+    /// what an equality predicate on floats means. Codes of values within
+    /// `eps` of `c` exactly are definite, and codes of values near either
+    /// end, which `(x - c).abs() <= eps` may accept or reject depending on
+    /// how it rounds, are maybe; see [FloatValue].
+    pub fn from_approx_eq<T: FloatValue>(dict: &Dict<T>, c: T::Float, eps: T::Float) -> Self {
+        match (T::tolerance_bounds(c, eps), T::tolerance_interior(c, eps)) {
+            (Some(bounds), Some(interior)) => Self::from_sets(
+                &Self::from_range(dict, &interior).definite_set(),
+                &Self::from_range(dict, &bounds).candidate_set(),
+            ),
+            _ => CodePredicate::default(),
+        }
+    }
+
+    fn from_range_inexact<T: ValReq>(dict: &Dict<T>, pred: &RangePredicate<T>) -> Self {
+        match code_range(dict, pred) {
+            Some((lo, hi, lo_definite, hi_definite)) => {
+                Self::from_code_range(lo, hi, lo_definite, hi_definite)
+            }
+            None => CodePredicate::default(),
+        }
+    }
+
+    // The predicate admitting codes `lo..=hi`, where the codes strictly
+    // between the ends are definite and each end is definite as given.
+    pub(crate) fn from_code_range(
+        lo: Code,
+        hi: Code,
+        lo_definite: bool,
+        hi_definite: bool,
+    ) -> Self {
+        let mut out = CodePredicate::default();
+        if lo > hi {
+            return out;
+        }
+        if lo == hi {
+            if lo_definite && hi_definite {
+                out.definite.push(lo..=hi);
+            } else {
+                out.maybe.push(lo..=hi);
+            }
+            return out;
+        }
+        if !lo_definite {
+            out.maybe.push(lo..=lo);
+        }
+        let first = if lo_definite { lo } else { Code(lo.0 + 1) };
+        let last = if hi_definite { hi } else { Code(hi.0 - 1) };
+        if first <= last {
+            out.definite.push(first..=last);
+        }
+        if !hi_definite {
+            out.maybe.push(hi..=hi);
+        }
+        out
+    }
+
+    /// Rewrite a range predicate on the present values of a dictionary over
+    /// optional values. As in SQL, comparing `None` with anything is
+    /// unknown rather than true, so rows holding `None` never satisfy the
+    /// predicate, even when it is unbounded on both sides: they are excluded
+    /// outright if `None` has an exact code, and otherwise share a maybe
+    /// code with the least present values.
+    pub fn from_optional_range<T: ValReq>(
+        dict: &Dict<Option<T>>,
+        pred: &RangePredicate<T>,
+    ) -> Self {
+        let lift = |b: &Bound<T>| match b {
+            Bound::Included(v) => Bound::Included(Some(v.clone())),
+            Bound::Excluded(v) => Bound::Excluded(Some(v.clone())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let lower = match &pred.0 {
+            // `None` sorts before every present value.
+            Bound::Unbounded => Bound::Excluded(None),
+            b => lift(b),
+        };
+        Self::from_range(dict, &(lower, lift(&pred.1)))
+    }
+
+    /// Rewrite `IS NULL` for a dictionary over optional values.
+    pub fn is_none<T: ValReq>(dict: &Dict<Option<T>>) -> Self {
+        Self::from_range(dict, &(Bound::Included(None), Bound::Included(None)))
+    }
+
+    /// The ranges of codes whose rows may satisfy the predicate, definite or
+    /// not, sorted and with adjacent ranges merged.
+    pub fn candidates(&self) -> Vec<RangeInclusive<Code>> {
+        self.candidate_set().ranges().to_vec()
+    }
+
+    /// The codes whose rows definitely satisfy the predicate, as a set.
+    pub fn definite_set(&self) -> CodeSet {
+        CodeSet::from_ranges(self.definite.iter().cloned())
+    }
+
+    /// The codes whose rows may satisfy the predicate, definite or not, as a
+    /// set.
+    pub fn candidate_set(&self) -> CodeSet {
+        CodeSet::from_ranges(self.definite.iter().chain(self.maybe.iter()).cloned())
+    }
+
+    /// Make the predicate whose definite codes are `definite`, and whose
+    /// maybe codes are the rest of `candidates`. Predicates on one column
+    /// combine through their sets: for a conjunction, intersect both the
+    /// definite and the candidate sets; for a disjunction, unite them; for a
+    /// negation, the definite codes are the complement of the candidates
+    /// and the candidates the complement of the definite codes.
+    pub fn from_sets(definite: &CodeSet, candidates: &CodeSet) -> Self {
+        CodePredicate {
+            definite: definite.ranges().to_vec(),
+            maybe: candidates.difference(definite).ranges().to_vec(),
+        }
+    }
+
+    /// Rewrite the predicate `x IN (values...)` on the values of `dict`.
+    /// Values with exact codes contribute definite codes; others, maybe
+    /// codes, unless `dict` is [exact-complete](Dict::is_exact_complete)
+    /// and so no value without an exact code occurs.
+    pub fn from_in_list<T: ValReq>(dict: &Dict<T>, values: &[T]) -> Self {
+        let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+        let (exact, inexact): (Vec<Code>, Vec<Code>) = codes.iter().partition(|c| c.is_exact());
+        let definite = CodeSet::from_codes(&exact);
+        let mut candidates = definite.clone();
+        if !dict.is_exact_complete() {
+            candidates = candidates.union(&CodeSet::from_codes(&inexact));
+        }
+        Self::from_sets(&definite, &candidates)
+    }
+
+    /// Whether `code` is in one of the predicate's definite ranges.
+    pub fn is_definite(&self, code: Code) -> bool {
+        self.definite.iter().any(|r| r.contains(&code))
+    }
+
+    /// Whether `code` is in one of the predicate's maybe ranges.
+    pub fn is_maybe(&self, code: Code) -> bool {
+        self.maybe.iter().any(|r| r.contains(&code))
+    }
+
+    /// The value of the predicate on rows with `code`: true for definite
+    /// codes, maybe for maybe codes, and false for the rest. To evaluate
+    /// many codes, [compile](Self::compile) the predicate first.
+    pub fn evaluate(&self, code: Code) -> Ternary {
+        if self.is_definite(code) {
+            Ternary::True
+        } else if self.is_maybe(code) {
+            Ternary::Maybe
+        } else {
+            Ternary::False
+        }
+    }
+
+    /// Render the predicate as SQL over the code column `column`, which is
+    /// inserted verbatim and so must already be quoted if need be. Rows
+    /// selected by the candidates expression but not the definite one must
+    /// be re-checked against the underlying values.
+    pub fn to_sql(&self, column: &str) -> SqlFilter {
+        SqlFilter {
+            candidates: render_sql(column, &self.candidates()),
+            definite: render_sql(column, &self.definite),
+        }
+    }
+
+    /// Compile the predicate for a column of codes in `mode`. Ranges reaching
+    /// past the mode's greatest code are clipped.
+    pub fn compile(&self, mode: Mode) -> CompiledPredicate {
+        let size = mode.max_inexact_code().0 as usize + 1;
+        let mut table = vec![CLASS_NONE; size];
+        let mut fill = |ranges: &[RangeInclusive<Code>], class: u8| {
+            for r in ranges {
+                let hi = (r.end().0 as usize).min(size - 1);
+                for slot in table.iter_mut().take(hi + 1).skip(r.start().0 as usize) {
+                    *slot = class;
+                }
+            }
+        };
+        fill(&self.maybe, CLASS_MAYBE);
+        fill(&self.definite, CLASS_DEFINITE);
+        let single = |ranges: &[RangeInclusive<Code>]| match ranges {
+            [r] => Some(Thresholds {
+                lo: r.start().0,
+                hi: r.end().0,
+            }),
+            _ => None,
+        };
+        CompiledPredicate {
+            mode,
+            table,
+            candidates: single(&self.candidates()),
+            definite: single(&self.definite),
+        }
+    }
+}
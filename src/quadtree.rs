@@ -0,0 +1,131 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Covering rectangles with ranges of a space-filling curve, shared by the
+// Z-order and Hilbert key wrappers. Both curves map every aligned 2^k by 2^k
+// square of the 2^32 by 2^32 grid to a contiguous run of 4^k keys, so a
+// rectangle can be covered by refining a quadtree and emitting each square's
+// run of keys.
+
+use crate::zorder::Rect;
+use crate::{Code, Dict, ValReq};
+use std::ops::RangeInclusive;
+
+#[derive(Copy, Clone)]
+struct Quad {
+    x0: u32,
+    y0: u32,
+    level: u32,
+}
+
+impl Quad {
+    fn side(&self) -> u64 {
+        1u64 << self.level
+    }
+
+    fn children(&self) -> impl Iterator<Item = Quad> {
+        let (x0, y0, level) = (self.x0 as u64, self.y0 as u64, self.level - 1);
+        let half = 1u64 << level;
+        (0..4u64).map(move |q| Quad {
+            x0: (x0 + (q & 1) * half) as u32,
+            y0: (y0 + (q >> 1) * half) as u32,
+            level,
+        })
+    }
+
+    // Some(true) if the quad lies inside the rect, Some(false) if it only
+    // overlaps it, None if it is disjoint from it.
+    fn within(&self, r: &Rect) -> Option<bool> {
+        let (x0, y0) = (self.x0 as u64, self.y0 as u64);
+        let (x1, y1) = (x0 + self.side() - 1, y0 + self.side() - 1);
+        let (rx0, rx1) = (*r.x.start() as u64, *r.x.end() as u64);
+        let (ry0, ry1) = (*r.y.start() as u64, *r.y.end() as u64);
+        if x1 < rx0 || x0 > rx1 || y1 < ry0 || y0 > ry1 {
+            None
+        } else {
+            Some(rx0 <= x0 && x1 <= rx1 && ry0 <= y0 && y1 <= ry1)
+        }
+    }
+}
+
+// Cover `rect` with at most `max_ranges` sorted, disjoint, inclusive ranges of
+// curve keys, where `first_key(x0, y0, level)` gives the first key of the
+// aligned square of side 2^level with corner (x0, y0). Squares that only
+// partly overlap the rectangle are refined one level at a time for as long as
+// the (pre-merge) number of ranges stays within budget; what remains is
+// covered whole.
+pub(crate) fn cover<F: Fn(u32, u32, u32) -> u64>(
+    rect: &Rect,
+    max_ranges: usize,
+    first_key: F,
+) -> Vec<RangeInclusive<u64>> {
+    assert!(max_ranges != 0);
+    let mut inside: Vec<Quad> = Vec::new();
+    let mut partial: Vec<Quad> = Vec::new();
+    let root = Quad {
+        x0: 0,
+        y0: 0,
+        level: 32,
+    };
+    match root.within(rect) {
+        None => return Vec::new(),
+        Some(true) => inside.push(root),
+        Some(false) => partial.push(root),
+    }
+    while !partial.is_empty() && partial[0].level > 0 {
+        let (mut next_inside, mut next_partial) = (Vec::new(), Vec::new());
+        for q in partial.iter().flat_map(|q| q.children()) {
+            match q.within(rect) {
+                None => (),
+                Some(true) => next_inside.push(q),
+                Some(false) => next_partial.push(q),
+            }
+        }
+        if inside.len() + next_inside.len() + next_partial.len() > max_ranges {
+            break;
+        }
+        inside.extend(next_inside);
+        partial = next_partial;
+    }
+    let mut ranges: Vec<(u64, u64)> = inside
+        .iter()
+        .chain(partial.iter())
+        .map(|q| {
+            let lo = first_key(q.x0, q.y0, q.level);
+            let span = ((1u128 << (2 * q.level)) - 1) as u64;
+            (lo, lo + span)
+        })
+        .collect();
+    ranges.sort_unstable();
+    let mut merged: Vec<RangeInclusive<u64>> = Vec::with_capacity(ranges.len());
+    for (lo, hi) in ranges {
+        if let Some(last) = merged.last_mut() {
+            if last.end().checked_add(1) == Some(lo) {
+                *last = *last.start()..=hi;
+                continue;
+            }
+        }
+        merged.push(lo..=hi);
+    }
+    merged
+}
+
+// Map sorted, disjoint key ranges onto a sorted union of disjoint inclusive
+// code ranges, merging ranges whose codes touch or overlap.
+pub(crate) fn code_ranges<K: ValReq>(
+    dict: &Dict<K>,
+    ranges: impl IntoIterator<Item = RangeInclusive<K>>,
+) -> Vec<RangeInclusive<Code>> {
+    let mut out: Vec<RangeInclusive<Code>> = Vec::new();
+    for r in ranges {
+        let (lo, hi) = (dict.encode(r.start()), dict.encode(r.end()));
+        if let Some(last) = out.last_mut() {
+            if last.end().0 as u32 + 1 >= lo.0 as u32 {
+                *last = *last.start()..=hi;
+                continue;
+            }
+        }
+        out.push(lo..=hi);
+    }
+    out
+}
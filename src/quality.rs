@@ -0,0 +1,520 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Tools for judging how well a [Dict] fits the data it will encode, before
+//! committing to encoding a large amount of it.
+
+use crate::{Code, Dict, Mode, ValReq};
+
+/// Quality measurements of a dictionary against a set of values it was not
+/// necessarily built from.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Metrics {
+    /// Fraction of the values that encode to an exact code, and so produce no
+    /// false positives under equality predicates.
+    pub exact_hit_rate: f64,
+
+    /// Interval width, measured in sample mass: for the values that encode to
+    /// an inexact code, the mean fraction of all the values sharing that code.
+    /// This is the expected false-positive rate of an equality predicate on
+    /// such a value. Zero if no value encoded to an inexact code.
+    pub inexact_width: f64,
+
+    /// The largest fraction of the values that encode to any single code. An
+    /// ideal dictionary spreads values evenly, so this approaches one over the
+    /// number of codes in use.
+    pub max_code_mass: f64,
+}
+
+impl Metrics {
+    /// Measure `dict` against `values`. Returns all-zero metrics if `values`
+    /// is empty.
+    pub fn measure<T: ValReq>(dict: &Dict<T>, values: &[T]) -> Metrics {
+        Metrics::of_codes(
+            values.iter().map(|v| dict.encode(v)),
+            dict.mode.max_inexact_code(),
+            |c| (c.0 & 1) == 0,
+        )
+    }
+
+    // Measure a stream of codes, none greater than `max`, of which those
+    // satisfying `is_exact` stand for single values.
+    pub(crate) fn of_codes<I, F>(codes: I, max: Code, is_exact: F) -> Metrics
+    where
+        I: Iterator<Item = Code>,
+        F: Fn(Code) -> bool,
+    {
+        let mut counts = vec![0usize; max.0 as usize + 1];
+        let mut total = 0usize;
+        for c in codes {
+            counts[c.0 as usize] += 1;
+            total += 1;
+        }
+        if total == 0 {
+            return Metrics::default();
+        }
+        let total = total as f64;
+        let mut exact = 0;
+        let mut inexact = 0;
+        let mut inexact_mass_sum = 0.0;
+        let mut max = 0;
+        for (code, &n) in counts.iter().enumerate() {
+            if is_exact(Code(code as u16)) {
+                exact += n;
+            } else {
+                inexact += n;
+                // Each of the n values sees a code holding n/total of the mass.
+                inexact_mass_sum += (n as f64) * (n as f64) / total;
+            }
+            max = max.max(n);
+        }
+        Metrics {
+            exact_hit_rate: exact as f64 / total,
+            inexact_width: if inexact == 0 {
+                0.0
+            } else {
+                inexact_mass_sum / inexact as f64
+            },
+            max_code_mass: max as f64 / total,
+        }
+    }
+}
+
+/// The mean and (population) variance of a metric across several trials.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Summary {
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl Summary {
+    fn of<I: Iterator<Item = f64> + Clone>(xs: I) -> Summary {
+        let n = xs.clone().count();
+        if n == 0 {
+            return Summary::default();
+        }
+        let mean = xs.clone().sum::<f64>() / n as f64;
+        let variance = xs.map(|x| (x - mean) * (x - mean)).sum::<f64>() / n as f64;
+        Summary { mean, variance }
+    }
+}
+
+/// The result of [cross_validate]: per-fold metrics and their summaries.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CrossValidation {
+    /// Metrics of each fold, measured on the fold's held-out values.
+    pub folds: Vec<Metrics>,
+    /// Summary of [Metrics::exact_hit_rate] across folds.
+    pub exact_hit_rate: Summary,
+    /// Summary of [Metrics::inexact_width] across folds.
+    pub inexact_width: Summary,
+    /// Summary of [Metrics::max_code_mass] across folds.
+    pub max_code_mass: Summary,
+}
+
+/// Estimate how well dictionaries built from samples like `sample` will
+/// generalize, by k-fold cross-validation: the sample is split into `k` folds,
+/// and for each fold a dictionary is built in `mode` from the other `k-1`
+/// folds and measured (see [Metrics]) against the held-out fold.
+///
+/// Low variance across folds, and held-out metrics close to those of the
+/// full sample, indicate that the sample is large enough to be
+/// representative. High variance indicates it is not.
+///
+/// Folds are assigned round-robin by position, so `sample` should already be
+/// in random order (as a sample drawn at random will be).
+///
+/// Panics if `k` is less than 2 or greater than the sample size.
+pub fn cross_validate<T: ValReq>(mode: Mode, sample: &[T], k: usize) -> CrossValidation {
+    assert!(k >= 2);
+    assert!(k <= sample.len());
+    let mut folds = Vec::with_capacity(k);
+    for fold in 0..k {
+        let mut training = Vec::with_capacity(sample.len() - sample.len() / k);
+        let mut held_out = Vec::with_capacity(sample.len() / k + 1);
+        for (i, v) in sample.iter().enumerate() {
+            if i % k == fold {
+                held_out.push(v.clone());
+            } else {
+                training.push(v.clone());
+            }
+        }
+        let dict = Dict::new(mode, training);
+        folds.push(Metrics::measure(&dict, &held_out));
+    }
+    CrossValidation {
+        exact_hit_rate: Summary::of(folds.iter().map(|m| m.exact_hit_rate)),
+        inexact_width: Summary::of(folds.iter().map(|m| m.inexact_width)),
+        max_code_mass: Summary::of(folds.iter().map(|m| m.max_code_mass)),
+        folds,
+    }
+}
+
+/// Estimated block-elision behaviour of a dictionary at one block size, as
+/// computed by [simulate_blocks].
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct BlockSimulation {
+    /// Number of entries per block.
+    pub block_size: usize,
+
+    /// Probability that a block contains a candidate for an equality
+    /// predicate, i.e. some row whose code matches the query's code. Such a
+    /// block must be read; all other blocks can be skipped.
+    pub candidate_rate: f64,
+
+    /// Probability that a block actually contains a row equal to the query
+    /// value. No sketch can skip these blocks.
+    pub match_rate: f64,
+
+    /// Probability that a block which does not contain the query value is
+    /// nonetheless a candidate, and so is read needlessly.
+    pub false_positive_rate: f64,
+}
+
+/// Estimate, for each of `block_sizes`, how often a block of that many rows
+/// would have to be read to answer an equality predicate using codes from
+/// `dict`. The `sample` is treated as the column itself, in order, cut into
+/// consecutive blocks; queries are the sample's own values, so frequent
+/// values are queried proportionally more often.
+///
+/// Comparing the results of a [Mode::Byte] and a [Mode::Word] dictionary over
+/// the same sample shows how the coarser byte codes, with their higher
+/// per-row chance of a false positive, fare as blocks grow: small blocks (a
+/// cache line of byte codes is 64 rows) tolerate coarse codes, while large
+/// blocks (a page of word codes is 2048 rows) need fine ones.
+///
+/// The sample's order matters: a sample in random order simulates a column
+/// with no clustering, which is the worst case for block elision.
+pub fn simulate_blocks<T: ValReq>(
+    dict: &Dict<T>,
+    sample: &[T],
+    block_sizes: &[usize],
+) -> Vec<BlockSimulation> {
+    use std::collections::BTreeMap;
+    let codes: Vec<u16> = sample.iter().map(|v| dict.encode(v).0).collect();
+    let mut sims = Vec::with_capacity(block_sizes.len());
+    for &block_size in block_sizes.iter() {
+        assert!(block_size != 0);
+        let nblocks = sample.len().div_ceil(block_size);
+        if nblocks == 0 {
+            sims.push(BlockSimulation {
+                block_size,
+                ..BlockSimulation::default()
+            });
+            continue;
+        }
+
+        // Count, for each code and each distinct value, the number of blocks
+        // in which it occurs at least once.
+        let mut blocks_with_code = vec![0usize; dict.mode.max_inexact_code().0 as usize + 1];
+        let mut blocks_with_value: BTreeMap<&T, usize> = BTreeMap::new();
+        for (vals, cs) in sample.chunks(block_size).zip(codes.chunks(block_size)) {
+            let mut cs = cs.to_vec();
+            cs.sort_unstable();
+            cs.dedup();
+            for c in cs {
+                blocks_with_code[c as usize] += 1;
+            }
+            let mut vals: Vec<&T> = vals.iter().collect();
+            vals.sort_unstable();
+            vals.dedup();
+            for v in vals {
+                *blocks_with_value.entry(v).or_insert(0) += 1;
+            }
+        }
+
+        // Average over one query per sample row.
+        let (mut candidates, mut matches) = (0usize, 0usize);
+        for (v, c) in sample.iter().zip(codes.iter()) {
+            candidates += blocks_with_code[*c as usize];
+            matches += blocks_with_value[v];
+        }
+        let queries = (sample.len() * nblocks) as f64;
+        let candidate_rate = candidates as f64 / queries;
+        let match_rate = matches as f64 / queries;
+        let false_positive_rate = if match_rate < 1.0 {
+            (candidate_rate - match_rate) / (1.0 - match_rate)
+        } else {
+            0.0
+        };
+        sims.push(BlockSimulation {
+            block_size,
+            candidate_rate,
+            match_rate,
+            false_positive_rate,
+        });
+    }
+    sims
+}
+
+/// One code's entry in a [Histogram].
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bucket<T> {
+    /// The code.
+    pub code: Code,
+
+    /// Number of sample values that encode to the code.
+    pub count: usize,
+
+    /// Fraction of the sample that encodes to the code.
+    pub fraction: f64,
+
+    /// The lower bound of the values covered by the code: the code's own value
+    /// if it is exact, otherwise the (excluded) value of the exact code below
+    /// it, or `None` for the lowest, unbounded interval.
+    pub lower: Option<T>,
+
+    /// The upper bound of the values covered by the code: the code's own value
+    /// if it is exact, otherwise the (excluded) value of the exact code above
+    /// it, or `None` for the highest, unbounded interval.
+    pub upper: Option<T>,
+}
+
+/// A plain, per-code breakdown of how a sample distributes over a
+/// dictionary's codes, for export to monitoring systems. Built by
+/// [Dict::export_histogram].
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Histogram<T> {
+    /// The mode of the dictionary the histogram was built from.
+    pub mode: Mode,
+
+    /// The total number of sample values.
+    pub total: usize,
+
+    /// One bucket per code the dictionary can produce, in code order.
+    pub buckets: Vec<Bucket<T>>,
+}
+
+impl<T: ValReq> Histogram<T> {
+    pub(crate) fn of(dict: &Dict<T>, sample: &[T]) -> Histogram<T> {
+        let ncodes = 2 * dict.codes.len() + 1;
+        let mut counts = vec![0usize; ncodes + 1];
+        for v in sample.iter() {
+            counts[dict.encode(v).0 as usize] += 1;
+        }
+        let total = sample.len();
+        let buckets = (1..=ncodes)
+            .map(|c| {
+                let (lower, upper) = dict.code_bounds(Code(c as u16));
+                Bucket {
+                    code: Code(c as u16),
+                    count: counts[c],
+                    fraction: if total == 0 {
+                        0.0
+                    } else {
+                        counts[c] as f64 / total as f64
+                    },
+                    lower: lower.cloned(),
+                    upper: upper.cloned(),
+                }
+            })
+            .collect();
+        Histogram {
+            mode: dict.mode,
+            total,
+            buckets,
+        }
+    }
+
+    /// Render the per-code counts in the Prometheus text exposition format,
+    /// as a gauge named `name` with `code` and `exact` labels.
+    pub fn to_prometheus(&self, name: &str) -> String {
+        use std::fmt::Write;
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP {} Sample values per dictionary code.", name);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for b in self.buckets.iter() {
+            let _ = writeln!(
+                out,
+                "{}{{code=\"{}\",exact=\"{}\"}} {}",
+                name,
+                b.code.0,
+                b.code.is_exact(),
+                b.count
+            );
+        }
+        out
+    }
+}
+
+/// The outcome of [advise_rebuild].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RebuildAdvice {
+    /// The two-sample Kolmogorov-Smirnov statistic: the largest difference
+    /// between the two samples' cumulative distributions over codes.
+    pub statistic: f64,
+
+    /// The approximate probability of seeing a statistic at least this large
+    /// if both samples came from the same distribution.
+    pub p_value: f64,
+
+    /// Whether `p_value` fell below the requested significance level, i.e.
+    /// whether the data has drifted enough that the dictionary should be
+    /// rebuilt from a fresh sample.
+    pub rebuild: bool,
+}
+
+// The Kolmogorov distribution's survival function, Q(λ) = 2 Σ (-1)^(j-1)
+// exp(-2 j² λ²), summed until the terms vanish.
+fn kolmogorov_q(lambda: f64) -> f64 {
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for j in 1..=100 {
+        let term = sign * (-2.0 * (j * j) as f64 * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+/// Decide whether `dict`, built from (a sample like) `construction_sample`,
+/// still fits the data, by comparing the distribution of `fresh_sample` over
+/// the dictionary's codes to that of `construction_sample` with a two-sample
+/// Kolmogorov-Smirnov test. Rebuilding is advised when the test's p-value is
+/// below `alpha` (0.05 or 0.01 are typical choices).
+///
+/// Comparing distributions over codes rather than over raw values means the
+/// test looks only at drift the dictionary can see: shifts of mass between
+/// codes. Because codes are discrete the test is conservative, erring on the
+/// side of not advising a rebuild.
+///
+/// If either sample is empty there is nothing to compare and the advice is
+/// not to rebuild.
+pub fn advise_rebuild<T: ValReq>(
+    dict: &Dict<T>,
+    construction_sample: &[T],
+    fresh_sample: &[T],
+    alpha: f64,
+) -> RebuildAdvice {
+    let (n1, n2) = (construction_sample.len(), fresh_sample.len());
+    if n1 == 0 || n2 == 0 {
+        return RebuildAdvice {
+            statistic: 0.0,
+            p_value: 1.0,
+            rebuild: false,
+        };
+    }
+    let ncodes = dict.mode.max_inexact_code().0 as usize + 1;
+    let mut c1 = vec![0usize; ncodes];
+    let mut c2 = vec![0usize; ncodes];
+    for v in construction_sample.iter() {
+        c1[dict.encode(v).0 as usize] += 1;
+    }
+    for v in fresh_sample.iter() {
+        c2[dict.encode(v).0 as usize] += 1;
+    }
+    let (mut f1, mut f2, mut d) = (0usize, 0usize, 0.0f64);
+    for (a, b) in c1.iter().zip(c2.iter()) {
+        f1 += a;
+        f2 += b;
+        d = d.max((f1 as f64 / n1 as f64 - f2 as f64 / n2 as f64).abs());
+    }
+    // Effective sample size and the small-sample correction from Stephens
+    // (1970), as given in Numerical Recipes.
+    let ne = ((n1 * n2) as f64 / (n1 + n2) as f64).sqrt();
+    let p_value = kolmogorov_q((ne + 0.12 + 0.11 / ne) * d);
+    RebuildAdvice {
+        statistic: d,
+        p_value,
+        rebuild: p_value < alpha,
+    }
+}
+
+/// What a code column costs to store, next to the base column it sketches,
+/// as computed by [measure_storage]. Costs are in bits per row.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct StorageReport {
+    /// The number of rows measured.
+    pub rows: usize,
+
+    /// The base column's cost, as given to [measure_storage].
+    pub base_bits: f64,
+
+    /// The codes stored at their mode's width, as in a sketch file: the
+    /// mode's [bits](Mode::bits).
+    pub stored_bits: f64,
+
+    /// The codes bit-packed at the fewest bits that hold the column's
+    /// greatest code.
+    pub packed_bits: f64,
+
+    /// The codes run-length encoded: each run of equal codes as its code at
+    /// the bit-packed width and its length as a LEB128 varint. Sorted or
+    /// clustered columns have long runs and so cost much less than a bit
+    /// per row.
+    pub rle_bits: f64,
+
+    /// Fraction of the dictionary's codes, exact and inexact, that occur in
+    /// the column. Low utilization means the dictionary spends codes on
+    /// values this column lacks.
+    pub code_utilization: f64,
+
+    /// Fraction of the mode's codespace the dictionary assigns. Low
+    /// utilization means a narrower mode would do.
+    pub codespace_utilization: f64,
+}
+
+impl StorageReport {
+    /// The cheapest of the encodings measured, in bits per row.
+    pub fn best_bits(&self) -> f64 {
+        self.stored_bits.min(self.packed_bits).min(self.rle_bits)
+    }
+
+    /// The cheapest encoding's cost as a fraction of the base column's: the
+    /// sketch's storage overhead. Zero if the base column costs nothing.
+    pub fn overhead(&self) -> f64 {
+        if self.base_bits > 0.0 {
+            self.best_bits() / self.base_bits
+        } else {
+            0.0
+        }
+    }
+}
+
+// The number of bytes of the LEB128 varint of `n`.
+fn varint_len(n: usize) -> usize {
+    let bits = usize::BITS - n.leading_zeros();
+    (bits as usize).div_ceil(7).max(1)
+}
+
+/// Measure what storing `codes`, encoded with `dict`, costs next to a base
+/// column of `base_bits` bits per row: a fixed width for numeric columns, or
+/// the mean length in bits for variable-width ones. Returns an all-zero
+/// report, but for the base and codespace figures, if `codes` is empty.
+pub fn measure_storage<T: ValReq>(dict: &Dict<T>, codes: &[Code], base_bits: f64) -> StorageReport {
+    // The dictionary's codes run from 1 to one past its last exact code.
+    let assigned = 2 * dict.codes.len() + 1;
+    let mut report = StorageReport {
+        rows: codes.len(),
+        base_bits,
+        codespace_utilization: assigned as f64 / dict.mode.max_inexact_code().0 as f64,
+        ..StorageReport::default()
+    };
+    if codes.is_empty() {
+        return report;
+    }
+    let rows = codes.len() as f64;
+    let max = codes.iter().max().map_or(0, |c| c.0);
+    let width = (16 - max.leading_zeros()).max(1) as usize;
+    let mut seen = vec![false; dict.mode.max_inexact_code().0 as usize + 1];
+    let mut rle = 0;
+    for run in codes.chunk_by(|a, b| a == b) {
+        seen[run[0].0 as usize] = true;
+        rle += width + 8 * varint_len(run.len());
+    }
+    report.stored_bits = dict.mode.bits() as f64;
+    report.packed_bits = width as f64;
+    report.rle_bits = rle as f64 / rows;
+    // Count only the dictionary's own codes, not code 0 on null rows.
+    let used = seen[1..=assigned].iter().filter(|s| **s).count();
+    report.code_utilization = used as f64 / assigned as f64;
+    report
+}
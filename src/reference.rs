@@ -0,0 +1,153 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Naive reference implementations of encoding and scanning, for checking
+//! other implementations against.
+//!
+//! An engine storing codes in its own layout, or evaluating predicates with
+//! its own kernels, should be able to check that it gets the same answers
+//! as this crate. The functions here are written straight from the
+//! definition of codes, as plainly as possible, with no searching, tables
+//! or early exits, and are what the crate's own optimized paths are
+//! differentially tested against. They are slow, and meant only for tests.
+//!
+//! Requires the `test-util` feature.
+
+use crate::predicate::{CLASS_DEFINITE, CLASS_MAYBE, CLASS_NONE};
+use crate::scan::BlockMatch;
+use crate::workload::RangePredicate;
+use crate::{Code, Dict, ValReq};
+use std::ops::Bound;
+
+/// The code of `value`: exact code 2(i+1) if it is `codes[i]`, and otherwise
+/// inexact code 2i+1, where `i` values of `codes` are less than it.
+pub fn encode<T: Ord>(codes: &[T], value: &T) -> Code {
+    let mut below = 0;
+    for c in codes {
+        if c == value {
+            return Code(2 * (below + 1));
+        }
+        if c < value {
+            below += 1;
+        }
+    }
+    Code(2 * below + 1)
+}
+
+/// Whether `value` satisfies `pred`.
+pub fn satisfies<T: Ord>(pred: &RangePredicate<T>, value: &T) -> bool {
+    let above = match &pred.0 {
+        Bound::Included(l) => l <= value,
+        Bound::Excluded(l) => l < value,
+        Bound::Unbounded => true,
+    };
+    let below = match &pred.1 {
+        Bound::Included(u) => value <= u,
+        Bound::Excluded(u) => value < u,
+        Bound::Unbounded => true,
+    };
+    above && below
+}
+
+/// The class of `code` of `dict` under `pred`, treating the values between
+/// two exact values as a continuum: an inexact code is definite if `pred`
+/// holds across the whole open interval it stands for, none if nowhere in
+/// it, and maybe otherwise. Codes the dictionary doesn't produce are none.
+pub fn classify<T: ValReq>(dict: &Dict<T>, pred: &RangePredicate<T>, code: Code) -> u8 {
+    let codes = &dict.codes;
+    let c = code.0 as usize;
+    if c == 0 || c > 2 * codes.len() + 1 {
+        return CLASS_NONE;
+    }
+    if code.is_exact() {
+        return if satisfies(pred, &codes[c / 2 - 1]) {
+            CLASS_DEFINITE
+        } else {
+            CLASS_NONE
+        };
+    }
+    // The open interval (lo, hi), unbounded where None.
+    let i = c / 2;
+    let lo = if i > 0 { Some(&codes[i - 1]) } else { None };
+    let hi = codes.get(i);
+    let starts_after = match (&pred.0, hi) {
+        (Bound::Included(l), Some(h)) | (Bound::Excluded(l), Some(h)) => l >= h,
+        _ => false,
+    };
+    let ends_before = match (&pred.1, lo) {
+        (Bound::Included(u), Some(l)) | (Bound::Excluded(u), Some(l)) => u <= l,
+        _ => false,
+    };
+    if starts_after || ends_before {
+        return CLASS_NONE;
+    }
+    let covers_lo = match (&pred.0, lo) {
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(l), Some(v)) | (Bound::Excluded(l), Some(v)) => l <= v,
+        (_, None) => false,
+    };
+    let covers_hi = match (&pred.1, hi) {
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(u), Some(v)) | (Bound::Excluded(u), Some(v)) => v <= u,
+        (_, None) => false,
+    };
+    if covers_lo && covers_hi {
+        CLASS_DEFINITE
+    } else if dict.is_exact_complete() {
+        // No values fall between exact ones.
+        CLASS_NONE
+    } else {
+        CLASS_MAYBE
+    }
+}
+
+/// The class of each of `codes`, of `dict`, under `pred`, as
+/// [classify] gives it.
+pub fn scan<T: ValReq>(dict: &Dict<T>, pred: &RangePredicate<T>, codes: &[Code]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for c in codes {
+        out.push(classify(dict, pred, *c));
+    }
+    out
+}
+
+/// What each block of `block_rows` of `codes`, of `dict`, needs under
+/// `pred`, as [block_match] gives it.
+pub fn scan_blocks<T: ValReq>(
+    dict: &Dict<T>,
+    pred: &RangePredicate<T>,
+    codes: &[Code],
+    block_rows: usize,
+) -> Vec<BlockMatch> {
+    let mut out = Vec::new();
+    for block in codes.chunks(block_rows) {
+        out.push(block_match(&scan(dict, pred, block)));
+    }
+    out
+}
+
+/// The class of each row of a conjunction: the least of its classes under
+/// each predicate.
+pub fn conjunction(classes: &[Vec<u8>]) -> Vec<u8> {
+    let rows = classes.first().map_or(0, |c| c.len());
+    let mut out = Vec::new();
+    for i in 0..rows {
+        let mut class = CLASS_DEFINITE;
+        for c in classes {
+            class = class.min(c[i]);
+        }
+        out.push(class);
+    }
+    out
+}
+
+/// What a block whose rows have the classes `classes` needs: a definite row
+/// makes it definite, failing that a maybe row makes it maybe.
+pub fn block_match(classes: &[u8]) -> BlockMatch {
+    let best = classes.iter().copied().max().unwrap_or(CLASS_NONE);
+    match best {
+        CLASS_DEFINITE => BlockMatch::Definite,
+        CLASS_MAYBE => BlockMatch::Maybe,
+        _ => BlockMatch::None,
+    }
+}
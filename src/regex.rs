@@ -0,0 +1,150 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Rewriting regular-expression matches on string columns.
+//!
+//! Codes only preserve order, so in general a regular expression can't be
+//! rewritten onto them. But a pattern anchored at the start with a literal,
+//! like `^ERROR.*timeout`, only matches strings with that literal as a
+//! prefix, and the strings with a given prefix form a range. That range
+//! rewrites onto codes like any other, giving a conservative set of
+//! candidate rows to run the full pattern over.
+//!
+//! Patterns are read in the common syntax shared by the `regex` crate, PCRE
+//! and most SQL engines. Anything this module doesn't understand ends the
+//! literal prefix early, which only ever widens the rewrite.
+
+use crate::predicate::CodePredicate;
+use crate::workload::RangePredicate;
+use crate::{Code, Dict};
+use std::ops::Bound;
+
+// Whether the pattern has a top-level alternation, which would let it match
+// strings without the prefix of its first branch.
+fn has_top_level_alternation(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    let mut depth = 0usize;
+    // Nesting depth of character classes, and whether the next character is
+    // the first of a class (where `]` is literal).
+    let mut class = 0usize;
+    let mut class_start = false;
+    while let Some(c) = chars.next() {
+        let first = std::mem::replace(&mut class_start, false);
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => {
+                class += 1;
+                class_start = true;
+            }
+            '^' if first => class_start = true,
+            ']' if class > 0 && !first => class -= 1,
+            _ if class > 0 => (),
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Extract the literal prefix every match of `pattern` must start with,
+/// along with whether the pattern matches exactly the strings with that
+/// prefix (as `^ERROR` does). Returns `None` if the pattern isn't anchored
+/// at the start of the string or has a top-level alternation.
+pub fn literal_prefix(pattern: &str) -> Option<(String, bool)> {
+    let rest = pattern
+        .strip_prefix('^')
+        .or_else(|| pattern.strip_prefix("\\A"))?;
+    if has_top_level_alternation(rest) {
+        return None;
+    }
+    let mut prefix = String::new();
+    let mut chars = rest.chars().peekable();
+    loop {
+        let c = match chars.peek() {
+            None => return Some((prefix, true)),
+            Some('\\') => {
+                let mut ahead = chars.clone();
+                ahead.next();
+                match ahead.next() {
+                    // Escaped punctuation is literal; other escapes are
+                    // classes, assertions or character codes.
+                    Some(e) if e.is_ascii_punctuation() => {
+                        chars = ahead;
+                        e
+                    }
+                    _ => break,
+                }
+            }
+            Some(c) if ".[]()*+?{}|^$".contains(*c) => break,
+            Some(c) => {
+                let c = *c;
+                chars.next();
+                c
+            }
+        };
+        match chars.peek() {
+            // The character may be absent, or repeated any number of times.
+            Some('*') | Some('?') | Some('{') => break,
+            // The character is present, but what follows is unknown.
+            Some('+') => {
+                prefix.push(c);
+                break;
+            }
+            _ => prefix.push(c),
+        }
+    }
+    Some((prefix, false))
+}
+
+// The least string greater than every string with `prefix`, if any.
+fn prefix_successor(prefix: &str) -> Option<String> {
+    let mut s = prefix.to_string();
+    while let Some(c) = s.pop() {
+        let next = match c as u32 + 1 {
+            0xd800 => Some('\u{e000}'),
+            n => char::from_u32(n),
+        };
+        if let Some(n) = next {
+            s.push(n);
+            return Some(s);
+        }
+    }
+    None
+}
+
+/// The range of strings starting with `prefix`.
+pub fn prefix_range(prefix: &str) -> RangePredicate<String> {
+    let upper = match prefix_successor(prefix) {
+        Some(s) => Bound::Excluded(s),
+        None => Bound::Unbounded,
+    };
+    (Bound::Included(prefix.to_string()), upper)
+}
+
+/// Rewrite "value matches `pattern`" onto the codes of `dict`. Rows in the
+/// result's definite ranges match only if `pattern` is itself just an
+/// anchored literal; otherwise every candidate row must be checked against
+/// the pattern. If no literal prefix can be found every code is a
+/// candidate.
+pub fn rewrite_regex(dict: &Dict<String>, pattern: &str) -> CodePredicate {
+    let (prefix, exact) = match literal_prefix(pattern) {
+        Some((prefix, exact)) if !prefix.is_empty() => (prefix, exact),
+        _ => {
+            let max = Code(2 * dict.codes.len() as u16 + 1);
+            return CodePredicate {
+                definite: Vec::new(),
+                maybe: vec![Code(1)..=max],
+            };
+        }
+    };
+    let mut pred = CodePredicate::from_range(dict, &prefix_range(&prefix));
+    if !exact {
+        pred.maybe = pred.candidates();
+        pred.definite.clear();
+    }
+    pred
+}
@@ -0,0 +1,278 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Loading [sketch files](crate::sketchfile) asynchronously, a byte range at
+//! a time, from object storage or any other source with costly reads.
+//!
+//! Fetching a whole sketch from an object store to scan a few of its blocks
+//! wastes most of the transfer, and blocking a thread on each fetch wastes
+//! the thread. A [RemoteSketch] fetches the header, zone map, checksums and
+//! block index when opened, and the dictionary and each block only when
+//! asked for, through a [RangeSource] such as an object store client
+//! returning futures.
+//!
+//! Blocks are usually wanted in order, and each fetch has latency to spare.
+//! A [BlockReader] walks a list of blocks, typically those a zone map
+//! couldn't rule out, keeping fetches of the next few in flight on the
+//! runtime while the current one is decoded and scanned.
+//!
+//! Requires the `tokio` feature; readahead spawns onto the current tokio
+//! runtime.
+
+use crate::format::{BinaryValue, FormatError};
+use crate::sketchfile::{crc32c, read_zones, ReadOptions, SketchHeader, SKETCH_HEADER_LEN};
+use crate::zonemap::ZoneMap;
+use crate::{Code, Dict, ValReq};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::task::JoinHandle;
+
+/// The future of a [RangeSource] read.
+pub type ReadFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Vec<u8>>> + Send + 'a>>;
+
+/// Something a sketch file can be read from a byte range at a time: an
+/// object in a store, a local file, or bytes already in memory.
+pub trait RangeSource: Send + Sync + 'static {
+    /// The size of the file, in bytes.
+    fn size(&self) -> u64;
+
+    /// Read the `len` bytes at `offset`. Fails if they aren't all there.
+    fn read_range(&self, offset: u64, len: u64) -> ReadFuture<'_>;
+}
+
+impl RangeSource for Vec<u8> {
+    fn size(&self) -> u64 {
+        self.len() as u64
+    }
+
+    fn read_range(&self, offset: u64, len: u64) -> ReadFuture<'_> {
+        let bytes = offset
+            .checked_add(len)
+            .and_then(|end| self.get(offset as usize..end as usize))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into());
+        Box::pin(async move { bytes })
+    }
+}
+
+/// A local file read through tokio, one range at a time.
+pub struct FileSource {
+    file: tokio::sync::Mutex<tokio::fs::File>,
+    size: u64,
+}
+
+impl FileSource {
+    /// Open the file at `path`.
+    pub async fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        Ok(FileSource {
+            file: tokio::sync::Mutex::new(file),
+            size,
+        })
+    }
+}
+
+impl RangeSource for FileSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_range(&self, offset: u64, len: u64) -> ReadFuture<'_> {
+        Box::pin(async move {
+            let mut file = self.file.lock().await;
+            file.seek(io::SeekFrom::Start(offset)).await?;
+            let mut bytes = vec![0; len as usize];
+            file.read_exact(&mut bytes).await?;
+            Ok(bytes)
+        })
+    }
+}
+
+fn invalid(e: FormatError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+// What a sketch's blocks are fetched and decoded with, shared with the
+// tasks fetching them ahead.
+struct Shared<S> {
+    source: S,
+    header: SketchHeader,
+    index: Vec<u8>,
+    sums: Vec<u8>,
+    verify: bool,
+}
+
+impl<S: RangeSource> Shared<S> {
+    // Fetch the stored bytes at `range` and check them against checksum `i`.
+    async fn fetch(&self, range: (u64, u64), i: usize) -> io::Result<Vec<u8>> {
+        let bytes = self.source.read_range(range.0, range.1).await?;
+        if self.verify
+            && self
+                .header
+                .checksum_at(&self.sums, i)
+                .is_some_and(|c| c != crc32c(&bytes))
+        {
+            return Err(invalid(FormatError::BadChecksum));
+        }
+        Ok(bytes)
+    }
+
+    async fn block(&self, i: usize) -> io::Result<Vec<Code>> {
+        let stored = self
+            .fetch(self.header.block_range(&self.index, i), 2 + i)
+            .await?;
+        let raw = self.header.decode_block(i, &stored).map_err(invalid)?;
+        let mut codes = Vec::with_capacity(self.header.rows_in_block(i));
+        self.header.mode.unpack_codes(&raw, &mut codes);
+        Ok(codes)
+    }
+}
+
+/// A sketch file read from a [RangeSource]. See the [module
+/// documentation](self).
+pub struct RemoteSketch<S> {
+    shared: Arc<Shared<S>>,
+    zones: ZoneMap,
+}
+
+impl<S: RangeSource> RemoteSketch<S> {
+    /// Open the sketch file in `source`, fetching and checking its header,
+    /// zone map, checksums and block index, and checking the zone map
+    /// against its checksum. A file that isn't a readable sketch is an
+    /// [io::ErrorKind::InvalidData] error wrapping the [FormatError].
+    pub async fn open(source: S) -> io::Result<Self> {
+        Self::open_with_options(source, &ReadOptions::default()).await
+    }
+
+    /// Like [RemoteSketch::open], with checksums only verified if `options`
+    /// says so. This applies to the dictionary and blocks fetched later too.
+    pub async fn open_with_options(source: S, options: &ReadOptions) -> io::Result<Self> {
+        let size = source.size();
+        let prefix = source
+            .read_range(0, size.min(SKETCH_HEADER_LEN as u64))
+            .await?;
+        let header = SketchHeader::read_prefix(&prefix, size).map_err(invalid)?;
+        let index = source
+            .read_range(header.codes.0, header.index_len())
+            .await?;
+        header.check_index(&index).map_err(invalid)?;
+        let sums = source
+            .read_range(header.checksums.0, header.checksums.1)
+            .await?;
+        let shared = Shared {
+            source,
+            header,
+            index,
+            sums,
+            verify: options.verify_checksums,
+        };
+        let zones = read_zones(&shared.fetch(header.zones, 1).await?);
+        Ok(RemoteSketch {
+            shared: Arc::new(shared),
+            zones,
+        })
+    }
+
+    /// Return the file's header.
+    pub fn header(&self) -> &SketchHeader {
+        &self.shared.header
+    }
+
+    /// Return the zone map, to choose the blocks worth fetching.
+    pub fn zones(&self) -> &ZoneMap {
+        &self.zones
+    }
+
+    /// The number of rows.
+    pub fn rows(&self) -> usize {
+        self.shared.header.rows as usize
+    }
+
+    /// The number of zone-map blocks.
+    pub fn blocks(&self) -> usize {
+        self.shared.header.blocks()
+    }
+
+    /// Fetch the serialized dictionary, as written by [Dict::to_bytes].
+    pub async fn dict_bytes(&self) -> io::Result<Vec<u8>> {
+        let stored = self.shared.fetch(self.shared.header.dict, 0).await?;
+        let raw = self.shared.header.decode_dict(&stored).map_err(invalid)?;
+        Ok(raw.into_owned())
+    }
+
+    /// Fetch and decode the dictionary.
+    pub async fn dict<T: ValReq + BinaryValue>(&self) -> io::Result<Dict<T>> {
+        let dict = Dict::from_bytes(&self.dict_bytes().await?).map_err(invalid)?;
+        if dict.mode != self.shared.header.mode {
+            return Err(invalid(FormatError::BadCodes));
+        }
+        Ok(dict)
+    }
+
+    /// Fetch and decode the codes of block `i`.
+    ///
+    /// Panics if there is no block `i`.
+    pub async fn block(&self, i: usize) -> io::Result<Vec<Code>> {
+        assert!(i < self.blocks());
+        self.shared.block(i).await
+    }
+
+    /// Read the blocks `blocks` in order, with fetches of up to `readahead`
+    /// blocks after the one being read in flight at once.
+    ///
+    /// Panics if any of `blocks` is out of range.
+    pub fn read_blocks(&self, blocks: Vec<usize>, readahead: usize) -> BlockReader<S> {
+        assert!(blocks.iter().all(|i| *i < self.blocks()));
+        BlockReader {
+            shared: self.shared.clone(),
+            rest: blocks.into_iter(),
+            pending: VecDeque::new(),
+            readahead,
+        }
+    }
+}
+
+/// A reader of a list of a [RemoteSketch]'s blocks, made by
+/// [RemoteSketch::read_blocks]. Dropping it abandons the fetches in
+/// flight.
+pub struct BlockReader<S> {
+    shared: Arc<Shared<S>>,
+    rest: std::vec::IntoIter<usize>,
+    pending: VecDeque<(usize, JoinHandle<io::Result<Vec<Code>>>)>,
+    readahead: usize,
+}
+
+impl<S: RangeSource> BlockReader<S> {
+    /// Return the next block's index and codes, or None once all have been
+    /// read.
+    pub async fn next(&mut self) -> Option<io::Result<(usize, Vec<Code>)>> {
+        while self.pending.len() <= self.readahead {
+            let i = match self.rest.next() {
+                Some(i) => i,
+                None => break,
+            };
+            let shared = self.shared.clone();
+            let task = tokio::spawn(async move { shared.block(i).await });
+            self.pending.push_back((i, task));
+        }
+        let (i, task) = self.pending.pop_front()?;
+        Some(match task.await {
+            Ok(codes) => codes.map(|c| (i, c)),
+            Err(e) => Err(io::Error::other(e)),
+        })
+    }
+}
+
+impl<S> Drop for BlockReader<S> {
+    fn drop(&mut self) {
+        for (_, task) in &self.pending {
+            task.abort();
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Incremental sampling across ingest batches.
+//!
+//! A dictionary is only as good as its sample, and the data worth sampling
+//! may arrive in batches over days, long before (and long after) anyone
+//! wants to build. An [OnlineSampler] is fed every batch as it arrives and
+//! keeps a bounded sample representative of everything seen so far: a
+//! uniform reservoir, plus approximate counts of the most frequent values so
+//! that those get exact codes even when the reservoir is small. A fresh
+//! [Dict] can be built from it at any point.
+
+use crate::summary::{SampleSummary, SummaryEntry};
+use crate::{BuildOptions, Dict, Mode, ValReq};
+use std::collections::BTreeMap;
+
+/// A bounded, incrementally maintained sample. See the [module
+/// documentation](self).
+pub struct OnlineSampler<T: ValReq> {
+    reservoir: Vec<T>,
+    capacity: usize,
+    // Misra-Gries counters: each undercounts its value by at most the number
+    // of values seen over the number of counters.
+    heavy: BTreeMap<T, u64>,
+    heavy_capacity: usize,
+    seen: u64,
+    rng: u64,
+}
+
+impl<T: ValReq> OnlineSampler<T> {
+    /// Make a sampler keeping a reservoir of up to `capacity` values and
+    /// frequency counters for up to `heavy_capacity` values.
+    pub fn new(capacity: usize, heavy_capacity: usize) -> Self {
+        assert!(capacity != 0);
+        OnlineSampler {
+            reservoir: Vec::with_capacity(capacity),
+            capacity,
+            heavy: BTreeMap::new(),
+            heavy_capacity,
+            seen: 0,
+            rng: 0x853c_49e6_748f_ea9b,
+        }
+    }
+
+    /// Return the number of values observed so far.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// Return the values currently in the reservoir, in no particular order.
+    pub fn reservoir(&self) -> &[T] {
+        &self.reservoir
+    }
+
+    /// Observe one value.
+    pub fn observe(&mut self, value: &T) {
+        self.seen += 1;
+        if self.reservoir.len() < self.capacity {
+            self.reservoir.push(value.clone());
+        } else {
+            self.rng = self
+                .rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let j = (self.rng >> 32) % self.seen;
+            if (j as usize) < self.capacity {
+                self.reservoir[j as usize] = value.clone();
+            }
+        }
+        if self.heavy_capacity == 0 {
+            return;
+        }
+        if let Some(n) = self.heavy.get_mut(value) {
+            *n += 1;
+        } else if self.heavy.len() < self.heavy_capacity {
+            self.heavy.insert(value.clone(), 1);
+        } else {
+            self.heavy.retain(|_, n| {
+                *n -= 1;
+                *n != 0
+            });
+        }
+    }
+
+    /// Observe every value in an ingest batch.
+    pub fn observe_batch(&mut self, batch: &[T]) {
+        for v in batch {
+            self.observe(v);
+        }
+    }
+
+    /// Summarize everything observed so far: the reservoir's values, with
+    /// counts scaled up to the number of values seen and raised to the
+    /// frequency counters' lower bounds where those are higher.
+    pub fn summary(&self) -> SampleSummary<T> {
+        let mut sorted = self.reservoir.clone();
+        sorted.sort_unstable();
+        let scale = self.seen as f64 / sorted.len().max(1) as f64;
+        let mut counts: BTreeMap<T, u64> = BTreeMap::new();
+        for v in sorted.into_iter() {
+            *counts.entry(v).or_insert(0) += 1;
+        }
+        for n in counts.values_mut() {
+            *n = (*n as f64 * scale).round() as u64;
+        }
+        for (v, n) in self.heavy.iter() {
+            let c = counts.entry(v.clone()).or_insert(0);
+            *c = (*c).max(*n);
+        }
+        let entries = counts
+            .into_iter()
+            .map(|(value, count)| SummaryEntry {
+                value,
+                count,
+                below: 0,
+            })
+            .collect();
+        SampleSummary { entries, above: 0 }
+    }
+
+    /// Build a dictionary from everything observed so far.
+    pub fn dict(&self, mode: Mode, options: &BuildOptions) -> Dict<T> {
+        Dict::from_summary(mode, &self.summary(), options)
+    }
+}
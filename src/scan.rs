@@ -0,0 +1,795 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Scanning code columns.
+//!
+//! When a scan only has to decide which blocks of the underlying data need
+//! reading, it doesn't need a result per row: a block needs reading as soon
+//! as one of its codes might satisfy the predicate. [scan_chunks] works
+//! through the column one block at a time and stops looking at a block once
+//! one of its codes is a definite match.
+//!
+//! [scan_conjunction] evaluates a conjunction of predicates over several
+//! parallel code columns in one pass, rather than producing a result per
+//! column and intersecting them.
+//!
+//! [WordKernel] classifies word codes against arbitrary code sets, not just
+//! ranges, with two 256-entry byte tables: the layout a pshufb-style
+//! vectorized lookup needs.
+//!
+//! [scan_bytes] and [scan_words] evaluate a predicate over a column of byte
+//! or word codes, as a [Sketch](crate::sketch::Sketch) holds them, into
+//! bitmaps. When the predicate's candidate and definite codes each form at
+//! most one range, they test many codes per instruction: 32 byte codes or
+//! 16 word codes with AVX2 on x86-64, or 16 byte codes or 8 word codes with
+//! NEON on aarch64, the instruction set chosen at runtime. On other CPUs,
+//! the `portable-simd` feature, which needs a nightly compiler, tests 64
+//! codes at a time with `std::simd`. Otherwise they look each code up in
+//! the predicate's table.
+//!
+//! A [ScanBitmap] also converts to selection vectors of the matching row
+//! indices, the form vectorized engines pass between operators.
+//!
+//! With the `roaring` feature, a [ScanBitmap]'s rows convert to Roaring
+//! bitmaps, for engines that combine results from several indexes that way.
+
+use crate::predicate::{CompiledPredicate, Ternary, Thresholds, CLASS_DEFINITE, CLASS_NONE};
+use crate::{Code, Mode};
+
+/// What a chunked scan learned about one block.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BlockMatch {
+    /// No row in the block can satisfy the predicate; the block can be
+    /// skipped.
+    None,
+    /// Some rows might satisfy the predicate, but none definitely does; the
+    /// block must be read and those rows re-checked.
+    Maybe,
+    /// At least one row definitely satisfies the predicate.
+    Definite,
+}
+
+impl BlockMatch {
+    /// Whether the block has to be read.
+    pub fn needs_read(self) -> bool {
+        self != BlockMatch::None
+    }
+}
+
+/// Scan `codes` in chunks of `block_rows` codes and classify each chunk
+/// against `pred` with existence semantics. A chunk's scan ends at its first
+/// definitely-matching code.
+///
+/// Panics if `block_rows` is zero.
+pub fn scan_chunks(pred: &CompiledPredicate, codes: &[Code], block_rows: usize) -> Vec<BlockMatch> {
+    assert!(block_rows != 0);
+    codes
+        .chunks(block_rows)
+        .map(|block| {
+            let mut result = BlockMatch::None;
+            for c in block.iter() {
+                match pred.classify(*c) {
+                    CLASS_NONE => {}
+                    CLASS_DEFINITE => return BlockMatch::Definite,
+                    _ => result = BlockMatch::Maybe,
+                }
+            }
+            result
+        })
+        .collect()
+}
+
+/// Evaluate the conjunction of predicates over parallel code columns, each
+/// predicate paired with the column it applies to, returning the class of
+/// each row: [CLASS_DEFINITE] if every predicate definitely holds,
+/// [CLASS_NONE] if any fails, and
+/// [CLASS_MAYBE](crate::predicate::CLASS_MAYBE) otherwise. A row's
+/// evaluation stops at the first predicate that fails, so later columns are
+/// only read for rows still in play. With no columns there are no rows, and
+/// the result is empty.
+///
+/// Panics if the columns differ in length.
+pub fn scan_conjunction(columns: &[(&CompiledPredicate, &[Code])]) -> Vec<u8> {
+    let rows = columns.first().map_or(0, |(_, c)| c.len());
+    assert!(columns.iter().all(|(_, c)| c.len() == rows));
+    (0..rows)
+        .map(|i| {
+            let mut class = CLASS_DEFINITE;
+            for (pred, codes) in columns.iter() {
+                class = class.min(pred.classify(codes[i]));
+                if class == CLASS_NONE {
+                    break;
+                }
+            }
+            class
+        })
+        .collect()
+}
+
+/// A set of 16-bit codes as two 256-entry tables indexed by a code's low and
+/// high bytes. Each distinct non-empty set of low bytes occurring among the
+/// codes sharing a high byte is assigned one of 8 bits; the high table maps
+/// a high byte to its set's bit, the low table maps a low byte to the bits
+/// of the sets containing it, and a code is a member iff the two entries
+/// share a bit.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SplitTable {
+    pub lo: [u8; 256],
+    pub hi: [u8; 256],
+}
+
+impl SplitTable {
+    /// Build the table of the codes for which `member` holds, or None if
+    /// they need more than 8 distinct low-byte sets.
+    pub fn new<F: Fn(u16) -> bool>(member: F) -> Option<SplitTable> {
+        let mut patterns: Vec<[u64; 4]> = Vec::new();
+        let mut table = SplitTable {
+            lo: [0; 256],
+            hi: [0; 256],
+        };
+        for hi in 0..256usize {
+            let mut pattern = [0u64; 4];
+            for lo in 0..256usize {
+                if member((hi << 8 | lo) as u16) {
+                    pattern[lo / 64] |= 1 << (lo % 64);
+                }
+            }
+            if pattern == [0; 4] {
+                continue;
+            }
+            let bit = match patterns.iter().position(|p| *p == pattern) {
+                Some(i) => i,
+                None if patterns.len() < 8 => {
+                    patterns.push(pattern);
+                    patterns.len() - 1
+                }
+                None => return None,
+            };
+            table.hi[hi] = 1 << bit;
+        }
+        for (bit, pattern) in patterns.iter().enumerate() {
+            for lo in 0..256 {
+                if pattern[lo / 64] & (1 << (lo % 64)) != 0 {
+                    table.lo[lo] |= 1 << bit;
+                }
+            }
+        }
+        Some(table)
+    }
+
+    /// Whether `code` is in the set.
+    #[inline]
+    pub fn contains(&self, code: u16) -> bool {
+        self.lo[(code & 0xff) as usize] & self.hi[(code >> 8) as usize] != 0
+    }
+}
+
+/// A word-mode predicate evaluated by byte-table lookups; see
+/// [SplitTable].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WordKernel {
+    candidates: SplitTable,
+    definite: SplitTable,
+}
+
+impl WordKernel {
+    /// Build the kernel for a compiled word-mode predicate, or None if its
+    /// candidate or definite codes are too irregular for [SplitTable].
+    pub fn new(pred: &CompiledPredicate) -> Option<WordKernel> {
+        if pred.mode != Mode::Word {
+            return None;
+        }
+        let class = |c: u16| pred.table[c as usize];
+        Some(WordKernel {
+            candidates: SplitTable::new(|c| class(c) != CLASS_NONE)?,
+            definite: SplitTable::new(|c| class(c) == CLASS_DEFINITE)?,
+        })
+    }
+
+    /// Write the class of each of `codes` to the same position of `out`, as
+    /// [CompiledPredicate::classify_into] would.
+    ///
+    /// Panics if `out` is shorter than `codes`.
+    pub fn classify_into(&self, codes: &[u16], out: &mut [u8]) {
+        for (o, c) in out[..codes.len()].iter_mut().zip(codes.iter()) {
+            *o = self.candidates.contains(*c) as u8 + self.definite.contains(*c) as u8;
+        }
+    }
+}
+
+/// The result of evaluating a predicate over a code column: a bitmap of
+/// the rows that may satisfy it and one of the rows that definitely do.
+/// Row `i` is bit `i % 64` of word `i / 64`; bits past the last row are
+/// clear.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScanBitmap {
+    rows: usize,
+    candidates: Vec<u64>,
+    definite: Vec<u64>,
+}
+
+impl ScanBitmap {
+    fn new(rows: usize) -> Self {
+        let words = rows.div_ceil(64);
+        ScanBitmap {
+            rows,
+            candidates: vec![0; words],
+            definite: vec![0; words],
+        }
+    }
+
+    fn set(&mut self, row: usize, class: u8) {
+        let bit = 1u64 << (row % 64);
+        if class != CLASS_NONE {
+            self.candidates[row / 64] |= bit;
+        }
+        if class == CLASS_DEFINITE {
+            self.definite[row / 64] |= bit;
+        }
+    }
+
+    /// The number of rows scanned.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The bitmap of rows that may satisfy the predicate, definite or not.
+    pub fn candidates(&self) -> &[u64] {
+        &self.candidates
+    }
+
+    /// The bitmap of rows that definitely satisfy the predicate.
+    pub fn definite(&self) -> &[u64] {
+        &self.definite
+    }
+
+    /// The value of the predicate on row `i`.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn evaluate(&self, i: usize) -> Ternary {
+        assert!(i < self.rows);
+        let bit = 1u64 << (i % 64);
+        if self.definite[i / 64] & bit != 0 {
+            Ternary::True
+        } else if self.candidates[i / 64] & bit != 0 {
+            Ternary::Maybe
+        } else {
+            Ternary::False
+        }
+    }
+
+    /// The number of rows that may satisfy the predicate.
+    pub fn count_candidates(&self) -> usize {
+        self.candidates
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum()
+    }
+
+    /// The number of rows that definitely satisfy the predicate.
+    pub fn count_definite(&self) -> usize {
+        self.definite.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// The indices of the rows that may satisfy the predicate, definite or
+    /// not, in order.
+    ///
+    /// Panics if there are more than 2^32 rows.
+    pub fn candidate_selection(&self) -> Vec<u32> {
+        set_rows(self.rows, self.candidates.iter().copied()).collect()
+    }
+
+    /// The indices of the rows that may satisfy the predicate, split into
+    /// the definite and the maybe rows.
+    ///
+    /// Panics if there are more than 2^32 rows.
+    pub fn selection(&self) -> Selection {
+        let words = self.candidates.iter().zip(self.definite.iter());
+        Selection {
+            definite: set_rows(self.rows, self.definite.iter().copied()).collect(),
+            maybe: set_rows(self.rows, words.map(|(c, d)| c & !d)).collect(),
+        }
+    }
+
+    /// The indices of the rows that may satisfy the predicate, in order,
+    /// each tagged with [Ternary::True] if it definitely does and
+    /// [Ternary::Maybe] if it needs re-checking.
+    ///
+    /// Panics if there are more than 2^32 rows.
+    pub fn tagged_selection(&self) -> Vec<(u32, Ternary)> {
+        set_rows(self.rows, self.candidates.iter().copied())
+            .map(|i| {
+                let definite = self.definite[i as usize / 64] & 1 << (i % 64) != 0;
+                (
+                    i,
+                    if definite {
+                        Ternary::True
+                    } else {
+                        Ternary::Maybe
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// The positions of the rows that may satisfy the predicate, definite
+    /// or not, as a Roaring bitmap.
+    ///
+    /// Panics if there are more than 2^32 rows.
+    #[cfg(feature = "roaring")]
+    pub fn candidates_roaring(&self) -> roaring::RoaringBitmap {
+        to_roaring(self.rows, self.candidates.iter().copied())
+    }
+
+    /// The positions of the rows that definitely satisfy the predicate, as
+    /// a Roaring bitmap.
+    ///
+    /// Panics if there are more than 2^32 rows.
+    #[cfg(feature = "roaring")]
+    pub fn definite_roaring(&self) -> roaring::RoaringBitmap {
+        to_roaring(self.rows, self.definite.iter().copied())
+    }
+
+    /// The positions of the rows that may satisfy the predicate but need
+    /// re-checking against their values, as a Roaring bitmap.
+    ///
+    /// Panics if there are more than 2^32 rows.
+    #[cfg(feature = "roaring")]
+    pub fn maybe_roaring(&self) -> roaring::RoaringBitmap {
+        let words = self.candidates.iter().zip(self.definite.iter());
+        to_roaring(self.rows, words.map(|(c, d)| c & !d))
+    }
+}
+
+/// The rows of a [ScanBitmap] that may satisfy its predicate, as selection
+/// vectors of row indices in order.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Selection {
+    /// The rows that definitely satisfy the predicate.
+    pub definite: Vec<u32>,
+    /// The rows that may satisfy the predicate, and must be re-checked
+    /// against their values.
+    pub maybe: Vec<u32>,
+}
+
+// The indices of the set bits of `words`, a bitmap of `rows` rows.
+fn set_rows<I: Iterator<Item = u64>>(rows: usize, words: I) -> impl Iterator<Item = u32> {
+    assert!(rows as u64 <= 1 << 32, "too many rows for u32 row indices");
+    words.enumerate().flat_map(|(i, mut w)| {
+        std::iter::from_fn(move || {
+            if w == 0 {
+                return None;
+            }
+            let bit = w.trailing_zeros();
+            w &= w - 1;
+            Some(i as u32 * 64 + bit)
+        })
+    })
+}
+
+#[cfg(feature = "roaring")]
+fn to_roaring<I: Iterator<Item = u64>>(rows: usize, words: I) -> roaring::RoaringBitmap {
+    assert!(rows as u64 <= 1 << 32, "too many rows for a Roaring bitmap");
+    let bytes: Vec<u8> = words.flat_map(|w| w.to_le_bytes()).collect();
+    roaring::RoaringBitmap::from_lsb0_bytes(0, &bytes)
+}
+
+// The candidate and definite ranges of `pred`, clipped to codes up to
+// `max`, if the candidates form one range and the definite codes at most
+// one: what the vectorized kernels test. The definite range is None when
+// there are no definite codes.
+fn kernel_ranges(pred: &CompiledPredicate, max: u16) -> Option<(Thresholds, Option<Thresholds>)> {
+    let clip = |t: Thresholds| {
+        if t.lo > t.hi || t.lo > max {
+            None
+        } else {
+            Some(Thresholds {
+                lo: t.lo,
+                hi: t.hi.min(max),
+            })
+        }
+    };
+    let candidates = clip(pred.candidates?)?;
+    let definite = match pred.definite {
+        Some(t) => Some(clip(t)?),
+        None => {
+            // Definite codes are candidates too, so none outside this range.
+            let range = candidates.lo as usize..=candidates.hi as usize;
+            if pred.table[range].contains(&CLASS_DEFINITE) {
+                return None;
+            }
+            None
+        }
+    };
+    Some((candidates, definite))
+}
+
+/// Evaluate `pred` over a column of [Mode::Byte] codes.
+///
+/// Panics if `pred` was compiled for a different mode.
+pub fn scan_bytes(pred: &CompiledPredicate, codes: &[u8]) -> ScanBitmap {
+    assert!(pred.mode == Mode::Byte);
+    let mut out = ScanBitmap::new(codes.len());
+    let mut done = 0;
+    if let Some((candidates, definite)) = kernel_ranges(pred, u8::MAX as u16) {
+        done = kernel_bytes(codes, candidates, definite, &mut out);
+    }
+    for (i, c) in codes.iter().enumerate().skip(done) {
+        out.set(i, pred.table[*c as usize]);
+    }
+    out
+}
+
+/// Evaluate `pred` over a column of [Mode::Packed] or [Mode::Word] codes,
+/// one per u16.
+///
+/// Panics if `pred` was compiled for [Mode::Byte]. The codes must fit
+/// `pred`'s mode.
+pub fn scan_words(pred: &CompiledPredicate, codes: &[u16]) -> ScanBitmap {
+    assert!(pred.mode != Mode::Byte);
+    let mut out = ScanBitmap::new(codes.len());
+    let mut done = 0;
+    if let Some((candidates, definite)) = kernel_ranges(pred, u16::MAX) {
+        done = kernel_words(codes, candidates, definite, &mut out);
+    }
+    for (i, c) in codes.iter().enumerate().skip(done) {
+        out.set(i, pred.table[*c as usize]);
+    }
+    out
+}
+
+// Fill `out` for the leading whole 64-row blocks of `codes` with a
+// vectorized kernel, if the CPU has one, returning the number of rows
+// done.
+fn kernel_bytes(
+    codes: &[u8],
+    candidates: Thresholds,
+    definite: Option<Thresholds>,
+    out: &mut ScanBitmap,
+) -> usize {
+    let words = codes.len() / 64;
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safety: the CPU supports AVX2.
+            unsafe {
+                avx2::scan_bytes(
+                    &codes[..words * 64],
+                    candidates,
+                    definite,
+                    &mut out.candidates[..words],
+                    &mut out.definite[..words],
+                )
+            };
+            return words * 64;
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // Safety: the CPU supports NEON.
+            unsafe {
+                neon::scan_bytes(
+                    &codes[..words * 64],
+                    candidates,
+                    definite,
+                    &mut out.candidates[..words],
+                    &mut out.definite[..words],
+                )
+            };
+            return words * 64;
+        }
+    }
+    #[cfg(feature = "portable-simd")]
+    {
+        portable::scan_bytes(
+            &codes[..words * 64],
+            candidates,
+            definite,
+            &mut out.candidates[..words],
+            &mut out.definite[..words],
+        );
+        words * 64
+    }
+    #[cfg(not(feature = "portable-simd"))]
+    {
+        let _ = (words, candidates, definite, out);
+        0
+    }
+}
+
+// As [kernel_bytes], for word codes.
+fn kernel_words(
+    codes: &[u16],
+    candidates: Thresholds,
+    definite: Option<Thresholds>,
+    out: &mut ScanBitmap,
+) -> usize {
+    let words = codes.len() / 64;
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safety: the CPU supports AVX2.
+            unsafe {
+                avx2::scan_words(
+                    &codes[..words * 64],
+                    candidates,
+                    definite,
+                    &mut out.candidates[..words],
+                    &mut out.definite[..words],
+                )
+            };
+            return words * 64;
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // Safety: the CPU supports NEON.
+            unsafe {
+                neon::scan_words(
+                    &codes[..words * 64],
+                    candidates,
+                    definite,
+                    &mut out.candidates[..words],
+                    &mut out.definite[..words],
+                )
+            };
+            return words * 64;
+        }
+    }
+    #[cfg(feature = "portable-simd")]
+    {
+        portable::scan_words(
+            &codes[..words * 64],
+            candidates,
+            definite,
+            &mut out.candidates[..words],
+            &mut out.definite[..words],
+        );
+        words * 64
+    }
+    #[cfg(not(feature = "portable-simd"))]
+    {
+        let _ = (words, candidates, definite, out);
+        0
+    }
+}
+
+// The AVX2 kernels. Each takes whole 64-row blocks of codes and writes a
+// word of each bitmap per block. A code is in `lo..=hi` iff `code - lo`,
+// wrapping, is at most `hi - lo`, which is one subtract, one unsigned min
+// and one compare per lane.
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use crate::predicate::Thresholds;
+    use std::arch::x86_64::*;
+
+    // Bit `i` set iff byte `i` of `v` is in the range starting at `lo`
+    // with width `span`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn bytes_in(v: __m256i, lo: __m256i, span: __m256i) -> u32 {
+        let d = _mm256_sub_epi8(v, lo);
+        _mm256_movemask_epi8(_mm256_cmpeq_epi8(_mm256_min_epu8(d, span), d)) as u32
+    }
+
+    // As [bytes_in], over the 32 words of `a` and then `b`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn words_in(a: __m256i, b: __m256i, lo: __m256i, span: __m256i) -> u32 {
+        let da = _mm256_sub_epi16(a, lo);
+        let db = _mm256_sub_epi16(b, lo);
+        let ma = _mm256_cmpeq_epi16(_mm256_min_epu16(da, span), da);
+        let mb = _mm256_cmpeq_epi16(_mm256_min_epu16(db, span), db);
+        // Packing interleaves the 128-bit lanes of `ma` and `mb`; put them
+        // back in row order.
+        let packed = _mm256_permute4x64_epi64(_mm256_packs_epi16(ma, mb), 0b11_01_10_00);
+        _mm256_movemask_epi8(packed) as u32
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn scan_bytes(
+        codes: &[u8],
+        candidates: Thresholds,
+        definite: Option<Thresholds>,
+        candidates_out: &mut [u64],
+        definite_out: &mut [u64],
+    ) {
+        let bounds = |t: Thresholds| {
+            (
+                _mm256_set1_epi8(t.lo as u8 as i8),
+                _mm256_set1_epi8((t.hi - t.lo) as u8 as i8),
+            )
+        };
+        let (clo, cspan) = bounds(candidates);
+        let definite = definite.map(bounds);
+        for (i, block) in codes.chunks_exact(64).enumerate() {
+            let a = _mm256_loadu_si256(block.as_ptr() as *const __m256i);
+            let b = _mm256_loadu_si256(block.as_ptr().add(32) as *const __m256i);
+            candidates_out[i] =
+                bytes_in(a, clo, cspan) as u64 | (bytes_in(b, clo, cspan) as u64) << 32;
+            if let Some((dlo, dspan)) = definite {
+                definite_out[i] =
+                    bytes_in(a, dlo, dspan) as u64 | (bytes_in(b, dlo, dspan) as u64) << 32;
+            }
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn scan_words(
+        codes: &[u16],
+        candidates: Thresholds,
+        definite: Option<Thresholds>,
+        candidates_out: &mut [u64],
+        definite_out: &mut [u64],
+    ) {
+        let bounds = |t: Thresholds| {
+            (
+                _mm256_set1_epi16(t.lo as i16),
+                _mm256_set1_epi16((t.hi - t.lo) as i16),
+            )
+        };
+        let (clo, cspan) = bounds(candidates);
+        let definite = definite.map(bounds);
+        for (i, block) in codes.chunks_exact(64).enumerate() {
+            let p = block.as_ptr() as *const __m256i;
+            let v = [
+                _mm256_loadu_si256(p),
+                _mm256_loadu_si256(p.add(1)),
+                _mm256_loadu_si256(p.add(2)),
+                _mm256_loadu_si256(p.add(3)),
+            ];
+            candidates_out[i] = words_in(v[0], v[1], clo, cspan) as u64
+                | (words_in(v[2], v[3], clo, cspan) as u64) << 32;
+            if let Some((dlo, dspan)) = definite {
+                definite_out[i] = words_in(v[0], v[1], dlo, dspan) as u64
+                    | (words_in(v[2], v[3], dlo, dspan) as u64) << 32;
+            }
+        }
+    }
+}
+
+// The NEON kernels, as the AVX2 ones. NEON has no byte movemask, so each
+// lane's compare result is masked to its bit of a byte and the bytes of
+// each half of the vector summed.
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use crate::predicate::Thresholds;
+    use std::arch::aarch64::*;
+
+    // Bit `i` set iff lane `i` of `m`, a compare result, is set.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn movemask(m: uint8x16_t) -> u16 {
+        const BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+        let bits = vandq_u8(m, vld1q_u8(BITS.as_ptr()));
+        vaddv_u8(vget_low_u8(bits)) as u16 | (vaddv_u8(vget_high_u8(bits)) as u16) << 8
+    }
+
+    // Bit `i` set iff byte `i` of the 16 at `p` is in the range starting at
+    // `lo` with width `span`.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn bytes_in(p: *const u8, lo: uint8x16_t, span: uint8x16_t) -> u64 {
+        movemask(vcleq_u8(vsubq_u8(vld1q_u8(p), lo), span)) as u64
+    }
+
+    // As [bytes_in], over the 16 words at `p`.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn words_in(p: *const u16, lo: uint16x8_t, span: uint16x8_t) -> u64 {
+        let a = vcleq_u16(vsubq_u16(vld1q_u16(p), lo), span);
+        let b = vcleq_u16(vsubq_u16(vld1q_u16(p.add(8)), lo), span);
+        movemask(vcombine_u8(vmovn_u16(a), vmovn_u16(b))) as u64
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn scan_bytes(
+        codes: &[u8],
+        candidates: Thresholds,
+        definite: Option<Thresholds>,
+        candidates_out: &mut [u64],
+        definite_out: &mut [u64],
+    ) {
+        let bounds = |t: Thresholds| (vdupq_n_u8(t.lo as u8), vdupq_n_u8((t.hi - t.lo) as u8));
+        let (clo, cspan) = bounds(candidates);
+        let definite = definite.map(bounds);
+        for (i, block) in codes.chunks_exact(64).enumerate() {
+            let p = block.as_ptr();
+            let mut c = 0;
+            let mut d = 0;
+            for j in 0..4 {
+                c |= bytes_in(p.add(16 * j), clo, cspan) << (16 * j);
+                if let Some((dlo, dspan)) = definite {
+                    d |= bytes_in(p.add(16 * j), dlo, dspan) << (16 * j);
+                }
+            }
+            candidates_out[i] = c;
+            definite_out[i] = d;
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn scan_words(
+        codes: &[u16],
+        candidates: Thresholds,
+        definite: Option<Thresholds>,
+        candidates_out: &mut [u64],
+        definite_out: &mut [u64],
+    ) {
+        let bounds = |t: Thresholds| (vdupq_n_u16(t.lo), vdupq_n_u16(t.hi - t.lo));
+        let (clo, cspan) = bounds(candidates);
+        let definite = definite.map(bounds);
+        for (i, block) in codes.chunks_exact(64).enumerate() {
+            let p = block.as_ptr();
+            let mut c = 0;
+            let mut d = 0;
+            for j in 0..4 {
+                c |= words_in(p.add(16 * j), clo, cspan) << (16 * j);
+                if let Some((dlo, dspan)) = definite {
+                    d |= words_in(p.add(16 * j), dlo, dspan) << (16 * j);
+                }
+            }
+            candidates_out[i] = c;
+            definite_out[i] = d;
+        }
+    }
+}
+
+// The `std::simd` kernels, as the AVX2 ones, with a 64-lane vector per
+// block.
+#[cfg(feature = "portable-simd")]
+mod portable {
+    use crate::predicate::Thresholds;
+    use std::simd::cmp::SimdPartialOrd;
+    use std::simd::Simd;
+
+    pub(super) fn scan_bytes(
+        codes: &[u8],
+        candidates: Thresholds,
+        definite: Option<Thresholds>,
+        candidates_out: &mut [u64],
+        definite_out: &mut [u64],
+    ) {
+        let bounds = |t: Thresholds| {
+            (
+                Simd::<u8, 64>::splat(t.lo as u8),
+                Simd::<u8, 64>::splat((t.hi - t.lo) as u8),
+            )
+        };
+        let (clo, cspan) = bounds(candidates);
+        let definite = definite.map(bounds);
+        for (i, block) in codes.chunks_exact(64).enumerate() {
+            let v = Simd::<u8, 64>::from_slice(block);
+            candidates_out[i] = (v - clo).simd_le(cspan).to_bitmask();
+            if let Some((dlo, dspan)) = definite {
+                definite_out[i] = (v - dlo).simd_le(dspan).to_bitmask();
+            }
+        }
+    }
+
+    pub(super) fn scan_words(
+        codes: &[u16],
+        candidates: Thresholds,
+        definite: Option<Thresholds>,
+        candidates_out: &mut [u64],
+        definite_out: &mut [u64],
+    ) {
+        let bounds = |t: Thresholds| {
+            (
+                Simd::<u16, 64>::splat(t.lo),
+                Simd::<u16, 64>::splat(t.hi - t.lo),
+            )
+        };
+        let (clo, cspan) = bounds(candidates);
+        let definite = definite.map(bounds);
+        for (i, block) in codes.chunks_exact(64).enumerate() {
+            let v = Simd::<u16, 64>::from_slice(block);
+            candidates_out[i] = (v - clo).simd_le(cspan).to_bitmask();
+            if let Some((dlo, dspan)) = definite {
+                definite_out[i] = (v - dlo).simd_le(dspan).to_bitmask();
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Serde support, with the `serde` feature.
+//
+// A dictionary serializes as its mode, its exact-code values and its
+// number of reserved codes, as in the binary format of [crate::format], and
+// deserializes after checking the values are strictly increasing and fit
+// the mode less its reserved codes. The reserved count defaults to zero, so
+// dictionaries serialized before it was recorded still read. The mode serializes as its [bits](Mode::bits), a code as
+// its u16 and a float wrapper as its float.
+
+use crate::{BuildOptions, Code, Dict, DictF32, DictF64, Mode, ValReq};
+use float_ord::FloatOrd;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+impl Serialize for Mode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bits().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Mode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = u8::deserialize(deserializer)?;
+        Mode::from_bits(bits).ok_or_else(|| D::Error::custom(format!("no {}-bit mode", bits)))
+    }
+}
+
+impl Serialize for Code {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Code {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u16::deserialize(deserializer).map(Code)
+    }
+}
+
+macro_rules! float_serde {
+    ($wrapper:ident, $float:ty) => {
+        impl Serialize for $wrapper {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                (self.0).0.serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $wrapper {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$float>::deserialize(deserializer).map(|f| $wrapper(FloatOrd(f)))
+            }
+        }
+    };
+}
+
+float_serde!(DictF32, f32);
+float_serde!(DictF64, f64);
+
+#[derive(Serialize)]
+struct DictRef<'a, T> {
+    mode: Mode,
+    codes: &'a [T],
+    reserved_codes: u16,
+    dense_codes: bool,
+}
+
+#[derive(Deserialize)]
+struct DictOwned<T> {
+    mode: Mode,
+    codes: Vec<T>,
+    #[serde(default)]
+    reserved_codes: u16,
+    #[serde(default)]
+    dense_codes: bool,
+}
+
+impl<T: ValReq + Serialize> Serialize for Dict<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DictRef {
+            mode: self.mode,
+            codes: &self.codes,
+            reserved_codes: self.reserved,
+            dense_codes: self.dense,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: ValReq + Deserialize<'de>> Deserialize<'de> for Dict<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let d = DictOwned::<T>::deserialize(deserializer)?;
+        let options = BuildOptions {
+            reserved_codes: d.reserved_codes,
+            dense_codes: d.dense_codes,
+            ..BuildOptions::default()
+        };
+        if !options.stored_layout_fits(d.mode, d.codes.len()) {
+            return Err(D::Error::custom(format!(
+                "{} exact codes and {} reserved codes is too many for {:?} mode",
+                d.codes.len(),
+                d.reserved_codes,
+                d.mode
+            )));
+        }
+        if d.codes.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(D::Error::custom(
+                "exact-code values are not strictly increasing",
+            ));
+        }
+        Ok(Dict::from_stored_codes(d.mode, d.codes, &options))
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! An in-memory code column at its mode's width.
+//!
+//! A `Vec<Code>` spends two bytes on every code, even the byte codes that
+//! exist to keep sketches small. A [Sketch] holds [Mode::Byte] codes as
+//! bytes and [Mode::Word] codes as u16s, in one contiguous vector either
+//! way: the layout vectorized scan kernels read and
+//! [Mode::pack_codes] writes from. [Mode::Packed] codes are held as u16s,
+//! and only packed into 12 bits when written out.
+
+use crate::predicate::CompiledPredicate;
+use crate::scan::{scan_bytes, scan_words, ScanBitmap};
+use crate::{Code, Dict, Mode, ValReq};
+use std::ops::Range;
+
+/// The codes of a [Sketch], at their width in memory.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SketchCodes {
+    /// The codes of a [Mode::Byte] sketch.
+    Byte(Vec<u8>),
+    /// The codes of a [Mode::Packed] or [Mode::Word] sketch.
+    Word(Vec<u16>),
+}
+
+/// A code column. See the [module documentation](self).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sketch {
+    mode: Mode,
+    codes: SketchCodes,
+}
+
+impl Sketch {
+    /// Make an empty sketch of codes of `mode`.
+    pub fn new(mode: Mode) -> Self {
+        Self::with_capacity(mode, 0)
+    }
+
+    /// Make an empty sketch of codes of `mode`, with room for `rows` codes.
+    pub fn with_capacity(mode: Mode, rows: usize) -> Self {
+        let codes = match mode {
+            Mode::Byte => SketchCodes::Byte(Vec::with_capacity(rows)),
+            Mode::Packed | Mode::Word => SketchCodes::Word(Vec::with_capacity(rows)),
+        };
+        Sketch { mode, codes }
+    }
+
+    /// Make a sketch of `codes`, of `mode`.
+    ///
+    /// Panics if a code is too wide for `mode`.
+    pub fn from_codes(mode: Mode, codes: &[Code]) -> Self {
+        let mut sketch = Self::with_capacity(mode, codes.len());
+        sketch.extend(codes.iter().copied());
+        sketch
+    }
+
+    /// The mode of the sketch's codes.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The codes, for kernels that work on them directly.
+    pub fn codes(&self) -> &SketchCodes {
+        &self.codes
+    }
+
+    /// The number of codes.
+    pub fn len(&self) -> usize {
+        match &self.codes {
+            SketchCodes::Byte(c) => c.len(),
+            SketchCodes::Word(c) => c.len(),
+        }
+    }
+
+    /// Whether there are no codes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The code of row `i`.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> Code {
+        match &self.codes {
+            SketchCodes::Byte(c) => Code(c[i] as u16),
+            SketchCodes::Word(c) => Code(c[i]),
+        }
+    }
+
+    /// Iterate over the codes, in order.
+    pub fn iter(&self) -> impl Iterator<Item = Code> + '_ {
+        (0..self.len()).map(move |i| self.get(i))
+    }
+
+    /// Append one code.
+    ///
+    /// Panics if `code` is too wide for the sketch's mode.
+    pub fn push(&mut self, code: Code) {
+        assert!(
+            code <= self.mode.max_inexact_code(),
+            "code too wide for the sketch's mode"
+        );
+        match &mut self.codes {
+            SketchCodes::Byte(c) => c.push(code.0 as u8),
+            SketchCodes::Word(c) => c.push(code.0),
+        }
+    }
+
+    /// Encode each of `values` with `dict` and append its code.
+    ///
+    /// Panics if `dict` was built in a different mode.
+    pub fn extend_from_values<T: ValReq>(&mut self, dict: &Dict<T>, values: &[T]) {
+        assert!(dict.mode == self.mode);
+        match &mut self.codes {
+            SketchCodes::Byte(c) => {
+                c.reserve(values.len());
+                dict.encode_runs(values, |code| c.push(code.0 as u8));
+            }
+            SketchCodes::Word(c) => {
+                c.reserve(values.len());
+                dict.encode_runs(values, |code| c.push(code.0));
+            }
+        }
+    }
+
+    /// Append the codes of `stored`, codes of the sketch's mode as
+    /// [Mode::pack_codes] stores them; see [Mode::unpack_codes].
+    pub fn extend_from_stored(&mut self, stored: &[u8]) {
+        let mode = self.mode;
+        match &mut self.codes {
+            SketchCodes::Byte(c) => c.extend_from_slice(stored),
+            SketchCodes::Word(c) => {
+                let n = stored.len() * 8 / mode.bits() as usize;
+                c.extend((0..n).map(|i| mode.stored_code(stored, i).0));
+            }
+        }
+    }
+
+    /// Write the class of each code under `pred` to the same position of
+    /// `out`.
+    ///
+    /// Panics if `pred` was compiled for a different mode or `out` is
+    /// shorter than the sketch.
+    pub fn classify_into(&self, pred: &CompiledPredicate, out: &mut [u8]) {
+        assert!(pred.mode == self.mode);
+        let out = &mut out[..self.len()];
+        match &self.codes {
+            SketchCodes::Byte(codes) => {
+                for (o, c) in out.iter_mut().zip(codes.iter()) {
+                    *o = pred.table[*c as usize];
+                }
+            }
+            SketchCodes::Word(codes) => {
+                for (o, c) in out.iter_mut().zip(codes.iter()) {
+                    *o = pred.table[*c as usize];
+                }
+            }
+        }
+    }
+
+    /// Evaluate `pred` over the codes; see [scan_bytes] and [scan_words].
+    ///
+    /// Panics if `pred` was compiled for a different mode.
+    pub fn scan(&self, pred: &CompiledPredicate) -> ScanBitmap {
+        assert!(pred.mode == self.mode);
+        match &self.codes {
+            SketchCodes::Byte(c) => scan_bytes(pred, c),
+            SketchCodes::Word(c) => scan_words(pred, c),
+        }
+    }
+
+    /// Append the codes to `out` as a sketch file stores them; see
+    /// [Mode::pack_codes].
+    pub fn write_stored(&self, out: &mut Vec<u8>) {
+        self.write_stored_rows(0..self.len(), out)
+    }
+
+    /// Append the codes of `rows` to `out` as a sketch file stores them.
+    ///
+    /// Panics if `rows` is out of bounds.
+    pub fn write_stored_rows(&self, rows: Range<usize>, out: &mut Vec<u8>) {
+        match &self.codes {
+            SketchCodes::Byte(c) => out.extend_from_slice(&c[rows]),
+            SketchCodes::Word(c) => {
+                let codes: Vec<Code> = c[rows].iter().map(|c| Code(*c)).collect();
+                self.mode.pack_codes(&codes, out);
+            }
+        }
+    }
+
+    /// Return the codes as a `Vec<Code>`.
+    pub fn to_codes(&self) -> Vec<Code> {
+        self.iter().collect()
+    }
+}
+
+impl Extend<Code> for Sketch {
+    fn extend<I: IntoIterator<Item = Code>>(&mut self, codes: I) {
+        for c in codes {
+            self.push(c);
+        }
+    }
+}
@@ -0,0 +1,569 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! A self-contained file holding everything needed to scan one column of one
+//! segment: its dictionary, zone maps and codes.
+//!
+//! A sketch is an artifact of its own, shipped, cached and versioned apart
+//! from the base data, so it should be one file rather than several that
+//! must be kept together. The format, conventionally stored with the
+//! extension `.obg`, is:
+//!
+//!   - an 88-byte header: the magic bytes `ORDK`, the format version (u16),
+//!     the required-feature flags (u16), the mode (u8: its
+//!     [bits](Mode::bits), 8, 12 or 16), a reserved zero byte and u16, the
+//!     rows per zone-map block (u32), the number of rows (u64), and then
+//!     the offset and length (u64 each) of the dictionary, zone map, code
+//!     and checksum sections, in that order;
+//!   - the dictionary section: a dictionary as written by
+//!     [Dict::to_bytes];
+//!   - the zone map section: each block's least and greatest codes (u16
+//!     each) and number of rows (u32);
+//!   - the code section: one code per row, a u8 each in [Mode::Byte], a
+//!     u16 each in [Mode::Word] and two to every three bytes in
+//!     [Mode::Packed], as [Mode::pack_codes] stores them, starting at a
+//!     multiple of 8 bytes from the start of the file. Each block is stored
+//!     on its own, so in [Mode::Packed] a block of an odd number of rows
+//!     ends in a code taking two bytes;
+//!   - the checksum section, which may be empty: the CRC-32C of the
+//!     dictionary section and of the zone map section, then of each
+//!     block's codes (u32 each).
+//!
+//! Checksums catch bitrot that would otherwise silently corrupt scans. They
+//! are verified on reading unless [ReadOptions::verify_checksums] is off.
+//!
+//! With the `zstd` feature, `SketchFile::to_bytes_compressed` writes files
+//! with the [FLAG_ZSTD] flag set, in which the dictionary section is a zstd
+//! frame of the dictionary, and the code section is an index of each
+//! block's frame (u64 offsets from the start of the section, one per block
+//! and one more for the end of the last) followed by a zstd frame per block.
+//! A scan decompresses only the blocks the zone map can't rule out.
+//! Checksums cover the stored, compressed bytes.
+//!
+//! All integers are little-endian. Bytes not covered by a section are
+//! ignored, and the version, flags and compatibility contract are those of
+//! the [format](crate::format) module.
+
+use crate::format::{BinaryValue, FormatError, KNOWN_FLAGS, READABLE_VERSIONS, WRITE_VERSION};
+use crate::sketch::Sketch;
+use crate::zonemap::{Zone, ZoneMap};
+use crate::{Code, Dict, Mode, ValReq};
+use std::borrow::Cow;
+use std::io;
+
+/// The magic bytes that begin every sketch file.
+pub const SKETCH_MAGIC: [u8; 4] = *b"ORDK";
+
+/// The size of a sketch file's fixed header.
+pub const SKETCH_HEADER_LEN: usize = 88;
+
+/// The size of one block's entry in the zone map section.
+pub const ZONE_LEN: usize = 8;
+
+/// The flag marking a sketch file whose dictionary and code blocks are
+/// zstd-compressed.
+pub const FLAG_ZSTD: u16 = 1;
+
+/// The flags this build can read in a sketch file.
+#[cfg(feature = "zstd")]
+pub const SKETCH_KNOWN_FLAGS: u16 = KNOWN_FLAGS | FLAG_ZSTD;
+
+/// The flags this build can read in a sketch file.
+#[cfg(not(feature = "zstd"))]
+pub const SKETCH_KNOWN_FLAGS: u16 = KNOWN_FLAGS;
+
+/// A column segment's dictionary, zone map and codes. See the [module
+/// documentation](self).
+pub struct SketchFile<T: ValReq> {
+    /// The dictionary the codes were encoded with.
+    pub dict: Dict<T>,
+    /// The number of rows in each zone-map block.
+    pub block_rows: usize,
+    /// The zone map of the codes.
+    pub zones: ZoneMap,
+    /// One code per row.
+    pub codes: Sketch,
+}
+
+/// The fixed header of a sketch file.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SketchHeader {
+    pub version: u16,
+    pub flags: u16,
+    pub mode: Mode,
+    /// The number of rows in each zone-map block.
+    pub block_rows: u32,
+    /// The number of rows, and so of codes.
+    pub rows: u64,
+    /// The byte range of the dictionary section.
+    pub dict: (u64, u64),
+    /// The byte range of the zone map section.
+    pub zones: (u64, u64),
+    /// The byte range of the code section.
+    pub codes: (u64, u64),
+    /// The byte range of the checksum section.
+    pub checksums: (u64, u64),
+}
+
+/// Options for reading a sketch file.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ReadOptions {
+    /// Whether to check the sections read against their checksums, if the
+    /// file has them. On by default.
+    pub verify_checksums: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            verify_checksums: true,
+        }
+    }
+}
+
+// The CRC-32C (Castagnoli) lookup table, for the reflected polynomial.
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                (c >> 1) ^ 0x82f6_3b78
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC-32C of `bytes`, as stored in a sketch file's checksum section.
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    let mut c = !0u32;
+    for b in bytes {
+        c = CRC32C_TABLE[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+// The byte range `(offset, len)` of `bytes`, which must hold it.
+pub(crate) fn section(bytes: &[u8], (offset, len): (u64, u64)) -> &[u8] {
+    &bytes[offset as usize..(offset + len) as usize]
+}
+
+// The number of bytes the code section of an uncompressed file takes:
+// each block is stored on its own, so that every block starts on a byte.
+fn codes_len(mode: Mode, rows: u64, block_rows: u32) -> u64 {
+    let full = rows / block_rows as u64;
+    let rest = (rows % block_rows as u64) as usize;
+    full * mode.stored_len(block_rows as usize) as u64 + mode.stored_len(rest) as u64
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(stored: &[u8]) -> Result<Vec<u8>, FormatError> {
+    zstd::decode_all(stored).map_err(|_| FormatError::BadCompression)
+}
+
+// Unreachable, since without the feature the flag is refused on reading.
+#[cfg(not(feature = "zstd"))]
+fn decompress(_: &[u8]) -> Result<Vec<u8>, FormatError> {
+    Err(FormatError::UnsupportedFlags(FLAG_ZSTD))
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    let mut b = [0; 8];
+    b.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(b)
+}
+
+impl SketchHeader {
+    /// Read and check the header at the start of `bytes`, including that its
+    /// sections lie within `bytes` and have the sizes the row count implies.
+    pub fn read(bytes: &[u8]) -> Result<SketchHeader, FormatError> {
+        let header = Self::read_prefix(bytes, bytes.len() as u64)?;
+        let index = &section(bytes, header.codes)[..header.index_len() as usize];
+        header.check_index(index)?;
+        Ok(header)
+    }
+
+    /// Read and check the header at the start of `bytes`, the first bytes of
+    /// a file `file_len` bytes long, as far as can be done without reading
+    /// anything beyond the fixed header. [SketchHeader::check_index] does
+    /// the rest.
+    pub fn read_prefix(bytes: &[u8], file_len: u64) -> Result<SketchHeader, FormatError> {
+        if bytes.len() < SKETCH_HEADER_LEN {
+            return Err(if bytes.len() >= 4 && bytes[..4] != SKETCH_MAGIC {
+                FormatError::BadMagic
+            } else {
+                FormatError::Truncated
+            });
+        }
+        if bytes[..4] != SKETCH_MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if !READABLE_VERSIONS.contains(&version) {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let flags = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if flags & !SKETCH_KNOWN_FLAGS != 0 {
+            return Err(FormatError::UnsupportedFlags(flags));
+        }
+        let mode = Mode::from_bits(bytes[8]).ok_or(FormatError::BadMode(bytes[8]))?;
+        let block_rows = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+        let rows = read_u64(bytes, 16);
+        let range = |at: usize| (read_u64(bytes, at), read_u64(bytes, at + 8));
+        let header = SketchHeader {
+            version,
+            flags,
+            mode,
+            block_rows,
+            rows,
+            dict: range(24),
+            zones: range(40),
+            codes: range(56),
+            checksums: range(72),
+        };
+        for (offset, len) in [header.dict, header.zones, header.codes, header.checksums] {
+            if offset.checked_add(len).is_none_or(|end| end > file_len) {
+                return Err(FormatError::Truncated);
+            }
+        }
+        let blocks = header.zones.1 / ZONE_LEN as u64;
+        if block_rows == 0
+            || blocks != rows.div_ceil(block_rows as u64)
+            || !header.zones.1.is_multiple_of(ZONE_LEN as u64)
+            || !header.codes.0.is_multiple_of(8)
+            || (header.checksums.1 != 0 && header.checksums.1 != (2 + blocks) * 4)
+        {
+            return Err(FormatError::BadCodes);
+        }
+        if header.is_compressed() {
+            if header.codes.1 < header.index_len() {
+                return Err(FormatError::Truncated);
+            }
+        } else if header.codes.1 != codes_len(mode, rows, block_rows) {
+            return Err(FormatError::BadCodes);
+        }
+        Ok(header)
+    }
+
+    /// The number of zone-map blocks.
+    pub fn blocks(&self) -> usize {
+        (self.zones.1 / ZONE_LEN as u64) as usize
+    }
+
+    /// The length of the block index at the start of the code section of a
+    /// compressed file, or 0 if the file isn't compressed.
+    pub fn index_len(&self) -> u64 {
+        if self.is_compressed() {
+            (self.blocks() as u64 + 1) * 8
+        } else {
+            0
+        }
+    }
+
+    /// Check the block index of a compressed file, the first
+    /// [SketchHeader::index_len] bytes of its code section: it must run in
+    /// order from its own end to the end of the section.
+    pub fn check_index(&self, index: &[u8]) -> Result<(), FormatError> {
+        let mut prev = self.index_len();
+        for i in 0..index.len() / 8 {
+            let at = read_u64(index, i * 8);
+            if at < prev || at > self.codes.1 || (i == self.blocks() && at != self.codes.1) {
+                return Err(FormatError::BadCodes);
+            }
+            prev = at;
+        }
+        Ok(())
+    }
+
+    /// The byte range in the file of block `i`'s stored codes, given the
+    /// file's block index (empty if it isn't compressed).
+    pub fn block_range(&self, index: &[u8], i: usize) -> (u64, u64) {
+        if self.is_compressed() {
+            let (at, end) = (read_u64(index, i * 8), read_u64(index, i * 8 + 8));
+            return (self.codes.0 + at, end - at);
+        }
+        let stride = self.mode.stored_len(self.block_rows as usize) as u64;
+        (
+            self.codes.0 + i as u64 * stride,
+            self.mode.stored_len(self.rows_in_block(i)) as u64,
+        )
+    }
+
+    /// Decode block `i`'s stored codes to the form [Mode::pack_codes]
+    /// writes, decompressing them if the file is compressed.
+    pub fn decode_block<'a>(
+        &self,
+        i: usize,
+        stored: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, FormatError> {
+        if !self.is_compressed() {
+            return Ok(Cow::Borrowed(stored));
+        }
+        let raw = decompress(stored)?;
+        if raw.len() != self.mode.stored_len(self.rows_in_block(i)) {
+            return Err(FormatError::BadCodes);
+        }
+        Ok(Cow::Owned(raw))
+    }
+
+    /// Decode the stored dictionary section to the dictionary's serialized
+    /// form, decompressing it if the file is compressed.
+    pub fn decode_dict<'a>(&self, stored: &'a [u8]) -> Result<Cow<'a, [u8]>, FormatError> {
+        if self.is_compressed() {
+            Ok(Cow::Owned(decompress(stored)?))
+        } else {
+            Ok(Cow::Borrowed(stored))
+        }
+    }
+
+    /// The checksum at position `i` of the checksum section `sums`: the
+    /// dictionary's at 0, the zone map's at 1 and block `b`'s at `b + 2`.
+    /// None if the file has no checksums.
+    pub fn checksum_at(&self, sums: &[u8], i: usize) -> Option<u32> {
+        let c = sums.get(i * 4..i * 4 + 4)?;
+        Some(u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+    }
+
+    /// Whether the file's dictionary and code blocks are compressed; see
+    /// [FLAG_ZSTD].
+    pub fn is_compressed(&self) -> bool {
+        self.flags & FLAG_ZSTD != 0
+    }
+
+    /// The serialized dictionary in `bytes`, the file this header was read
+    /// from, as written by [Dict::to_bytes]: borrowed from `bytes` unless
+    /// it has to be decompressed.
+    pub fn dict_bytes<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>, FormatError> {
+        self.decode_dict(section(bytes, self.dict))
+    }
+
+    /// The number of rows in block `i`.
+    pub fn rows_in_block(&self, i: usize) -> usize {
+        let start = i as u64 * self.block_rows as u64;
+        ((start + self.block_rows as u64).min(self.rows) - start) as usize
+    }
+
+    /// Block `i`'s codes in `bytes`, the file this header was read from, as
+    /// [Mode::pack_codes] writes them: borrowed from `bytes` unless they
+    /// have to be decompressed.
+    pub fn block_codes<'a>(&self, bytes: &'a [u8], i: usize) -> Result<Cow<'a, [u8]>, FormatError> {
+        self.decode_block(i, section(bytes, self.block_bytes(bytes, i)))
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&SKETCH_MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.flags.to_le_bytes());
+        out.push(self.mode.bits());
+        out.extend_from_slice(&[0, 0, 0]);
+        out.extend_from_slice(&self.block_rows.to_le_bytes());
+        out.extend_from_slice(&self.rows.to_le_bytes());
+        for (offset, len) in [self.dict, self.zones, self.codes, self.checksums] {
+            out.extend_from_slice(&offset.to_le_bytes());
+            out.extend_from_slice(&len.to_le_bytes());
+        }
+    }
+
+    fn checksum(&self, bytes: &[u8], i: usize) -> Option<u32> {
+        self.checksum_at(section(bytes, self.checksums), i)
+    }
+
+    /// Check the dictionary and zone map sections of `bytes`, the file this
+    /// header was read from, against their checksums. Passes if the file
+    /// has none.
+    pub fn verify_metadata(&self, bytes: &[u8]) -> Result<(), FormatError> {
+        for (i, range) in [self.dict, self.zones].iter().enumerate() {
+            if self
+                .checksum(bytes, i)
+                .is_some_and(|c| c != crc32c(section(bytes, *range)))
+            {
+                return Err(FormatError::BadChecksum);
+            }
+        }
+        Ok(())
+    }
+
+    /// The byte range of block `i`'s stored codes in `bytes`, the file this
+    /// header was read from.
+    pub fn block_bytes(&self, bytes: &[u8], i: usize) -> (u64, u64) {
+        let index = &section(bytes, self.codes)[..self.index_len() as usize];
+        self.block_range(index, i)
+    }
+
+    /// Check block `i`'s codes in `bytes`, the file this header was read
+    /// from, against their checksum. Passes if the file has none.
+    pub fn verify_block(&self, bytes: &[u8], i: usize) -> Result<(), FormatError> {
+        match self.checksum(bytes, 2 + i) {
+            Some(c) if c != crc32c(section(bytes, self.block_bytes(bytes, i))) => {
+                Err(FormatError::BadChecksum)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Decode a zone map section.
+pub(crate) fn read_zones(bytes: &[u8]) -> ZoneMap {
+    let zones = bytes
+        .chunks_exact(ZONE_LEN)
+        .map(|z| Zone {
+            min: Code(u16::from_le_bytes([z[0], z[1]])),
+            max: Code(u16::from_le_bytes([z[2], z[3]])),
+            rows: u32::from_le_bytes([z[4], z[5], z[6], z[7]]) as usize,
+        })
+        .collect();
+    ZoneMap { zones }
+}
+
+// Compresses a section's bytes for writing.
+type Compressor<'a> = dyn Fn(&[u8]) -> Vec<u8> + 'a;
+
+impl<T: ValReq + BinaryValue> SketchFile<T> {
+    /// Make a sketch of `codes`, encoded with `dict`, with a zone map of
+    /// blocks of `block_rows` rows.
+    ///
+    /// Panics if `block_rows` is zero or doesn't fit in a u32, or if `codes`
+    /// are of a different mode from `dict`.
+    pub fn new(dict: Dict<T>, codes: Sketch, block_rows: usize) -> Self {
+        assert!(block_rows != 0 && block_rows <= u32::MAX as usize);
+        assert!(codes.mode() == dict.mode);
+        let zones = ZoneMap::of_sketch(&codes, block_rows);
+        SketchFile {
+            dict,
+            block_rows,
+            zones,
+            codes,
+        }
+    }
+
+    /// Serialize the sketch in the current format version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.serialize(None)
+    }
+
+    /// Serialize the sketch in the current format version, compressing the
+    /// dictionary and each block of codes with zstd at `level`. Requires the
+    /// `zstd` feature.
+    #[cfg(feature = "zstd")]
+    pub fn to_bytes_compressed(&self, level: i32) -> Vec<u8> {
+        let compress = |raw: &[u8]| zstd::encode_all(raw, level).expect("in-memory zstd");
+        self.serialize(Some(&compress))
+    }
+
+    // The codes of each block, as stored.
+    fn raw_blocks(&self) -> Vec<Vec<u8>> {
+        (0..self.codes.len())
+            .step_by(self.block_rows)
+            .map(|start| {
+                let rows = start..(start + self.block_rows).min(self.codes.len());
+                let mut raw = Vec::with_capacity(self.dict.mode.stored_len(rows.len()));
+                self.codes.write_stored_rows(rows, &mut raw);
+                raw
+            })
+            .collect()
+    }
+
+    fn serialize(&self, compress: Option<&Compressor<'_>>) -> Vec<u8> {
+        let mut dict = self.dict.to_bytes();
+        let mut blocks = self.raw_blocks();
+        let mut codes = Vec::new();
+        if let Some(compress) = compress {
+            dict = compress(&dict);
+            let mut at = (blocks.len() as u64 + 1) * 8;
+            for b in blocks.iter_mut() {
+                *b = compress(b);
+                codes.extend_from_slice(&at.to_le_bytes());
+                at += b.len() as u64;
+            }
+            codes.extend_from_slice(&at.to_le_bytes());
+        }
+        for b in blocks.iter() {
+            codes.extend_from_slice(b);
+        }
+        let dict_at = SKETCH_HEADER_LEN as u64;
+        let zones_at = dict_at + dict.len() as u64;
+        let zones_len = (self.zones.zones.len() * ZONE_LEN) as u64;
+        let codes_at = (zones_at + zones_len).next_multiple_of(8);
+        let codes_len = codes.len() as u64;
+        let checksums_len = (2 + self.zones.zones.len() as u64) * 4;
+        let header = SketchHeader {
+            version: WRITE_VERSION,
+            flags: if compress.is_some() { FLAG_ZSTD } else { 0 },
+            mode: self.dict.mode,
+            block_rows: self.block_rows as u32,
+            rows: self.codes.len() as u64,
+            dict: (dict_at, dict.len() as u64),
+            zones: (zones_at, zones_len),
+            codes: (codes_at, codes_len),
+            checksums: (codes_at + codes_len, checksums_len),
+        };
+        let mut out = Vec::with_capacity((codes_at + codes_len + checksums_len) as usize);
+        header.write(&mut out);
+        out.extend_from_slice(&dict);
+        for z in self.zones.zones.iter() {
+            out.extend_from_slice(&z.min.0.to_le_bytes());
+            out.extend_from_slice(&z.max.0.to_le_bytes());
+            out.extend_from_slice(&(z.rows as u32).to_le_bytes());
+        }
+        out.resize(codes_at as usize, 0);
+        out.extend_from_slice(&codes);
+        let mut sums = vec![crc32c(&dict), crc32c(section(&out, header.zones))];
+        for i in 0..self.zones.zones.len() {
+            sums.push(crc32c(section(&out, header.block_bytes(&out, i))));
+        }
+        for c in sums {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+        out
+    }
+
+    /// Write the serialized sketch to `out`.
+    pub fn write_to<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(&self.to_bytes())
+    }
+
+    /// Deserialize a sketch written by [SketchFile::to_bytes] (or, with the
+    /// `zstd` feature, `SketchFile::to_bytes_compressed`), verifying its
+    /// checksums.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        Self::from_bytes_with_options(bytes, &ReadOptions::default())
+    }
+
+    /// Like [SketchFile::from_bytes], with checksums only verified if
+    /// `options` says so.
+    pub fn from_bytes_with_options(
+        bytes: &[u8],
+        options: &ReadOptions,
+    ) -> Result<Self, FormatError> {
+        let header = SketchHeader::read(bytes)?;
+        if options.verify_checksums {
+            header.verify_metadata(bytes)?;
+            for i in 0..header.zones.1 as usize / ZONE_LEN {
+                header.verify_block(bytes, i)?;
+            }
+        }
+        let dict = Dict::from_bytes(&header.dict_bytes(bytes)?)?;
+        if dict.mode != header.mode {
+            return Err(FormatError::BadMode(bytes[8]));
+        }
+        let zones = read_zones(section(bytes, header.zones));
+        let mut codes = Sketch::with_capacity(header.mode, header.rows as usize);
+        for i in 0..zones.zones.len() {
+            codes.extend_from_stored(&header.block_codes(bytes, i)?);
+        }
+        Ok(SketchFile {
+            dict,
+            block_rows: header.block_rows as usize,
+            zones,
+            codes,
+        })
+    }
+}
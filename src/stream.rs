@@ -0,0 +1,148 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Encoding in async pipelines, with [futures] streams and sinks.
+//!
+//! An async ETL pipeline moves values through streams and sinks, and the
+//! encoding step should fit between them without buffering a whole segment
+//! or outrunning whatever consumes its codes. [EncodeStream] turns a stream
+//! of values into a stream of chunks of codes; [EncodeSink] turns a sink of
+//! chunks of codes into a sink of values. Each holds at most one chunk of
+//! codes, and only pulls or accepts another value when it has room, so
+//! backpressure passes straight through.
+//!
+//! Requires the `futures` feature.
+
+use crate::{Code, Dict, ValReq};
+use futures::{Sink, Stream};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A stream of chunks of the codes of a stream of values. See the [module
+/// documentation](self).
+pub struct EncodeStream<S, T: ValReq> {
+    values: S,
+    dict: Arc<Dict<T>>,
+    chunk_rows: usize,
+    chunk: Vec<Code>,
+    done: bool,
+}
+
+impl<S: Stream<Item = T> + Unpin, T: ValReq> EncodeStream<S, T> {
+    /// Encode `values` with `dict`, in chunks of `chunk_rows` codes. The
+    /// last chunk may be short.
+    ///
+    /// Panics if `chunk_rows` is zero.
+    pub fn new(values: S, dict: Arc<Dict<T>>, chunk_rows: usize) -> Self {
+        assert!(chunk_rows != 0);
+        EncodeStream {
+            values,
+            dict,
+            chunk_rows,
+            chunk: Vec::with_capacity(chunk_rows),
+            done: false,
+        }
+    }
+}
+
+impl<S: Stream<Item = T> + Unpin, T: ValReq> Stream for EncodeStream<S, T> {
+    type Item = Vec<Code>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<Code>>> {
+        let this = self.get_mut();
+        while !this.done && this.chunk.len() < this.chunk_rows {
+            match Pin::new(&mut this.values).poll_next(cx) {
+                Poll::Ready(Some(v)) => this.chunk.push(this.dict.encode(&v)),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        if this.chunk.is_empty() {
+            return Poll::Ready(None);
+        }
+        let full = Vec::with_capacity(this.chunk_rows);
+        Poll::Ready(Some(std::mem::replace(&mut this.chunk, full)))
+    }
+}
+
+/// A sink of values, encoding them into chunks of codes for a sink of
+/// chunks. See the [module documentation](self).
+pub struct EncodeSink<K, T: ValReq> {
+    chunks: K,
+    dict: Arc<Dict<T>>,
+    chunk_rows: usize,
+    chunk: Vec<Code>,
+}
+
+impl<K: Sink<Vec<Code>> + Unpin, T: ValReq> EncodeSink<K, T> {
+    /// Encode values sent to the sink with `dict`, sending them on to
+    /// `chunks` in chunks of `chunk_rows` codes. Flushing or closing the
+    /// sink sends a short chunk of any codes left over.
+    ///
+    /// Panics if `chunk_rows` is zero.
+    pub fn new(chunks: K, dict: Arc<Dict<T>>, chunk_rows: usize) -> Self {
+        assert!(chunk_rows != 0);
+        EncodeSink {
+            chunks,
+            dict,
+            chunk_rows,
+            chunk: Vec::with_capacity(chunk_rows),
+        }
+    }
+
+    /// Return the sink of chunks, dropping any codes not yet sent to it.
+    pub fn into_inner(self) -> K {
+        self.chunks
+    }
+
+    // Send the buffered codes on, if there are any, once `chunks` has room.
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), K::Error>> {
+        if self.chunk.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        match Pin::new(&mut self.chunks).poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                let full = Vec::with_capacity(self.chunk_rows);
+                let chunk = std::mem::replace(&mut self.chunk, full);
+                Poll::Ready(Pin::new(&mut self.chunks).start_send(chunk))
+            }
+            other => other,
+        }
+    }
+}
+
+impl<K: Sink<Vec<Code>> + Unpin, T: ValReq> Sink<T> for EncodeSink<K, T> {
+    type Error = K::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), K::Error>> {
+        let this = self.get_mut();
+        if this.chunk.len() < this.chunk_rows {
+            return Poll::Ready(Ok(()));
+        }
+        this.poll_send(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, value: T) -> Result<(), K::Error> {
+        let this = self.get_mut();
+        assert!(this.chunk.len() < this.chunk_rows, "sink not ready");
+        this.chunk.push(this.dict.encode(&value));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), K::Error>> {
+        let this = self.get_mut();
+        match this.poll_send(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.chunks).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), K::Error>> {
+        let this = self.get_mut();
+        match this.poll_send(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.chunks).poll_close(cx),
+            other => other,
+        }
+    }
+}
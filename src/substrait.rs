@@ -0,0 +1,145 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Rendering code predicates as Substrait expressions.
+//!
+//! A plan that filters a code column can be shipped to remote workers as a
+//! Substrait plan. [to_substrait] renders a [CodePredicate] as a pair of
+//! Substrait `Expression` messages over the code column, in the protobuf
+//! JSON encoding: one selecting the candidate rows (to be read and
+//! re-checked) and one selecting the definite rows. The comparison and
+//! boolean functions are referenced through anchors which the enclosing plan
+//! must declare; [SubstraitFunctions::extensions] produces those
+//! declarations.
+
+use crate::predicate::CodePredicate;
+use crate::Code;
+use std::ops::RangeInclusive;
+
+const COMPARISON_URI: &str =
+    "https://github.com/substrait-io/substrait/blob/main/extensions/functions_comparison.yaml";
+const BOOLEAN_URI: &str =
+    "https://github.com/substrait-io/substrait/blob/main/extensions/functions_boolean.yaml";
+
+/// The function anchors the rendered expressions refer to.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SubstraitFunctions {
+    /// The anchor of the URI declaring the comparison functions.
+    pub comparison_uri: u32,
+    /// The anchor of the URI declaring the boolean functions.
+    pub boolean_uri: u32,
+    pub equal: u32,
+    pub gte: u32,
+    pub lte: u32,
+    pub and: u32,
+    pub or: u32,
+}
+
+impl Default for SubstraitFunctions {
+    fn default() -> Self {
+        SubstraitFunctions {
+            comparison_uri: 1,
+            boolean_uri: 2,
+            equal: 1,
+            gte: 2,
+            lte: 3,
+            and: 4,
+            or: 5,
+        }
+    }
+}
+
+impl SubstraitFunctions {
+    /// The plan's `extensionUris` and `extensions` entries declaring these
+    /// anchors, as JSON arrays.
+    pub fn extensions(&self) -> (String, String) {
+        let uris = format!(
+            r#"[{{"extensionUriAnchor":{},"uri":"{}"}},{{"extensionUriAnchor":{},"uri":"{}"}}]"#,
+            self.comparison_uri, COMPARISON_URI, self.boolean_uri, BOOLEAN_URI
+        );
+        let decl = |uri: u32, anchor: u32, name: &str| {
+            format!(
+                r#"{{"extensionFunction":{{"extensionUriReference":{},"functionAnchor":{},"name":"{}"}}}}"#,
+                uri, anchor, name
+            )
+        };
+        let extensions = [
+            decl(self.comparison_uri, self.equal, "equal:any_any"),
+            decl(self.comparison_uri, self.gte, "gte:any_any"),
+            decl(self.comparison_uri, self.lte, "lte:any_any"),
+            decl(self.boolean_uri, self.and, "and:bool"),
+            decl(self.boolean_uri, self.or, "or:bool"),
+        ];
+        (uris, format!("[{}]", extensions.join(",")))
+    }
+}
+
+/// A code predicate rendered as Substrait expressions in JSON.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SubstraitFilter {
+    /// Selects rows whose codes may satisfy the predicate.
+    pub candidates: String,
+    /// Selects rows whose codes definitely satisfy the predicate.
+    pub definite: String,
+}
+
+fn call(function: u32, args: &[String]) -> String {
+    let args: Vec<String> = args
+        .iter()
+        .map(|a| format!(r#"{{"value":{}}}"#, a))
+        .collect();
+    format!(
+        r#"{{"scalarFunction":{{"functionReference":{},"arguments":[{}],"outputType":{{"bool":{{"nullability":"NULLABILITY_REQUIRED"}}}}}}}}"#,
+        function,
+        args.join(",")
+    )
+}
+
+fn field(index: i32) -> String {
+    format!(
+        r#"{{"selection":{{"directReference":{{"structField":{{"field":{}}}}},"rootReference":{{}}}}}}"#,
+        index
+    )
+}
+
+fn literal(code: Code) -> String {
+    format!(r#"{{"literal":{{"i32":{}}}}}"#, code.0)
+}
+
+fn render(ranges: &[RangeInclusive<Code>], index: i32, f: &SubstraitFunctions) -> String {
+    let terms: Vec<String> = ranges
+        .iter()
+        .map(|r| {
+            if r.start() == r.end() {
+                call(f.equal, &[field(index), literal(*r.start())])
+            } else {
+                call(
+                    f.and,
+                    &[
+                        call(f.gte, &[field(index), literal(*r.start())]),
+                        call(f.lte, &[field(index), literal(*r.end())]),
+                    ],
+                )
+            }
+        })
+        .collect();
+    match terms.len() {
+        0 => r#"{"literal":{"boolean":false}}"#.to_string(),
+        1 => terms[0].clone(),
+        _ => call(f.or, &terms),
+    }
+}
+
+/// Render `pred` over the code column at field `index` of the input,
+/// referring to functions by the anchors in `functions`. Codes are compared
+/// as `i32` literals.
+pub fn to_substrait(
+    pred: &CodePredicate,
+    index: i32,
+    functions: &SubstraitFunctions,
+) -> SubstraitFilter {
+    SubstraitFilter {
+        candidates: render(&pred.candidates(), index, functions),
+        definite: render(&pred.definite, index, functions),
+    }
+}
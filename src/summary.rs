@@ -0,0 +1,119 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Compressed summaries of construction samples.
+//!
+//! Refitting a dictionary, building one in a different mode, or re-binning
+//! codes for some custom purpose all want the sample the dictionary was
+//! built from, which is usually too big to keep. A [SampleSummary] keeps a
+//! bounded number of the sample's distinct values: every value frequent
+//! enough to matter, plus values at regular intervals of sample mass, each
+//! with the count of sample values equal to it and of those between it and
+//! the previous kept value. [Dict::from_summary](crate::Dict::from_summary)
+//! builds a dictionary from one.
+//!
+//! Summaries also let a build be distributed: each shard summarizes its own
+//! part of the sample, ships the summary (see
+//! [SampleSummary::to_bytes](crate::summary::SampleSummary::to_bytes)) rather
+//! than the sample, and the summaries are combined with
+//! [SampleSummary::merge] into one to build from.
+
+use crate::ValReq;
+
+/// One retained value of a [SampleSummary].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SummaryEntry<T> {
+    /// A value occurring in the sample.
+    pub value: T,
+    /// The number of sample values equal to `value`.
+    pub count: u64,
+    /// The number of sample values strictly between the previous entry's
+    /// value (or the start of the sample) and `value`.
+    pub below: u64,
+}
+
+/// A bounded-size summary of a sample's distribution. See the
+/// [module documentation](self).
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SampleSummary<T> {
+    /// The retained values, in increasing order.
+    pub entries: Vec<SummaryEntry<T>>,
+    /// The number of sample values after the last entry's value.
+    pub above: u64,
+}
+
+impl<T: ValReq> SampleSummary<T> {
+    /// Summarize `sample` in at most about `2 * capacity` entries.
+    pub fn new(mut sample: Vec<T>, capacity: usize) -> Self {
+        sample.sort_unstable();
+        let mut runs: Vec<(T, u64)> = Vec::new();
+        for v in sample {
+            match runs.last_mut() {
+                Some((last, n)) if *last == v => *n += 1,
+                _ => runs.push((v, 1)),
+            }
+        }
+        Self::of_runs(runs.iter().map(|(v, n)| (v, *n, 0)), 0, capacity)
+    }
+
+    /// Merge several summaries into one of about `capacity` entries, as if
+    /// summarizing the concatenation of their samples. Merging is
+    /// associative up to the loss of the discarded values; each input's
+    /// `above` mass, which has no retained value to place it by, is kept
+    /// above every entry.
+    pub fn merge(summaries: &[&SampleSummary<T>], capacity: usize) -> Self {
+        let mut entries: Vec<&SummaryEntry<T>> =
+            summaries.iter().flat_map(|s| s.entries.iter()).collect();
+        entries.sort_by(|a, b| a.value.cmp(&b.value));
+        let mut runs: Vec<(&T, u64, u64)> = Vec::with_capacity(entries.len());
+        for e in entries {
+            match runs.last_mut() {
+                Some((v, count, below)) if **v == e.value => {
+                    *count += e.count;
+                    *below += e.below;
+                }
+                _ => runs.push((&e.value, e.count, e.below)),
+            }
+        }
+        let above = summaries.iter().map(|s| s.above).sum();
+        Self::of_runs(runs.into_iter(), above, capacity)
+    }
+
+    // Summarize a sorted sample given as runs of equal values, each with the
+    // mass of unrepresented values just below it, and `above` such mass
+    // after the last run.
+    pub(crate) fn of_runs<'a, I>(runs: I, above: u64, capacity: usize) -> Self
+    where
+        I: Iterator<Item = (&'a T, u64, u64)> + Clone,
+        T: 'a,
+    {
+        let total: u64 = above + runs.clone().map(|(_, n, b)| n + b).sum::<u64>();
+        // A value at least this frequent is always kept, and a kept value is
+        // emitted at least every this much mass.
+        let step = (total / capacity.max(1) as u64).max(1);
+        let mut entries = Vec::new();
+        let mut pending = 0;
+        for (value, count, below) in runs {
+            pending += below;
+            if count >= step || pending + count >= step {
+                entries.push(SummaryEntry {
+                    value: value.clone(),
+                    count,
+                    below: pending,
+                });
+                pending = 0;
+            } else {
+                pending += count;
+            }
+        }
+        SampleSummary {
+            entries,
+            above: pending + above,
+        }
+    }
+
+    /// The number of values in the summarized sample.
+    pub fn total(&self) -> u64 {
+        self.above + self.entries.iter().map(|e| e.count + e.below).sum::<u64>()
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use crate::{Code, Dict, ValReq};
+use arc_swap::{ArcSwap, Guard};
+use std::sync::{Arc, Mutex};
+
+/// A shared slot holding the current [Dict] for some column, which a
+/// background rebuild can replace while readers continue to encode and scan
+/// without taking any locks.
+///
+/// Readers call [SwappableDict::load] (or [SwappableDict::encode]) and get a
+/// snapshot of whichever dictionary was current at that moment; a snapshot
+/// stays valid, and keeps its dictionary alive, for as long as the reader holds
+/// it, even if a new dictionary is published in the meantime. Writers are
+/// serialized with respect to one another, so that the old/new pair passed to
+/// a [SwappableDict::publish_with] hook is exactly the pair being swapped.
+///
+/// Requires the `swap` feature.
+pub struct SwappableDict<T: ValReq> {
+    current: ArcSwap<Dict<T>>,
+    writer: Mutex<()>,
+}
+
+impl<T: ValReq> SwappableDict<T> {
+    /// Make a new slot holding `dict`.
+    pub fn new(dict: Dict<T>) -> Self {
+        Self::from_arc(Arc::new(dict))
+    }
+
+    /// Make a new slot holding an already-shared `dict`.
+    pub fn from_arc(dict: Arc<Dict<T>>) -> Self {
+        SwappableDict {
+            current: ArcSwap::new(dict),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Return a cheap, lock-free snapshot of the current dictionary. Codes
+    /// produced through one snapshot are all relative to the same dictionary;
+    /// a caller encoding several values that must agree with one another (for
+    /// example both ends of a range predicate) should encode them through a
+    /// single snapshot rather than through repeated calls to
+    /// [SwappableDict::encode].
+    pub fn load(&self) -> Guard<Arc<Dict<T>>> {
+        self.current.load()
+    }
+
+    /// Return an owned handle to the current dictionary, suitable for holding
+    /// for a long time (for example for the duration of a long scan).
+    pub fn load_full(&self) -> Arc<Dict<T>> {
+        self.current.load_full()
+    }
+
+    /// Encode `query` with whichever dictionary is current.
+    pub fn encode(&self, query: &T) -> Code {
+        self.current.load().encode(query)
+    }
+
+    /// Publish `dict` as the new current dictionary, returning the one it
+    /// replaced. Readers that already hold a snapshot of the old dictionary
+    /// are unaffected.
+    pub fn publish(&self, dict: Dict<T>) -> Arc<Dict<T>> {
+        self.publish_with(dict, |_, _| ()).0
+    }
+
+    /// Publish `dict` as the new current dictionary, first calling `hook` with
+    /// the dictionary being replaced and the one replacing it. This is the
+    /// place to derive anything that must be paired with the swap, such as a
+    /// table remapping codes of the old dictionary to codes of the new one,
+    /// before any reader can observe the new dictionary. Returns the replaced
+    /// dictionary along with the hook's result.
+    pub fn publish_with<R, F>(&self, dict: Dict<T>, hook: F) -> (Arc<Dict<T>>, R)
+    where
+        F: FnOnce(&Dict<T>, &Dict<T>) -> R,
+    {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let new = Arc::new(dict);
+        let old = self.current.load_full();
+        let r = hook(&old, &new);
+        self.current.store(new);
+        (old, r)
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Per-file column metrics for table formats.
+//!
+//! Iceberg and Delta Lake keep, for each data file, the bounds and null
+//! counts of its columns, and prune whole files with them. A writer that
+//! sketches a column can derive those metrics from the file's codes and the
+//! dictionary instead of tracking extremes itself. The bounds are the values
+//! bracketing the file's least and greatest codes, so they contain every
+//! value in the file but may be wider than its actual extremes; a file whose
+//! codes include one of the dictionary's unbounded inexact codes gets no
+//! bound on that side.
+
+use crate::column::CodeColumn;
+use crate::parquet::PlainEncode;
+use crate::zonemap::{lower_bound, upper_bound};
+use crate::{Dict, DictF32, DictF64, ValReq};
+
+fn bounds<'a, T: ValReq>(dict: &'a Dict<T>, column: &CodeColumn) -> (Option<&'a T>, Option<&'a T>) {
+    match column.code_range() {
+        Some((min, max)) => (lower_bound(dict, min), upper_bound(dict, max)),
+        None => (None, None),
+    }
+}
+
+/// The metrics Iceberg records for one column of a data file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IcebergMetrics {
+    pub value_count: i64,
+    pub null_value_count: i64,
+    /// The lower bound in Iceberg's single-value binary serialization.
+    pub lower_bound: Option<Vec<u8>>,
+    /// The upper bound in Iceberg's single-value binary serialization.
+    pub upper_bound: Option<Vec<u8>>,
+}
+
+/// Compute the Iceberg metrics of `column`, encoded with `dict`. Iceberg's
+/// single-value serialization of ints, longs, floats, doubles, strings and
+/// binary matches their Parquet PLAIN encoding.
+pub fn iceberg_metrics<T: ValReq + PlainEncode>(
+    dict: &Dict<T>,
+    column: &CodeColumn,
+) -> IcebergMetrics {
+    let (lower, upper) = bounds(dict, column);
+    IcebergMetrics {
+        value_count: column.len() as i64,
+        null_value_count: column.null_count() as i64,
+        lower_bound: lower.map(|v| v.plain_bytes()),
+        upper_bound: upper.map(|v| v.plain_bytes()),
+    }
+}
+
+/// Types whose values can appear in Delta Lake file statistics.
+pub trait DeltaValue {
+    /// The value as a JSON literal, or None if JSON can't represent it.
+    fn json(&self) -> Option<String>;
+}
+
+impl DeltaValue for i32 {
+    fn json(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl DeltaValue for i64 {
+    fn json(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl DeltaValue for DictF32 {
+    fn json(&self) -> Option<String> {
+        let f = self.0 .0;
+        if f.is_finite() {
+            Some(format!("{:?}", f))
+        } else {
+            None
+        }
+    }
+}
+
+impl DeltaValue for DictF64 {
+    fn json(&self) -> Option<String> {
+        let f = self.0 .0;
+        if f.is_finite() {
+            Some(format!("{:?}", f))
+        } else {
+            None
+        }
+    }
+}
+
+impl DeltaValue for String {
+    fn json(&self) -> Option<String> {
+        let mut out = String::with_capacity(self.len() + 2);
+        out.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        Some(out)
+    }
+}
+
+/// Render the Delta Lake `stats` JSON object of a file holding the single
+/// column `name`, encoded with `dict` as `column`. `name` is inserted
+/// verbatim and must not need escaping. Bounds JSON can't represent are
+/// omitted.
+pub fn delta_stats<T: ValReq + DeltaValue>(
+    dict: &Dict<T>,
+    name: &str,
+    column: &CodeColumn,
+) -> String {
+    let (lower, upper) = bounds(dict, column);
+    let bound = |v: Option<&T>| match v.and_then(|v| v.json()) {
+        Some(json) => format!(r#"{{"{}":{}}}"#, name, json),
+        None => "{}".to_string(),
+    };
+    format!(
+        r#"{{"numRecords":{},"minValues":{},"maxValues":{},"nullCount":{{"{}":{}}}}}"#,
+        column.len(),
+        bound(lower),
+        bound(upper),
+        name,
+        column.null_count()
+    )
+}
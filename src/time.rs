@@ -0,0 +1,117 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Key extractors for sketching time columns at a chosen resolution.
+//!
+//! A dictionary over raw high-resolution timestamps wastes its codes telling
+//! apart instants no query distinguishes. Truncating timestamps to a bucket
+//! (a minute, an hour, a day) first gives codes that line up with the ranges
+//! queries actually ask about. Each [TimeBucket] provides a key function for
+//! use with [Dict::new_with_key](crate::Dict::new_with_key), so a time column
+//! can be sketched in one line, e.g.
+//! `Dict::new_with_key(Mode::Word, &times, TimeBucket::Hour.key())`.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A timestamp that can be reduced to whole seconds since the Unix epoch.
+/// Times before the epoch are negative.
+pub trait Timestamp {
+    /// The timestamp in whole seconds since 1970-01-01T00:00:00 UTC,
+    /// rounded towards negative infinity.
+    fn epoch_seconds(&self) -> i64;
+}
+
+impl Timestamp for SystemTime {
+    fn epoch_seconds(&self) -> i64 {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            // Round towards negative infinity, like the integer types below.
+            Err(e) => {
+                let d = e.duration();
+                -(d.as_secs() as i64) - (d.subsec_nanos() != 0) as i64
+            }
+        }
+    }
+}
+
+/// A [Duration] is taken to be the time elapsed since the Unix epoch.
+impl Timestamp for Duration {
+    fn epoch_seconds(&self) -> i64 {
+        self.as_secs() as i64
+    }
+}
+
+/// Seconds since the Unix epoch.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct EpochSeconds(pub i64);
+
+/// Milliseconds since the Unix epoch.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct EpochMillis(pub i64);
+
+/// Microseconds since the Unix epoch.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct EpochMicros(pub i64);
+
+/// Nanoseconds since the Unix epoch.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct EpochNanos(pub i64);
+
+impl Timestamp for EpochSeconds {
+    fn epoch_seconds(&self) -> i64 {
+        self.0
+    }
+}
+
+impl Timestamp for EpochMillis {
+    fn epoch_seconds(&self) -> i64 {
+        self.0.div_euclid(1_000)
+    }
+}
+
+impl Timestamp for EpochMicros {
+    fn epoch_seconds(&self) -> i64 {
+        self.0.div_euclid(1_000_000)
+    }
+}
+
+impl Timestamp for EpochNanos {
+    fn epoch_seconds(&self) -> i64 {
+        self.0.div_euclid(1_000_000_000)
+    }
+}
+
+/// A resolution to truncate timestamps to. Buckets are aligned to the Unix
+/// epoch, so days are UTC days.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum TimeBucket {
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+impl TimeBucket {
+    /// The length of the bucket in seconds.
+    pub fn seconds(&self) -> i64 {
+        match self {
+            TimeBucket::Second => 1,
+            TimeBucket::Minute => 60,
+            TimeBucket::Hour => 3600,
+            TimeBucket::Day => 86400,
+        }
+    }
+
+    /// Truncate `t` to the start of its bucket, in seconds since the epoch.
+    pub fn truncate<T: Timestamp>(&self, t: &T) -> i64 {
+        let s = t.epoch_seconds();
+        s - s.rem_euclid(self.seconds())
+    }
+
+    /// A key function truncating timestamps to this bucket, for building
+    /// dictionaries with [Dict::new_with_key](crate::Dict::new_with_key) and
+    /// encoding values against them.
+    pub fn key<T: Timestamp>(self) -> impl Fn(&T) -> i64 {
+        move |t| self.truncate(t)
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Finding the blocks that hold a column's largest or smallest values.
+//!
+//! A top-N query (`ORDER BY x DESC LIMIT n`) needs the `n` largest values of
+//! a column, which lie in only a few of its blocks. Since codes preserve
+//! order, and the values of distinct codes don't overlap, every value with
+//! a code below `t` is less than every value with code `t` or above. So if
+//! at least `n` rows have codes of `t` or above, the `n` largest values are
+//! among those rows, and only the blocks holding such codes need reading.
+//! The values read are then sorted and cut to `n` as usual.
+//!
+//! [top_n_candidate_blocks] finds `t` by counting the codes themselves.
+//! [zone_top_n_candidates] only reads the column's zone map: it needs no
+//! codes, but, knowing only each block's least and greatest code, finds a
+//! more conservative `t` and so may keep more blocks.
+
+use crate::zonemap::ZoneMap;
+use crate::Code;
+
+/// Which end of the order a top-N query wants.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Extreme {
+    /// The largest values, as for `ORDER BY x DESC`.
+    Largest,
+    /// The smallest values, as for `ORDER BY x ASC`.
+    Smallest,
+}
+
+impl Extreme {
+    // Whether `code` is at `bound` or beyond it, towards this end.
+    fn reaches(self, code: Code, bound: Code) -> bool {
+        match self {
+            Extreme::Largest => code >= bound,
+            Extreme::Smallest => code <= bound,
+        }
+    }
+}
+
+/// Return the code nearest `end` of the order such that at least `n` of
+/// `codes` are at it or beyond it, towards `end`, or None if `n` is zero or
+/// `codes` is empty. If there are fewer than `n` codes, that is the code
+/// furthest from `end`, so every row is kept.
+pub fn top_n_threshold(codes: &[Code], n: usize, end: Extreme) -> Option<Code> {
+    if n == 0 || codes.is_empty() {
+        return None;
+    }
+    let mut counts = vec![0usize; u16::MAX as usize + 1];
+    for c in codes {
+        counts[c.0 as usize] += 1;
+    }
+    let mut seen = 0;
+    let mut last = None;
+    let mut visit = |c: usize| {
+        if counts[c] != 0 {
+            seen += counts[c];
+            last = Some(Code(c as u16));
+        }
+        seen >= n
+    };
+    match end {
+        Extreme::Largest => (0..counts.len()).rev().any(&mut visit),
+        Extreme::Smallest => (0..counts.len()).any(&mut visit),
+    };
+    last
+}
+
+/// Return the indices of the blocks of `block_rows` rows of `codes` that
+/// may hold one of the column's `n` largest or smallest values, as `end`
+/// says: the blocks holding a code at or beyond [top_n_threshold]. Every
+/// one of the `n` rows is in a returned block.
+///
+/// Panics if `block_rows` is zero.
+pub fn top_n_candidate_blocks(
+    codes: &[Code],
+    block_rows: usize,
+    n: usize,
+    end: Extreme,
+) -> Vec<usize> {
+    assert!(block_rows != 0);
+    let t = match top_n_threshold(codes, n, end) {
+        Some(t) => t,
+        None => return Vec::new(),
+    };
+    codes
+        .chunks(block_rows)
+        .enumerate()
+        .filter(|(_, block)| block.iter().any(|c| end.reaches(*c, t)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Return the indices of the blocks of `zones` that may hold one of the
+/// column's `n` largest or smallest values, as `end` says, from the zone
+/// map alone. All of a block's rows reach its code furthest from `end`, so
+/// taking blocks in order of that code until they hold `n` rows gives a
+/// threshold the `n` rows reach; the blocks returned are those with a code
+/// reaching it.
+pub fn zone_top_n_candidates(zones: &ZoneMap, n: usize, end: Extreme) -> Vec<usize> {
+    if n == 0 {
+        return Vec::new();
+    }
+    // Each block's furthest code from `end`: all its rows reach it.
+    let floor = |i: usize| match end {
+        Extreme::Largest => zones.zones[i].min,
+        Extreme::Smallest => zones.zones[i].max,
+    };
+    let mut order: Vec<usize> = (0..zones.zones.len()).collect();
+    order.sort_by_key(|i| floor(*i));
+    if end == Extreme::Largest {
+        order.reverse();
+    }
+    let mut seen = 0;
+    let mut t = None;
+    for i in order {
+        seen += zones.zones[i].rows;
+        t = Some(floor(i));
+        if seen >= n {
+            break;
+        }
+    }
+    let t = match t {
+        Some(t) => t,
+        None => return Vec::new(),
+    };
+    zones
+        .zones
+        .iter()
+        .enumerate()
+        .filter(|(_, z)| match end {
+            Extreme::Largest => end.reaches(z.max, t),
+            Extreme::Smallest => end.reaches(z.min, t),
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
@@ -0,0 +1,149 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Checking stored codes against the values they were meant to encode.
+//!
+//! After a crash, a botched migration or a bug in a writer, a code column may
+//! no longer agree with its base data, and pruning with it may silently drop
+//! rows. A [Verifier] is fed the column's `(value, code)` pairs as a stream,
+//! without holding either column in memory, and reports every pair whose
+//! code doesn't stand for its value.
+//!
+//! Codes written under an older dictionary whose exact values are all exact
+//! in the current one remain usable after remapping (see
+//! [Compatibility::Refinable](crate::format::Compatibility::Refinable)). A
+//! verifier made with [Verifier::remapped] accepts such a code as long as
+//! the range of current codes it remaps onto contains the value's code.
+
+use crate::family::refine_code;
+use crate::{Code, Dict, ValReq};
+use std::ops::RangeInclusive;
+
+/// A pair whose code doesn't stand for its value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Violation<T> {
+    /// The pair's position in the stream, from 0.
+    pub index: u64,
+    /// The value.
+    pub value: T,
+    /// The stored code.
+    pub code: Code,
+    /// The code the value encodes to in the dictionary being verified
+    /// against.
+    pub expected: Code,
+}
+
+/// The outcome of a [Verifier].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VerifyReport<T> {
+    /// The number of pairs checked.
+    pub checked: u64,
+    /// The number of violations found.
+    pub violations: u64,
+    /// The first violations found, up to the verifier's limit.
+    pub samples: Vec<Violation<T>>,
+}
+
+impl<T> VerifyReport<T> {
+    /// Whether every pair checked was consistent.
+    pub fn is_consistent(&self) -> bool {
+        self.violations == 0
+    }
+}
+
+/// A streaming consistency check of codes against values. See the [module
+/// documentation](self).
+pub struct Verifier<'a, T: ValReq> {
+    dict: &'a Dict<T>,
+    writer: Option<&'a Dict<T>>,
+    max_samples: usize,
+    report: VerifyReport<T>,
+}
+
+impl<'a, T: ValReq> Verifier<'a, T> {
+    /// Verify codes written by `dict`, keeping up to `max_samples` of the
+    /// violations found.
+    pub fn new(dict: &'a Dict<T>, max_samples: usize) -> Self {
+        Verifier {
+            dict,
+            writer: None,
+            max_samples,
+            report: VerifyReport {
+                checked: 0,
+                violations: 0,
+                samples: Vec::new(),
+            },
+        }
+    }
+
+    /// Verify codes written by `writer` as they remap onto `dict`, keeping up
+    /// to `max_samples` of the violations found.
+    ///
+    /// Panics if some exact value of `writer` isn't exact in `dict`.
+    pub fn remapped(writer: &'a Dict<T>, dict: &'a Dict<T>, max_samples: usize) -> Self {
+        assert!(
+            writer
+                .codes
+                .iter()
+                .all(|v| dict.codes.binary_search(v).is_ok()),
+            "writer's codes don't remap onto the dictionary"
+        );
+        Verifier {
+            writer: Some(writer),
+            ..Verifier::new(dict, max_samples)
+        }
+    }
+
+    // The codes of the verified dictionary `code` may stand for.
+    fn accepts(&self, code: Code) -> Option<RangeInclusive<Code>> {
+        let writer = self.writer.unwrap_or(self.dict);
+        if code.0 == 0 || code.0 > 2 * writer.codes.len() as u16 + 1 {
+            return None;
+        }
+        match self.writer {
+            None => Some(code..=code),
+            Some(w) => Some(refine_code(w, self.dict, code)),
+        }
+    }
+
+    /// Check one pair, returning whether it is consistent.
+    pub fn check(&mut self, value: &T, code: Code) -> bool {
+        let index = self.report.checked;
+        self.report.checked += 1;
+        let expected = self.dict.encode(value);
+        if self.accepts(code).is_some_and(|r| r.contains(&expected)) {
+            return true;
+        }
+        self.report.violations += 1;
+        if self.report.samples.len() < self.max_samples {
+            self.report.samples.push(Violation {
+                index,
+                value: value.clone(),
+                code,
+                expected,
+            });
+        }
+        false
+    }
+
+    /// Check every pair of `pairs`.
+    pub fn check_all<'b, I>(&mut self, pairs: I)
+    where
+        I: IntoIterator<Item = (&'b T, Code)>,
+        T: 'b,
+    {
+        for (v, c) in pairs {
+            self.check(v, c);
+        }
+    }
+
+    /// Return the report so far.
+    pub fn report(&self) -> &VerifyReport<T> {
+        &self.report
+    }
+
+    /// Finish verifying, returning the report.
+    pub fn finish(self) -> VerifyReport<T> {
+        self.report
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use crate::{Code, Dict, Mode, ValReq, ValueRange};
+use std::borrow::Borrow;
+
+/// A dictionary over borrowed exact-code values: the lookups of a [Dict]
+/// without owning the `Vec<T>`, so values held elsewhere, such as in the
+/// pages of a memory-mapped file, can be queried without copying. A [Dict]
+/// lends one out with [Dict::view].
+///
+/// For values in serialized form, rather than as a `&[T]`, see
+/// [ArchivedDict](crate::archive::ArchivedDict).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DictView<'a, T> {
+    /// The mode the dictionary was built in.
+    pub mode: Mode,
+
+    /// The sorted values assigned exact codes, as in [Dict::codes].
+    pub codes: &'a [T],
+}
+
+impl<'a, T: ValReq> DictView<'a, T> {
+    /// Make a view of the dictionary with `mode` and the exact-code values
+    /// `codes`, which must be sorted and no more than
+    /// [Mode::num_exact_codes].
+    pub fn new(mode: Mode, codes: &'a [T]) -> Self {
+        DictView { mode, codes }
+    }
+
+    /// Look up the code for a value, as [Dict::encode] does.
+    pub fn encode<Q: Ord + ?Sized>(&self, query: &Q) -> Code
+    where
+        T: Borrow<Q>,
+    {
+        // The `self.code` array stores the input values assigned to "exact"
+        // codes, counting upwards from code 2. Thus a successful binary search
+        // landing at `idx` returns exact code `2*(idx+1)`. An unsuccessful
+        // binary search lands on the _next_ exact code greater than the query
+        // value, so we subtract 1 from that code to denote the inexact code
+        // covering the range below that next exact code.
+        let code = match self.codes.binary_search_by(|c| c.borrow().cmp(query)) {
+            Ok(idx) => 2 * (idx + 1),
+            Err(idx) => (2 * (idx + 1)) - 1,
+        };
+        assert!(code <= 0xffff);
+        Code(code as u16)
+    }
+
+    /// Return the values bracketing `code`, as [Dict::code_bounds] does.
+    pub fn code_bounds(&self, code: Code) -> (Option<&'a T>, Option<&'a T>) {
+        let codes = self.codes;
+        let c = code.0 as usize;
+        if c == 0 || c > 2 * codes.len() + 1 {
+            (None, None)
+        } else if code.is_exact() {
+            let v = &codes[c / 2 - 1];
+            (Some(v), Some(v))
+        } else {
+            ((c / 2).checked_sub(1).map(|i| &codes[i]), codes.get(c / 2))
+        }
+    }
+
+    /// Return the values `code` stands for, as [Dict::decode] does.
+    pub fn decode(&self, code: Code) -> Option<ValueRange<T>> {
+        let c = code.0 as usize;
+        if c == 0 || c > 2 * self.codes.len() + 1 {
+            return None;
+        }
+        let (lower, upper) = self.code_bounds(code);
+        Some(if code.is_exact() {
+            ValueRange::Exact(lower?.clone())
+        } else {
+            ValueRange::Between {
+                lower: lower.cloned(),
+                upper: upper.cloned(),
+            }
+        })
+    }
+
+    /// Copy the viewed values into an owned dictionary, as
+    /// [Dict::from_codes] makes one.
+    pub fn to_dict(&self) -> Dict<T> {
+        Dict::from_codes(self.mode, self.codes.to_vec())
+    }
+}
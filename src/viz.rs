@@ -0,0 +1,162 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Plots of a [Dict]'s code assignments, rendered as SVG documents.
+//!
+//! The quickest way to spot a bad sample is often to look at the codes it
+//! produced: big jumps in the value-vs-code curve, very wide intervals, or a
+//! few codes carrying most of the mass all point at a sample that is too small
+//! or not representative. Each function here returns an SVG document as a
+//! string, for the caller to write out or serve as it sees fit.
+//!
+//! Plotting needs a numeric position for each value, so the functions that
+//! plot values take a projection from `T` to `f64`.
+//!
+//! Requires the `viz` feature.
+
+use crate::{Dict, ValReq};
+use plotters::prelude::*;
+
+/// An error while rendering a plot.
+#[derive(Debug)]
+pub struct VizError(pub String);
+
+impl std::fmt::Display for VizError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "plot rendering failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for VizError {}
+
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for VizError {
+    fn from(e: DrawingAreaErrorKind<E>) -> Self {
+        VizError(e.to_string())
+    }
+}
+
+const SIZE: (u32, u32) = (800, 480);
+
+fn bounds(ys: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (lo, hi) = ys
+        .filter(|y| y.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), y| {
+            (lo.min(y), hi.max(y))
+        });
+    if lo > hi {
+        (0.0, 1.0)
+    } else if lo == hi {
+        (lo - 1.0, hi + 1.0)
+    } else {
+        (lo, hi)
+    }
+}
+
+fn render(
+    title: &str,
+    x_desc: &str,
+    y_desc: &str,
+    points: &[(f64, f64)],
+    line: bool,
+) -> Result<String, VizError> {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, SIZE).into_drawing_area();
+        root.fill(&WHITE)?;
+        let (x0, x1) = bounds(points.iter().map(|p| p.0));
+        let (y0, y1) = bounds(points.iter().map(|p| p.1));
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x0..x1, y0..y1)?;
+        chart
+            .configure_mesh()
+            .x_desc(x_desc)
+            .y_desc(y_desc)
+            .draw()?;
+        let finite = points
+            .iter()
+            .copied()
+            .filter(|(x, y)| x.is_finite() && y.is_finite());
+        if line {
+            chart.draw_series(LineSeries::new(finite, &BLUE))?;
+        } else {
+            chart.draw_series(finite.map(|p| Circle::new(p, 2, BLUE.filled())))?;
+        }
+        root.present()?;
+    }
+    Ok(svg)
+}
+
+/// Plot each exact code's value (projected through `project`) against its
+/// index in [Dict::codes]. A well-fitted dictionary over a smooth distribution
+/// gives a smooth curve shaped like the distribution's inverse CDF.
+pub fn code_assignments<T: ValReq, F: Fn(&T) -> f64>(
+    dict: &Dict<T>,
+    project: F,
+) -> Result<String, VizError> {
+    let points: Vec<(f64, f64)> = dict
+        .codes
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i as f64, project(v)))
+        .collect();
+    render(
+        "code assignments",
+        "exact code index",
+        "value",
+        &points,
+        false,
+    )
+}
+
+/// Plot the width (in projected units) of each bounded inexact interval,
+/// i.e. the distance between consecutive exact code values, against the
+/// interval's index. Spikes show where the sample was too thin to place
+/// codes closely.
+pub fn interval_widths<T: ValReq, F: Fn(&T) -> f64>(
+    dict: &Dict<T>,
+    project: F,
+) -> Result<String, VizError> {
+    let points: Vec<(f64, f64)> = dict
+        .codes
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| (i as f64, project(&w[1]) - project(&w[0])))
+        .collect();
+    render(
+        "inexact interval widths",
+        "interval index",
+        "width",
+        &points,
+        false,
+    )
+}
+
+/// Plot the fraction of `sample` that encodes to each code in use, against
+/// the code. Exact codes should stand out as spikes on heavy hitters; the
+/// inexact codes between them should carry roughly equal mass.
+pub fn code_mass<T: ValReq>(dict: &Dict<T>, sample: &[T]) -> Result<String, VizError> {
+    let mut counts = vec![0usize; dict.mode.max_inexact_code().0 as usize + 1];
+    for v in sample.iter() {
+        counts[dict.encode(v).0 as usize] += 1;
+    }
+    let total = sample.len().max(1) as f64;
+    let used = 2 * dict.codes.len() + 1;
+    let points: Vec<(f64, f64)> = counts
+        .iter()
+        .enumerate()
+        .take(used + 1)
+        .skip(1)
+        .map(|(c, n)| (c as f64, *n as f64 / total))
+        .collect();
+    render(
+        "per-code sample mass",
+        "code",
+        "fraction of sample",
+        &points,
+        true,
+    )
+}
@@ -0,0 +1,94 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Dictionaries fitted to a sliding window of recent data.
+//!
+//! A store that only retains the last so many days of a time series wants
+//! codes fitted to the values it still holds, not to values that have long
+//! since expired. A [WindowedDict] keeps a [SampleSummary] per epoch (a day,
+//! an hour, a batch: whatever the caller advances it by), drops summaries
+//! once they fall out of the window, and refits its dictionary from the
+//! merged summaries of the epochs that remain.
+
+use crate::summary::SampleSummary;
+use crate::{BuildOptions, Dict, Mode, ValReq};
+use std::collections::VecDeque;
+
+/// Options for a [WindowedDict].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct WindowOptions {
+    /// The number of most recent epochs the dictionary reflects.
+    pub epochs: usize,
+
+    /// Refit the dictionary after every this many epochs; 0 means only when
+    /// [WindowedDict::refit] is called.
+    pub refit_every: usize,
+
+    /// The capacity of each epoch's summary, and of their merge.
+    pub summary_capacity: usize,
+
+    /// The options each refit builds with.
+    pub build: BuildOptions,
+}
+
+/// A dictionary maintained over a sliding window of epochs. See the [module
+/// documentation](self).
+pub struct WindowedDict<T: ValReq> {
+    mode: Mode,
+    options: WindowOptions,
+    epochs: VecDeque<SampleSummary<T>>,
+    since_refit: usize,
+    dict: Dict<T>,
+}
+
+impl<T: ValReq> WindowedDict<T> {
+    /// Start an empty window in `mode`. Until the first refit the dictionary
+    /// is the one [Dict::new] builds from an empty sample.
+    pub fn new(mode: Mode, options: WindowOptions) -> Self {
+        assert!(options.epochs != 0);
+        WindowedDict {
+            mode,
+            options,
+            epochs: VecDeque::with_capacity(options.epochs),
+            since_refit: 0,
+            dict: Dict::new_with_options(mode, Vec::new(), &options.build),
+        }
+    }
+
+    /// Return the current dictionary.
+    pub fn dict(&self) -> &Dict<T> {
+        &self.dict
+    }
+
+    /// Return the number of epochs currently in the window.
+    pub fn epochs(&self) -> usize {
+        self.epochs.len()
+    }
+
+    /// Close an epoch whose data was sampled as `sample`, expiring the oldest
+    /// epoch if the window is full. Returns true if this refit the
+    /// dictionary, per [WindowOptions::refit_every].
+    pub fn push_epoch(&mut self, sample: Vec<T>) -> bool {
+        if self.epochs.len() == self.options.epochs {
+            self.epochs.pop_front();
+        }
+        let summary = SampleSummary::new(sample, self.options.summary_capacity);
+        self.epochs.push_back(summary);
+        self.since_refit += 1;
+        if self.options.refit_every != 0 && self.since_refit >= self.options.refit_every {
+            self.refit();
+            return true;
+        }
+        false
+    }
+
+    /// Refit the dictionary to the epochs currently in the window, returning
+    /// it.
+    pub fn refit(&mut self) -> &Dict<T> {
+        let epochs: Vec<&SampleSummary<T>> = self.epochs.iter().collect();
+        let merged = SampleSummary::merge(&epochs, self.options.summary_capacity);
+        self.dict = Dict::from_summary(self.mode, &merged, &self.options.build);
+        self.since_refit = 0;
+        &self.dict
+    }
+}
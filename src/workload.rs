@@ -0,0 +1,166 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Replaying recorded query workloads against a dictionary and code column,
+//! to see how much a sketch would save before integrating it.
+
+use crate::predicate::{CodePredicate, CLASS_DEFINITE, CLASS_NONE};
+use crate::{Code, Dict, ValReq};
+use std::ops::Bound;
+
+/// A recorded range predicate `lower <(=) x <(=) upper` on the underlying
+/// values. Equality on `v` is `(Included(v), Included(v))`; one-sided
+/// comparisons leave the other side `Unbounded`.
+pub type RangePredicate<T> = (Bound<T>, Bound<T>);
+
+/// What replaying one predicate over the code column found.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct PredicateReport {
+    /// Rows whose code shows they definitely satisfy the predicate.
+    pub definite_rows: usize,
+
+    /// Rows whose code shows they might satisfy the predicate, and must be
+    /// re-checked against the underlying value. Does not include
+    /// `definite_rows`.
+    pub candidate_rows: usize,
+
+    /// Blocks holding at least one definite or candidate row.
+    pub blocks_read: usize,
+
+    /// Blocks holding neither, which a scan can skip.
+    pub blocks_skipped: usize,
+}
+
+/// The result of [Replay::run].
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ReplayReport {
+    /// One report per replayed predicate, in order.
+    pub predicates: Vec<PredicateReport>,
+
+    /// Fraction of all (predicate, block) pairs that were skipped.
+    pub skip_rate: f64,
+
+    /// Fraction of all (predicate, row) pairs that needed re-checking.
+    pub candidate_rate: f64,
+
+    /// Fraction of all (predicate, row) pairs that definitely matched.
+    pub definite_rate: f64,
+
+    /// Bytes of underlying data that the skipped blocks would have cost to
+    /// read, summed over the workload.
+    pub bytes_saved: u64,
+}
+
+// The inclusive range of codes that may hold values satisfying the
+// predicate, or None if no code can, and whether each end of the range is
+// a definite match.
+pub(crate) fn code_range<T: ValReq>(
+    dict: &Dict<T>,
+    pred: &RangePredicate<T>,
+) -> Option<(Code, Code, bool, bool)> {
+    let max = 2 * dict.codes.len() as u16 + 1;
+    let (lo, lo_definite) = match &pred.0 {
+        Bound::Unbounded => (1, true),
+        Bound::Included(v) => {
+            let c = dict.encode(v);
+            (c.0, c.is_exact())
+        }
+        // Excluding an exact code's value excludes the whole code.
+        Bound::Excluded(v) => {
+            let c = dict.encode(v);
+            if c.is_exact() {
+                (c.0 + 1, true)
+            } else {
+                (c.0, false)
+            }
+        }
+    };
+    let (hi, hi_definite) = match &pred.1 {
+        Bound::Unbounded => (max, true),
+        Bound::Included(v) => {
+            let c = dict.encode(v);
+            (c.0, c.is_exact())
+        }
+        Bound::Excluded(v) => {
+            let c = dict.encode(v);
+            if c.is_exact() {
+                (c.0 - 1, true)
+            } else {
+                (c.0, false)
+            }
+        }
+    };
+    if lo > hi {
+        None
+    } else {
+        Some((Code(lo), Code(hi), lo_definite, hi_definite))
+    }
+}
+
+/// A harness that replays a workload of predicates against a dictionary and
+/// a column of codes produced by it, reporting how many blocks of the
+/// underlying data each predicate lets a scan skip and how many rows it
+/// leaves to re-check.
+pub struct Replay<'a, T: ValReq> {
+    dict: &'a Dict<T>,
+    codes: &'a [Code],
+    block_rows: usize,
+    block_bytes: u64,
+}
+
+impl<'a, T: ValReq> Replay<'a, T> {
+    /// Make a harness over `codes`, encoded with `dict`, divided into blocks
+    /// of `block_rows` rows each, where reading one block of the underlying
+    /// data costs `block_bytes` bytes of I/O.
+    pub fn new(dict: &'a Dict<T>, codes: &'a [Code], block_rows: usize, block_bytes: u64) -> Self {
+        assert!(block_rows != 0);
+        Replay {
+            dict,
+            codes,
+            block_rows,
+            block_bytes,
+        }
+    }
+
+    fn replay_one(&self, pred: &RangePredicate<T>) -> PredicateReport {
+        let mut report = PredicateReport::default();
+        let pred = CodePredicate::from_range(self.dict, pred).compile(self.dict.mode);
+        for block in self.codes.chunks(self.block_rows) {
+            let (mut definite, mut candidate) = (0, 0);
+            for c in block.iter() {
+                match pred.classify(*c) {
+                    CLASS_NONE => {}
+                    CLASS_DEFINITE => definite += 1,
+                    _ => candidate += 1,
+                }
+            }
+            if definite + candidate == 0 {
+                report.blocks_skipped += 1;
+            } else {
+                report.blocks_read += 1;
+            }
+            report.definite_rows += definite;
+            report.candidate_rows += candidate;
+        }
+        report
+    }
+
+    /// Replay each of `predicates` in turn over the whole column.
+    pub fn run(&self, predicates: &[RangePredicate<T>]) -> ReplayReport {
+        let reports: Vec<PredicateReport> = predicates.iter().map(|p| self.replay_one(p)).collect();
+        let blocks: usize = reports
+            .iter()
+            .map(|r| r.blocks_read + r.blocks_skipped)
+            .sum();
+        let skipped: usize = reports.iter().map(|r| r.blocks_skipped).sum();
+        let rows = (self.codes.len() * reports.len()) as f64;
+        let ratio = |n: usize, d: f64| if d == 0.0 { 0.0 } else { n as f64 / d };
+        ReplayReport {
+            skip_rate: ratio(skipped, blocks as f64),
+            candidate_rate: ratio(reports.iter().map(|r| r.candidate_rows).sum(), rows),
+            definite_rate: ratio(reports.iter().map(|r| r.definite_rows).sum(), rows),
+            bytes_saved: skipped as u64 * self.block_bytes,
+            predicates: reports,
+        }
+    }
+}
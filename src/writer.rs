@@ -0,0 +1,144 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Writing a code column as it is produced.
+//!
+//! An ingest pipeline encodes rows as they arrive, and shouldn't have to
+//! hold a whole segment's codes in memory to persist them and summarize
+//! them in a [ZoneMap]. A [CodeColumnWriter] is pushed codes, or values to
+//! encode, a few at a time. It buffers at most one block, and as each block
+//! fills it writes the block's codes to its sink and records its zone.
+//!
+//! The codes are written as in the code section of an uncompressed
+//! [sketch file](crate::sketchfile), each block as [Mode::pack_codes]
+//! stores it, with nothing between blocks.
+
+use crate::sketch::Sketch;
+use crate::zonemap::{Zone, ZoneMap};
+use crate::{Code, Dict, Mode, ValReq};
+use std::io;
+
+/// A push-style writer of a code column. See the [module
+/// documentation](self).
+pub struct CodeColumnWriter<W: io::Write> {
+    sink: W,
+    mode: Mode,
+    block_rows: usize,
+    block: Vec<u8>,
+    zone: Option<Zone>,
+    zones: ZoneMap,
+    rows: u64,
+}
+
+impl<W: io::Write> CodeColumnWriter<W> {
+    /// Write codes of `mode` to `sink`, with a zone map of blocks of
+    /// `block_rows` rows.
+    ///
+    /// Panics if `block_rows` is zero.
+    pub fn new(sink: W, mode: Mode, block_rows: usize) -> Self {
+        assert!(block_rows != 0);
+        CodeColumnWriter {
+            sink,
+            mode,
+            block_rows,
+            block: Vec::new(),
+            zone: None,
+            zones: ZoneMap { zones: Vec::new() },
+            rows: 0,
+        }
+    }
+
+    /// The number of codes pushed so far.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Return the zones of the blocks written so far.
+    pub fn zones(&self) -> &ZoneMap {
+        &self.zones
+    }
+
+    /// Push one code, writing out the block if it fills.
+    ///
+    /// Panics if `code` is too wide for the writer's mode.
+    pub fn push(&mut self, code: Code) -> io::Result<()> {
+        assert!(
+            code <= self.mode.max_inexact_code(),
+            "code too wide for the writer's mode"
+        );
+        let in_block = self.zone.map_or(0, |z| z.rows);
+        match self.mode {
+            // Every second code shares a byte with the one before.
+            Mode::Packed if in_block % 2 == 1 => {
+                *self.block.last_mut().unwrap() |= (code.0 << 4) as u8;
+                self.block.push((code.0 >> 4) as u8);
+            }
+            _ => self.mode.pack_codes(&[code], &mut self.block),
+        }
+        self.zone = Some(match self.zone {
+            None => Zone {
+                min: code,
+                max: code,
+                rows: 1,
+            },
+            Some(z) => Zone {
+                min: z.min.min(code),
+                max: z.max.max(code),
+                rows: z.rows + 1,
+            },
+        });
+        self.rows += 1;
+        if self.zone.is_some_and(|z| z.rows == self.block_rows) {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Push each of `codes`.
+    pub fn push_all(&mut self, codes: &[Code]) -> io::Result<()> {
+        for c in codes {
+            self.push(*c)?;
+        }
+        Ok(())
+    }
+
+    /// Push each code of `sketch`.
+    ///
+    /// Panics if `sketch` is of a different mode.
+    pub fn push_sketch(&mut self, sketch: &Sketch) -> io::Result<()> {
+        assert!(sketch.mode() == self.mode);
+        for c in sketch.iter() {
+            self.push(c)?;
+        }
+        Ok(())
+    }
+
+    /// Encode each of `values` with `dict` and push its code.
+    ///
+    /// Panics if `dict` was built in a different mode.
+    pub fn push_values<T: ValReq>(&mut self, dict: &Dict<T>, values: &[T]) -> io::Result<()> {
+        assert!(dict.mode == self.mode);
+        for v in values {
+            self.push(dict.encode(v))?;
+        }
+        Ok(())
+    }
+
+    // Write out the buffered block and record its zone.
+    fn flush_block(&mut self) -> io::Result<()> {
+        if let Some(zone) = self.zone.take() {
+            self.sink.write_all(&self.block)?;
+            self.block.clear();
+            self.zones.zones.push(zone);
+        }
+        Ok(())
+    }
+
+    /// Write out the last, possibly short, block and flush the sink,
+    /// returning it and the zone map of every block written.
+    pub fn finish(mut self) -> io::Result<(W, ZoneMap)> {
+        self.flush_block()?;
+        self.sink.flush()?;
+        Ok((self.sink, self.zones))
+    }
+}
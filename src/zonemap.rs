@@ -0,0 +1,108 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Zone maps over code columns: the least and greatest code in each block.
+//!
+//! Since codes preserve order, a block whose code range doesn't meet a
+//! predicate's code range can be skipped without reading either its codes or
+//! its underlying values.
+
+use crate::sketch::{Sketch, SketchCodes};
+use crate::{Code, Dict, ValReq};
+
+/// The summary of one block of a code column.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Zone {
+    /// The least code in the block.
+    pub min: Code,
+    /// The greatest code in the block.
+    pub max: Code,
+    /// The number of rows in the block.
+    pub rows: usize,
+}
+
+impl Zone {
+    /// Whether the block may hold a code in the inclusive range `[lo, hi]`.
+    pub fn may_contain(&self, lo: Code, hi: Code) -> bool {
+        self.min <= hi && lo <= self.max
+    }
+}
+
+/// The zones of a code column divided into fixed-size blocks.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ZoneMap {
+    /// One zone per block, in column order.
+    pub zones: Vec<Zone>,
+}
+
+impl ZoneMap {
+    /// Summarize `codes` in blocks of `block_rows` rows. The last block may
+    /// be short.
+    ///
+    /// Panics if `block_rows` is zero.
+    pub fn new(codes: &[Code], block_rows: usize) -> Self {
+        assert!(block_rows != 0);
+        let zones = codes
+            .chunks(block_rows)
+            .map(|block| Zone {
+                min: *block.iter().min().unwrap(),
+                max: *block.iter().max().unwrap(),
+                rows: block.len(),
+            })
+            .collect();
+        ZoneMap { zones }
+    }
+
+    /// Summarize the codes of `sketch` as [ZoneMap::new] does.
+    ///
+    /// Panics if `block_rows` is zero.
+    pub fn of_sketch(sketch: &Sketch, block_rows: usize) -> Self {
+        fn zones<C: Copy + Ord + Into<u16>>(codes: &[C], block_rows: usize) -> Vec<Zone> {
+            codes
+                .chunks(block_rows)
+                .map(|block| Zone {
+                    min: Code((*block.iter().min().unwrap()).into()),
+                    max: Code((*block.iter().max().unwrap()).into()),
+                    rows: block.len(),
+                })
+                .collect()
+        }
+        assert!(block_rows != 0);
+        let zones = match sketch.codes() {
+            SketchCodes::Byte(c) => zones(c, block_rows),
+            SketchCodes::Word(c) => zones(c, block_rows),
+        };
+        ZoneMap { zones }
+    }
+
+    /// Return the indices of the blocks that may hold a code in the
+    /// inclusive range `[lo, hi]`.
+    pub fn candidates(&self, lo: Code, hi: Code) -> Vec<usize> {
+        self.zones
+            .iter()
+            .enumerate()
+            .filter(|(_, z)| z.may_contain(lo, hi))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+// The least value `code` can stand for, or None if it is the inexact code
+// unbounded below.
+pub(crate) fn lower_bound<T: ValReq>(dict: &Dict<T>, code: Code) -> Option<&T> {
+    dict.code_bounds(code).0
+}
+
+// The greatest value `code` can stand for, or None if it is the inexact code
+// unbounded above.
+pub(crate) fn upper_bound<T: ValReq>(dict: &Dict<T>, code: Code) -> Option<&T> {
+    dict.code_bounds(code).1
+}
+
+// The values bracketing a zone, or None if it is unbounded on either side.
+pub(crate) fn value_bounds<T: ValReq>(dict: &Dict<T>, zone: &Zone) -> Option<(T, T)> {
+    Some((
+        lower_bound(dict, zone.min)?.clone(),
+        upper_bound(dict, zone.max)?.clone(),
+    ))
+}
@@ -0,0 +1,94 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Z-order (Morton) composite keys, for sketching two numeric columns as one.
+//!
+//! Interleaving the bits of two keys gives a single key whose order keeps
+//! points that are close in both dimensions mostly close together, so a
+//! [Dict] over it (and the codes it assigns) can prune by both columns at
+//! once, for example by tenant and timestamp. A rectangular predicate on the
+//! two columns becomes a union of contiguous ranges of composite keys, and
+//! from there a union of code ranges.
+
+use crate::{quadtree, Code, Dict};
+use std::ops::RangeInclusive;
+
+/// A pair of `u32` keys `(x, y)` with their bits interleaved, `x` in the even
+/// bits and `y` in the odd bits.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct ZKey(pub u64);
+
+// Spread the 32 bits of v out to the even bits of a u64.
+fn spread(v: u32) -> u64 {
+    let mut v = v as u64;
+    v = (v | (v << 16)) & 0x0000_ffff_0000_ffff;
+    v = (v | (v << 8)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+    v = (v | (v << 1)) & 0x5555_5555_5555_5555;
+    v
+}
+
+// Gather the even bits of v into a u32; the inverse of spread.
+fn gather(v: u64) -> u32 {
+    let mut v = v & 0x5555_5555_5555_5555;
+    v = (v | (v >> 1)) & 0x3333_3333_3333_3333;
+    v = (v | (v >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v >> 4)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v >> 8)) & 0x0000_ffff_0000_ffff;
+    v = (v | (v >> 16)) & 0x0000_0000_ffff_ffff;
+    v as u32
+}
+
+impl ZKey {
+    /// Interleave `x` and `y`.
+    pub fn new(x: u32, y: u32) -> Self {
+        ZKey(spread(x) | (spread(y) << 1))
+    }
+
+    /// The `x` key.
+    pub fn x(&self) -> u32 {
+        gather(self.0)
+    }
+
+    /// The `y` key.
+    pub fn y(&self) -> u32 {
+        gather(self.0 >> 1)
+    }
+}
+
+/// An axis-aligned rectangle of `(x, y)` keys, inclusive on all sides.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rect {
+    /// The range of `x` keys.
+    pub x: RangeInclusive<u32>,
+    /// The range of `y` keys.
+    pub y: RangeInclusive<u32>,
+}
+
+/// Cover the keys inside `rect` with at most `max_ranges` contiguous, sorted,
+/// disjoint inclusive ranges of [ZKey]s. The cover is exact if `max_ranges`
+/// allows; otherwise it includes some keys outside the rectangle, which is
+/// safe for pruning but adds false positives.
+///
+/// Panics if `max_ranges` is zero.
+pub fn rect_to_ranges(rect: &Rect, max_ranges: usize) -> Vec<RangeInclusive<ZKey>> {
+    quadtree::cover(rect, max_ranges, |x0, y0, _| ZKey::new(x0, y0).0)
+        .into_iter()
+        .map(|r| ZKey(*r.start())..=ZKey(*r.end()))
+        .collect()
+}
+
+/// Translate the rectangle predicate `rect` into a sorted union of disjoint
+/// inclusive code ranges of `dict`: a row's code lies in one of them if its
+/// key may lie in the rectangle. `max_ranges` bounds the number of key ranges
+/// the rectangle is first decomposed into (see [rect_to_ranges]); the code
+/// ranges are never more numerous, and are often fewer, since neighbouring
+/// key ranges frequently share codes.
+pub fn rect_to_code_ranges(
+    dict: &Dict<ZKey>,
+    rect: &Rect,
+    max_ranges: usize,
+) -> Vec<RangeInclusive<Code>> {
+    quadtree::code_ranges(dict, rect_to_ranges(rect, max_ranges))
+}
@@ -0,0 +1,213 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::adaptive::{cost, AdaptiveDict, Alternating, AssignmentStrategy, Choice, Point};
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+use std::ops::RangeBounds;
+
+// Heavy values below 10_000, a near-uniform spread above.
+fn mixed() -> Vec<u64> {
+    let heavy: Vec<u64> = (0..20).map(|i| i * 500).collect();
+    common::heavy_hitters(5, 40_000, 0.5, &heavy, 10_000..1_010_000)
+}
+
+#[test]
+fn adaptive_spends_exact_codes_only_on_heavy_values() {
+    let sample = mixed();
+    let dict = AdaptiveDict::new(Mode::Byte, sample.clone(), 0.5);
+    assert!(dict.max_code() <= Mode::Byte.max_inexact_code());
+    let exact: Vec<u64> = dict
+        .points
+        .iter()
+        .filter_map(|p| match p {
+            Point::Exact(v) => Some(*v),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(exact, (0..20).map(|i| i * 500).collect::<Vec<u64>>());
+    for v in exact.iter() {
+        assert!(dict.is_exact(dict.encode(v)));
+    }
+
+    // The uniform range gets many more intervals than a plain dictionary
+    // gives it.
+    let plain = Dict::new(Mode::Byte, sample.clone());
+    let intervals = |lo: u64| {
+        let hi = 1_010_000;
+        (dict.encode(&hi).0 - dict.encode(&lo).0) as usize
+    };
+    let plain_intervals = plain.codes.iter().filter(|v| **v >= 10_000).count();
+    assert!(intervals(10_000) > (3 * plain_intervals) / 2);
+
+    let mut sorted = sample;
+    sorted.sort_unstable();
+    for w in sorted.windows(2) {
+        let (a, b) = (dict.encode(&w[0]), dict.encode(&w[1]));
+        assert!(a <= b);
+        if w[0] == w[1] {
+            assert_eq!(a, b);
+        }
+    }
+}
+
+#[test]
+fn adaptive_rewrites_are_conservative() {
+    let sample = mixed();
+    let dict = AdaptiveDict::new(Mode::Byte, sample.clone(), 0.5);
+    let boundary = dict
+        .points
+        .iter()
+        .find_map(|p| match p {
+            Point::Boundary(v) => Some(*v),
+            _ => None,
+        })
+        .unwrap();
+    let preds = [
+        (Included(500), Included(2000)),
+        (Excluded(500), Excluded(2000)),
+        (Included(boundary), Excluded(boundary + 50_000)),
+        (Unbounded, Excluded(boundary)),
+        (Included(3), Included(3)),
+        (Included(7000), Unbounded),
+    ];
+    for pred in preds.iter() {
+        let rewrite = dict.rewrite(pred);
+        for v in sample.iter() {
+            let c = dict.encode(v);
+            if rewrite.is_definite(c) {
+                assert!(pred.contains(v), "{:?} {:?}", pred, v);
+            }
+            if pred.contains(v) {
+                assert!(rewrite.is_definite(c) || rewrite.is_maybe(c));
+            }
+        }
+    }
+    let eq = dict.rewrite(&(Included(500), Included(500)));
+    assert_eq!(eq.definite, vec![dict.encode(&500)..=dict.encode(&500)]);
+    assert!(eq.maybe.is_empty());
+
+    let layout = AdaptiveDict::from_points(
+        Mode::Byte,
+        vec![Point::Boundary(10), Point::Exact(20), Point::Boundary(30)],
+    );
+    let codes: Vec<Code> = [5, 10, 15, 20, 25, 30, 35]
+        .iter()
+        .map(|v| layout.encode(v))
+        .collect();
+    assert_eq!(
+        codes,
+        [1, 2, 2, 3, 4, 5, 5]
+            .iter()
+            .map(|c| Code(*c))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(layout.max_code(), Code(5));
+}
+
+#[test]
+fn exact_fraction_sets_the_layout() {
+    let sample = mixed();
+    let count = |dict: &AdaptiveDict<u64>| {
+        let exact = (1..=dict.max_code().0)
+            .filter(|c| dict.is_exact(Code(*c)))
+            .count();
+        (exact, dict.max_code().0 as usize)
+    };
+    for (fraction, expected) in [(0.0, 0), (0.1, 26), (0.25, 64), (0.5, 127), (0.9, 127)] {
+        let dict = AdaptiveDict::with_exact_fraction(Mode::Byte, sample.clone(), fraction);
+        let (exact, total) = count(&dict);
+        assert_eq!(exact, expected, "{}", fraction);
+        assert!((250..=255).contains(&total), "{} {}", fraction, total);
+    }
+
+    // The heavy values are the first to get exact codes.
+    let dict = AdaptiveDict::with_exact_fraction(Mode::Byte, sample.clone(), 0.1);
+    for v in (0..20).map(|i| i * 500) {
+        assert!(dict.is_exact(dict.encode(&v)));
+    }
+    for pred in [
+        (Included(1000), Excluded(600_000)),
+        (Excluded(9_500), Unbounded),
+    ] {
+        let rewrite = dict.rewrite(&pred);
+        for v in sample.iter() {
+            let c = dict.encode(v);
+            if rewrite.is_definite(c) {
+                assert!(pred.contains(v));
+            }
+            if pred.contains(v) {
+                assert!(rewrite.is_definite(c) || rewrite.is_maybe(c));
+            }
+        }
+    }
+}
+
+// Splits the domain at fixed multiples, whatever the sample.
+struct EveryThousand;
+
+impl AssignmentStrategy<u64> for EveryThousand {
+    fn assign(&self, runs: &[(u64, usize)], budget: usize) -> Vec<Point<u64>> {
+        let max = runs.last().map_or(0, |r| r.0);
+        (1..budget as u64)
+            .map(|i| Point::Boundary(i * 1000))
+            .take_while(|p| *p.value() <= max)
+            .collect()
+    }
+}
+
+#[test]
+fn strategies_are_pluggable() {
+    let sample = mixed();
+    let alternating = AdaptiveDict::with_strategy(Mode::Byte, sample.clone(), &Alternating);
+    let plain = Dict::new(Mode::Byte, sample.clone());
+    for v in sample.iter().take(5000) {
+        assert_eq!(alternating.encode(v), plain.encode(v));
+    }
+
+    let fixed = AdaptiveDict::with_strategy(Mode::Byte, sample.clone(), &EveryThousand);
+    assert_eq!(fixed.encode(&999), Code(1));
+    assert_eq!(fixed.encode(&1000), Code(2));
+    assert_eq!(fixed.encode(&u64::MAX), Code(255));
+
+    let boxed: Box<dyn AssignmentStrategy<u64>> = Box::new(EveryThousand);
+    let dynamic = AdaptiveDict::with_strategy(Mode::Byte, sample, boxed.as_ref());
+    assert_eq!(dynamic.points, fixed.points);
+}
+
+#[test]
+fn auto_picks_the_cheapest_strategy() {
+    let sample = mixed();
+    for weight in [0.0, 0.5, 1.0] {
+        let (dict, stats) = AdaptiveDict::auto(Mode::Byte, sample.clone(), weight);
+        // Too many distinct values to try exact-only.
+        let tried: Vec<Choice> = stats.candidates.iter().map(|c| c.0).collect();
+        assert_eq!(
+            tried,
+            [Choice::EquiDepth, Choice::SkewAware, Choice::Hybrid]
+        );
+        let least = stats
+            .candidates
+            .iter()
+            .map(|c| c.2)
+            .fold(f64::INFINITY, f64::min);
+        assert_eq!(cost(&stats.metrics, weight), least);
+        assert_eq!(dict.metrics(&sample), stats.metrics);
+    }
+    // Ranking purely by equality false positives favours exact codes for the
+    // heavy values.
+    let (_, stats) = AdaptiveDict::auto(Mode::Byte, sample, 1.0);
+    assert_ne!(stats.choice, Choice::EquiDepth);
+
+    // A low-cardinality sample is encoded exactly, whether by exact-only or
+    // by a skew-aware layout that ties with it.
+    let small: Vec<u64> = mixed().into_iter().map(|v| v % 37).collect();
+    let (dict, stats) = AdaptiveDict::auto(Mode::Byte, small.clone(), 0.5);
+    assert!(stats.candidates.iter().any(|c| c.0 == Choice::ExactOnly));
+    assert_eq!(stats.metrics.exact_hit_rate, 1.0);
+    for v in small.iter() {
+        assert!(dict.is_exact(dict.encode(v)));
+    }
+}
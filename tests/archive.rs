@@ -0,0 +1,68 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use float_ord::FloatOrd;
+use ordbog::archive::ArchivedDict;
+use ordbog::format::{append_section, FormatError};
+use ordbog::{Dict, DictF64, Mode};
+
+#[test]
+fn archived_dicts_encode_in_place() {
+    let values: Vec<i64> = (0..20_000).map(|i| (i * 7919) % 9000 - 4500).collect();
+    for mode in [Mode::Byte, Mode::Packed, Mode::Word].iter().copied() {
+        let dict = Dict::new(mode, values.clone());
+        let mut bytes = dict.to_bytes();
+        append_section(&mut bytes, 0x7777, b"ignored");
+        let archived = ArchivedDict::<i64>::new(&bytes).unwrap();
+        assert_eq!(archived.mode(), mode);
+        assert_eq!(archived.len(), dict.codes.len());
+        assert_eq!(archived.value(3), dict.codes[3]);
+        for v in values.iter().chain([-10_000, 10_000].iter()) {
+            assert_eq!(archived.encode(v), dict.encode(v), "{}", v);
+        }
+        assert_eq!(archived.to_dict().codes, dict.codes);
+    }
+
+    let floats = Dict::from_codes(
+        Mode::Byte,
+        vec![DictF64(FloatOrd(-0.5)), DictF64(FloatOrd(4.0))],
+    );
+    let bytes = floats.to_bytes();
+    let archived = ArchivedDict::<DictF64>::new(&bytes).unwrap();
+    for f in [-1.0, -0.5, 0.0, 4.0, 9.0].iter() {
+        let v = DictF64(FloatOrd(*f));
+        assert_eq!(archived.encode(&v), floats.encode(&v));
+    }
+    let empty = Dict::<u32>::from_codes(Mode::Word, Vec::new()).to_bytes();
+    let archived = ArchivedDict::<u32>::new(&empty).unwrap();
+    assert!(archived.is_empty());
+    assert_eq!(archived.encode(&7).0, 1);
+}
+
+#[test]
+fn archived_dicts_check_their_bytes() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    let bytes = dict.to_bytes();
+    // The wrong width.
+    assert_eq!(
+        ArchivedDict::<u64>::new(&bytes).unwrap_err(),
+        FormatError::BadValue
+    );
+    assert_eq!(
+        ArchivedDict::<u32>::new(&bytes[..bytes.len() - 1]).unwrap_err(),
+        FormatError::Truncated
+    );
+    // Out of order: swap the last two values.
+    let mut swapped = bytes.clone();
+    let n = swapped.len();
+    swapped[n - 12..n - 8].copy_from_slice(&30u32.to_le_bytes());
+    swapped[n - 4..].copy_from_slice(&20u32.to_le_bytes());
+    assert_eq!(
+        ArchivedDict::<u32>::new(&swapped).unwrap_err(),
+        FormatError::BadCodes
+    );
+    assert_eq!(
+        ArchivedDict::<u32>::new(b"ORDX").unwrap_err(),
+        FormatError::BadMagic
+    );
+}
@@ -0,0 +1,82 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "arrow")]
+
+use arrow_array::types::Int32Type;
+use arrow_array::{Array, DictionaryArray, Int64Array, LargeStringArray, StringArray};
+use ordbog::arrow::{encode_dictionary, EncodeArray};
+use ordbog::{Code, Dict, Mode};
+use std::sync::Arc;
+
+#[test]
+fn encodes_primitive_and_string_arrays() {
+    let dict = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let array = Int64Array::from(vec![Some(10), None, Some(25)]);
+    assert_eq!(array.encode(&dict), vec![Code(2), Code(0), Code(5)]);
+
+    let words: Vec<String> = ["ant", "bee", "cat"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let dict = Dict::new(Mode::Byte, words);
+    let array = StringArray::from(vec![Some("bee"), Some("cow"), None]);
+    assert_eq!(array.encode(&dict), vec![Code(4), Code(7), Code(0)]);
+}
+
+#[test]
+fn dictionary_arrays_match_plain_encoding() {
+    let words: Vec<String> = (0..500)
+        .map(|i| format!("w{:03}", (i * 37) % 200))
+        .collect();
+    let dict = Dict::new(Mode::Byte, words.clone());
+    let rows: Vec<Option<&str>> = words
+        .iter()
+        .enumerate()
+        .map(|(i, w)| if i % 17 == 0 { None } else { Some(w.as_str()) })
+        .collect();
+    let plain = StringArray::from(rows.clone());
+    let dictionary: DictionaryArray<Int32Type> = rows.iter().copied().collect();
+    assert_eq!(dictionary.encode(&dict), plain.encode(&dict));
+    let large = LargeStringArray::from(rows.clone());
+    assert_eq!(large.encode(&dict), plain.encode(&dict));
+    let keys = dictionary.keys().clone();
+    let strings = dictionary.values().as_any().downcast_ref::<StringArray>();
+    let values: LargeStringArray = strings.unwrap().iter().collect();
+    let large_dictionary = DictionaryArray::new(keys, Arc::new(values));
+    assert_eq!(large_dictionary.encode(&dict), plain.encode(&dict));
+    assert!(encode_dictionary::<Int32Type, Int64Array, i64>(
+        &Dict::new(Mode::Byte, vec![1]),
+        &dictionary
+    )
+    .is_none());
+}
+
+#[test]
+fn nulls_propagate_into_code_columns() {
+    use ordbog::predicate::CodePredicate;
+    use std::ops::Bound::*;
+
+    let dict = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let array = Int64Array::from(vec![Some(10), None, Some(25), Some(20)]);
+    let column = array.encode_column(&dict);
+    assert_eq!(column.codes[1], Code(0));
+    assert_eq!(column.validity, Some(vec![true, false, true, true]));
+    assert_eq!(column.null_count(), 1);
+
+    // Everything but null satisfies an unbounded range.
+    let all = CodePredicate::from_range(&dict, &(Unbounded, Unbounded));
+    assert_eq!(column.candidates(&all), vec![true, false, true, true]);
+    let p = CodePredicate::from_range(&dict, &(Included(12), Excluded(25)));
+    assert_eq!(column.candidates(&p), vec![false, false, true, true]);
+    assert_eq!(column.definite(&p), vec![false, false, false, true]);
+
+    // Nulls among a dictionary array's values are logical nulls too.
+    let values = StringArray::from(vec![Some("bee"), None]);
+    let keys = arrow_array::Int32Array::from(vec![0, 1, 0]);
+    let array = DictionaryArray::<Int32Type>::try_new(keys, std::sync::Arc::new(values)).unwrap();
+    let words: Vec<String> = ["ant", "bee"].iter().map(|s| s.to_string()).collect();
+    let column = array.encode_column(&Dict::new(Mode::Byte, words));
+    assert_eq!(column.codes, vec![Code(4), Code(0), Code(4)]);
+    assert_eq!(column.validity, Some(vec![true, false, true]));
+}
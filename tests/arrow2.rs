@@ -0,0 +1,56 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "arrow2")]
+
+use arrow2::array::{
+    DictionaryArray, Int64Array, MutableDictionaryArray, MutableUtf8Array, TryExtend, Utf8Array,
+};
+use ordbog::arrow2::{encode_dictionary, EncodeArray};
+use ordbog::{Code, Dict, Mode};
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn encodes_primitive_and_string_arrays() {
+    let dict = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let array = Int64Array::from(&[Some(10), None, Some(25)]);
+    assert_eq!(array.encode(&dict), vec![Code(2), Code(0), Code(5)]);
+    let column = array.encode_column(&dict);
+    assert_eq!(column.validity, Some(vec![true, false, true]));
+
+    let dict = Dict::new(Mode::Byte, words(&["ant", "bee", "cat"]));
+    let array = Utf8Array::<i32>::from([Some("bee"), Some("cow"), None]);
+    assert_eq!(array.encode(&dict), vec![Code(4), Code(7), Code(0)]);
+    let large = Utf8Array::<i64>::from([Some("bee"), Some("cow"), None]);
+    assert_eq!(large.encode(&dict), array.encode(&dict));
+}
+
+#[test]
+fn dictionary_arrays_match_plain_encoding() {
+    let all: Vec<String> = (0..500)
+        .map(|i| format!("w{:03}", (i * 37) % 200))
+        .collect();
+    let dict = Dict::new(Mode::Byte, all.clone());
+    let rows: Vec<Option<&str>> = all
+        .iter()
+        .enumerate()
+        .map(|(i, w)| if i % 17 == 0 { None } else { Some(w.as_str()) })
+        .collect();
+    let plain = Utf8Array::<i32>::from(&rows);
+    let mut builder = MutableDictionaryArray::<i32, MutableUtf8Array<i32>>::new();
+    builder.try_extend(rows.iter().copied()).unwrap();
+    let dictionary: DictionaryArray<i32> = builder.into();
+    assert_eq!(dictionary.encode(&dict), plain.encode(&dict));
+    assert_eq!(
+        dictionary.encode_column(&dict).validity,
+        plain.encode_column(&dict).validity
+    );
+    assert!(encode_dictionary::<i32, Int64Array, i64>(
+        &Dict::new(Mode::Byte, vec![1]),
+        &dictionary
+    )
+    .is_none());
+}
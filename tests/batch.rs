@@ -0,0 +1,61 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{Code, Dict, Mode, WidthError};
+
+#[test]
+fn batches_encode_like_single_values() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    let values = [0u32, 5, 10, 10, 12, 19, 20, 25, 25, 30, 31, 99, 10, 0];
+    let single: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    assert_eq!(dict.encode_slice(&values), single);
+    assert_eq!(
+        single,
+        [1, 1, 2, 2, 3, 3, 4, 5, 5, 6, 7, 7, 2, 1]
+            .iter()
+            .map(|c| Code(*c))
+            .collect::<Vec<_>>()
+    );
+
+    let mut out = vec![Code(9)];
+    dict.encode_extend(&values[..3], &mut out);
+    dict.encode_extend(&[], &mut out);
+    assert_eq!(out, [Code(9), Code(1), Code(1), Code(2)]);
+
+    let empty = Dict::from_codes(Mode::Byte, Vec::<u32>::new());
+    assert_eq!(empty.encode_slice(&[1, 2]), [Code(1), Code(1)]);
+}
+
+#[test]
+fn batches_encode_into_stored_widths() {
+    let values = [5u32, 10, 10, 25, 40];
+    let byte = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    let mut bytes = [0xaau8; 6];
+    byte.encode_into_u8(&values, &mut bytes).unwrap();
+    assert_eq!(bytes, [1, 2, 2, 5, 7, 0xaa]);
+
+    let word = Dict::from_codes(Mode::Word, vec![10u32, 20, 30]);
+    let mut words = [0u16; 5];
+    word.encode_into_u16(&values, &mut words).unwrap();
+    assert_eq!(words, [1, 2, 2, 5, 7]);
+
+    let err = byte.encode_into_u16(&values, &mut words).unwrap_err();
+    assert_eq!(
+        err,
+        WidthError {
+            mode: Mode::Byte,
+            bits: 16
+        }
+    );
+    assert_eq!(err.to_string(), "Byte mode stores 8-bit codes, not 16-bit");
+    let packed = Dict::from_codes(Mode::Packed, vec![10u32]);
+    assert!(packed.encode_into_u8(&values, &mut bytes).is_err());
+    assert!(packed.encode_into_u16(&values, &mut words).is_err());
+}
+
+#[test]
+#[should_panic]
+fn short_buffers_are_refused() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32]);
+    let _ = dict.encode_into_u8(&[1, 2, 3], &mut [0u8; 2]);
+}
@@ -0,0 +1,23 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{Code, Dict, Mode};
+
+#[test]
+fn string_dicts_encode_borrowed_keys() {
+    let words = ["apple", "banana", "cherry", "damson"];
+    let dict = Dict::new(Mode::Byte, words.iter().map(|w| w.to_string()).collect());
+    for w in words.iter() {
+        assert_eq!(dict.encode(*w), dict.encode(&w.to_string()));
+        assert!(dict.encode(*w).is_exact());
+    }
+    assert_eq!(dict.encode("blueberry"), Code(5));
+    assert_eq!(dict.encode_dense("apple").0, 1);
+    assert_eq!(
+        dict.encode_signed("zucchini"),
+        Mode::Byte.to_signed(Code(9))
+    );
+
+    let bytes = Dict::new(Mode::Byte, vec![b"ab".to_vec(), b"cd".to_vec()]);
+    assert_eq!(bytes.encode(&b"cd"[..]), Code(4));
+}
@@ -0,0 +1,142 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use float_ord::FloatOrd;
+use ordbog::format::Fingerprint;
+use ordbog::{Dict, DictF64, Mode};
+
+const QUIET_NAN: u64 = 0x7ff8_0000_0000_0000;
+const PAYLOAD_NAN: u64 = 0x7ff8_0000_0000_0042;
+const NEGATIVE_NAN: u64 = 0xfff8_0000_0000_0001;
+
+fn f(v: f64) -> DictF64 {
+    DictF64(FloatOrd(v))
+}
+
+fn floats() -> Vec<DictF64> {
+    let mut sample: Vec<DictF64> = (0..3000)
+        .map(|i| f((i % 300) as f64 * 0.5 - 20.0))
+        .collect();
+    let edges = [
+        f64::from_bits(QUIET_NAN),
+        f64::from_bits(PAYLOAD_NAN),
+        f64::from_bits(NEGATIVE_NAN),
+        0.0,
+        -0.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::MIN_POSITIVE,
+    ];
+    for v in edges.iter() {
+        sample.extend(std::iter::repeat_n(f(*v), 40));
+    }
+    sample
+}
+
+fn strings() -> Vec<String> {
+    let words = [
+        "apple",
+        "Apple",
+        "APPLE",
+        "éclair",
+        "eclair",
+        "zebra",
+        "Zebra",
+        "",
+        "a\u{0}b",
+        "ß",
+        "ss",
+        "\u{1f600}",
+    ];
+    (0..2000)
+        .map(|i| format!("{}{}", words[i % words.len()], i % 37))
+        .collect()
+}
+
+fn ints() -> Vec<i64> {
+    (0..5000).map(|i: i64| (i * 7919) % 1234 - 600).collect()
+}
+
+// Pinned so that any platform, or any change to construction or the format,
+// producing different bytes for the same samples fails here.
+#[test]
+fn fingerprints_are_pinned() {
+    let pinned = [
+        (
+            Mode::Byte,
+            [
+                16444556800260400752,
+                6169493941575156710,
+                16901219740990911949,
+            ],
+        ),
+        (
+            Mode::Word,
+            [508908350461739541, 14472608230547549400, 171843503724058908],
+        ),
+    ];
+    for (mode, [floats_fp, strings_fp, ints_fp]) in pinned.iter() {
+        assert_eq!(
+            Dict::new(*mode, floats()).fingerprint(),
+            Fingerprint(*floats_fp)
+        );
+        assert_eq!(
+            Dict::new(*mode, strings()).fingerprint(),
+            Fingerprint(*strings_fp)
+        );
+        assert_eq!(
+            Dict::new(*mode, ints()).fingerprint(),
+            Fingerprint(*ints_fp)
+        );
+    }
+}
+
+#[test]
+fn sample_order_does_not_matter() {
+    for mode in [Mode::Byte, Mode::Word] {
+        let mut shuffled = floats();
+        shuffled.reverse();
+        shuffled.rotate_left(777);
+        assert_eq!(
+            Dict::new(mode, floats()).to_bytes(),
+            Dict::new(mode, shuffled).to_bytes()
+        );
+        let mut shuffled = strings();
+        shuffled.reverse();
+        assert_eq!(
+            Dict::new(mode, strings()).to_bytes(),
+            Dict::new(mode, shuffled).to_bytes()
+        );
+    }
+}
+
+#[test]
+fn float_edge_cases_are_distinct_and_preserved() {
+    let dict = Dict::new(Mode::Word, floats());
+    let back: Dict<DictF64> = Dict::from_bytes(&dict.to_bytes()).unwrap();
+    let bits: Vec<u64> = back.codes.iter().map(|v| (v.0).0.to_bits()).collect();
+    for b in [
+        QUIET_NAN,
+        PAYLOAD_NAN,
+        NEGATIVE_NAN,
+        0,
+        0x8000_0000_0000_0000,
+    ] {
+        assert!(bits.contains(&b), "{:#x} lost", b);
+    }
+    let code = |b: u64| dict.encode(&f(f64::from_bits(b)));
+    assert!(code(0x8000_0000_0000_0000) < code(0));
+    assert!(code(QUIET_NAN) < code(PAYLOAD_NAN));
+    assert!(code(NEGATIVE_NAN) < code(f64::NEG_INFINITY.to_bits()));
+}
+
+#[test]
+fn strings_order_by_code_point() {
+    let dict = Dict::new(Mode::Word, strings());
+    let pos = |s: &str| dict.codes.iter().position(|v| v == s).unwrap();
+    assert!(pos("APPLE0") < pos("Apple0"));
+    assert!(pos("Zebra0") < pos("apple0"));
+    assert!(pos("zebra0") < pos("ß0"));
+    assert!(pos("ß0") < pos("éclair0"));
+    assert!(pos("éclair0") < pos("\u{1f600}0"));
+}
@@ -0,0 +1,102 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{BuildOptions, Dict, Mode};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counts the allocations made on each thread, so tests running in parallel
+// don't see each other's.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let r = f();
+    (r, ALLOCATIONS.with(Cell::get) - before)
+}
+
+fn sample() -> Vec<u64> {
+    (0..4000u64).map(|i| (i * 7919) % 1000).collect()
+}
+
+#[test]
+fn capacity_is_set_aside_for_growth() {
+    let exact = Dict::new(Mode::Byte, sample());
+    assert_eq!(exact.codes.capacity(), exact.codes.len());
+
+    let options = BuildOptions::default().with_capacity(127);
+    let mut dict = Dict::new_with_options(Mode::Byte, sample(), &options);
+    assert_eq!(dict.codes, exact.codes);
+    assert_eq!(dict.codes.capacity(), 127);
+    let before = dict.codes.as_ptr();
+    let appended = dict.extend_max(&[2000, 3000]);
+    assert_eq!(appended, 2);
+    assert_eq!(dict.codes.as_ptr(), before);
+    assert_eq!(dict.codes.capacity(), 127);
+
+    // A capacity below the codes needed changes nothing.
+    let small = Dict::new_with_options(
+        Mode::Byte,
+        sample(),
+        &BuildOptions::default().with_capacity(3),
+    );
+    assert_eq!(small.codes.capacity(), small.codes.len());
+}
+
+#[test]
+fn codes_live_in_caller_storage() {
+    let storage: Vec<u64> = Vec::with_capacity(200);
+    let at = storage.as_ptr();
+    let dict = Dict::new_with_storage(Mode::Byte, sample(), &BuildOptions::default(), storage);
+    assert_eq!(dict.codes, Dict::new(Mode::Byte, sample()).codes);
+    assert_eq!(
+        dict.encode(&500),
+        Dict::new(Mode::Byte, sample()).encode(&500)
+    );
+    let codes = dict.into_codes();
+    assert_eq!(codes.as_ptr(), at);
+    assert_eq!(codes.capacity(), 200);
+
+    // Leftovers are cleared, and too small a vector grows.
+    let dict = Dict::new_with_storage(
+        Mode::Byte,
+        sample(),
+        &BuildOptions::default().with_capacity(150),
+        vec![7; 3],
+    );
+    assert_eq!(dict.codes, Dict::new(Mode::Byte, sample()).codes);
+    assert!(dict.codes.capacity() >= 150);
+}
+
+#[test]
+fn caller_storage_is_written_in_place() {
+    let options = BuildOptions::default();
+    for sample in [sample(), (0..100u64).collect(), Vec::new()] {
+        let copy = sample.clone();
+        let (_, usual) = allocations(|| Dict::new_with_options(Mode::Byte, copy, &options));
+        let storage: Vec<u64> = Vec::with_capacity(200);
+        let (dict, in_place) =
+            allocations(|| Dict::new_with_storage(Mode::Byte, sample, &options, storage));
+        // Everything but the code vector is allocated as usual.
+        assert_eq!(in_place + 1, usual);
+        assert_eq!(dict.codes.capacity(), 200);
+    }
+}
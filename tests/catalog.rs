@@ -0,0 +1,118 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::catalog::{Catalog, CatalogEntry};
+use ordbog::format::FormatError;
+use ordbog::sketch::Sketch;
+use ordbog::sketchfile::SketchFile;
+use ordbog::{Code, Dict, Mode};
+
+fn sketch(mode: Mode, seed: u64) -> (Dict<u32>, Vec<u8>) {
+    let values: Vec<u32> = common::uniform(seed, 3_000, 0..50_000);
+    let dict = Dict::new(mode, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let file = SketchFile::new(
+        Dict::from_codes(mode, dict.codes.clone()),
+        Sketch::from_codes(mode, &codes),
+        500,
+    );
+    (dict, file.to_bytes())
+}
+
+fn catalog() -> (Catalog, Dict<u32>) {
+    let (old, a) = sketch(Mode::Byte, 1);
+    let (new, b) = sketch(Mode::Word, 2);
+    let (_, c) = sketch(Mode::Byte, 3);
+    let mut catalog = Catalog::new();
+    // Two sketches packed into one file, one in another.
+    let entries = [
+        CatalogEntry::for_sketch("seg-2", "price", "seg-2.obg", 0, &b).unwrap(),
+        CatalogEntry::for_sketch("seg-1", "price", "seg-1.obg", 0, &a).unwrap(),
+        CatalogEntry::for_sketch("seg-1", "qty", "seg-1.obg", a.len() as u64, &c).unwrap(),
+    ];
+    for e in entries.iter() {
+        assert!(catalog.insert(e.clone()).is_none());
+    }
+    assert_eq!(
+        catalog.get("seg-1", "price").unwrap().fingerprint,
+        old.fingerprint()
+    );
+    (catalog, new)
+}
+
+#[test]
+fn catalogs_look_up_sketches() {
+    let (mut catalog, new) = catalog();
+    assert_eq!(catalog.len(), 3);
+    let e = catalog.get("seg-2", "price").unwrap();
+    assert_eq!((e.mode, e.rows), (Mode::Word, 3000));
+    assert_eq!(e.fingerprint, new.fingerprint());
+    assert!(catalog.get("seg-2", "qty").is_none());
+
+    let columns: Vec<&str> = catalog
+        .segment("seg-1")
+        .map(|e| e.column.as_str())
+        .collect();
+    assert_eq!(columns, ["price", "qty"]);
+    let segments: Vec<&str> = catalog
+        .column("price")
+        .map(|e| e.segment.as_str())
+        .collect();
+    assert_eq!(segments, ["seg-1", "seg-2"]);
+    let stale: Vec<&str> = catalog
+        .stale("price", &new)
+        .map(|e| e.segment.as_str())
+        .collect();
+    assert_eq!(stale, ["seg-1"]);
+
+    let qty = catalog.get("seg-1", "qty").unwrap().clone();
+    assert_eq!(qty.offset, catalog.get("seg-1", "price").unwrap().len);
+    let moved = CatalogEntry {
+        path: "seg-1-qty.obg".to_string(),
+        offset: 0,
+        ..qty.clone()
+    };
+    assert_eq!(catalog.insert(moved), Some(qty));
+    assert!(catalog.remove("seg-2", "price").is_some());
+    assert!(catalog.remove("seg-2", "price").is_none());
+    assert_eq!(catalog.len(), 2);
+}
+
+#[test]
+fn catalogs_round_trip() {
+    let (catalog, _) = catalog();
+    let bytes = catalog.to_bytes();
+    assert_eq!(Catalog::from_bytes(&bytes).unwrap(), catalog);
+    assert_eq!(
+        Catalog::from_bytes(&Catalog::new().to_bytes()).unwrap(),
+        Catalog::new()
+    );
+
+    // Trailing bytes are room for additions.
+    let mut longer = bytes.clone();
+    longer.extend_from_slice(b"more");
+    assert_eq!(Catalog::from_bytes(&longer).unwrap(), catalog);
+
+    assert_eq!(
+        Catalog::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(FormatError::Truncated)
+    );
+    let mut bad = bytes.clone();
+    bad[0] = b'X';
+    assert_eq!(Catalog::from_bytes(&bad), Err(FormatError::BadMagic));
+    let mut bad = bytes;
+    bad[6] = 0x80;
+    assert!(matches!(
+        Catalog::from_bytes(&bad),
+        Err(FormatError::UnsupportedFlags(_))
+    ));
+
+    let path = std::env::temp_dir().join(format!("ordbog-catalog-{}.ordc", std::process::id()));
+    catalog.save(&path).unwrap();
+    assert_eq!(Catalog::load(&path).unwrap(), catalog);
+    Catalog::new().save(&path).unwrap();
+    assert!(Catalog::load(&path).unwrap().is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
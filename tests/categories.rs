@@ -0,0 +1,41 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{Code, Dict, Mode};
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+enum State {
+    #[default]
+    Unknown,
+    Pending,
+    Running,
+    Failed,
+    Done,
+}
+
+#[test]
+fn categories_get_exact_codes_in_order() {
+    use State::*;
+    let dict = Dict::from_categories(Mode::Byte, vec![Pending, Running, Done]);
+    assert_eq!(dict.encode(&Pending), Code(2));
+    assert_eq!(dict.encode(&Running), Code(4));
+    assert_eq!(dict.encode(&Done), Code(6));
+    // Values outside the set fall into the intervals around it.
+    assert_eq!(dict.encode(&Unknown), Code(1));
+    assert_eq!(dict.encode(&Failed), Code(5));
+
+    let names = vec!["blue".to_string(), "green".to_string(), "red".to_string()];
+    let dict = Dict::from_categories(Mode::Word, names);
+    assert_eq!(dict.encode(&"green".to_string()), Code(4));
+    assert_eq!(dict.encode(&"purple".to_string()), Code(5));
+    assert_eq!(dict.encode(&"yellow".to_string()), Code(7));
+
+    let empty: Dict<State> = Dict::from_categories(Mode::Byte, vec![]);
+    assert_eq!(empty.codes, vec![Unknown]);
+}
+
+#[test]
+#[should_panic(expected = "increasing")]
+fn categories_must_be_ordered() {
+    Dict::from_categories(Mode::Byte, vec![State::Done, State::Pending]);
+}
@@ -0,0 +1,53 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::predicate::CodePredicate;
+use ordbog::{Code, CodeRange, Dict, Mode};
+use std::ops::{Bound, RangeInclusive};
+
+#[test]
+fn code_ranges_iterate_and_count() {
+    let r = CodeRange::new(Code(3), Code(7));
+    let codes: Vec<u16> = r.iter().map(|c| c.0).collect();
+    assert_eq!(codes, [3, 4, 5, 6, 7]);
+    assert_eq!(r.len(), 5);
+    assert_eq!(r.iter().len(), 5);
+    assert_eq!(r.iter().rev().collect::<Vec<_>>()[0], Code(7));
+    assert_eq!(r.into_iter().filter(|c| c.is_exact()).count(), 2);
+    assert!(r.contains(Code(3)) && r.contains(Code(7)) && !r.contains(Code(8)));
+
+    let empty = CodeRange::new(Code(5), Code(4));
+    assert!(empty.is_empty());
+    assert_eq!(empty.len(), 0);
+    assert_eq!(empty.iter().next(), None);
+    assert!(!CodeRange::new(Code(5), Code(5)).is_empty());
+}
+
+#[test]
+fn code_ranges_reach_the_top_of_the_codespace() {
+    assert_eq!(Mode::Byte.codes().len(), 255);
+    let word = Mode::Word.codes();
+    assert_eq!(word.len(), 0xffff);
+    assert_eq!(word.iter().fold(0, |n, _| n + 1), 0xffff);
+    let mut it = CodeRange::new(Code(0xfffe), Code(0xffff)).iter();
+    assert_eq!(it.next(), Some(Code(0xfffe)));
+    assert_eq!(it.next_back(), Some(Code(0xffff)));
+    assert_eq!(it.next(), None);
+    assert_eq!(it.next_back(), None);
+}
+
+#[test]
+fn rewritten_ranges_can_be_walked() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30, 40]);
+    let pred = CodePredicate::from_range(&dict, &(Bound::Included(15), Bound::Excluded(40)));
+    let mut table = vec![0u8; 256];
+    for r in pred.definite.iter() {
+        for c in CodeRange::from(r.clone()) {
+            table[c.0 as usize] = 2;
+        }
+    }
+    let definite: Vec<usize> = (0..256).filter(|c| table[*c] == 2).collect();
+    assert_eq!(definite, [4, 5, 6, 7]);
+    let back: RangeInclusive<Code> = CodeRange::from(pred.definite[0].clone()).into();
+    assert_eq!(back, pred.definite[0]);
+}
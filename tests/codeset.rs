@@ -0,0 +1,124 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::codeset::CodeSet;
+use ordbog::predicate::{CodePredicate, CLASS_DEFINITE, CLASS_MAYBE};
+use ordbog::{Code, CodeRange, Dict, Mode};
+use std::ops::Bound;
+
+fn set(ranges: &[(u16, u16)]) -> CodeSet {
+    CodeSet::from_ranges(ranges.iter().map(|(lo, hi)| Code(*lo)..=Code(*hi)))
+}
+
+fn ranges(s: &CodeSet) -> Vec<(u16, u16)> {
+    s.ranges()
+        .iter()
+        .map(|r| (r.start().0, r.end().0))
+        .collect()
+}
+
+#[test]
+fn sets_merge_overlapping_and_adjacent_ranges() {
+    let s = set(&[(10, 12), (1, 3), (4, 5), (11, 20), (30, 29)]);
+    assert_eq!(ranges(&s), [(1, 5), (10, 20)]);
+    assert_eq!(s.len(), 16);
+    assert!(s.contains(Code(1)) && s.contains(Code(5)) && s.contains(Code(20)));
+    assert!(!s.contains(Code(0)) && !s.contains(Code(6)) && !s.contains(Code(21)));
+    assert_eq!(
+        s.iter().take(6).map(|c| c.0).collect::<Vec<_>>(),
+        [1, 2, 3, 4, 5, 10]
+    );
+
+    let codes = CodeSet::from_codes(&[Code(7), Code(3), Code(4), Code(7)]);
+    assert_eq!(ranges(&codes), [(3, 4), (7, 7)]);
+    assert!(CodeSet::new().is_empty());
+    assert_eq!(
+        CodeSet::from(CodeRange::new(Code(2), Code(1))),
+        CodeSet::new()
+    );
+}
+
+#[test]
+fn sets_combine() {
+    let a = set(&[(1, 10), (20, 30)]);
+    let b = set(&[(5, 25), (40, 40)]);
+    assert_eq!(ranges(&a.union(&b)), [(1, 30), (40, 40)]);
+    assert_eq!(ranges(&a.intersection(&b)), [(5, 10), (20, 25)]);
+    assert_eq!(ranges(&a.difference(&b)), [(1, 4), (26, 30)]);
+    assert_eq!(ranges(&b.difference(&a)), [(11, 19), (40, 40)]);
+    assert!(a.intersection(&CodeSet::new()).is_empty());
+    assert_eq!(a.difference(&CodeSet::new()), a);
+
+    // Every code of each operation against membership in the operands.
+    for c in 0..64 {
+        let c = Code(c);
+        assert_eq!(a.union(&b).contains(c), a.contains(c) || b.contains(c));
+        assert_eq!(
+            a.intersection(&b).contains(c),
+            a.contains(c) && b.contains(c)
+        );
+        assert_eq!(
+            a.difference(&b).contains(c),
+            a.contains(c) && !b.contains(c)
+        );
+    }
+}
+
+#[test]
+fn complements_span_the_codespace_from_one() {
+    assert_eq!(ranges(&CodeSet::new().complement(Mode::Byte)), [(1, 0xff)]);
+    assert_eq!(
+        CodeSet::all(Mode::Word).complement(Mode::Word),
+        CodeSet::new()
+    );
+    let s = set(&[(0, 3), (10, 0xff)]);
+    assert_eq!(ranges(&s.complement(Mode::Byte)), [(4, 9)]);
+    assert_eq!(ranges(&s.complement(Mode::Word)), [(4, 9), (0x100, 0xffff)]);
+    let top = set(&[(0xfff0, 0xffff)]);
+    assert_eq!(ranges(&top.complement(Mode::Word)), [(1, 0xffef)]);
+    assert_eq!(top.complement(Mode::Word).complement(Mode::Word), top);
+    assert_eq!(
+        ranges(&set(&[(0, 0xffff)]).difference(&set(&[(0, 0), (0xffff, 0xffff)]))),
+        [(1, 0xfffe)]
+    );
+}
+
+#[test]
+fn predicates_round_trip_through_sets() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30, 40]);
+    let pred = CodePredicate::from_range(&dict, &(Bound::Excluded(10), Bound::Included(35)));
+    let definite = pred.definite_set();
+    let candidates = pred.candidate_set();
+    assert_eq!(candidates.ranges(), &pred.candidates()[..]);
+    let back = CodePredicate::from_sets(&definite, &candidates);
+    assert_eq!(back.definite_set(), definite);
+    assert_eq!(back.candidate_set(), candidates);
+
+    let compiled = back.compile(Mode::Byte);
+    assert_eq!(compiled.class_set(CLASS_DEFINITE), definite);
+    assert_eq!(
+        compiled.class_set(CLASS_MAYBE),
+        candidates.difference(&definite)
+    );
+
+    // A conjunction with x <= 20 through set intersection.
+    let other = CodePredicate::from_range(&dict, &(Bound::Unbounded, Bound::Included(20)));
+    let both = CodePredicate::from_sets(
+        &definite.intersection(&other.definite_set()),
+        &candidates.intersection(&other.candidate_set()),
+    );
+    assert_eq!(ranges(&both.definite_set()), [(3, 4)]);
+    assert_eq!(ranges(&both.candidate_set()), [(3, 4)]);
+}
+
+#[test]
+fn in_lists_rewrite_to_sets() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    let pred = CodePredicate::from_in_list(&dict, &[30, 10, 15, 17, 99]);
+    assert_eq!(ranges(&pred.definite_set()), [(2, 2), (6, 6)]);
+    assert_eq!(ranges(&pred.candidate_set()), [(2, 3), (6, 7)]);
+    assert!(pred.is_definite(Code(2)) && pred.is_maybe(Code(3)));
+    assert!(CodePredicate::from_in_list(&dict, &[])
+        .candidate_set()
+        .is_empty());
+}
@@ -0,0 +1,72 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::column::{CodeColumn, ColumnError, ValidateOptions};
+use ordbog::predicate::CodePredicate;
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+
+#[test]
+fn null_slots_are_never_selected() {
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let column = CodeColumn::from_codes(vec![Code(2), Code(0), Code(7)]);
+    assert!(column.is_null(1) && !column.is_null(0));
+    assert_eq!(column.null_count(), 1);
+    let p = CodePredicate::from_range(&dict, &(Unbounded, Unbounded));
+    assert_eq!(column.candidates(&p), vec![true, false, true]);
+    assert_eq!(column.definite(&p), vec![true, false, true]);
+    assert_eq!(CodeColumn::from_codes(vec![Code(2)]).validity, None);
+}
+
+#[test]
+fn validate_flags_corrupt_columns() {
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let max = Code(2 * dict.codes.len() as u16 + 1);
+    let codes: Vec<Code> = [5, 10, 0, 25, 35].iter().map(|v| dict.encode(v)).collect();
+    let mut column = CodeColumn::from_codes(codes);
+    column.codes[2] = Code(0);
+    column.validity = Some(vec![true, true, false, true, true]);
+    assert_eq!(column.validate(&dict), Ok(()));
+
+    let mut bad = column.clone();
+    bad.codes[2] = Code(3);
+    assert_eq!(
+        bad.validate(&dict),
+        Err(ColumnError::NullWithCode {
+            slot: 2,
+            code: Code(3)
+        })
+    );
+    let mut bad = column.clone();
+    bad.codes[1] = Code(0);
+    assert_eq!(bad.validate(&dict), Err(ColumnError::ZeroCode { slot: 1 }));
+    let mut bad = column.clone();
+    bad.codes[4] = Code(max.0 + 1);
+    assert_eq!(
+        bad.validate(&dict),
+        Err(ColumnError::OutOfRange {
+            slot: 4,
+            code: Code(max.0 + 1),
+            max
+        })
+    );
+    let mut bad = column.clone();
+    bad.validity = Some(vec![true]);
+    assert_eq!(
+        bad.validate(&dict),
+        Err(ColumnError::ValidityLength {
+            codes: 5,
+            validity: 1
+        })
+    );
+
+    let sorted = ValidateOptions { sorted: true };
+    assert_eq!(column.validate_with_options(&dict, &sorted), Ok(()));
+    let mut bad = column;
+    bad.codes.swap(0, 1);
+    assert!(bad.validate(&dict).is_ok());
+    assert!(matches!(
+        bad.validate_with_options(&dict, &sorted),
+        Err(ColumnError::Unsorted { slot: 1, .. })
+    ));
+}
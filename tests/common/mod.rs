@@ -0,0 +1,68 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+//! Sample shapes shared by the integration tests, drawn from seeded
+//! generators so every run sees the same values. Each test binary uses only
+//! some of them.
+
+#![allow(dead_code)]
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rand_distr::uniform::SampleUniform;
+use rand_distr::{Bernoulli, Distribution, Uniform};
+use std::ops::Range;
+
+/// A generator seeded with `seed`, for tests drawing their own shapes.
+pub fn rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// `n` values drawn uniformly from `range`.
+pub fn uniform<T: SampleUniform>(seed: u64, n: usize, range: Range<T>) -> Vec<T> {
+    Uniform::from(range)
+        .sample_iter(rng(seed))
+        .take(n)
+        .collect()
+}
+
+/// `n` values drawn uniformly from `range`, sorted, as in a column whose
+/// zone map can rule out blocks.
+pub fn sorted_uniform<T: SampleUniform + Ord>(seed: u64, n: usize, range: Range<T>) -> Vec<T> {
+    let mut values = uniform(seed, n, range);
+    values.sort_unstable();
+    values
+}
+
+/// `n` values, each with probability `share` one of the `heavy` values and
+/// otherwise drawn uniformly from `background`.
+pub fn heavy_hitters(
+    seed: u64,
+    n: usize,
+    share: f64,
+    heavy: &[u64],
+    background: Range<u64>,
+) -> Vec<u64> {
+    let mut rng = rng(seed);
+    let is_heavy = Bernoulli::new(share).unwrap();
+    let which = Uniform::new(0, heavy.len());
+    let background = Uniform::from(background);
+    (0..n)
+        .map(|_| {
+            if is_heavy.sample(&mut rng) {
+                heavy[which.sample(&mut rng)]
+            } else {
+                background.sample(&mut rng)
+            }
+        })
+        .collect()
+}
+
+/// The common values of a [skewed] sample.
+pub const COMMON_VALUES: [u64; 4] = [0, 1000, 2000, 3000];
+
+/// `n` values, a `share` of them among [COMMON_VALUES] and the rest spread
+/// uniformly over `0..100_000`.
+pub fn skewed(seed: u64, n: usize, share: f64) -> Vec<u64> {
+    heavy_hitters(seed, n, share, &COMMON_VALUES, 0..100_000)
+}
@@ -0,0 +1,146 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Schedule-exploring tests of SwappableDict under shuttle. Every thread
+// yields to the scheduler between operations, so each run tries a different
+// interleaving of encodes, scans, publishes and the release of old
+// dictionaries; a failing schedule is reported with a seed that replays it.
+// Operations are the unit of interleaving: the swap itself is arc-swap's,
+// which has its own model-checked tests. Hooks run under the writers' std
+// mutex, so they must not reach a shuttle scheduling point: the scheduler
+// would switch to a thread blocked on that mutex and hang.
+
+#![cfg(feature = "swap")]
+
+use ordbog::predicate::CodePredicate;
+use ordbog::{Code, Dict, Mode, SwappableDict};
+use shuttle::thread;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, Weak};
+
+const ITERATIONS: usize = 1000;
+
+// Generation `g`'s dictionary: exact codes for 10g and 10g + 5, so a value's
+// code tells which generation encoded it.
+fn generation(g: u32) -> Dict<u32> {
+    Dict::from_codes(Mode::Byte, vec![10 * g, 10 * g + 5])
+}
+
+fn generation_of(dict: &Dict<u32>) -> u32 {
+    dict.codes[0] / 10
+}
+
+#[test]
+fn snapshots_stay_consistent_across_publishes() {
+    shuttle::check_random(
+        || {
+            let slot = Arc::new(SwappableDict::new(generation(0)));
+            let readers: Vec<_> = (0..2)
+                .map(|_| {
+                    let slot = slot.clone();
+                    thread::spawn(move || {
+                        let mut last = 0;
+                        for _ in 0..4 {
+                            let d = slot.load_full();
+                            let g = generation_of(&d);
+                            // Loads never go back in time.
+                            assert!(g >= last);
+                            last = g;
+                            thread::yield_now();
+                            // Both ends of a range, encoded through one
+                            // snapshot, agree even if a publish intervened.
+                            let pred = (Bound::Included(10 * g), Bound::Included(10 * g + 5));
+                            let codes = [d.encode(&(10 * g)), d.encode(&(10 * g + 5))];
+                            let compiled = CodePredicate::from_range(&d, &pred).compile(d.mode);
+                            let mut out = [0; 2];
+                            compiled.classify_into(&codes, &mut out);
+                            assert_eq!(codes, [Code(2), Code(4)]);
+                            assert_eq!(out, [2, 2]);
+                            thread::yield_now();
+                        }
+                    })
+                })
+                .collect();
+            for g in 1..4 {
+                slot.publish(generation(g));
+                thread::yield_now();
+            }
+            for r in readers {
+                r.join().unwrap();
+            }
+            assert_eq!(generation_of(&slot.load()), 3);
+        },
+        ITERATIONS,
+    );
+}
+
+#[test]
+fn concurrent_publishes_form_one_chain() {
+    shuttle::check_random(
+        || {
+            let slot = Arc::new(SwappableDict::new(generation(0)));
+            let swaps = Arc::new(Mutex::new(Vec::new()));
+            let writers: Vec<_> = (0..2u32)
+                .map(|w| {
+                    let (slot, swaps) = (slot.clone(), swaps.clone());
+                    thread::spawn(move || {
+                        for i in 0..3 {
+                            // Writers publish distinct generations.
+                            let g = 1 + w + 2 * i;
+                            let (old, ()) = slot.publish_with(generation(g), |old, new| {
+                                swaps
+                                    .lock()
+                                    .unwrap()
+                                    .push((generation_of(old), generation_of(new)));
+                            });
+                            assert_ne!(generation_of(&old), g);
+                            thread::yield_now();
+                        }
+                    })
+                })
+                .collect();
+            for w in writers {
+                w.join().unwrap();
+            }
+            // Each hook saw exactly the pair being swapped: every swap
+            // replaced what the one before installed.
+            let swaps = swaps.lock().unwrap();
+            assert_eq!(swaps.len(), 6);
+            assert_eq!(swaps[0].0, 0);
+            for pair in swaps.windows(2) {
+                assert_eq!(pair[0].1, pair[1].0);
+            }
+            assert_eq!(generation_of(&slot.load()), swaps[5].1);
+        },
+        ITERATIONS,
+    );
+}
+
+#[test]
+fn replaced_dictionaries_are_freed_after_their_last_reader() {
+    shuttle::check_random(
+        || {
+            let slot = Arc::new(SwappableDict::new(generation(0)));
+            let first: Weak<Dict<u32>> = Arc::downgrade(&slot.load_full());
+            let reader = {
+                let slot = slot.clone();
+                thread::spawn(move || {
+                    let d = slot.load_full();
+                    thread::yield_now();
+                    let code = d.encode(&(10 * generation_of(&d)));
+                    assert_eq!(code, Code(2));
+                    generation_of(&d)
+                })
+            };
+            let old = slot.publish(generation(1));
+            assert_eq!(generation_of(&old), 0);
+            drop(old);
+            thread::yield_now();
+            let seen = reader.join().unwrap();
+            assert!(seen <= 1);
+            // Nothing holds generation 0 any more, whichever the reader saw.
+            assert!(first.upgrade().is_none());
+        },
+        ITERATIONS,
+    );
+}
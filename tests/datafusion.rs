@@ -0,0 +1,181 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "datafusion")]
+
+use datafusion_common::arrow::array::{
+    Array, ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, UInt16Array, UInt8Array,
+};
+use datafusion_common::arrow::datatypes::{DataType, Field, Schema};
+use datafusion_common::ScalarValue;
+use datafusion_expr::Operator;
+use datafusion_physical_expr::expressions::{binary, col, in_list, lit, not};
+use datafusion_physical_expr::PhysicalExpr;
+use ordbog::datafusion::CodeRewriter;
+use ordbog::{Dict, Mode};
+use std::sync::Arc;
+
+fn values() -> Vec<Option<i64>> {
+    (0..2000i64)
+        .map(|i| {
+            if i % 13 == 0 {
+                None
+            } else {
+                Some((i * 7919) % 1000)
+            }
+        })
+        .collect()
+}
+
+fn batch(dict: &Dict<i64>) -> RecordBatch {
+    let values = values();
+    let codes: UInt8Array = values
+        .iter()
+        .map(|v| v.map(|v| dict.encode(&v).0 as u8))
+        .collect();
+    let schema = Schema::new(vec![
+        Field::new("v", DataType::Int64, true),
+        Field::new("v_code", DataType::UInt8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![Arc::new(Int64Array::from(values)), Arc::new(codes)];
+    RecordBatch::try_new(Arc::new(schema), columns).unwrap()
+}
+
+fn evaluate(expr: &Arc<dyn PhysicalExpr>, batch: &RecordBatch) -> BooleanArray {
+    let array = expr
+        .evaluate(batch)
+        .unwrap()
+        .into_array(batch.num_rows())
+        .unwrap();
+    array
+        .as_any()
+        .downcast_ref::<BooleanArray>()
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn rewritten_filters_match_the_originals() {
+    let dict = Dict::new(Mode::Byte, values().into_iter().flatten().collect());
+    let batch = batch(&dict);
+    let schema = batch.schema();
+    let mut rewriter = CodeRewriter::new();
+    rewriter.add_column("v", "v_code", dict);
+
+    let v = || col("v", &schema).unwrap();
+    let cmp = |op, x: i64| binary(v(), op, lit(x), &schema).unwrap();
+    let exprs: Vec<Arc<dyn PhysicalExpr>> = vec![
+        cmp(Operator::Eq, 42),
+        cmp(Operator::Eq, 1234),
+        cmp(Operator::Lt, 300),
+        cmp(Operator::LtEq, 0),
+        cmp(Operator::Gt, 999),
+        binary(lit(500i64), Operator::GtEq, v(), &schema).unwrap(),
+        binary(
+            cmp(Operator::GtEq, 100),
+            Operator::And,
+            cmp(Operator::Lt, 200),
+            &schema,
+        )
+        .unwrap(),
+        in_list(
+            v(),
+            vec![lit(5i64), lit(7i64), lit(5000i64)],
+            &false,
+            &schema,
+        )
+        .unwrap(),
+        not(cmp(Operator::Eq, 42)).unwrap(),
+        not(cmp(Operator::Eq, 1234)).unwrap(),
+        not(cmp(Operator::Lt, 300)).unwrap(),
+    ];
+    for expr in exprs {
+        let rewritten = rewriter.rewrite(expr.clone(), &schema).unwrap();
+        assert!(format!("{}", rewritten).contains("v_code"), "{}", rewritten);
+        assert_eq!(
+            evaluate(&rewritten, &batch),
+            evaluate(&expr, &batch),
+            "{}",
+            rewritten
+        );
+    }
+}
+
+#[test]
+fn leaves_unsupported_comparisons_alone() {
+    let dict = Dict::new(Mode::Byte, values().into_iter().flatten().collect());
+    let batch = batch(&dict);
+    let schema = batch.schema();
+    let mut rewriter = CodeRewriter::new();
+    rewriter.add_column("v", "v_code", dict);
+
+    let v = col("v", &schema).unwrap();
+    let exprs: Vec<Arc<dyn PhysicalExpr>> = vec![
+        binary(v.clone(), Operator::NotEq, lit(42i64), &schema).unwrap(),
+        binary(
+            v.clone(),
+            Operator::Eq,
+            lit(ScalarValue::Int64(None)),
+            &schema,
+        )
+        .unwrap(),
+        in_list(v.clone(), vec![lit(5i64)], &true, &schema).unwrap(),
+        binary(
+            col("v_code", &schema).unwrap(),
+            Operator::Eq,
+            lit(3u8),
+            &schema,
+        )
+        .unwrap(),
+    ];
+    for expr in exprs {
+        let rewritten = rewriter.rewrite(expr.clone(), &schema).unwrap();
+        assert_eq!(format!("{}", rewritten), format!("{}", expr));
+    }
+}
+
+#[test]
+fn rewrites_string_columns_onto_wide_codes() {
+    let words: Vec<String> = (0..3000)
+        .map(|i| format!("w{:04}", (i * 37) % 2000))
+        .collect();
+    let dict = Dict::new(Mode::Word, words.clone());
+    let rows: Vec<Option<&str>> = words
+        .iter()
+        .enumerate()
+        .map(|(i, w)| if i % 11 == 0 { None } else { Some(w.as_str()) })
+        .collect();
+    let codes: UInt16Array = rows
+        .iter()
+        .map(|w| w.map(|w| dict.encode(&w.to_string()).0))
+        .collect();
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("w", DataType::Utf8, true),
+        Field::new("w_code", DataType::UInt16, true),
+    ]));
+    let columns: Vec<ArrayRef> = vec![Arc::new(StringArray::from(rows)), Arc::new(codes)];
+    let batch = RecordBatch::try_new(schema.clone(), columns).unwrap();
+    let mut rewriter = CodeRewriter::new();
+    rewriter.add_column("w", "w_code", dict);
+
+    let w = || col("w", &schema).unwrap();
+    let exprs: Vec<Arc<dyn PhysicalExpr>> = vec![
+        binary(w(), Operator::Eq, lit("w0123"), &schema).unwrap(),
+        binary(w(), Operator::Lt, lit("w0500"), &schema).unwrap(),
+        in_list(w(), vec![lit("w0001"), lit("zzz")], &false, &schema).unwrap(),
+    ];
+    for expr in exprs {
+        let rewritten = rewriter.rewrite(expr.clone(), &schema).unwrap();
+        assert!(format!("{}", rewritten).contains("w_code"), "{}", rewritten);
+        assert_eq!(evaluate(&rewritten, &batch), evaluate(&expr, &batch));
+    }
+}
+
+#[test]
+fn missing_code_columns_are_plan_errors() {
+    let schema = Schema::new(vec![Field::new("v", DataType::Int64, true)]);
+    let mut rewriter = CodeRewriter::new();
+    rewriter.add_column("v", "v_code", Dict::new(Mode::Byte, vec![1i64, 2, 3]));
+    let expr = binary(col("v", &schema).unwrap(), Operator::Eq, lit(2i64), &schema).unwrap();
+    assert!(rewriter.rewrite(expr, &schema).is_err());
+}
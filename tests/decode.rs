@@ -0,0 +1,58 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::predicate::CodePredicate;
+use ordbog::{Code, Dict, Mode, ValueRange};
+use std::ops::Bound::*;
+
+#[test]
+fn codes_decode_to_their_values() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    assert_eq!(dict.decode(Code(4)), Some(ValueRange::Exact(20)));
+    assert_eq!(
+        dict.decode(Code(1)),
+        Some(ValueRange::Between {
+            lower: None,
+            upper: Some(10)
+        })
+    );
+    assert_eq!(
+        dict.decode(Code(5)),
+        Some(ValueRange::Between {
+            lower: Some(20),
+            upper: Some(30)
+        })
+    );
+    assert_eq!(
+        dict.decode(Code(7)),
+        Some(ValueRange::Between {
+            lower: Some(30),
+            upper: None
+        })
+    );
+    assert_eq!(dict.decode(Code(0)), None);
+    assert_eq!(dict.decode(Code(8)), None);
+    assert_eq!(
+        dict.decode(Code(5)).unwrap().to_predicate(),
+        (Excluded(20), Excluded(30))
+    );
+    assert_eq!(
+        dict.decode(Code(1)).unwrap().to_predicate(),
+        (Unbounded, Excluded(10))
+    );
+}
+
+#[test]
+fn decoded_ranges_hold_what_encodes_to_them() {
+    let sample: Vec<u32> = (0..5000).map(|i| (i * 7919) % 3001).collect();
+    let dict = Dict::new(Mode::Byte, sample);
+    for v in 0..3100u32 {
+        let code = dict.encode(&v);
+        let range = dict.decode(code).unwrap();
+        assert!(range.contains(&v));
+        // The range rewrites back onto exactly its own code.
+        let pred = CodePredicate::from_range(&dict, &range.to_predicate());
+        assert_eq!(pred.definite, vec![code..=code]);
+        assert!(pred.maybe.is_empty());
+    }
+}
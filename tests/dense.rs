@@ -0,0 +1,75 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::archive::ArchivedDict;
+use ordbog::format::{Header, FLAG_DENSE_CODES, FLAG_RESERVED_CODES};
+use ordbog::{BuildOptions, Code, DenseCode, Dict, Mode};
+
+#[test]
+fn dense_codes_start_at_zero() {
+    let sample: Vec<u32> = (0..10_000).map(|i| (i * 7919) % 5_000).collect();
+    let options = BuildOptions {
+        dense_codes: true,
+        ..BuildOptions::default()
+    };
+    let dict = Dict::new_with_options(Mode::Byte, sample.clone(), &options);
+    assert_eq!(dict.encode_dense(&0), DenseCode(1));
+    let top = DenseCode(2 * dict.codes.len() as u16);
+    assert_eq!(dict.encode_dense(&u32::MAX), top);
+    for v in sample.iter() {
+        let (c, d) = (dict.encode(v), dict.encode_dense(v));
+        assert_eq!(c.is_exact(), d.is_exact());
+        assert_eq!(c.to_dense(), Some(d));
+        assert_eq!(d.to_code(), Some(c));
+    }
+    assert_eq!(Code(0).to_dense(), None);
+    assert_eq!(DenseCode(0xffff).to_code(), None);
+}
+
+#[test]
+fn dense_codes_recover_an_exact_code() {
+    let sample: Vec<u32> = (0..10_000).collect();
+    for mode in [Mode::Byte, Mode::Word] {
+        let sparse = BuildOptions {
+            reserved_codes: 1,
+            ..BuildOptions::default()
+        };
+        let dense = BuildOptions {
+            dense_codes: true,
+            ..sparse
+        };
+        assert_eq!(sparse.num_exact_codes(mode) + 1, mode.num_exact_codes());
+        assert_eq!(dense.num_exact_codes(mode), mode.num_exact_codes());
+
+        let dict = Dict::new_with_options(mode, sample.clone(), &dense);
+        let max = mode.max_inexact_code().0;
+        assert_eq!(dict.reserved_codes(), None);
+        assert_eq!(
+            dict.reserved_dense_codes(),
+            Some((DenseCode(max), DenseCode(max)))
+        );
+        assert!(dict.encode_dense(&u32::MAX) < DenseCode(max));
+    }
+}
+
+#[test]
+fn dense_layout_survives_serialization() {
+    let sample: Vec<u32> = (0..10_000).collect();
+    let options = BuildOptions {
+        reserved_codes: 1,
+        dense_codes: true,
+        ..BuildOptions::default()
+    };
+    let dict = Dict::new_with_options(Mode::Byte, sample, &options);
+    let bytes = dict.to_bytes();
+    let header = Header::read(&bytes).unwrap();
+    assert_eq!(header.flags, FLAG_RESERVED_CODES | FLAG_DENSE_CODES);
+    let read = Dict::<u32>::from_bytes(&bytes).unwrap();
+    let archived = ArchivedDict::<u32>::new(&bytes).unwrap().to_dict();
+    for back in [read, archived] {
+        assert_eq!(back.reserved_codes(), None);
+        assert_eq!(back.reserved_dense_codes(), dict.reserved_dense_codes());
+        assert_eq!(back.num_exact_codes(), Mode::Byte.num_exact_codes());
+        assert_eq!(back.codes, dict.codes);
+    }
+}
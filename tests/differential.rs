@@ -0,0 +1,178 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Differential tests of the crate's optimized paths against the naive
+// reference implementations.
+
+use ordbog::hashed::HashDict;
+use ordbog::memo::EncodeMemo;
+use ordbog::predicate::{CodePredicate, CLASS_DEFINITE, CLASS_MAYBE, CLASS_NONE};
+use ordbog::reference as oracle;
+use ordbog::scan::{scan_chunks, scan_conjunction, WordKernel};
+use ordbog::workload::RangePredicate;
+use ordbog::{Code, Dict, Mode};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::ops::Bound;
+
+fn mode() -> impl Strategy<Value = Mode> {
+    prop_oneof![Just(Mode::Byte), Just(Mode::Packed), Just(Mode::Word)]
+}
+
+fn bound() -> impl Strategy<Value = Bound<u32>> {
+    prop_oneof![
+        (0..600u32).prop_map(Bound::Included),
+        (0..600u32).prop_map(Bound::Excluded),
+        Just(Bound::Unbounded),
+    ]
+}
+
+// Predicates some value could satisfy, so that "none" means the same to the
+// crate, which can't tell an empty predicate from a narrow one, and to the
+// oracle.
+fn predicate() -> impl Strategy<Value = RangePredicate<u32>> {
+    (bound(), bound()).prop_filter("empty predicate", |(lo, hi)| match (lo, hi) {
+        (Bound::Included(l), Bound::Included(h)) => l <= h,
+        (Bound::Included(l), Bound::Excluded(h)) | (Bound::Excluded(l), Bound::Included(h)) => {
+            l < h
+        }
+        // Leave room for a value strictly between.
+        (Bound::Excluded(l), Bound::Excluded(h)) => l < h,
+        _ => true,
+    })
+}
+
+// Samples over a domain small enough for repeats, and for queries and
+// predicates to land on exact values.
+fn sample() -> impl Strategy<Value = Vec<u32>> {
+    vec(0..500u32, 0..3000)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn encoding_matches_the_oracle(mode in mode(), sample in sample(),
+                                   queries in vec(0..600u32, 1..200)) {
+        let dict = Dict::new(mode, sample);
+        let mut memo = EncodeMemo::new(&dict, 4);
+        for q in queries.iter().chain(queries.iter().rev()) {
+            let expected = oracle::encode(&dict.codes, q);
+            prop_assert_eq!(dict.encode(q), expected);
+            prop_assert_eq!(memo.encode(q), expected);
+            prop_assert_eq!(dict.encode_signed(q), mode.to_signed(expected));
+            prop_assert_eq!(dict.encode_dense(q).0, expected.0 - 1);
+        }
+        let mut sorted = queries.clone();
+        sorted.sort_unstable();
+        for qs in [queries, sorted].iter() {
+            let expected: Vec<Code> = qs.iter().map(|q| oracle::encode(&dict.codes, q)).collect();
+            prop_assert_eq!(dict.encode_slice(qs), expected);
+        }
+    }
+
+    #[test]
+    fn hashing_matches_the_oracle(mode in mode(), sample in sample(),
+                                  queries in vec(0..600u32, 1..200)) {
+        let dict = HashDict::new(mode, sample);
+        let max = mode.max_inexact_code();
+        for q in queries.iter() {
+            let code = dict.encode(q);
+            // Linear, rather than through the map.
+            let unique = dict.unique.iter().find(|(v, _)| *v == q).map(|(_, c)| *c);
+            match unique {
+                Some(c) => prop_assert_eq!(code, c),
+                None => prop_assert!(!code.is_exact() && code <= max),
+            }
+        }
+        let codes: Vec<Code> = queries.iter().map(|q| dict.encode(q)).collect();
+        let pred = dict.rewrite_in(&queries);
+        for c in codes.iter() {
+            prop_assert_eq!(pred.is_definite(*c), c.is_exact());
+            prop_assert_eq!(pred.is_maybe(*c), !c.is_exact());
+        }
+        prop_assert_eq!(pred.candidate_set().len(), {
+            let mut distinct = codes.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            distinct.len()
+        });
+    }
+
+    #[test]
+    fn compiled_predicates_match_the_oracle(mode in mode(), sample in sample(),
+                                            pred in predicate()) {
+        let dict = Dict::new(mode, sample);
+        let code_pred = CodePredicate::from_range(&dict, &pred);
+        let compiled = code_pred.compile(mode);
+        for (c, class) in compiled.table.iter().enumerate() {
+            let code = Code(c as u16);
+            prop_assert_eq!(*class, oracle::classify(&dict, &pred, code), "code {}", c);
+            let scalar = if code_pred.is_definite(code) {
+                CLASS_DEFINITE
+            } else if code_pred.is_maybe(code) {
+                CLASS_MAYBE
+            } else {
+                CLASS_NONE
+            };
+            prop_assert_eq!(*class, scalar);
+            if let Some(t) = compiled.candidates {
+                prop_assert_eq!(t.contains(code.0), *class != CLASS_NONE);
+            }
+            if let Some(t) = compiled.definite {
+                prop_assert_eq!(t.contains(code.0), *class == CLASS_DEFINITE);
+            }
+        }
+    }
+
+    #[test]
+    fn scans_match_the_oracle(mode in mode(), sample in sample(),
+                              pred in predicate(), other in predicate(),
+                              block_rows in 1..300usize) {
+        let dict = Dict::new(mode, sample.clone());
+        let codes: Vec<Code> = sample.iter().map(|v| dict.encode(v)).collect();
+        let shifted: Vec<Code> = sample.iter().rev().map(|v| dict.encode(v)).collect();
+        let expected = oracle::scan(&dict, &pred, &codes);
+
+        let compiled = CodePredicate::from_range(&dict, &pred).compile(mode);
+        let mut out = vec![0xff; codes.len()];
+        compiled.classify_into(&codes, &mut out);
+        prop_assert_eq!(&out, &expected);
+        let candidates: Vec<bool> = expected.iter().map(|c| *c != CLASS_NONE).collect();
+        prop_assert_eq!(compiled.candidates(&codes), candidates);
+
+        if let Some(kernel) = WordKernel::new(&compiled) {
+            let words: Vec<u16> = codes.iter().map(|c| c.0).collect();
+            let mut out = vec![0xff; words.len()];
+            kernel.classify_into(&words, &mut out);
+            prop_assert_eq!(&out, &expected);
+        }
+
+        let other_compiled = CodePredicate::from_range(&dict, &other).compile(mode);
+        prop_assert_eq!(
+            scan_conjunction(&[(&compiled, &codes), (&other_compiled, &shifted)]),
+            oracle::conjunction(&[expected.clone(), oracle::scan(&dict, &other, &shifted)])
+        );
+
+        prop_assert_eq!(
+            scan_chunks(&compiled, &codes, block_rows),
+            oracle::scan_blocks(&dict, &pred, &codes, block_rows)
+        );
+    }
+}
+
+// The oracle itself, on cases worked by hand.
+#[test]
+fn the_oracle_agrees_with_the_definition_of_codes() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    let codes: Vec<u16> = [0u32, 10, 15, 20, 30, 31]
+        .iter()
+        .map(|v| oracle::encode(&dict.codes, v).0)
+        .collect();
+    assert_eq!(codes, [1, 2, 3, 4, 6, 7]);
+    let pred = (Bound::Excluded(10), Bound::Included(25));
+    let classes: Vec<u8> = (0..9)
+        .map(|c| oracle::classify(&dict, &pred, Code(c)))
+        .collect();
+    assert_eq!(classes, [0, 0, 0, 2, 2, 1, 0, 0, 0]);
+}
@@ -0,0 +1,63 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::equidepth::EquiDepthDict;
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+use std::ops::RangeBounds;
+
+fn readings() -> Vec<u64> {
+    common::uniform(11, 20_000, 0..1 << 24)
+}
+
+#[test]
+fn equidepth_doubles_range_resolution() {
+    let sample = readings();
+    let dict = EquiDepthDict::new(Mode::Byte, sample.clone());
+    let plain = Dict::new(Mode::Byte, sample.clone());
+    assert_eq!(dict.max_code(), Code(255));
+    assert!(dict.max_code().0 as usize > 2 * plain.codes.len());
+
+    let mut counts = vec![0usize; 256];
+    for v in sample.iter() {
+        counts[dict.encode(v).0 as usize] += 1;
+    }
+    assert_eq!(counts[0], 0);
+    let (min, max) = (counts[1..].iter().min(), counts[1..].iter().max());
+    assert!(*max.unwrap() < 2 * *min.unwrap());
+    assert_eq!(dict.encode(&0), Code(1));
+    assert_eq!(dict.encode(&u64::MAX), Code(255));
+}
+
+#[test]
+fn equidepth_rewrites_are_conservative() {
+    let sample = readings();
+    let dict = EquiDepthDict::new(Mode::Byte, sample.clone());
+    let b = dict.boundaries[40];
+    let preds = [
+        (Included(b), Excluded(dict.boundaries[90])),
+        (Excluded(b), Included(b + 100_000)),
+        (Unbounded, Excluded(b + 1)),
+        (Included(b + 1), Unbounded),
+        (Included(b), Included(b)),
+        (Included(b + 5), Excluded(b + 1)),
+    ];
+    for pred in preds.iter() {
+        let rewrite = dict.rewrite(pred);
+        for v in sample.iter() {
+            let c = dict.encode(v);
+            let sat = pred.contains(v);
+            if rewrite.is_definite(c) {
+                assert!(sat, "{:?} {:?}", pred, v);
+            }
+            if sat {
+                assert!(rewrite.is_definite(c) || rewrite.is_maybe(c));
+            }
+        }
+    }
+    let whole = dict.rewrite(&(Included(b), Excluded(dict.boundaries[90])));
+    assert!(whole.maybe.is_empty());
+    assert_eq!(whole.definite, vec![Code(42)..=Code(91)]);
+}
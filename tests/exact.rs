@@ -0,0 +1,47 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::predicate::CodePredicate;
+use ordbog::{CardinalityError, Dict, Mode};
+use std::ops::Bound::*;
+
+#[test]
+fn exact_dictionaries_have_no_false_positives() {
+    let column: Vec<u32> = (0..10_000).map(|i| (i * 7919) % 120 * 5).collect();
+    let dict = Dict::new_exact(Mode::Byte, column.clone()).unwrap();
+    assert!(dict.is_exact_complete());
+    assert_eq!(dict.codes.len(), 120);
+    assert!(!Dict::new(Mode::Byte, column.clone()).is_exact_complete());
+
+    for (lo, hi) in [(0, 0), (3, 17), (100, 450), (590, 1000)] {
+        let pred = CodePredicate::from_range(&dict, &(Included(lo), Excluded(hi)));
+        assert!(pred.maybe.is_empty());
+        for v in column.iter() {
+            let c = dict.encode(v);
+            assert!(c.is_exact());
+            assert_eq!(pred.is_definite(c), lo <= *v && *v < hi);
+        }
+    }
+
+    let mut grown = dict;
+    grown.extend_max(&[10_000]);
+    assert!(!grown.is_exact_complete());
+}
+
+#[test]
+fn exact_dictionaries_refuse_high_cardinality() {
+    let sample: Vec<u32> = (0..128).collect();
+    let err = Dict::new_exact(Mode::Byte, sample.clone()).err().unwrap();
+    assert_eq!(
+        err,
+        CardinalityError {
+            distinct: 128,
+            limit: 127
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "sample has 128 distinct values but only 127 exact codes"
+    );
+    assert!(Dict::new_exact(Mode::Word, sample).is_ok());
+}
@@ -0,0 +1,49 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{BuildOptions, Code, Dict, Mode};
+
+#[test]
+fn extend_max_keeps_existing_codes() {
+    let sample: Vec<u64> = (0..10_000).map(|i| 1_000_000 + i * 3).collect();
+    let options = BuildOptions {
+        retain_weights: true,
+        ..BuildOptions::default()
+    };
+    let mut dict = Dict::new_with_options(Mode::Word, sample.clone(), &options);
+    let n = dict.codes.len();
+    let before: Vec<Code> = sample.iter().map(|v| dict.encode(v)).collect();
+    let max = *dict.codes.last().unwrap();
+
+    let newer: Vec<u64> = (1..=10).map(|i| max + i * 100).collect();
+    let unordered = [max, max - 1, newer[0], newer[0], newer[1], newer[1] - 5];
+    assert_eq!(dict.extend_max(&unordered), 2);
+    assert_eq!(dict.extend_max(&newer[2..]), newer.len() - 2);
+    assert_eq!(dict.codes.len(), n + newer.len());
+    assert!(dict.codes.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(dict.code_weight(Code(1)), None);
+
+    for (v, c) in sample.iter().zip(before.iter()) {
+        if *v < max {
+            assert_eq!(dict.encode(v), *c);
+        }
+    }
+    for (i, v) in newer.iter().enumerate() {
+        assert_eq!(dict.encode(v), Code(2 * (n + i + 1) as u16));
+    }
+    assert_eq!(
+        dict.encode(&u64::MAX),
+        Code(2 * dict.codes.len() as u16 + 1)
+    );
+}
+
+#[test]
+fn extend_max_stops_at_code_limit() {
+    let mut dict = Dict::new(Mode::Byte, vec![1u32, 2, 3]);
+    let many: Vec<u32> = (4..1000).collect();
+    let appended = dict.extend_max(&many);
+    assert_eq!(dict.codes.len(), Mode::Byte.num_exact_codes());
+    assert_eq!(appended, Mode::Byte.num_exact_codes() - 3);
+    assert_eq!(dict.extend_max(&[5000]), 0);
+    assert!(dict.encode(&5000) <= Mode::Byte.max_inexact_code());
+}
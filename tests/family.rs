@@ -0,0 +1,55 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::family::DictFamily;
+use ordbog::Code;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+fn check_family(sample: Vec<i32>, sizes: &[usize]) {
+    let family = DictFamily::new(sample.clone(), sizes);
+    assert_eq!(family.levels.len(), 3);
+    let mut sorted = sizes.to_vec();
+    sorted.sort();
+    for (level, n) in sorted.iter().enumerate() {
+        assert!(family.levels[level].codes.len() <= *n);
+    }
+    // Nesting: coarse exact values are finer exact values.
+    for w in family.levels.windows(2) {
+        for v in w[0].codes.iter() {
+            assert!(w[1].codes.binary_search(v).is_ok());
+        }
+    }
+    let probes = sample.iter().chain([i32::MIN, i32::MAX, 0].iter());
+    for v in probes {
+        for from in 0..3 {
+            for to in 0..=from {
+                let (f, c) = (family.encode(from, v), family.encode(to, v));
+                assert_eq!(family.coarsen(from, to, f), c);
+                assert!(family.refine(to, from, c).contains(&f));
+            }
+        }
+    }
+    // Refinement of every coarse code partitions the finer codes.
+    for to in 1..3 {
+        let mut next = 1u16;
+        for c in 1..=2 * family.levels[to - 1].codes.len() as u16 + 1 {
+            let r = family.refine(to - 1, to, Code(c));
+            assert_eq!(r.start().0, next);
+            next = r.end().0 + 1;
+        }
+        assert_eq!(next as usize, 2 * family.levels[to].codes.len() + 2);
+    }
+}
+
+proptest! {
+    #[test]
+    fn nested_levels_map_consistently(sample in vec(-5000..5000i32, 0..5000)) {
+        check_family(sample, &[32767, 7, 127]);
+    }
+
+    #[test]
+    fn levels_smaller_than_their_mode(sample in vec(any::<i32>(), 0..2000)) {
+        check_family(sample, &[100, 3, 1000]);
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "ffi")]
+
+use ordbog::ffi::*;
+use ordbog::{Dict, Mode};
+use std::ptr;
+
+#[test]
+fn handles_encode_like_dicts() {
+    let sample: Vec<i64> = (0..1000).map(|i| (i * 7919) % 300).collect();
+    let dict = Dict::new(Mode::Byte, sample.clone());
+    unsafe {
+        let h = ordbog_dict_i64_new(8, sample.as_ptr(), sample.len());
+        assert!(!h.is_null());
+        let mut out = vec![0u16; sample.len()];
+        let status = ordbog_dict_i64_encode(h, sample.as_ptr(), sample.len(), out.as_mut_ptr());
+        assert_eq!(status, OrdbogStatus::Ok);
+        for (v, c) in sample.iter().zip(out.iter()) {
+            assert_eq!(dict.encode(v).0, *c);
+        }
+        let status = ordbog_dict_i64_encode(h, sample.as_ptr(), 1, ptr::null_mut());
+        assert_eq!(status, OrdbogStatus::NullPointer);
+        ordbog_dict_i64_free(h);
+
+        let packed = ordbog_dict_i64_new(12, sample.as_ptr(), sample.len());
+        assert!(!packed.is_null());
+        ordbog_dict_i64_free(packed);
+        assert!(ordbog_dict_i64_new(10, sample.as_ptr(), sample.len()).is_null());
+        assert!(ordbog_dict_f64_new(16, ptr::null(), 3).is_null());
+        ordbog_dict_f64_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn info_respects_caller_struct_size() {
+    let sample = [1.0f64, 2.0, 2.0, 3.0];
+    unsafe {
+        let h = ordbog_dict_f64_new(16, sample.as_ptr(), sample.len());
+        let mut info = OrdbogDictInfo {
+            struct_size: std::mem::size_of::<OrdbogDictInfo>() as u32,
+            ..Default::default()
+        };
+        assert_eq!(ordbog_dict_f64_info(h, &mut info), OrdbogStatus::Ok);
+        assert_eq!(info.abi_version, ordbog_abi_version());
+        assert_eq!(info.mode_bits, 16);
+        assert_eq!(info.max_code, 2 * info.num_exact_codes + 1);
+
+        // An older caller knowing only the first three fields.
+        let mut old = OrdbogDictInfo {
+            struct_size: 12,
+            ..Default::default()
+        };
+        assert_eq!(ordbog_dict_f64_info(h, &mut old), OrdbogStatus::Ok);
+        assert_eq!(
+            (old.struct_size, old.mode_bits, old.num_exact_codes),
+            (12, 16, 0)
+        );
+
+        let mut bad = OrdbogDictInfo::default();
+        assert_eq!(
+            ordbog_dict_f64_info(h, &mut bad),
+            OrdbogStatus::BadStructSize
+        );
+        ordbog_dict_f64_free(h);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use float_ord::FloatOrd;
+use ordbog::format::{
+    append_section, sections, FormatError, Header, READABLE_VERSIONS, WRITE_VERSION,
+};
+use ordbog::{Dict, DictF64, Mode};
+
+#[test]
+fn round_trips() {
+    let sample: Vec<i64> = (0..5000).map(|i| (i * 7919) % 1000 - 500).collect();
+    let dict = Dict::new(Mode::Word, sample.clone());
+    let back = Dict::<i64>::from_bytes(&dict.to_bytes()).unwrap();
+    assert_eq!(back.mode, Mode::Word);
+    assert_eq!(back.codes, dict.codes);
+
+    let words: Vec<String> = ["", "ant", "bee", "bee", "cat"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let dict = Dict::new(Mode::Byte, words);
+    assert_eq!(
+        Dict::<String>::from_bytes(&dict.to_bytes()).unwrap().codes,
+        dict.codes
+    );
+
+    let floats: Vec<DictF64> = [f64::NEG_INFINITY, -0.0, 0.0, 1.5, f64::NAN]
+        .iter()
+        .map(|f| DictF64(FloatOrd(*f)))
+        .collect();
+    let dict = Dict::new(Mode::Byte, floats);
+    let back = Dict::<DictF64>::from_bytes(&dict.to_bytes()).unwrap();
+    let bits = |d: &Dict<DictF64>| d.codes.iter().map(|f| f.0 .0.to_bits()).collect::<Vec<_>>();
+    assert_eq!(bits(&back), bits(&dict));
+}
+
+#[test]
+fn versions_flags_and_sections() {
+    assert!(READABLE_VERSIONS.contains(&WRITE_VERSION));
+    let dict = Dict::new(Mode::Byte, vec![1u32, 2, 3]);
+    let mut bytes = dict.to_bytes();
+    let header = Header::read(&bytes).unwrap();
+    assert_eq!(
+        (header.version, header.flags, header.num_codes),
+        (WRITE_VERSION, 0, 3)
+    );
+
+    // Unknown trailing sections are skipped.
+    append_section(&mut bytes, 0x7777, b"from the future");
+    assert_eq!(
+        sections(&bytes).unwrap(),
+        vec![(0x7777, &b"from the future"[..])]
+    );
+    assert_eq!(
+        Dict::<u32>::from_bytes(&bytes).unwrap().codes,
+        vec![1, 2, 3]
+    );
+
+    // Unknown flags and versions are refused.
+    let mut flagged = bytes.clone();
+    flagged[6] = 0x80;
+    assert_eq!(
+        Dict::<u32>::from_bytes(&flagged).err(),
+        Some(FormatError::UnsupportedFlags(0x80))
+    );
+    let mut newer = bytes.clone();
+    newer[4] = 99;
+    assert_eq!(
+        Dict::<u32>::from_bytes(&newer).err(),
+        Some(FormatError::UnsupportedVersion(99))
+    );
+
+    // Damage is reported, not misread.
+    assert_eq!(
+        Dict::<u32>::from_bytes(&bytes[..bytes.len() - 1]).err(),
+        Some(FormatError::Truncated)
+    );
+    assert_eq!(
+        Dict::<u32>::from_bytes(&bytes[..20]).err(),
+        Some(FormatError::Truncated)
+    );
+    assert_eq!(
+        Dict::<u64>::from_bytes(&bytes).err(),
+        Some(FormatError::BadValue)
+    );
+    assert_eq!(
+        Dict::<u32>::from_bytes(b"nope, not at all").err(),
+        Some(FormatError::BadMagic)
+    );
+}
+
+#[test]
+fn compatibility_of_persisted_codes() {
+    use ordbog::format::{fingerprint, CodeOrigin, Compatibility};
+
+    let coarse = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let fine = Dict::new(Mode::Byte, vec![10i64, 15, 20, 25, 30]);
+    let other = Dict::new(Mode::Byte, vec![10i64, 21, 30]);
+    let bytes = coarse.to_bytes();
+
+    assert_eq!(
+        coarse.can_interpret_codes_of(CodeOrigin::Serialized(&bytes)),
+        Compatibility::Identical
+    );
+    assert_eq!(
+        fine.can_interpret_codes_of(CodeOrigin::Serialized(&bytes)),
+        Compatibility::Refinable
+    );
+    assert_eq!(
+        other.can_interpret_codes_of(CodeOrigin::Serialized(&bytes)),
+        Compatibility::Incompatible
+    );
+    assert_eq!(
+        coarse.can_interpret_codes_of(CodeOrigin::Serialized(b"junk")),
+        Compatibility::Incompatible
+    );
+
+    let f = coarse.fingerprint();
+    assert_ne!(f, fine.fingerprint());
+    assert_eq!(
+        coarse.can_interpret_codes_of(CodeOrigin::Fingerprint(f)),
+        Compatibility::Identical
+    );
+    assert_eq!(
+        fine.can_interpret_codes_of(CodeOrigin::Fingerprint(f)),
+        Compatibility::Incompatible
+    );
+
+    // Sections don't change the fingerprint.
+    let mut with_section = bytes.clone();
+    append_section(&mut with_section, 1, b"x");
+    assert_eq!(fingerprint(&with_section), Ok(f));
+}
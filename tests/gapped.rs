@@ -0,0 +1,48 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::gapped::GappedDict;
+use ordbog::{Code, Mode};
+
+#[test]
+fn gapped_codes_preserve_order() {
+    let sample: Vec<u32> = (0..10_000).map(|i| (i * 7919) % 50_000).collect();
+    let dict = GappedDict::new(Mode::Byte, sample.clone(), 4);
+    assert!(dict.entries.len() <= 31);
+    assert!(dict.entries.windows(2).all(|w| w[1].1 .0 - w[0].1 .0 >= 6));
+    let mut sorted = sample;
+    sorted.push(u32::MAX);
+    sorted.sort_unstable();
+    for w in sorted.windows(2) {
+        let (a, b) = (dict.encode(&w[0]), dict.encode(&w[1]));
+        assert!(a <= b);
+        if a.is_exact() || b.is_exact() {
+            assert!(w[0] == w[1] || a < b);
+        }
+    }
+}
+
+#[test]
+fn inserts_leave_existing_codes_alone() {
+    let sample: Vec<u32> = (0..1000).map(|i| i * 10).collect();
+    let mut dict = GappedDict::new(Mode::Byte, sample, 8);
+    let before = dict.entries.clone();
+    let (v, c) = before[3];
+    assert_eq!(dict.insert(v), None);
+
+    let code = dict.insert(v + 1).unwrap();
+    assert!(code.is_exact() && c < code && code < before[4].1);
+    assert_eq!(dict.encode(&(v + 1)), code);
+    for (v, c) in before.iter() {
+        assert_eq!(dict.encode(v), *c);
+    }
+
+    // Keep splitting the same gap until it fills up.
+    let mut n = 0;
+    while dict.insert(v + 2 + n).is_some() {
+        n += 1;
+    }
+    assert!(n >= 1);
+    let codes: Vec<Code> = dict.entries.iter().map(|e| e.1).collect();
+    assert!(codes.windows(2).all(|w| w[0].0 + 2 <= w[1].0));
+}
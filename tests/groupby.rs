@@ -0,0 +1,68 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::groupby::{ExactGroup, GroupAccumulator};
+use ordbog::{Code, Dict, Mode};
+use std::collections::BTreeMap;
+
+#[test]
+fn low_cardinality_groups_come_from_codes_alone() {
+    let keys: Vec<u32> = (0..10_000).map(|i| (i * 7) % 12).collect();
+    let amounts: Vec<u64> = (0..10_000).collect();
+    let dict = Dict::new(Mode::Byte, keys.clone());
+    let codes: Vec<Code> = keys.iter().map(|k| dict.encode(k)).collect();
+
+    // Accumulated in two parts and merged.
+    let mut acc = GroupAccumulator::<u64>::new(Mode::Byte);
+    acc.accumulate(&codes[..4000], &amounts[..4000], |s, v| *s += v);
+    let mut rest = GroupAccumulator::new(Mode::Byte);
+    rest.accumulate(&codes[4000..], &amounts[4000..], |s, v| *s += v);
+    acc.merge(&rest, |s, t| *s += t);
+
+    let mut expected: BTreeMap<u32, (u64, u64)> = BTreeMap::new();
+    for (k, a) in keys.iter().zip(amounts.iter()) {
+        let e = expected.entry(*k).or_default();
+        e.0 += 1;
+        e.1 += a;
+    }
+    let groups = acc.groups(&dict);
+    assert!(groups.residual.is_empty());
+    assert_eq!(groups.residual_rows, 0);
+    let got: Vec<(u32, u64, u64)> = groups
+        .exact
+        .iter()
+        .map(|g| (g.value, g.rows, g.aggregate))
+        .collect();
+    let want: Vec<(u32, u64, u64)> = expected.iter().map(|(k, (n, s))| (*k, *n, *s)).collect();
+    assert_eq!(got, want);
+}
+
+#[test]
+fn inexact_codes_are_left_as_residual_work() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    let values = [10u32, 15, 17, 20, 20, 99];
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let mut acc = GroupAccumulator::<()>::new(Mode::Byte);
+    acc.count(&codes);
+    acc.count(&[Code(0)]);
+    assert_eq!(acc.rows(Code(3)), 2);
+    assert_eq!(acc.aggregate(Code(3)), &());
+    let groups = acc.groups(&dict);
+    assert_eq!(
+        groups.exact,
+        vec![
+            ExactGroup {
+                value: 10,
+                rows: 1,
+                aggregate: ()
+            },
+            ExactGroup {
+                value: 20,
+                rows: 2,
+                aggregate: ()
+            },
+        ]
+    );
+    assert_eq!(groups.residual, [Code(0), Code(3), Code(7)]);
+    assert_eq!(groups.residual_rows, 4);
+}
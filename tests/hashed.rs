@@ -0,0 +1,91 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::hashed::HashDict;
+use ordbog::predicate::{Predicate, Ternary};
+use ordbog::sketch::Sketch;
+use ordbog::{Code, Mode};
+
+#[test]
+fn frequent_values_get_unique_codes() {
+    let mut sample: Vec<u32> = (0..10_000).collect();
+    sample.extend(std::iter::repeat_n(5_000_000, 50));
+    sample.extend(std::iter::repeat_n(7, 20));
+    let dict = HashDict::new(Mode::Byte, sample);
+    assert_eq!(dict.unique.len(), 127);
+    assert_eq!(dict.encode(&5_000_000), Code(2));
+    assert_eq!(dict.encode(&7), Code(4));
+    // Singletons fill the rest in value order.
+    assert_eq!(dict.encode(&0), Code(6));
+
+    for v in 0..100_000u32 {
+        let c = dict.encode(&v);
+        assert!(c.0 >= 1 && c <= Mode::Byte.max_inexact_code());
+        assert_eq!(c.is_exact(), dict.unique.contains_key(&v));
+        assert_eq!(c, dict.encode(&v));
+    }
+}
+
+#[test]
+fn hashed_codes_spread_long_tail() {
+    let dict = HashDict::new(Mode::Byte, vec![1u64, 2, 3]);
+    let mut counts = [0usize; 256];
+    for v in 1000..129_000u64 {
+        counts[dict.encode(&v).0 as usize] += 1;
+    }
+    // 128 odd buckets, 1000 values each on average.
+    for (c, n) in counts.iter().enumerate() {
+        if c % 2 == 1 {
+            assert!(*n > 800 && *n < 1200, "bucket {} has {}", c, n);
+        } else {
+            assert_eq!(*n, 0);
+        }
+    }
+    let pred = dict.rewrite_in(&[3, 1, 3, 50_000]);
+    let mut definite = vec![dict.encode(&3), dict.encode(&1)];
+    definite.sort();
+    assert_eq!(pred.definite_set().iter().collect::<Vec<_>>(), definite);
+    let bucket = dict.encode(&50_000);
+    assert_eq!(pred.maybe, vec![bucket..=bucket]);
+    assert!(dict.rewrite(&Predicate::Lt(3)).is_none());
+}
+
+#[test]
+fn hashed_columns_scan_end_to_end() {
+    let sample: Vec<u32> = (0..5000)
+        .map(|i| if i % 2 == 0 { i % 40 } else { i })
+        .collect();
+    let dict = HashDict::new(Mode::Byte, sample);
+    let column: Vec<u32> = (0..3000)
+        .map(|i| if i % 3 == 0 { i % 40 } else { i * 7 })
+        .collect();
+    let mut sketch = Sketch::new(Mode::Byte);
+    sketch.extend(column.iter().map(|v| dict.encode(v)));
+
+    let wanted = [12u32, 14, 778 * 7, 123_456];
+    let pred = dict.rewrite(&Predicate::InSet(wanted.to_vec())).unwrap();
+    let bitmap = sketch.scan(&pred.compile(Mode::Byte));
+    let selection = bitmap.selection();
+    // Frequent values have unique codes, so their rows are definite, and
+    // only they are; rows of the long tail sharing a wanted bucket are
+    // maybe rows, and include the one wanted tail value present.
+    for (i, v) in column.iter().enumerate() {
+        let row = i as u32;
+        match bitmap.evaluate(i) {
+            Ternary::True => assert!(*v == 12 || *v == 14),
+            Ternary::Maybe => assert!(!dict.encode(v).is_exact()),
+            Ternary::False => assert!(!wanted.contains(v)),
+        }
+        if *v == 12 || *v == 14 {
+            assert!(selection.definite.contains(&row));
+        }
+    }
+    assert!(selection.maybe.contains(&778));
+    assert_eq!(
+        dict.rewrite(&Predicate::Eq(12))
+            .unwrap()
+            .definite_set()
+            .len(),
+        1
+    );
+}
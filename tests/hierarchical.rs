@@ -0,0 +1,43 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::family::DictFamily;
+use ordbog::hierarchical::{HierarchicalSketch, BLOCK_ROWS};
+
+#[test]
+fn coarse_pruning_agrees_with_row_codes() {
+    // Roughly time-ordered values, so blocks cover narrow ranges.
+    let values: Vec<u32> = (0..100_000u32).map(|i| i / 3 + (i * 7919) % 50).collect();
+    let family = DictFamily::new(values.iter().step_by(7).cloned().collect(), &[127, 32767]);
+    let sketch = HierarchicalSketch::new(&family, &values);
+    assert_eq!(sketch.len(), values.len());
+
+    for (lo, hi) in [
+        (0, 10),
+        (12_345, 12_400),
+        (20_000, 25_000),
+        (40_000, 50_000),
+    ] {
+        let blocks = sketch.candidate_blocks(&family, &lo, &hi);
+        let hits = sketch.scan(&family, &lo, &hi);
+        let (wlo, whi) = (family.encode(1, &lo).0, family.encode(1, &hi).0);
+        for (row, v) in values.iter().enumerate() {
+            let code = sketch.rows()[row];
+            let in_range = wlo <= code && code <= whi;
+            // Pruning never drops a row the word codes would keep...
+            assert_eq!(hits[row], in_range);
+            if lo <= *v && *v <= hi {
+                assert!(hits[row]);
+                assert!(blocks.contains(&(row / BLOCK_ROWS)));
+            }
+        }
+        // ...and most blocks are pruned at the coarse level.
+        let nblocks = values.len().div_ceil(BLOCK_ROWS);
+        assert!(
+            blocks.len() * 2 < nblocks,
+            "{} of {}",
+            blocks.len(),
+            nblocks
+        );
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::hilbert::{bbox_to_code_ranges, bbox_to_ranges, BBox, HilbertKey};
+use ordbog::{Dict, Mode};
+use proptest::prelude::*;
+use rand_distr::{Distribution, Uniform};
+
+proptest! {
+    #[test]
+    fn keys_roundtrip_to_nearby_points(lat in -90.0..90.0f64, lon in -180.0..180.0f64) {
+        let (lat2, lon2) = HilbertKey::new(lat, lon).lat_lon();
+        prop_assert!((lat - lat2).abs() < 1e-6);
+        prop_assert!((lon - lon2).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn bbox_covers_points_inside() {
+    let mut rng = common::rng(7);
+    let jitter = Uniform::new(-0.5, 0.5);
+    // Points clustered around a few cities.
+    let centres = [(51.5, -0.1), (40.7, -74.0), (35.7, 139.7), (-33.9, 151.2)];
+    let points: Vec<(f64, f64)> = (0..20_000)
+        .map(|i| {
+            let (lat, lon) = centres[i % centres.len()];
+            (lat + jitter.sample(&mut rng), lon + jitter.sample(&mut rng))
+        })
+        .collect();
+    let keys: Vec<HilbertKey> = points
+        .iter()
+        .map(|(a, o)| HilbertKey::new(*a, *o))
+        .collect();
+    let dict = Dict::new(Mode::Word, keys.clone());
+
+    let bbox = BBox {
+        min_lat: 51.3,
+        max_lat: 51.7,
+        min_lon: -0.4,
+        max_lon: 0.2,
+    };
+    for max in [1, 8, 64] {
+        let ranges = bbox_to_ranges(&bbox, max);
+        assert!(!ranges.is_empty() && ranges.len() <= max);
+        let codes = bbox_to_code_ranges(&dict, &bbox, max);
+        let mut candidates = 0;
+        for ((lat, lon), k) in points.iter().zip(keys.iter()) {
+            let c = dict.encode(k);
+            let hit = codes.iter().any(|r| r.contains(&c));
+            if bbox.min_lat <= *lat
+                && *lat <= bbox.max_lat
+                && bbox.min_lon <= *lon
+                && *lon <= bbox.max_lon
+            {
+                assert!(ranges.iter().any(|r| r.contains(k)));
+                assert!(hit);
+            }
+            candidates += hit as usize;
+        }
+        // Points around the other cities are pruned, and with enough ranges
+        // so is most of the city outside the box.
+        if max >= 8 {
+            assert!(candidates <= points.len() / 4);
+        }
+        if max >= 64 {
+            assert!(candidates < points.len() / 8);
+        }
+    }
+}
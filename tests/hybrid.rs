@@ -0,0 +1,32 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::hybrid::HybridSketch;
+use ordbog::{Dict, Mode};
+
+#[test]
+fn bloom_filters_prune_long_tail_lookups() {
+    // A high-cardinality column: far more distinct values than codes.
+    let values: Vec<u64> = (0..100_000u64).map(|i| (i * 7919) % 1_000_003).collect();
+    let dict = Dict::new(Mode::Byte, values.iter().step_by(10).cloned().collect());
+    let sketch = HybridSketch::new(&dict, &values, 1024, 8192);
+    assert_eq!(sketch.num_blocks(), 98);
+
+    let mut code_only = 0;
+    let mut hybrid = 0;
+    for (row, v) in values.iter().enumerate().step_by(997) {
+        let code = dict.encode(v);
+        let blocks = sketch.candidate_blocks_eq(&dict, v);
+        // No false negatives.
+        assert!(blocks.contains(&(row / 1024)));
+        hybrid += blocks.len();
+        code_only += sketch.candidate_blocks_in(code, code).len();
+    }
+    assert!(hybrid * 5 < code_only);
+
+    // Exact codes need no filter and are never pruned wrongly.
+    let exact = &dict.codes[10];
+    for b in sketch.candidate_blocks_eq(&dict, exact) {
+        assert!(sketch.block_codes(b).contains(&dict.encode(exact)));
+    }
+}
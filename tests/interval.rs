@@ -0,0 +1,53 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::interval::IntervalDict;
+use ordbog::predicate::Ternary;
+use ordbog::Mode;
+
+fn intervals() -> Vec<(u32, u32)> {
+    let starts = common::uniform(99, 5000, 0..100_000);
+    let lens = common::uniform(100, 5000, 1..501);
+    starts
+        .into_iter()
+        .zip(lens)
+        .map(|(s, l)| (s, s + l))
+        .collect()
+}
+
+#[test]
+fn rewritten_predicates_agree_with_intervals() {
+    let ivs = intervals();
+    let dict = IntervalDict::new(Mode::Byte, ivs.iter().step_by(4).cloned().collect());
+    let codes: Vec<_> = ivs.iter().map(|(s, e)| dict.encode(s, e)).collect();
+    let (mut conclusive, mut total) = (0, 0);
+    for p in (0..101_000).step_by(997) {
+        let pred = dict.contains_point(&p);
+        for ((s, e), c) in ivs.iter().zip(codes.iter()) {
+            let truth = *s <= p && p < *e;
+            match pred.eval(*c) {
+                Ternary::True => assert!(truth),
+                Ternary::False => assert!(!truth),
+                Ternary::Maybe => {}
+            }
+            if pred.eval(*c).is_conclusive() {
+                conclusive += 1;
+            }
+            total += 1;
+        }
+        let hi = p + 250;
+        let pred = dict.overlaps(&p, &hi);
+        for ((s, e), c) in ivs.iter().zip(codes.iter()) {
+            let truth = *s < hi && p < *e;
+            match pred.eval(*c) {
+                Ternary::True => assert!(truth),
+                Ternary::False => assert!(!truth),
+                Ternary::Maybe => {}
+            }
+        }
+    }
+    // Most rows are ruled in or out by codes alone.
+    assert!(conclusive * 10 > total * 9);
+}
@@ -0,0 +1,60 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "jni")]
+
+use ordbog::jni::{
+    code_predicate_to_ints, predicate_from_op, OP_BETWEEN, OP_EQ, OP_GE, OP_IN, OP_LT,
+};
+use ordbog::predicate::Predicate;
+use ordbog::{Dict, Mode};
+
+#[test]
+fn builds_predicates_from_operators() {
+    assert_eq!(predicate_from_op(OP_EQ, vec![3]), Some(Predicate::Eq(3)));
+    assert_eq!(predicate_from_op(OP_GE, vec![3]), Some(Predicate::Ge(3)));
+    assert_eq!(
+        predicate_from_op(OP_BETWEEN, vec![3, 9]),
+        Some(Predicate::Between(3, 9))
+    );
+    assert_eq!(
+        predicate_from_op(OP_IN, vec![1, 2, 5]),
+        Some(Predicate::InSet(vec![1, 2, 5]))
+    );
+    assert_eq!(
+        predicate_from_op::<i64>(OP_IN, vec![]),
+        Some(Predicate::InSet(vec![]))
+    );
+    assert_eq!(predicate_from_op::<i64>(OP_LT, vec![]), None);
+    assert_eq!(predicate_from_op(OP_EQ, vec![1, 2]), None);
+    assert_eq!(predicate_from_op(OP_BETWEEN, vec![1]), None);
+    assert_eq!(predicate_from_op(OP_BETWEEN, vec![1, 2, 3]), None);
+    assert_eq!(predicate_from_op(99, vec![1]), None);
+}
+
+#[test]
+fn flattens_code_predicates() {
+    let sample: Vec<i64> = (0..2000).map(|i| (i * 7919) % 1000).collect();
+    let dict = Dict::new(Mode::Byte, sample);
+    for op in [OP_EQ, OP_LT, OP_GE, OP_BETWEEN, OP_IN] {
+        let operands = if op == OP_BETWEEN || op == OP_IN {
+            vec![100, 400]
+        } else {
+            vec![250]
+        };
+        let pred = dict.rewrite(&predicate_from_op(op, operands).unwrap());
+        let ints = code_predicate_to_ints(&pred);
+        let definite = ints[0] as usize;
+        let bounds: Vec<u16> = ints[1..].iter().map(|i| *i as u16).collect();
+        assert_eq!(bounds.len(), 2 * (pred.definite.len() + pred.maybe.len()));
+        assert_eq!(definite, pred.definite.len());
+        for (r, b) in pred
+            .definite
+            .iter()
+            .chain(pred.maybe.iter())
+            .zip(bounds.chunks(2))
+        {
+            assert_eq!((r.start().0, r.end().0), (b[0], b[1]));
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::materialize::{CandidateBlock, Candidates};
+use ordbog::predicate::{Predicate, Ternary};
+use ordbog::sketch::Sketch;
+use ordbog::{Dict, Mode};
+
+#[test]
+fn batches_by_block_and_skips_empty_blocks() {
+    let mut bitmap = vec![false; 10];
+    for i in [1, 3, 8, 9] {
+        bitmap[i] = true;
+    }
+    let blocks: Vec<CandidateBlock> = Candidates::new(&bitmap, 4).collect();
+    assert_eq!(
+        blocks,
+        vec![
+            CandidateBlock {
+                block: 0,
+                rows: vec![1, 3]
+            },
+            CandidateBlock {
+                block: 2,
+                rows: vec![0, 1]
+            },
+        ]
+    );
+    assert_eq!(blocks[1].absolute_rows(4).collect::<Vec<_>>(), vec![8, 9]);
+    assert_eq!(Candidates::new(&[], 4).count(), 0);
+}
+
+#[test]
+fn covers_every_matching_row_of_a_code_scan() {
+    let values: Vec<u32> = (0..5000u32).map(|i| (i * 7919) % 1000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let (lo, hi) = (dict.encode(&200), dict.encode(&260));
+    let bitmap: Vec<bool> = values
+        .iter()
+        .map(|v| {
+            let c = dict.encode(v);
+            lo <= c && c <= hi
+        })
+        .collect();
+    let rows: Vec<usize> = Candidates::new(&bitmap, 64)
+        .flat_map(|b| b.absolute_rows(64).collect::<Vec<_>>())
+        .collect();
+    assert_eq!(rows.len(), bitmap.iter().filter(|b| **b).count());
+    for (i, v) in values.iter().enumerate() {
+        if (200..=260).contains(v) {
+            assert!(rows.binary_search(&i).is_ok());
+        }
+    }
+}
+
+#[test]
+fn walks_the_words_of_a_scan_bitmap() {
+    let values: Vec<u32> = (0..5000u32).map(|i| (i * 7919) % 1000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let mut sketch = Sketch::new(Mode::Byte);
+    sketch.extend_from_values(&dict, &values);
+    let scan = sketch.scan(
+        &dict
+            .rewrite(&Predicate::Between(200, 260))
+            .compile(Mode::Byte),
+    );
+    let flags: Vec<bool> = (0..scan.rows())
+        .map(|i| scan.evaluate(i) != Ternary::False)
+        .collect();
+    // Block sizes below, at and across word boundaries.
+    for block_rows in [1, 7, 64, 100, 129, 5000, 9000].iter().copied() {
+        let from_words: Vec<CandidateBlock> = Candidates::from_scan(&scan, block_rows).collect();
+        let from_flags: Vec<CandidateBlock> = Candidates::new(&flags, block_rows).collect();
+        assert_eq!(from_words, from_flags);
+    }
+
+    let words = [1u64 << 63, 0, 0, 1 | (1 << 5)];
+    let blocks: Vec<CandidateBlock> = Candidates::from_words(&words, 200, 10).collect();
+    assert_eq!(
+        blocks,
+        vec![
+            CandidateBlock {
+                block: 6,
+                rows: vec![3]
+            },
+            CandidateBlock {
+                block: 19,
+                rows: vec![2, 7]
+            },
+        ]
+    );
+    assert_eq!(Candidates::from_words(&words, 192, 10).count(), 1);
+}
@@ -0,0 +1,24 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::memo::EncodeMemo;
+use ordbog::{Dict, Mode};
+
+#[test]
+fn memo_agrees_with_dict_and_evicts_lru() {
+    let words: Vec<String> = (0..1000)
+        .map(|i| format!("w{}", (i * 7919) % 400))
+        .collect();
+    let dict = Dict::new(Mode::Byte, words.clone());
+    let mut memo = EncodeMemo::new(&dict, 2);
+    let (a, b, c) = ("w1".to_string(), "w2".to_string(), "w3".to_string());
+    for w in words.iter().take(50).chain([&a, &b, &a, &c]) {
+        assert_eq!(memo.encode(w), dict.encode(w));
+    }
+    // `a` was used more recently than `b`, so `b` was evicted for `c`.
+    assert!(memo.contains(&a) && memo.contains(&c) && !memo.contains(&b));
+
+    let mut none = EncodeMemo::new(&dict, 0);
+    assert_eq!(none.encode(&a), dict.encode(&a));
+    assert!(!none.contains(&a));
+}
@@ -0,0 +1,145 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(all(feature = "mmap", target_endian = "little"))]
+
+mod common;
+
+use ordbog::format::{fingerprint, FormatError};
+use ordbog::mmap::{MappedCodes, MappedSketch};
+use ordbog::predicate::{CodePredicate, CLASS_NONE};
+use ordbog::sketch::Sketch;
+use ordbog::sketchfile::{ReadOptions, SketchFile};
+use ordbog::{Code, Dict, Mode};
+use std::io;
+use std::ops::Bound::*;
+use std::path::PathBuf;
+
+// Sorted, so the zone map can rule out blocks.
+fn column() -> Vec<u32> {
+    common::sorted_uniform(9, 10_000, 0..1_000_000)
+}
+
+fn write_sketch(name: &str, mode: Mode) -> (PathBuf, Dict<u32>, Vec<Code>) {
+    let values = column();
+    let dict = Dict::new(mode, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let sketch = SketchFile::new(
+        Dict::from_codes(mode, dict.codes.clone()),
+        Sketch::from_codes(mode, &codes),
+        1000,
+    );
+    let path = std::env::temp_dir().join(format!("ordbog-{}-{}.obg", name, std::process::id()));
+    std::fs::write(&path, sketch.to_bytes()).unwrap();
+    (path, dict, codes)
+}
+
+#[test]
+fn mapped_sketches_scan_in_place() {
+    for (name, mode) in [
+        ("byte", Mode::Byte),
+        ("packed", Mode::Packed),
+        ("word", Mode::Word),
+    ] {
+        let (path, dict, codes) = write_sketch(name, mode);
+        let mapped = MappedSketch::open(&path).unwrap();
+        assert_eq!(mapped.rows(), codes.len());
+        assert_eq!(mapped.blocks(), 10);
+        assert_eq!(
+            fingerprint(&mapped.dict_bytes().unwrap()).unwrap(),
+            dict.fingerprint()
+        );
+        assert_eq!(mapped.dict::<u32>().unwrap().codes, dict.codes);
+        match (mode, mapped.codes()) {
+            (Mode::Byte, MappedCodes::Byte(_))
+            | (Mode::Packed, MappedCodes::Packed { .. })
+            | (Mode::Word, MappedCodes::Word(_)) => (),
+            _ => panic!("codes at the wrong width"),
+        }
+        let stored: Vec<Code> = (0..mapped.rows()).map(|i| mapped.codes().get(i)).collect();
+        assert_eq!(stored, codes);
+        assert_eq!(
+            mapped.to_sketch().unwrap(),
+            Sketch::from_codes(mode, &codes)
+        );
+
+        let pred =
+            CodePredicate::from_range(&dict, &(Included(200_000), Excluded(300_000))).compile(mode);
+        let blocks = mapped.candidate_blocks(&pred);
+        assert!(!blocks.is_empty() && blocks.len() < 4);
+        let mut out = vec![0; mapped.rows()];
+        mapped.classify_into(&pred, 0..mapped.rows(), &mut out);
+        let mut expected = vec![0; codes.len()];
+        pred.classify_into(&codes, &mut expected);
+        assert_eq!(out, expected);
+        for i in 0..mapped.blocks() {
+            if !blocks.contains(&i) {
+                assert!(mapped.block_range(i).all(|r| out[r] == CLASS_NONE));
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn mapped_blocks_are_verified_on_demand() {
+    let (path, _, _) = write_sketch("rot", Mode::Byte);
+    let mut bytes = std::fs::read(&path).unwrap();
+    let at = MappedSketch::open(&path).unwrap().header().codes.0 as usize;
+    bytes[at + 1500] ^= 0x04;
+    std::fs::write(&path, &bytes).unwrap();
+    let mapped = MappedSketch::open(&path).unwrap();
+    assert!(mapped.verify_block(0).is_ok());
+    assert_eq!(mapped.verify_block(1), Err(FormatError::BadChecksum));
+
+    // A damaged dictionary is refused on opening, unless asked not to.
+    bytes[mapped.header().dict.0 as usize + 20] ^= 0x01;
+    std::fs::write(&path, &bytes).unwrap();
+    let err = MappedSketch::open(&path).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let skip = ReadOptions {
+        verify_checksums: false,
+    };
+    assert!(MappedSketch::open_with_options(&path, &skip).is_ok());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compressed_blocks_are_scanned_one_at_a_time() {
+    let values = column();
+    let dict = Dict::new(Mode::Word, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let sketch = SketchFile::new(
+        Dict::from_codes(Mode::Word, dict.codes.clone()),
+        Sketch::from_codes(Mode::Word, &codes),
+        1000,
+    );
+    let path = std::env::temp_dir().join(format!("ordbog-zstd-{}.obg", std::process::id()));
+    std::fs::write(&path, sketch.to_bytes_compressed(1)).unwrap();
+    let mapped = MappedSketch::open(&path).unwrap();
+    assert!(mapped.header().is_compressed());
+    assert_eq!(mapped.dict::<u32>().unwrap().codes, dict.codes);
+    assert_eq!(mapped.to_sketch().unwrap().to_codes(), codes);
+
+    let pred = CodePredicate::from_range(&dict, &(Included(500_000), Included(600_000)))
+        .compile(Mode::Word);
+    let mut expected = vec![0; codes.len()];
+    pred.classify_into(&codes, &mut expected);
+    for i in mapped.candidate_blocks(&pred) {
+        assert!(mapped.verify_block(i).is_ok());
+        let mut out = vec![0; 1000];
+        mapped.classify_block_into(&pred, i, &mut out).unwrap();
+        assert_eq!(&out[..], &expected[mapped.block_range(i)]);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn mapping_a_non_sketch_fails() {
+    let path = std::env::temp_dir().join(format!("ordbog-bad-{}.obg", std::process::id()));
+    std::fs::write(&path, vec![7u8; 100]).unwrap();
+    let err = MappedSketch::open(&path).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,62 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "napi")]
+
+use float_ord::FloatOrd;
+use napi::bindgen_prelude::{BigInt64Array, Either, Float64Array, Int32Array};
+use ordbog::napi::{NapiDictF64, NapiDictI64};
+use ordbog::{Dict, DictF64, Mode};
+
+#[test]
+fn byte_mode_encodes_to_uint8_arrays() {
+    let sample: Vec<i32> = (0..1000).map(|i| (i * 37) % 500).collect();
+    let dict = NapiDictI64::from_int32_array(8, Int32Array::new(sample.clone())).unwrap();
+    assert_eq!(dict.mode_bits(), 8);
+    let expected = Dict::new(Mode::Byte, sample.iter().map(|v| *v as i64).collect());
+    let values = vec![-1, 0, 17, 250, 499, 1000];
+    let codes = match dict.encode_int32_array(Int32Array::new(values.clone())) {
+        Either::A(codes) => codes.to_vec(),
+        Either::B(_) => panic!("byte mode codes in a Uint16Array"),
+    };
+    let want: Vec<u8> = values
+        .iter()
+        .map(|v| expected.encode(&(*v as i64)).0 as u8)
+        .collect();
+    assert_eq!(codes, want);
+}
+
+#[test]
+fn wider_modes_encode_to_uint16_arrays() {
+    let sample: Vec<i64> = (0..10_000).map(|i| i * i).collect();
+    for bits in [12, 16] {
+        let dict =
+            NapiDictI64::from_big_int64_array(bits, BigInt64Array::new(sample.clone())).unwrap();
+        let expected = Dict::new(Mode::from_bits(bits as u8).unwrap(), sample.clone());
+        let codes = match dict.encode_big_int64_array(BigInt64Array::new(sample.clone())) {
+            Either::A(_) => panic!("wide codes in a Uint8Array"),
+            Either::B(codes) => codes.to_vec(),
+        };
+        let want: Vec<u16> = sample.iter().map(|v| expected.encode(v).0).collect();
+        assert_eq!(codes, want);
+    }
+
+    let sample: Vec<f64> = (0..5000).map(|i| (i as f64).sqrt()).collect();
+    let dict = NapiDictF64::from_float64_array(16, Float64Array::new(sample.clone())).unwrap();
+    let expected = Dict::new_with_key(Mode::Word, &sample, |f| DictF64(FloatOrd(*f)));
+    let codes = match dict.encode_float64_array(Float64Array::new(sample.clone())) {
+        Either::A(_) => panic!("wide codes in a Uint8Array"),
+        Either::B(codes) => codes.to_vec(),
+    };
+    let want: Vec<u16> = sample
+        .iter()
+        .map(|f| expected.encode(&DictF64(FloatOrd(*f))).0)
+        .collect();
+    assert_eq!(codes, want);
+}
+
+#[test]
+fn rejects_unknown_modes() {
+    assert!(NapiDictI64::from_int32_array(9, Int32Array::new(vec![1, 2, 3])).is_err());
+    assert!(NapiDictF64::from_float64_array(0, Float64Array::new(vec![1.0])).is_err());
+}
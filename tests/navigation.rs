@@ -0,0 +1,40 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{Code, Dict, Mode};
+
+#[test]
+fn codes_step_within_their_mode() {
+    assert!(Code(3).is_inexact() && !Code(4).is_inexact());
+    assert_eq!(Code(4).predecessor(Mode::Byte), Code(3));
+    assert_eq!(Code(4).successor(Mode::Byte), Code(5));
+    assert_eq!(Code(1).predecessor(Mode::Byte), Code(1));
+    assert_eq!(Code(0).predecessor(Mode::Byte), Code(1));
+    assert_eq!(Code(0xff).successor(Mode::Byte), Code(0xff));
+    assert_eq!(Code(0xff).successor(Mode::Word), Code(0x100));
+    assert_eq!(Code(0xffff).successor(Mode::Word), Code(0xffff));
+    assert_eq!(Code(0xfff).successor(Mode::Packed), Code(0xfff));
+}
+
+#[test]
+fn bounds_bracket_each_code() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10u32, 20, 30]);
+    assert_eq!(dict.code_bounds(Code(1)), (None, Some(&10)));
+    assert_eq!(dict.code_bounds(Code(2)), (Some(&10), Some(&10)));
+    assert_eq!(dict.code_bounds(Code(5)), (Some(&20), Some(&30)));
+    assert_eq!(dict.code_bounds(Code(7)), (Some(&30), None));
+    assert_eq!(dict.code_bounds(Code(0)), (None, None));
+    assert_eq!(dict.code_bounds(Code(8)), (None, None));
+
+    // Every value lies within its code's bounds.
+    for v in 0..40u32 {
+        let code = dict.encode(&v);
+        let (lo, hi) = dict.code_bounds(code);
+        assert!(lo.is_none_or(|l| *l <= v) && hi.is_none_or(|h| v <= *h));
+        if code.is_inexact() {
+            let (below, above) = (code.predecessor(dict.mode), code.successor(dict.mode));
+            assert!(below == code || dict.code_bounds(below).1 == lo);
+            assert!(above == code || dict.code_bounds(above).0 == hi);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::predicate::CodePredicate;
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+
+fn column() -> Vec<Option<i32>> {
+    (0..5000)
+        .map(|i| {
+            if i % 7 == 0 {
+                None
+            } else {
+                Some((i * 7919) % 1000)
+            }
+        })
+        .collect()
+}
+
+fn satisfies(v: &Option<i32>, lo: i32, hi: i32) -> bool {
+    matches!(v, Some(x) if lo <= *x && *x < hi)
+}
+
+#[test]
+fn none_never_satisfies_comparisons() {
+    let values = column();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    assert_eq!(dict.encode(&None), Code(2));
+    assert!(dict.encode(&None) < dict.encode(&Some(i32::MIN)));
+
+    let everything = CodePredicate::from_optional_range(&dict, &(Unbounded, Unbounded));
+    assert!(!everything.is_definite(Code(2)) && !everything.is_maybe(Code(2)));
+
+    for (lo, hi) in [(i32::MIN, 100), (250, 750), (900, i32::MAX)] {
+        let pred = CodePredicate::from_optional_range(&dict, &(Included(lo), Excluded(hi)));
+        for v in values.iter() {
+            let c = dict.encode(v);
+            if pred.is_definite(c) {
+                assert!(satisfies(v, lo, hi));
+            }
+            if satisfies(v, lo, hi) {
+                assert!(pred.is_definite(c) || pred.is_maybe(c));
+            }
+        }
+    }
+    let nulls = CodePredicate::is_none(&dict);
+    assert_eq!(nulls.definite, vec![Code(2)..=Code(2)]);
+}
+
+#[test]
+fn rare_none_shares_a_maybe_code() {
+    let mut values: Vec<Option<i32>> = (0..5000).map(|i| Some(i % 2000)).collect();
+    values.push(None);
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let none = dict.encode(&None);
+    assert!(!none.is_exact());
+    let pred = CodePredicate::from_optional_range(&dict, &(Unbounded, Excluded(10)));
+    assert!(pred.is_maybe(none) && !pred.is_definite(none));
+    assert!(CodePredicate::is_none(&dict).is_maybe(none));
+
+    let back: Dict<Option<i32>> = Dict::from_bytes(&dict.to_bytes()).unwrap();
+    assert_eq!(back.codes, dict.codes);
+}
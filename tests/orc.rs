@@ -0,0 +1,47 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "orc")]
+
+use ordbog::orc::{stripe_statistics, TypedStatistics};
+use ordbog::{Code, Dict, Mode};
+
+#[test]
+fn stripe_statistics_bracket_values() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let encode = |vs: &[i64]| -> Vec<Code> { vs.iter().map(|v| dict.encode(v)).collect() };
+
+    let stats = stripe_statistics(&dict, &encode(&[12, 20, 25, 20]));
+    assert_eq!(stats.number_of_values, 4);
+    assert_eq!(stats.exact_hits, 2);
+    assert_eq!(
+        stats.typed,
+        Some(TypedStatistics::Integer {
+            minimum: 10,
+            maximum: 30
+        })
+    );
+
+    // Values past the last exact code have no upper bound.
+    let stats = stripe_statistics(&dict, &encode(&[20, 31]));
+    assert_eq!(stats.typed, None);
+    assert_eq!(stripe_statistics(&dict, &[]).number_of_values, 0);
+}
+
+#[test]
+fn string_statistics() {
+    let words: Vec<String> = ["ant", "bee", "cat", "dog"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let dict = Dict::new(Mode::Byte, words.clone());
+    let codes: Vec<Code> = words[1..3].iter().map(|w| dict.encode(w)).collect();
+    assert_eq!(
+        stripe_statistics(&dict, &codes).typed,
+        Some(TypedStatistics::String {
+            minimum: "bee".to_string(),
+            maximum: "cat".to_string()
+        })
+    );
+}
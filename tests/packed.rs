@@ -0,0 +1,100 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::predicate::CodePredicate;
+use ordbog::sketch::Sketch;
+use ordbog::sketchfile::{SketchFile, SketchHeader};
+use ordbog::writer::CodeColumnWriter;
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound;
+
+fn column() -> Vec<u32> {
+    common::uniform(11, 20_000, 0..1_000_000)
+}
+
+#[test]
+fn packed_codes_take_twelve_bits() {
+    let mode = Mode::Packed;
+    assert_eq!(mode.num_exact_codes(), 2047);
+    assert_eq!(mode.max_exact_code(), Code(0xffe));
+    assert_eq!(mode.codes().len(), 4095);
+    assert_eq!(Mode::from_bits(mode.bits()), Some(mode));
+    assert_eq!(Mode::from_bits(10), None);
+    assert!(Mode::Byte < mode && mode < Mode::Word);
+    assert_eq!(mode.to_signed(Code(0)), -2048);
+    assert_eq!(mode.to_signed(Code(0xfff)), 2047);
+    assert_eq!(mode.from_signed(mode.to_signed(Code(0x9a5))), Code(0x9a5));
+
+    let mut out = Vec::new();
+    mode.pack_codes(&[Code(0xabc), Code(0x123), Code(0xfff)], &mut out);
+    assert_eq!(out, [0xbc, 0x3a, 0x12, 0xff, 0x0f]);
+    assert_eq!(out.len(), mode.stored_len(3));
+    assert_eq!(mode.stored_code(&out, 1), Code(0x123));
+    let mut back = Vec::new();
+    mode.unpack_codes(&out, &mut back);
+    assert_eq!(back, [Code(0xabc), Code(0x123), Code(0xfff)]);
+    for n in 0..6 {
+        assert_eq!(mode.stored_len(n), (3 * n).div_ceil(2));
+    }
+}
+
+#[test]
+#[should_panic]
+fn packed_codes_refuse_wider_codes() {
+    Mode::Packed.pack_codes(&[Code(0x1000)], &mut Vec::new());
+}
+
+#[test]
+fn packed_dicts_sit_between_byte_and_word() {
+    let values = column();
+    let dict = Dict::new(Mode::Packed, values.clone());
+    assert!(dict.codes.len() > Mode::Byte.num_exact_codes());
+    assert!(dict.codes.len() <= Mode::Packed.num_exact_codes());
+    let pred = (Bound::Included(250_000), Bound::Excluded(260_000));
+    let compiled = CodePredicate::from_range(&dict, &pred).compile(Mode::Packed);
+    assert_eq!(compiled.table.len(), 0x1000);
+    for v in values.iter() {
+        let code = dict.encode(v);
+        assert!(code <= Mode::Packed.max_inexact_code());
+        if (250_000..260_000).contains(v) {
+            assert_ne!(compiled.table[code.0 as usize], 0);
+        }
+    }
+}
+
+#[test]
+fn packed_sketches_round_trip_with_odd_blocks() {
+    let values = column();
+    let dict = Dict::new(Mode::Packed, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    // Blocks of an odd number of rows each end in a half-empty byte.
+    let file = SketchFile::new(dict, Sketch::from_codes(Mode::Packed, &codes), 999);
+    let bytes = file.to_bytes();
+    let header = SketchHeader::read(&bytes).unwrap();
+    assert_eq!(header.mode, Mode::Packed);
+    assert_eq!(
+        header.codes.1 as usize,
+        20 * Mode::Packed.stored_len(999) + Mode::Packed.stored_len(20)
+    );
+    let raw = header.block_codes(&bytes, 3).unwrap();
+    assert_eq!(Mode::Packed.stored_code(&raw, 998), codes[3 * 999 + 998]);
+    let back = SketchFile::<u32>::from_bytes(&bytes).unwrap();
+    assert_eq!(back.codes.to_codes(), codes);
+    assert_eq!(back.zones, file.zones);
+
+    let mut writer = CodeColumnWriter::new(Vec::new(), Mode::Packed, 999);
+    writer.push_all(&codes).unwrap();
+    let (written, zones) = writer.finish().unwrap();
+    let (at, len) = header.codes;
+    assert_eq!(written, &bytes[at as usize..(at + len) as usize]);
+    assert_eq!(zones, file.zones);
+
+    #[cfg(feature = "zstd")]
+    {
+        let compressed = file.to_bytes_compressed(3);
+        let back = SketchFile::<u32>::from_bytes(&compressed).unwrap();
+        assert_eq!(back.codes.to_codes(), codes);
+    }
+}
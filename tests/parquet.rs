@@ -0,0 +1,60 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::parquet::{code_column_index, offset_index, value_column_index, BoundaryOrder};
+use ordbog::zonemap::ZoneMap;
+use ordbog::{Code, Dict, Mode};
+use std::convert::TryInto;
+
+#[test]
+fn code_and_value_domain_statistics() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let column = [10i64, 15, 20, 25, 30, 30, 12];
+    let codes: Vec<Code> = column.iter().map(|v| dict.encode(v)).collect();
+    let zones = ZoneMap::new(&codes, 2).zones;
+    assert_eq!(zones.len(), 4);
+
+    let ci = code_column_index(&zones);
+    assert_eq!(ci.null_pages, vec![false; 4]);
+    assert_eq!(ci.min_values[1], 4i32.to_le_bytes().to_vec());
+    assert_eq!(ci.max_values[1], 5i32.to_le_bytes().to_vec());
+    assert_eq!(ci.boundary_order, BoundaryOrder::Unordered);
+
+    let vi = value_column_index(&dict, &zones).unwrap();
+    let bounds: Vec<(i64, i64)> = vi
+        .min_values
+        .iter()
+        .zip(vi.max_values.iter())
+        .map(|(lo, hi)| {
+            let le = |b: &Vec<u8>| i64::from_le_bytes(b.as_slice().try_into().unwrap());
+            (le(lo), le(hi))
+        })
+        .collect();
+    assert_eq!(bounds, vec![(10, 20), (20, 30), (30, 30), (10, 20)]);
+    for (i, v) in column.iter().enumerate() {
+        assert!(bounds[i / 2].0 <= *v && *v <= bounds[i / 2].1);
+    }
+
+    // Values outside every exact code have one-sided intervals.
+    let codes: Vec<Code> = [5i64, 10].iter().map(|v| dict.encode(v)).collect();
+    assert!(value_column_index(&dict, &ZoneMap::new(&codes, 2).zones).is_none());
+}
+
+#[test]
+fn sorted_columns_have_ascending_boundaries() {
+    let values: Vec<i32> = (0..1000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let zones = ZoneMap::new(&codes, 100).zones;
+    assert_eq!(
+        code_column_index(&zones).boundary_order,
+        BoundaryOrder::Ascending
+    );
+    let pages: Vec<(i64, i32)> = (0..zones.len() as i64)
+        .map(|i| (4 + i * 300, 300))
+        .collect();
+    let oi = offset_index(&zones, &pages);
+    assert_eq!(oi.page_locations[3].first_row_index, 300);
+    assert_eq!(oi.page_locations[3].offset, 904);
+}
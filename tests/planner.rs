@@ -0,0 +1,49 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::planner::{plan, CostModel, Strategy};
+use ordbog::predicate::CodePredicate;
+use ordbog::{Dict, Mode};
+use std::ops::Bound::*;
+
+#[test]
+fn plans_follow_selectivity() {
+    // Sorted values, so clustered blocks; the planner's independence
+    // assumption makes its block estimates pessimistic here.
+    let values: Vec<i64> = (0..100_000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let stats = dict.export_histogram(&values);
+    let cost = CostModel::default();
+
+    // A tight range mostly inside one inexact code: few candidates, all of
+    // them maybes, so rechecking them individually is cheapest.
+    let p = CodePredicate::from_range(&dict, &(Included(500), Included(510)));
+    let chosen = plan(&p, &stats, &cost);
+    assert_eq!(chosen.strategy, Strategy::SketchWithRecheck);
+    assert!(chosen.maybe_fraction < 0.05);
+    assert!(chosen.cost < cost.value_cost);
+
+    // Everything matches definitely: the codes answer alone.
+    let p = CodePredicate::from_range(&dict, &(Unbounded, Unbounded));
+    let chosen = plan(&p, &stats, &cost);
+    assert_eq!(chosen.strategy, Strategy::SketchWithRecheck);
+    assert!((chosen.definite_fraction - 1.0).abs() < 1e-9);
+
+    // With expensive rechecks and candidates in every block, the sketch
+    // can't beat a raw scan.
+    let costly = CostModel {
+        recheck_cost: 1000.0,
+        ..cost
+    };
+    let p = CodePredicate::from_range(&dict, &(Included(500), Included(510)));
+    assert_eq!(plan(&p, &stats, &costly).strategy, Strategy::ScanRaw);
+
+    // With small blocks, existence checks skip most of them.
+    let small = CostModel {
+        block_rows: 4,
+        ..costly
+    };
+    let chosen = plan(&p, &stats, &small);
+    assert_eq!(chosen.strategy, Strategy::SketchExistencePerBlock);
+    assert!(chosen.block_fraction < 0.1);
+}
@@ -0,0 +1,54 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "polars-arrow")]
+
+use ordbog::polars_arrow::{encode_dictionary, EncodeArray};
+use ordbog::{Code, Dict, Mode};
+use polars_arrow::array::{
+    Array, DictionaryArray, Int64Array, PrimitiveArray, Utf8Array, Utf8ViewArray,
+};
+use polars_arrow::datatypes::{ArrowDataType, IntegerType};
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn encodes_primitive_and_string_arrays() {
+    let dict = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let array = Int64Array::from(&[Some(10), None, Some(25)]);
+    assert_eq!(array.encode(&dict), vec![Code(2), Code(0), Code(5)]);
+    assert_eq!(
+        array.encode_column(&dict).validity,
+        Some(vec![true, false, true])
+    );
+
+    let dict = Dict::new(Mode::Byte, words(&["ant", "bee", "cat"]));
+    let rows = [Some("bee"), Some("cow"), None];
+    let expected = vec![Code(4), Code(7), Code(0)];
+    assert_eq!(Utf8Array::<i64>::from(rows).encode(&dict), expected);
+    assert_eq!(Utf8ViewArray::from_slice(rows).encode(&dict), expected);
+}
+
+#[test]
+fn dictionary_arrays_of_views_match_plain_encoding() {
+    let dict = Dict::new(Mode::Byte, words(&["ant", "bee", "cat"]));
+    let values = Utf8ViewArray::from_slice([Some("cat"), None, Some("bee")]);
+    let keys = PrimitiveArray::<u32>::from(&[Some(0), Some(2), None, Some(1), Some(0)]);
+    let dtype =
+        ArrowDataType::Dictionary(IntegerType::UInt32, Box::new(values.dtype().clone()), false);
+    let array = DictionaryArray::try_new(dtype, keys, values.boxed()).unwrap();
+    assert_eq!(
+        array.encode(&dict),
+        vec![Code(6), Code(4), Code(0), Code(0), Code(6)]
+    );
+    assert_eq!(
+        array.encode_column(&dict).validity,
+        Some(vec![true, true, false, false, true])
+    );
+    assert!(
+        encode_dictionary::<u32, Int64Array, i64>(&Dict::new(Mode::Byte, vec![1]), &array)
+            .is_none()
+    );
+}
@@ -0,0 +1,310 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use float_ord::FloatOrd;
+use ordbog::predicate::{CodePredicate, FloatValue, Predicate, Ternary};
+use ordbog::{Code, Dict, DictF32, DictF64, Mode};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::ops::Bound::*;
+
+#[test]
+fn range_rewrites_split_definite_and_maybe() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let p = CodePredicate::from_range(&dict, &(Included(12), Included(30)));
+    assert_eq!(p.maybe, vec![Code(3)..=Code(3)]);
+    assert_eq!(p.definite, vec![Code(4)..=Code(6)]);
+    assert_eq!(p.candidates(), vec![Code(3)..=Code(6)]);
+
+    let p = CodePredicate::from_range(&dict, &(Included(12), Included(25)));
+    assert_eq!(p.maybe, vec![Code(3)..=Code(3), Code(5)..=Code(5)]);
+    assert_eq!(p.definite, vec![Code(4)..=Code(4)]);
+    assert!(p.is_definite(Code(4)) && p.is_maybe(Code(5)) && !p.is_maybe(Code(6)));
+
+    let p = CodePredicate::from_range(&dict, &(Included(12), Included(18)));
+    assert_eq!(p.maybe, vec![Code(3)..=Code(3)]);
+    assert!(p.definite.is_empty());
+
+    let p = CodePredicate::from_range(&dict, &(Excluded(30), Unbounded));
+    assert_eq!(p.definite, vec![Code(7)..=Code(7)]);
+    assert_eq!(
+        CodePredicate::from_range(&dict, &(Included(30), Included(20))),
+        CodePredicate::default()
+    );
+}
+
+#[test]
+fn rewrites_admit_every_matching_value() {
+    let values: Vec<i32> = (0..5000).map(|i| (i * 7919) % 2000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    for (lo, hi) in [(0, 0), (100, 180), (555, 1555), (1999, 2500)] {
+        let p = CodePredicate::from_range(&dict, &(Included(lo), Excluded(hi)));
+        for v in values.iter() {
+            let c = dict.encode(v);
+            if lo <= *v && *v < hi {
+                assert!(p.is_definite(c) || p.is_maybe(c));
+            } else {
+                assert!(!p.is_definite(c));
+            }
+        }
+    }
+}
+
+#[test]
+fn renders_sql() {
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let sql = CodePredicate::from_range(&dict, &(Included(12), Included(30))).to_sql("code_col");
+    assert_eq!(sql.candidates, "code_col BETWEEN 3 AND 6");
+    assert_eq!(sql.definite, "code_col BETWEEN 4 AND 6");
+
+    let p = CodePredicate {
+        definite: vec![Code(2)..=Code(2), Code(8)..=Code(10)],
+        maybe: vec![Code(5)..=Code(5)],
+    };
+    let sql = p.to_sql("\"c\"");
+    assert_eq!(sql.definite, "(\"c\" = 2 OR \"c\" BETWEEN 8 AND 10)");
+    assert_eq!(
+        sql.candidates,
+        "(\"c\" = 2 OR \"c\" = 5 OR \"c\" BETWEEN 8 AND 10)"
+    );
+    assert_eq!(CodePredicate::default().to_sql("c").candidates, "FALSE");
+}
+
+#[test]
+fn compiled_tables_agree_with_ranges() {
+    use ordbog::predicate::{CLASS_DEFINITE, CLASS_MAYBE, CLASS_NONE};
+
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let p = CodePredicate::from_range(&dict, &(Included(12), Included(25)));
+    let c = p.compile(Mode::Byte);
+    assert_eq!(c.table.len(), 256);
+    let classes: Vec<u8> = (1..=7).map(|i| c.classify(Code(i))).collect();
+    assert_eq!(
+        classes,
+        vec![
+            CLASS_NONE,
+            CLASS_NONE,
+            CLASS_MAYBE,
+            CLASS_DEFINITE,
+            CLASS_MAYBE,
+            CLASS_NONE,
+            CLASS_NONE
+        ]
+    );
+    let t = c.candidates.unwrap();
+    assert_eq!((t.lo, t.hi), (3, 5));
+    assert!(!t.contains(0) && t.contains(3) && t.contains(5) && !t.contains(6));
+    assert_eq!(c.definite.map(|t| (t.lo, t.hi)), Some((4, 4)));
+
+    let codes: Vec<Code> = (0..=255).map(Code).collect();
+    let mut out = vec![0u8; codes.len()];
+    c.classify_into(&codes, &mut out);
+    let flags = c.candidates(&codes);
+    for (i, code) in codes.iter().enumerate() {
+        assert_eq!(out[i] == CLASS_DEFINITE, p.is_definite(*code));
+        assert_eq!(out[i] == CLASS_MAYBE, p.is_maybe(*code));
+        assert_eq!(flags[i], out[i] != CLASS_NONE);
+    }
+
+    // Disjoint candidates fall back to the table; Word tables cover 16 bits.
+    let split = CodePredicate {
+        definite: vec![Code(2)..=Code(2), Code(900)..=Code(902)],
+        maybe: vec![],
+    }
+    .compile(Mode::Word);
+    assert_eq!(split.table.len(), 65536);
+    assert!(split.candidates.is_none() && split.definite.is_none());
+    assert_eq!(
+        split.candidates(&[Code(2), Code(3), Code(901)]),
+        vec![true, false, true]
+    );
+}
+
+#[test]
+fn approximate_float_equality_covers_its_neighbourhood() {
+    let sample = [0.1f64, 0.2, 0.30000000000000004, 0.4, -0.0, 1.5];
+    let dict = Dict::new_with_key(Mode::Byte, &sample, |f| DictF64(FloatOrd(*f)));
+    let key = |f: f64| DictF64(FloatOrd(f));
+
+    // The naive rewrite of 0.1 + 0.2 == 0.3 admits nothing stored.
+    let exact = CodePredicate::from_range(&dict, &(Included(key(0.3)), Included(key(0.3))));
+    assert!(exact.definite.is_empty());
+    let stored = dict.encode(&key(0.1 + 0.2));
+    assert!(!exact.is_definite(stored) && !exact.is_maybe(stored));
+
+    let p = CodePredicate::from_approx_eq(&dict, 0.3, 1e-9);
+    assert!(p.is_definite(stored));
+    assert!(!p.is_definite(dict.encode(&key(0.2))) && !p.is_maybe(dict.encode(&key(0.2))));
+    let wide = CodePredicate::from_approx_eq(&dict, 0.3, 0.1);
+    for v in [0.2, 0.30000000000000004].iter() {
+        assert!(wide.is_definite(dict.encode(&key(*v))));
+    }
+    // (0.4 - 0.3).abs() is 0.10000000000000003, but 0.4 is close enough to
+    // the end of the tolerance to need checking.
+    assert!(wide.is_maybe(dict.encode(&key(0.4))));
+
+    // Both zeros match a tolerance around zero.
+    let zero = CodePredicate::from_approx_eq(&dict, 0.0, 0.0);
+    assert!(zero.is_definite(dict.encode(&key(-0.0))));
+    assert!(zero.is_maybe(dict.encode(&key(0.0))) || zero.is_definite(dict.encode(&key(0.0))));
+
+    // Nothing satisfies a negative or NaN tolerance, or an infinite centre.
+    for (c, eps) in [(0.3, -1.0), (0.3, f64::NAN), (f64::INFINITY, 1.0)].iter() {
+        assert_eq!(
+            CodePredicate::from_approx_eq(&dict, *c, *eps),
+            CodePredicate::default()
+        );
+    }
+}
+
+#[test]
+fn tolerances_bound_single_precision_floats() {
+    let key = |f: f32| DictF32(FloatOrd(f));
+    assert_eq!(
+        DictF32::tolerance_interior(1.0f32, 0.25),
+        Some((Included(key(0.75)), Included(key(1.25))))
+    );
+    match DictF32::tolerance_bounds(1.0f32, 0.25).unwrap() {
+        (Included(lo), Included(hi)) => assert!(lo < key(0.75) && hi > key(1.25)),
+        bounds => panic!("{:?}", bounds),
+    }
+    let zero = DictF32::tolerance_interior(-0.5f32, 0.5).unwrap();
+    assert_eq!(zero.1, Included(key(0.0)));
+    assert_eq!(
+        DictF32::tolerance_interior(0.0f32, -0.0).unwrap().0,
+        Included(key(-0.0))
+    );
+}
+
+#[test]
+fn approximate_float_equality_covers_the_rounded_ends() {
+    let key = |f: f64| DictF64(FloatOrd(f));
+    let within = |x: f64, c: f64, eps: f64| (x - c).abs() <= eps;
+
+    // x sits an ulp above the rounded c + eps, yet (x - c).abs() <= eps.
+    let (c, eps, x) = (-125.02581943417908, 141.89478889151056, 16.868969457331477);
+    assert!(within(x, c, eps) && x > c + eps);
+    let sample: Vec<f64> = (-4..=4).map(|i| x + i as f64 * 1e-14).collect();
+    let dict = Dict::new_with_key(Mode::Word, &sample, |f| key(*f));
+    let p = CodePredicate::from_approx_eq(&dict, c, eps);
+    assert!(p.is_maybe(dict.encode(&key(x))));
+
+    // Around random tolerances, every value that passes is a candidate, and
+    // every value with a definite code passes.
+    let mut rng = StdRng::seed_from_u64(498);
+    for _ in 0..2000 {
+        let c: f64 = rng.gen_range(-1e3..1e3);
+        let eps: f64 = rng.gen_range(0.0..1e3);
+        let mut sample = vec![c];
+        for end in [c - eps, c + eps].iter() {
+            let mut v = end.next_down().next_down().next_down();
+            for _ in 0..7 {
+                sample.push(v);
+                v = v.next_up();
+            }
+        }
+        let dict = Dict::new_with_key(Mode::Word, &sample, |f| key(*f));
+        let p = CodePredicate::from_approx_eq(&dict, c, eps);
+        for x in sample.iter() {
+            let code = dict.encode(&key(*x));
+            if within(*x, c, eps) {
+                assert!(
+                    p.is_definite(code) || p.is_maybe(code),
+                    "{} {} {}",
+                    c,
+                    eps,
+                    x
+                );
+            }
+            if p.is_definite(code) {
+                assert!(within(*x, c, eps), "{} {} {}", c, eps, x);
+            }
+        }
+    }
+}
+
+#[test]
+fn value_predicates_rewrite_onto_codes() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::from_codes(Mode::Byte, vec![10, 20, 30]);
+    let p = dict.rewrite(&Predicate::Lt(20));
+    assert_eq!(p.definite, vec![Code(1)..=Code(3)]);
+    assert!(p.maybe.is_empty());
+    let p = dict.rewrite(&Predicate::Ge(25));
+    assert_eq!(p.maybe, vec![Code(5)..=Code(5)]);
+    assert_eq!(p.definite, vec![Code(6)..=Code(7)]);
+    let p = dict.rewrite(&Predicate::Between(20, 30));
+    assert_eq!(p.definite, vec![Code(4)..=Code(6)]);
+    assert_eq!(
+        dict.rewrite(&Predicate::Eq(15)).maybe,
+        vec![Code(3)..=Code(3)]
+    );
+    assert_eq!(
+        dict.rewrite(&Predicate::InSet(vec![30, 15])),
+        CodePredicate::from_in_list(&dict, &[30, 15])
+    );
+
+    // Every value against each predicate: satisfying values have candidate
+    // codes, and definite codes hold only satisfying values.
+    let values: Vec<i32> = (0..5000).map(|i| (i * 7919) % 2000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let preds = [
+        Predicate::Eq(1000),
+        Predicate::Lt(333),
+        Predicate::Le(333),
+        Predicate::Gt(1500),
+        Predicate::Ge(1500),
+        Predicate::Between(400, 1200),
+        Predicate::InSet(vec![5, 77, 1999, 3000]),
+    ];
+    for pred in preds.iter() {
+        let p = dict.rewrite(pred);
+        for v in values.iter() {
+            let holds = match pred {
+                Predicate::Eq(c) => v == c,
+                Predicate::Lt(c) => v < c,
+                Predicate::Le(c) => v <= c,
+                Predicate::Gt(c) => v > c,
+                Predicate::Ge(c) => v >= c,
+                Predicate::Between(lo, hi) => lo <= v && v <= hi,
+                Predicate::InSet(set) => set.contains(v),
+            };
+            let code = dict.encode(v);
+            if holds {
+                assert!(p.is_definite(code) || p.is_maybe(code), "{:?} {}", pred, v);
+            }
+            if p.is_definite(code) {
+                assert!(holds, "{:?} {}", pred, v);
+            }
+        }
+    }
+}
+
+#[test]
+fn predicates_evaluate_codes_three_ways() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::from_codes(Mode::Byte, vec![10, 20, 30]);
+    let p = dict.rewrite(&Predicate::Between(15, 30));
+    let expected = [
+        Ternary::False,
+        Ternary::False,
+        Ternary::False,
+        Ternary::Maybe,
+        Ternary::True,
+        Ternary::True,
+        Ternary::True,
+        Ternary::False,
+    ];
+    let codes: Vec<Code> = (0..8).map(Code).collect();
+    let compiled = p.compile(Mode::Byte);
+    let mut out = vec![Ternary::False; codes.len()];
+    compiled.evaluate_into(&codes, &mut out);
+    assert_eq!(out, expected);
+    for (c, e) in codes.iter().zip(expected.iter()) {
+        assert_eq!(p.evaluate(*c), *e);
+        assert_eq!(compiled.evaluate(*c), *e);
+    }
+    assert!(Ternary::True.is_conclusive() && Ternary::False.is_conclusive());
+    assert!(!Ternary::Maybe.is_conclusive());
+}
@@ -69,9 +69,10 @@ trait Testable: Ord + Clone + Default + Debug {
     }
 
     fn check_dict_of_sample(sample: Vec<Self>) {
-        for mode in vec![Mode::Byte, Mode::Word] {
+        for mode in [Mode::Byte, Mode::Word] {
             let d: Dict<Self> = Dict::new(mode, sample.clone());
             assert!(d.codes.len() <= mode.num_exact_codes());
+            assert!(d.codes.len() == d.codes.capacity());
             for slice in d.codes.windows(2) {
                 assert!(slice[0] != slice[1]);
                 assert!(slice[0] < slice[1]);
@@ -90,14 +91,14 @@ trait Testable: Ord + Clone + Default + Debug {
 
 impl Testable for i32 {
     fn next(x: &Self) -> Option<Self> {
-        if *x < std::i32::MAX {
+        if *x < i32::MAX {
             Some(*x + 1)
         } else {
             None
         }
     }
     fn prev(x: &Self) -> Option<Self> {
-        if *x > std::i32::MIN {
+        if *x > i32::MIN {
             Some(*x - 1)
         } else {
             None
@@ -0,0 +1,143 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::quality::{
+    advise_rebuild, cross_validate, measure_storage, simulate_blocks, Metrics, StorageReport,
+};
+use ordbog::{Code, Dict, Mode};
+
+#[test]
+fn metrics_of_low_cardinality_sample() {
+    let sample: Vec<u32> = (0..1000).map(|i| i % 10).collect();
+    let dict = Dict::new(Mode::Byte, sample.clone());
+    let m = Metrics::measure(&dict, &sample);
+    assert_eq!(m.exact_hit_rate, 1.0);
+    assert_eq!(m.inexact_width, 0.0);
+    assert_eq!(m.max_code_mass, 0.1);
+
+    // Values outside the dictionary land in the open intervals at the ends.
+    let m = Metrics::measure(&dict, &[100, 200, 300, 0]);
+    assert_eq!(m.exact_hit_rate, 0.25);
+    assert_eq!(m.inexact_width, 0.75);
+    assert_eq!(m.max_code_mass, 0.75);
+}
+
+#[test]
+fn cross_validation_of_representative_sample() {
+    // A pseudo-random but deterministic sample with a few heavy hitters.
+    let sample = common::skewed(12345, 20000, 0.25);
+    let cv = cross_validate(Mode::Byte, &sample, 5);
+    assert_eq!(cv.folds.len(), 5);
+    // The heavy hitters get exact codes in every fold.
+    assert!(cv.exact_hit_rate.mean > 0.2);
+    assert!(cv.exact_hit_rate.variance < 0.001);
+    assert!(cv.max_code_mass.mean < 0.2);
+}
+
+#[test]
+fn word_mode_elides_more_large_blocks_than_byte_mode() {
+    let sample: Vec<u64> = common::uniform(777, 50000, 0..1_000_000);
+    let byte = Dict::new(Mode::Byte, sample.clone());
+    let word = Dict::new(Mode::Word, sample.clone());
+    let sizes = [64, 1024, 4096];
+    let bs = simulate_blocks(&byte, &sample, &sizes);
+    let ws = simulate_blocks(&word, &sample, &sizes);
+    for (b, w) in bs.iter().zip(ws.iter()) {
+        assert_eq!(b.match_rate, w.match_rate);
+        assert!(b.candidate_rate >= b.match_rate);
+        assert!(w.candidate_rate >= w.match_rate);
+        assert!(w.false_positive_rate <= b.false_positive_rate);
+    }
+    // Byte codes over cache-line sized blocks still skip most blocks, but
+    // over page-sized blocks nearly every block is a candidate.
+    assert!(bs[0].candidate_rate < 0.5);
+    assert!(bs[2].candidate_rate > 0.9);
+    assert!(ws[2].candidate_rate < 0.5);
+}
+
+#[test]
+fn histogram_buckets_cover_every_code() {
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 20, 30]);
+    let h = dict.export_histogram(&[5, 10, 15, 20, 20, 20, 35]);
+    assert_eq!(h.total, 7);
+    let summary: Vec<(u16, usize, Option<i32>, Option<i32>)> = h
+        .buckets
+        .iter()
+        .map(|b| (b.code.0, b.count, b.lower, b.upper))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (1, 1, None, Some(10)),
+            (2, 1, Some(10), Some(10)),
+            (3, 1, Some(10), Some(20)),
+            (4, 3, Some(20), Some(20)),
+            (5, 0, Some(20), Some(30)),
+            (6, 0, Some(30), Some(30)),
+            (7, 1, Some(30), None),
+        ]
+    );
+    let prom = h.to_prometheus("ordbog_code_mass");
+    assert!(prom.contains("ordbog_code_mass{code=\"4\",exact=\"true\"} 3\n"));
+    assert!(prom.contains("ordbog_code_mass{code=\"7\",exact=\"false\"} 1\n"));
+}
+
+#[test]
+fn rebuild_advised_only_after_drift() {
+    let construction = common::uniform(4242, 5000, 0..10_000u64);
+    let same = common::uniform(4243, 5000, 0..10_000);
+    let drifted = common::uniform(4244, 5000, 2_000..12_000);
+    let dict = Dict::new(Mode::Byte, construction.clone());
+
+    let a = advise_rebuild(&dict, &construction, &same, 0.01);
+    assert!(!a.rebuild);
+    assert!(a.p_value > 0.01);
+
+    let a = advise_rebuild(&dict, &construction, &drifted, 0.01);
+    assert!(a.rebuild);
+    assert!(a.statistic > 0.15);
+    assert!(a.p_value < 1e-6);
+}
+
+#[test]
+fn storage_reports_weigh_codes_against_the_base_column() {
+    // Ten values, sorted: runs of 100 rows each.
+    let sample: Vec<u64> = (0..1000).map(|i| i / 100).collect();
+    let dict = Dict::new(Mode::Word, sample.clone());
+    let codes: Vec<Code> = sample.iter().map(|v| dict.encode(v)).collect();
+    let r = measure_storage(&dict, &codes, 64.0);
+    assert_eq!(r.rows, 1000);
+    assert_eq!(r.stored_bits, 16.0);
+    // The greatest code is 20, for the tenth value.
+    assert_eq!(r.packed_bits, 5.0);
+    // Ten runs, each a 5-bit code and a one-byte length.
+    assert_eq!(r.rle_bits, 10.0 * 13.0 / 1000.0);
+    assert_eq!(r.best_bits(), r.rle_bits);
+    assert_eq!(r.overhead(), r.rle_bits / 64.0);
+    // Ten exact codes in use, of the 21 assigned.
+    assert_eq!(r.code_utilization, 10.0 / 21.0);
+    assert_eq!(r.codespace_utilization, 21.0 / 65535.0);
+
+    // Unsorted, runs are short and packing wins.
+    let shuffled: Vec<Code> = (0..1000).map(|i| codes[(i * 101) % 1000]).collect();
+    let r = measure_storage(&dict, &shuffled, 64.0);
+    assert_eq!(r.best_bits(), 5.0);
+
+    // Null rows, with code 0, don't count as a code in use.
+    let every: Vec<Code> = (0..=21).map(Code).collect();
+    let r = measure_storage(&dict, &every, 64.0);
+    assert_eq!(r.code_utilization, 1.0);
+
+    let empty = measure_storage(&dict, &[], 64.0);
+    assert_eq!(
+        empty,
+        StorageReport {
+            base_bits: 64.0,
+            codespace_utilization: 21.0 / 65535.0,
+            ..StorageReport::default()
+        }
+    );
+    assert_eq!(empty.overhead(), 0.0);
+}
@@ -0,0 +1,66 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{BuildOptions, Code, Dict, Mode};
+
+const RETAIN: BuildOptions = BuildOptions {
+    retain_weights: true,
+    summary_capacity: 0,
+    reserved_codes: 0,
+    dense_codes: false,
+    code_capacity: 0,
+};
+
+#[test]
+fn quantiles_follow_sample_mass() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6; half the sample is 30.
+    let mut sample = vec![10, 20, 15, 25];
+    sample.extend([30; 4]);
+    let dict = Dict::new_with_options(Mode::Byte, sample.clone(), &RETAIN);
+    assert_eq!(dict.codes, vec![10, 15, 20, 25, 30]);
+    assert_eq!(dict.code_weight(Code(10)), Some(4));
+    assert_eq!(dict.code_weight(Code(11)), Some(0));
+    assert_eq!(dict.code_weight(Code(0)), None);
+    assert_eq!(dict.code_weight(Code(12)), None);
+    let q = dict.code_quantiles(&[0.0, 0.25, 0.5, 0.51, 1.0, 7.0, f64::NAN]);
+    assert_eq!(
+        q,
+        vec![
+            Code(2),
+            Code(4),
+            Code(8),
+            Code(10),
+            Code(10),
+            Code(10),
+            Code(2)
+        ]
+    );
+
+    // On a larger sample the quantile codes bracket the true quantiles.
+    let sample: Vec<u32> = (0..20_000u32).map(|i| (i * 7919) % 10_007).collect();
+    let dict = Dict::new_with_options(Mode::Byte, sample.clone(), &RETAIN);
+    let mut sorted = sample.clone();
+    sorted.sort_unstable();
+    for (q, code) in [0.05, 0.5, 0.95]
+        .iter()
+        .zip(dict.code_quantiles(&[0.05, 0.5, 0.95]))
+    {
+        let v = sorted[(q * sorted.len() as f64) as usize];
+        let c = dict.encode(&v);
+        assert!(
+            (c.0 as i32 - code.0 as i32).abs() <= 1,
+            "{:?} vs {:?}",
+            c,
+            code
+        );
+    }
+}
+
+#[test]
+fn quantiles_without_weights_use_exact_codes() {
+    let dict = Dict::from_codes(Mode::Byte, vec![10, 20, 30, 40, 50]);
+    assert_eq!(
+        dict.code_quantiles(&[0.0, 0.5, 1.0]),
+        vec![Code(2), Code(6), Code(10)]
+    );
+}
@@ -0,0 +1,73 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::regex::{literal_prefix, prefix_range, rewrite_regex};
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+
+#[test]
+fn extracts_anchored_literal_prefixes() {
+    let p = literal_prefix;
+    assert_eq!(p("^ERROR"), Some(("ERROR".to_string(), true)));
+    assert_eq!(p("^ERROR.*timeout"), Some(("ERROR".to_string(), false)));
+    assert_eq!(p("\\AWARN:"), Some(("WARN:".to_string(), true)));
+    assert_eq!(p("^a\\.b\\d"), Some(("a.b".to_string(), false)));
+    assert_eq!(p("^abc*"), Some(("ab".to_string(), false)));
+    assert_eq!(p("^abc?d"), Some(("ab".to_string(), false)));
+    assert_eq!(p("^abc+d"), Some(("abc".to_string(), false)));
+    assert_eq!(p("^ab(c|d)"), Some(("ab".to_string(), false)));
+    assert_eq!(p("^ab[|]c"), Some(("ab".to_string(), false)));
+    assert_eq!(p("^ab[]|]c"), Some(("ab".to_string(), false)));
+    assert_eq!(p("^ab|cd"), None);
+    assert_eq!(p("^ab[x]|cd"), None);
+    assert_eq!(p("ERROR"), None);
+    assert_eq!(p("(?i)^ERROR"), None);
+
+    assert_eq!(
+        prefix_range("ab"),
+        (Included("ab".to_string()), Excluded("ac".to_string()))
+    );
+    assert_eq!(
+        prefix_range("a\u{10ffff}"),
+        (
+            Included("a\u{10ffff}".to_string()),
+            Excluded("b".to_string())
+        )
+    );
+    assert_eq!(prefix_range("\u{d7ff}").1, Excluded("\u{e000}".to_string()));
+}
+
+#[test]
+fn regex_rewrites_admit_every_match() {
+    let levels = ["DEBUG", "ERROR", "INFO", "WARN"];
+    let lines: Vec<String> = (0..4000)
+        .map(|i| format!("{} {} request {}", levels[i % 4], i % 97, i))
+        .collect();
+    let dict = Dict::new(Mode::Byte, lines.clone());
+
+    let pred = rewrite_regex(&dict, "^ERROR.*7");
+    assert!(pred.definite.is_empty());
+    let candidates = lines
+        .iter()
+        .filter(|l| pred.is_maybe(dict.encode(*l)))
+        .count();
+    assert!(candidates < lines.len() / 2);
+    for l in lines.iter().filter(|l| l.starts_with("ERROR")) {
+        assert!(pred.is_maybe(dict.encode(l)));
+    }
+
+    let pred = rewrite_regex(&dict, "^INFO");
+    for l in lines.iter() {
+        let c = dict.encode(l);
+        if pred.is_definite(c) {
+            assert!(l.starts_with("INFO"));
+        }
+        if l.starts_with("INFO") {
+            assert!(pred.is_definite(c) || pred.is_maybe(c));
+        }
+    }
+
+    let all = rewrite_regex(&dict, "timeout");
+    let max = Code(2 * dict.codes.len() as u16 + 1);
+    assert_eq!(all.maybe, vec![Code(1)..=max]);
+}
@@ -0,0 +1,155 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use ordbog::remote::{FileSource, RangeSource, ReadFuture, RemoteSketch};
+use ordbog::sketch::Sketch;
+use ordbog::sketchfile::{ReadOptions, SketchFile};
+use ordbog::{Code, Dict, Mode};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Sorted, so the zone map can rule out blocks.
+fn column() -> Vec<u32> {
+    common::sorted_uniform(11, 10_000, 0..1_000_000)
+}
+
+fn sketch(mode: Mode) -> (Dict<u32>, Vec<Code>, Vec<u8>) {
+    let values = column();
+    let dict = Dict::new(mode, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let file = SketchFile::new(
+        Dict::from_codes(mode, dict.codes.clone()),
+        Sketch::from_codes(mode, &codes),
+        1000,
+    );
+    (dict, codes, file.to_bytes())
+}
+
+// An in-memory source counting the bytes fetched from it.
+struct Counted {
+    bytes: Vec<u8>,
+    fetched: Arc<AtomicUsize>,
+}
+
+impl RangeSource for Counted {
+    fn size(&self) -> u64 {
+        self.bytes.size()
+    }
+
+    fn read_range(&self, offset: u64, len: u64) -> ReadFuture<'_> {
+        self.fetched.fetch_add(len as usize, Ordering::Relaxed);
+        self.bytes.read_range(offset, len)
+    }
+}
+
+#[tokio::test]
+async fn remote_sketches_fetch_only_what_is_read() {
+    for mode in [Mode::Byte, Mode::Word].iter().copied() {
+        let (dict, codes, bytes) = sketch(mode);
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let len = bytes.len();
+        let remote = RemoteSketch::open(Counted {
+            bytes,
+            fetched: fetched.clone(),
+        })
+        .await
+        .unwrap();
+        assert_eq!(remote.rows(), codes.len());
+        assert_eq!(remote.blocks(), 10);
+        assert_eq!(remote.dict::<u32>().await.unwrap().codes, dict.codes);
+        assert_eq!(remote.block(3).await.unwrap(), &codes[3000..4000]);
+        // Nothing of the other nine blocks.
+        let width = if mode == Mode::Byte { 1 } else { 2 };
+        assert!(fetched.load(Ordering::Relaxed) <= len - 9 * 1000 * width);
+    }
+}
+
+#[tokio::test]
+async fn block_readers_read_ahead_in_order() {
+    let (dict, codes, bytes) = sketch(Mode::Word);
+    let remote = RemoteSketch::open(bytes).await.unwrap();
+    let lo = dict.encode(&200_000);
+    let hi = dict.encode(&400_000);
+    let wanted = remote.zones().candidates(lo, hi);
+    assert!(!wanted.is_empty() && wanted.len() < remote.blocks());
+    for readahead in 0..4 {
+        let mut reader = remote.read_blocks(wanted.clone(), readahead);
+        let mut seen = Vec::new();
+        while let Some(block) = reader.next().await {
+            let (i, block) = block.unwrap();
+            assert_eq!(block, &codes[i * 1000..(i + 1) * 1000]);
+            seen.push(i);
+        }
+        assert_eq!(seen, wanted);
+    }
+}
+
+#[tokio::test]
+async fn remote_blocks_are_verified() {
+    let (_, _, mut bytes) = sketch(Mode::Byte);
+    let remote = RemoteSketch::open(bytes.clone()).await.unwrap();
+    let (offset, _) = remote.header().codes;
+    bytes[offset as usize + 5500] ^= 1;
+    let remote = RemoteSketch::open(bytes.clone()).await.unwrap();
+    assert!(remote.block(4).await.is_ok());
+    let err = remote.block(5).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let mut reader = remote.read_blocks(vec![4, 5, 6], 2);
+    assert!(reader.next().await.unwrap().is_ok());
+    assert!(reader.next().await.unwrap().is_err());
+    let options = ReadOptions {
+        verify_checksums: false,
+    };
+    let remote = RemoteSketch::open_with_options(bytes, &options)
+        .await
+        .unwrap();
+    assert!(remote.block(5).await.is_ok());
+}
+
+#[tokio::test]
+async fn remote_sketches_open_local_files() {
+    let (dict, codes, bytes) = sketch(Mode::Byte);
+    let path = std::env::temp_dir().join(format!("ordbog-remote-{}.obg", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+    let remote = RemoteSketch::open(FileSource::open(&path).await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(remote.dict::<u32>().await.unwrap().codes, dict.codes);
+    let mut reader = remote.read_blocks((0..remote.blocks()).collect(), 3);
+    let mut read = Vec::new();
+    while let Some(block) = reader.next().await {
+        read.extend(block.unwrap().1);
+    }
+    assert_eq!(read, codes);
+    std::fs::remove_file(&path).unwrap();
+
+    let err = RemoteSketch::open(b"not a sketch".to_vec()).await;
+    assert_eq!(err.err().unwrap().kind(), io::ErrorKind::InvalidData);
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn compressed_remote_sketches_read_ahead() {
+    let (dict, codes, _) = sketch(Mode::Word);
+    let file = SketchFile::new(
+        Dict::from_codes(Mode::Word, dict.codes.clone()),
+        Sketch::from_codes(Mode::Word, &codes),
+        1000,
+    );
+    let remote = RemoteSketch::open(file.to_bytes_compressed(3))
+        .await
+        .unwrap();
+    assert_eq!(remote.dict::<u32>().await.unwrap().codes, dict.codes);
+    let mut reader = remote.read_blocks(vec![9, 2, 7], 1);
+    for i in [9, 2, 7].iter().copied() {
+        let (j, block) = reader.next().await.unwrap().unwrap();
+        assert_eq!(j, i);
+        assert_eq!(block, &codes[i * 1000..(i + 1) * 1000]);
+    }
+    assert!(reader.next().await.is_none());
+}
@@ -0,0 +1,81 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::archive::ArchivedDict;
+use ordbog::format::{FormatError, Header, FLAG_RESERVED_CODES};
+use ordbog::{BuildOptions, Code, Dict, Mode};
+
+#[test]
+fn encode_avoids_reserved_band() {
+    let sample: Vec<u32> = (0..100_000).map(|i| (i * 7919) % 50_000).collect();
+    for (mode, reserved) in [(Mode::Byte, 16), (Mode::Byte, 1), (Mode::Word, 100)] {
+        let options = BuildOptions {
+            reserved_codes: reserved,
+            ..BuildOptions::default()
+        };
+        let dict = Dict::new_with_options(mode, sample.clone(), &options);
+        let (first, last) = dict.reserved_codes().unwrap();
+        assert_eq!(last, mode.max_inexact_code());
+        assert_eq!(last.0 - first.0 + 1, reserved);
+        assert!(dict.codes.len() <= dict.num_exact_codes());
+        assert!(dict.num_exact_codes() < mode.num_exact_codes());
+        for v in sample.iter().chain([u32::MAX, 0].iter()) {
+            let c = dict.encode(v);
+            assert!(c != Code(0) && c < first, "{:?} in reserved band", c);
+        }
+    }
+    let plain = Dict::new(Mode::Byte, sample);
+    assert_eq!(plain.reserved_codes(), None);
+    assert_eq!(plain.num_exact_codes(), Mode::Byte.num_exact_codes());
+}
+
+#[test]
+#[should_panic]
+fn rejects_reserving_everything() {
+    let options = BuildOptions {
+        reserved_codes: 0xfd,
+        ..BuildOptions::default()
+    };
+    Dict::new_with_options(Mode::Byte, vec![1, 2, 3], &options);
+}
+
+#[test]
+fn reserved_band_survives_serialization() {
+    let sample: Vec<u32> = (0..100_000).map(|i| (i * 7919) % 50_000).collect();
+    let options = BuildOptions {
+        reserved_codes: 10,
+        ..BuildOptions::default()
+    };
+    let dict = Dict::new_with_options(Mode::Byte, sample, &options);
+    assert_eq!(dict.reserved_codes(), Some((Code(246), Code(255))));
+    let bytes = dict.to_bytes();
+    let read = Dict::<u32>::from_bytes(&bytes).unwrap();
+    let archived = ArchivedDict::<u32>::new(&bytes).unwrap().to_dict();
+    for mut back in [read, archived] {
+        assert_eq!(back.reserved_codes(), dict.reserved_codes());
+        assert_eq!(back.num_exact_codes(), 122);
+        // Extending fills the codes up to the band, and no further.
+        let top = *back.codes.last().unwrap();
+        let boundaries: Vec<u32> = (1..=10).map(|i| top + i).collect();
+        back.extend_max(&boundaries);
+        assert_eq!(back.codes.len(), 122);
+        assert!(back.encode(&u32::MAX) < Code(246));
+    }
+
+    // Dictionaries without a band serialize as they always have.
+    let plain = Dict::new(Mode::Byte, vec![1u32, 2, 3]);
+    assert_eq!(Header::read(&plain.to_bytes()).unwrap().flags, 0);
+    let header = Header::read(&bytes).unwrap();
+    assert_eq!(
+        (header.flags, header.reserved_codes),
+        (FLAG_RESERVED_CODES, 10)
+    );
+
+    // A band leaving no room for the exact codes is refused.
+    let mut damaged = bytes.clone();
+    damaged[14] = 0xfd;
+    assert_eq!(
+        Dict::<u32>::from_bytes(&damaged).err(),
+        Some(FormatError::BadCodes)
+    );
+}
@@ -0,0 +1,36 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "roaring")]
+
+use ordbog::predicate::{Predicate, Ternary};
+use ordbog::sketch::Sketch;
+use ordbog::{Dict, Mode};
+
+#[test]
+fn scans_emit_roaring_bitmaps() {
+    let values: Vec<i32> = (0..5000).map(|i| (i * 7919) % 2000).collect();
+    for mode in [Mode::Byte, Mode::Word].iter().copied() {
+        let dict = Dict::new(mode, values.clone());
+        let mut sketch = Sketch::new(mode);
+        sketch.extend_from_values(&dict, &values);
+        let pred = dict.rewrite(&Predicate::Between(500, 1500)).compile(mode);
+        let bitmap = sketch.scan(&pred);
+        let candidates = bitmap.candidates_roaring();
+        let definite = bitmap.definite_roaring();
+        let maybe = bitmap.maybe_roaring();
+        assert_eq!(candidates.len() as usize, bitmap.count_candidates());
+        assert_eq!(definite.len() as usize, bitmap.count_definite());
+        assert_eq!(&definite | &maybe, candidates);
+        assert!((&definite & &maybe).is_empty());
+        for i in 0..values.len() {
+            let row = i as u32;
+            match bitmap.evaluate(i) {
+                Ternary::True => assert!(definite.contains(row)),
+                Ternary::Maybe => assert!(maybe.contains(row)),
+                Ternary::False => assert!(!candidates.contains(row)),
+            }
+        }
+        assert!(candidates.max().unwrap() < values.len() as u32);
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::quality::Metrics;
+use ordbog::sampler::OnlineSampler;
+use ordbog::{BuildOptions, Dict, Mode};
+
+fn batch(seed: u64, n: usize) -> Vec<u64> {
+    common::skewed(seed, n, 0.125)
+}
+
+#[test]
+fn sampler_is_bounded_and_representative() {
+    let mut sampler = OnlineSampler::new(2_000, 64);
+    let mut all = Vec::new();
+    for day in 0..20 {
+        let b = batch(day, 10_000);
+        sampler.observe_batch(&b);
+        all.extend(b);
+    }
+    assert_eq!(sampler.seen(), 200_000);
+    assert_eq!(sampler.reservoir().len(), 2_000);
+    assert_eq!(sampler.summary().total() / 1000, 200);
+
+    let dict = sampler.dict(Mode::Byte, &BuildOptions::default());
+    for heavy in common::COMMON_VALUES {
+        assert!(dict.encode(&heavy).is_exact());
+    }
+    let direct = Dict::new(Mode::Byte, all.clone());
+    let (s, d) = (
+        Metrics::measure(&dict, &all),
+        Metrics::measure(&direct, &all),
+    );
+    assert!(
+        s.exact_hit_rate > 0.9 * d.exact_hit_rate,
+        "{:?} vs {:?}",
+        s,
+        d
+    );
+    assert!(
+        s.max_code_mass < 2.0 * d.max_code_mass,
+        "{:?} vs {:?}",
+        s,
+        d
+    );
+}
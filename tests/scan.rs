@@ -0,0 +1,232 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::predicate::{CodePredicate, CompiledPredicate};
+use ordbog::scan::{scan_chunks, BlockMatch};
+use ordbog::workload::{RangePredicate, Replay};
+use ordbog::{Code, Dict, Mode, ValReq};
+use std::ops::Bound::*;
+
+fn compile<T: ValReq>(dict: &Dict<T>, pred: &RangePredicate<T>) -> CompiledPredicate {
+    CodePredicate::from_range(dict, pred).compile(dict.mode)
+}
+
+#[test]
+fn chunks_are_classified_with_existence_semantics() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let column = [5, 10, 15, 20, 25, 30, 35, 10];
+    let codes: Vec<Code> = column.iter().map(|v| dict.encode(v)).collect();
+    use BlockMatch::*;
+    assert_eq!(
+        scan_chunks(&compile(&dict, &(Included(20), Included(20))), &codes, 2),
+        vec![None, Definite, None, None]
+    );
+    assert_eq!(
+        scan_chunks(&compile(&dict, &(Included(12), Included(18))), &codes, 2),
+        vec![None, Maybe, None, None]
+    );
+    assert_eq!(
+        scan_chunks(&compile(&dict, &(Included(40), Included(30))), &codes, 2),
+        vec![None, None, None, None]
+    );
+    // A short final chunk is classified too.
+    assert_eq!(
+        scan_chunks(&compile(&dict, &(Excluded(30), Unbounded)), &codes, 3),
+        vec![None, None, Definite]
+    );
+}
+
+#[test]
+fn blocks_to_read_agree_with_replay() {
+    let values: Vec<u32> = (0..10_000u32).map(|i| (i * 7919) % 3000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let preds = [
+        (Included(100), Included(140)),
+        (Excluded(1500), Unbounded),
+        (Unbounded, Excluded(7)),
+    ];
+    let report = Replay::new(&dict, &codes, 64, 4096).run(&preds);
+    for (pred, r) in preds.iter().zip(report.predicates.iter()) {
+        let blocks = scan_chunks(&compile(&dict, pred), &codes, 64);
+        assert_eq!(
+            blocks.iter().filter(|b| b.needs_read()).count(),
+            r.blocks_read
+        );
+        if r.definite_rows == 0 {
+            assert!(!blocks.contains(&BlockMatch::Definite));
+        }
+    }
+}
+
+#[test]
+fn conjunctions_combine_classes() {
+    use ordbog::predicate::{CodePredicate, CLASS_DEFINITE, CLASS_MAYBE, CLASS_NONE};
+    use ordbog::scan::scan_conjunction;
+
+    let xs: Vec<i32> = (0..2000).map(|i| (i * 7919) % 500).collect();
+    let ys: Vec<i32> = (0..2000).map(|i| (i * 104729) % 300).collect();
+    let dx = Dict::new(Mode::Byte, xs.clone());
+    let dy = Dict::new(Mode::Byte, ys.clone());
+    let cx: Vec<Code> = xs.iter().map(|v| dx.encode(v)).collect();
+    let cy: Vec<Code> = ys.iter().map(|v| dy.encode(v)).collect();
+    let px = CodePredicate::from_range(&dx, &(Included(100), Excluded(250)));
+    let py = CodePredicate::from_range(&dy, &(Excluded(20), Included(77)));
+    let (kx, ky) = (px.compile(Mode::Byte), py.compile(Mode::Byte));
+    let classes = scan_conjunction(&[(&kx, &cx), (&ky, &cy)]);
+    for i in 0..xs.len() {
+        let (a, b) = (kx.classify(cx[i]), ky.classify(cy[i]));
+        let expect = if a == CLASS_NONE || b == CLASS_NONE {
+            CLASS_NONE
+        } else if a == CLASS_DEFINITE && b == CLASS_DEFINITE {
+            CLASS_DEFINITE
+        } else {
+            CLASS_MAYBE
+        };
+        assert_eq!(classes[i], expect);
+        let truth = (100..250).contains(&xs[i]) && ys[i] > 20 && ys[i] <= 77;
+        if truth {
+            assert_ne!(classes[i], CLASS_NONE);
+        } else {
+            assert_ne!(classes[i], CLASS_DEFINITE);
+        }
+    }
+    assert!(scan_conjunction(&[]).is_empty());
+}
+
+#[test]
+fn word_kernel_matches_compiled_tables() {
+    use ordbog::predicate::CodePredicate;
+    use ordbog::scan::{SplitTable, WordKernel};
+
+    let preds = [
+        // A long range crossing many high bytes.
+        CodePredicate {
+            definite: vec![Code(300)..=Code(9000)],
+            maybe: vec![Code(299)..=Code(299), Code(9001)..=Code(9001)],
+        },
+        // An IN-list of scattered exact codes.
+        CodePredicate {
+            definite: [2u16, 514, 1000, 40000, 65534]
+                .iter()
+                .map(|c| Code(*c)..=Code(*c))
+                .collect(),
+            maybe: vec![Code(7)..=Code(7)],
+        },
+    ];
+    let codes: Vec<u16> = (0..=65535).collect();
+    for p in preds.iter() {
+        let compiled = p.compile(Mode::Word);
+        let kernel = WordKernel::new(&compiled).unwrap();
+        let mut got = vec![0u8; codes.len()];
+        kernel.classify_into(&codes, &mut got);
+        let wanted: Vec<Code> = codes.iter().map(|c| Code(*c)).collect();
+        let mut want = vec![0u8; codes.len()];
+        compiled.classify_into(&wanted, &mut want);
+        assert_eq!(got, want);
+    }
+
+    // Nine distinct low-byte patterns don't fit in 8 bits.
+    assert!(SplitTable::new(|c| (c >> 8) < 9 && (c & 0xff) == (c >> 8)).is_none());
+    assert!(WordKernel::new(&preds[0].compile(Mode::Byte)).is_none());
+}
+
+#[test]
+fn bitmap_scans_agree_with_compiled_tables() {
+    use ordbog::codeset::CodeSet;
+    use ordbog::predicate::{CodePredicate, Predicate, Ternary};
+    use ordbog::scan::{scan_bytes, scan_words};
+    use ordbog::sketch::Sketch;
+
+    let values: Vec<u32> = common::uniform(5, 1000, 0..10_000);
+    for mode in [Mode::Byte, Mode::Packed, Mode::Word].iter().copied() {
+        let dict = Dict::new(mode, values.clone());
+        let mut sketch = Sketch::new(mode);
+        sketch.extend_from_values(&dict, &values);
+        let codes = sketch.to_codes();
+        let preds = [
+            dict.rewrite(&Predicate::Between(2_000, 7_000)),
+            dict.rewrite(&Predicate::Lt(5_000)),
+            dict.rewrite(&Predicate::Gt(9_990)),
+            dict.rewrite(&Predicate::Eq(4_321)),
+            dict.rewrite(&Predicate::Eq(values[3])),
+            dict.rewrite(&Predicate::InSet(values[..20].to_vec())),
+            CodePredicate::default(),
+        ];
+        for pred in preds.iter() {
+            let compiled = pred.compile(mode);
+            let bitmap = sketch.scan(&compiled);
+            assert_eq!(bitmap.rows(), codes.len());
+            for (i, c) in codes.iter().enumerate() {
+                assert_eq!(bitmap.evaluate(i), compiled.evaluate(*c), "row {}", i);
+            }
+            let definite = codes.iter().filter(|c| pred.is_definite(**c)).count();
+            assert_eq!(bitmap.count_definite(), definite);
+            let maybe = codes.iter().filter(|c| pred.is_maybe(**c)).count();
+            assert_eq!(bitmap.count_candidates(), definite + maybe);
+        }
+    }
+
+    // The whole codespace, and bits past the last row left clear.
+    let all = CodeSet::all(Mode::Byte);
+    let pred = CodePredicate::from_sets(&all, &all).compile(Mode::Byte);
+    let codes: Vec<u8> = (0..=255).chain(1..=10).collect();
+    let bitmap = scan_bytes(&pred, &codes);
+    assert_eq!(bitmap.count_definite(), codes.len() - 1);
+    assert_eq!(bitmap.evaluate(0), Ternary::False);
+    assert_eq!(bitmap.definite()[4], (1 << 10) - 1);
+
+    let pred = CodePredicate::from_sets(
+        &CodeSet::from_range(Code(300)..=Code(0xfffe)),
+        &CodeSet::from_range(Code(299)..=Code(0xffff)),
+    )
+    .compile(Mode::Word);
+    let codes: Vec<u16> = (0..200).map(|i| i * 300 + i % 2).collect();
+    let bitmap = scan_words(&pred, &codes);
+    for (i, c) in codes.iter().enumerate() {
+        assert_eq!(bitmap.evaluate(i), pred.evaluate(Code(*c)));
+    }
+}
+
+#[test]
+fn scans_produce_selection_vectors() {
+    use ordbog::predicate::{Predicate, Ternary};
+    use ordbog::sketch::Sketch;
+
+    let values: Vec<i32> = (0..3000).map(|i| (i * 7919) % 2000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let mut sketch = Sketch::new(Mode::Byte);
+    sketch.extend_from_values(&dict, &values);
+    let pred = dict.rewrite(&Predicate::Lt(700)).compile(Mode::Byte);
+    let bitmap = sketch.scan(&pred);
+
+    let tagged = bitmap.tagged_selection();
+    let expected: Vec<(u32, Ternary)> = (0..values.len())
+        .map(|i| (i as u32, bitmap.evaluate(i)))
+        .filter(|(_, t)| *t != Ternary::False)
+        .collect();
+    assert_eq!(tagged, expected);
+    assert!(tagged.iter().any(|(_, t)| *t == Ternary::Maybe));
+
+    let candidates = bitmap.candidate_selection();
+    assert_eq!(
+        candidates,
+        tagged.iter().map(|(i, _)| *i).collect::<Vec<_>>()
+    );
+    let selection = bitmap.selection();
+    let with = |tag| {
+        tagged
+            .iter()
+            .filter(|(_, t)| *t == tag)
+            .map(|(i, _)| *i)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(selection.definite, with(Ternary::True));
+    assert_eq!(selection.maybe, with(Ternary::Maybe));
+    for i in selection.definite.iter() {
+        assert!(values[*i as usize] < 700);
+    }
+}
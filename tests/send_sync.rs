@@ -0,0 +1,16 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{Code, Dict, DictF32, DictF64, Mode};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn dict_is_send_and_sync() {
+    assert_send_sync::<Code>();
+    assert_send_sync::<Mode>();
+    assert_send_sync::<Dict<i32>>();
+    assert_send_sync::<Dict<String>>();
+    assert_send_sync::<Dict<DictF32>>();
+    assert_send_sync::<Dict<DictF64>>();
+}
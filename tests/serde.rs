@@ -0,0 +1,100 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "serde")]
+
+use float_ord::FloatOrd;
+use ordbog::{BuildOptions, Code, Dict, DictF64, Mode};
+
+#[test]
+fn dicts_round_trip_through_serde() {
+    let values: Vec<i64> = (0..5000).map(|i| (i * 7919) % 3000 - 1500).collect();
+    for mode in [Mode::Byte, Mode::Packed, Mode::Word].iter().copied() {
+        let dict = Dict::new(mode, values.clone());
+        let json = serde_json::to_string(&dict).unwrap();
+        let back: Dict<i64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.mode, mode);
+        assert_eq!(back.codes, dict.codes);
+        for v in values.iter() {
+            assert_eq!(back.encode(v), dict.encode(v));
+        }
+    }
+
+    let floats = Dict::new(
+        Mode::Byte,
+        vec![DictF64(FloatOrd(-1.5)), DictF64(FloatOrd(2.25))],
+    );
+    let json = serde_json::to_string(&floats).unwrap();
+    assert_eq!(
+        json,
+        r#"{"mode":8,"codes":[-1.5,2.25],"reserved_codes":0,"dense_codes":false}"#
+    );
+    let back: Dict<DictF64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.codes, floats.codes);
+
+    assert_eq!(serde_json::to_string(&Code(513)).unwrap(), "513");
+    assert_eq!(serde_json::from_str::<Code>("7").unwrap(), Code(7));
+    assert_eq!(serde_json::to_string(&Mode::Packed).unwrap(), "12");
+}
+
+#[test]
+fn malformed_dicts_are_refused() {
+    for json in [
+        r#"{"mode":10,"codes":[]}"#,
+        r#"{"mode":8,"codes":[3,2]}"#,
+        r#"{"mode":8,"codes":[1,1]}"#,
+    ]
+    .iter()
+    {
+        assert!(serde_json::from_str::<Dict<u32>>(json).is_err(), "{}", json);
+    }
+    let too_many = serde_json::json!({ "mode": 8, "codes": (0..128).collect::<Vec<u32>>() });
+    assert!(serde_json::from_value::<Dict<u32>>(too_many).is_err());
+    let too_many = serde_json::json!({
+        "mode": 8,
+        "codes": (0..120).collect::<Vec<u32>>(),
+        "reserved_codes": 16,
+    });
+    assert!(serde_json::from_value::<Dict<u32>>(too_many).is_err());
+}
+
+#[test]
+fn reserved_bands_round_trip_through_serde() {
+    let options = BuildOptions {
+        reserved_codes: 10,
+        ..BuildOptions::default()
+    };
+    let sample: Vec<u32> = (0..100_000).map(|i| (i * 7919) % 50_000).collect();
+    let dict = Dict::new_with_options(Mode::Byte, sample, &options);
+    let json = serde_json::to_string(&dict).unwrap();
+    let mut back: Dict<u32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.reserved_codes(), Some((Code(246), Code(255))));
+    let top = *back.codes.last().unwrap();
+    back.extend_max(&[top + 1, top + 2, top + 3, top + 4, top + 5]);
+    assert!(back.encode(&u32::MAX) < Code(246));
+
+    let dense = BuildOptions {
+        reserved_codes: 1,
+        dense_codes: true,
+        ..BuildOptions::default()
+    };
+    let dict = Dict::new_with_options(Mode::Byte, (0..10_000u32).collect(), &dense);
+    let back: Dict<u32> = serde_json::from_str(&serde_json::to_string(&dict).unwrap()).unwrap();
+    assert_eq!(back.reserved_dense_codes(), dict.reserved_dense_codes());
+    assert_eq!(back.num_exact_codes(), Mode::Byte.num_exact_codes());
+
+    // Dictionaries serialized before the band was recorded have none.
+    let old: Dict<u32> = serde_json::from_str(r#"{"mode":8,"codes":[1,2,3]}"#).unwrap();
+    assert_eq!(old.reserved_codes(), None);
+}
+
+#[test]
+fn histograms_round_trip_through_serde() {
+    let values: Vec<u32> = (0..2000).map(|i| (i * 31) % 700).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let hist = dict.export_histogram(&values);
+    let json = serde_json::to_string(&hist).unwrap();
+    let back: ordbog::quality::Histogram<u32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, hist);
+    assert!(json.starts_with(r#"{"mode":8,"total":2000,"buckets":[{"code":1,"#));
+}
@@ -0,0 +1,49 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::predicate::CodePredicate;
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+
+#[test]
+fn signed_codes_preserve_order() {
+    for mode in [Mode::Byte, Mode::Word] {
+        let max = mode.max_inexact_code().0;
+        let mut prev = None;
+        for c in 0..=max {
+            let s = mode.to_signed(Code(c));
+            assert_eq!(mode.from_signed(s), Code(c));
+            if let Some(p) = prev {
+                assert!(p < s);
+            }
+            prev = Some(s);
+        }
+        if mode == Mode::Byte {
+            assert_eq!(
+                (mode.to_signed(Code(0)), mode.to_signed(Code(255))),
+                (-128, 127)
+            );
+        } else {
+            assert_eq!(
+                (mode.to_signed(Code(0)), mode.to_signed(Code(65535))),
+                (i16::MIN, i16::MAX)
+            );
+        }
+    }
+}
+
+#[test]
+fn signed_thresholds_select_the_same_rows() {
+    let values: Vec<i32> = (0..3000).map(|i| (i * 7919) % 1000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let t = CodePredicate::from_range(&dict, &(Included(100), Included(700)))
+        .compile(Mode::Byte)
+        .candidates
+        .unwrap();
+    let (lo, hi) = t.to_signed(Mode::Byte);
+    for v in values.iter() {
+        let s = dict.encode_signed(v);
+        assert!((-128..=127).contains(&s));
+        assert_eq!(lo <= s && s <= hi, t.contains(dict.encode(v).0));
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::predicate::CodePredicate;
+use ordbog::sketch::{Sketch, SketchCodes};
+use ordbog::sketchfile::{SketchFile, SketchHeader};
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+
+fn column() -> Vec<u32> {
+    common::uniform(23, 3_000, 0..100_000)
+}
+
+#[test]
+fn sketches_hold_codes_at_their_width() {
+    for mode in [Mode::Byte, Mode::Packed, Mode::Word].iter().copied() {
+        let values = column();
+        let dict = Dict::new(mode, values.clone());
+        let codes = dict.encode_slice(&values);
+
+        let mut sketch = Sketch::new(mode);
+        sketch.extend_from_values(&dict, &values[..1000]);
+        sketch.extend(codes[1000..].iter().copied());
+        assert_eq!(sketch.len(), codes.len());
+        assert_eq!(sketch.get(1234), codes[1234]);
+        assert_eq!(sketch.to_codes(), codes);
+        match (mode, sketch.codes()) {
+            (Mode::Byte, SketchCodes::Byte(_)) | (_, SketchCodes::Word(_)) => (),
+            _ => panic!("codes at the wrong width"),
+        }
+
+        let pred =
+            CodePredicate::from_range(&dict, &(Included(20_000), Excluded(40_000))).compile(mode);
+        let mut out = vec![0; sketch.len()];
+        sketch.classify_into(&pred, &mut out);
+        let mut expected = vec![0; codes.len()];
+        pred.classify_into(&codes, &mut expected);
+        assert_eq!(out, expected);
+
+        // Stored as a one-block sketch file's code section.
+        let mut stored = Vec::new();
+        sketch.write_stored(&mut stored);
+        let file = SketchFile::new(dict, sketch.clone(), codes.len()).to_bytes();
+        let (at, len) = SketchHeader::read(&file).unwrap().codes;
+        assert_eq!(stored, &file[at as usize..(at + len) as usize]);
+    }
+}
+
+#[test]
+#[should_panic]
+fn byte_sketches_refuse_wide_codes() {
+    let mut sketch = Sketch::with_capacity(Mode::Byte, 1);
+    assert!(sketch.is_empty());
+    sketch.push(Code(0x100));
+}
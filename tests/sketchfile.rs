@@ -0,0 +1,170 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::format::FormatError;
+use ordbog::sketch::Sketch;
+use ordbog::sketchfile::{crc32c, ReadOptions, SketchFile, SketchHeader, SKETCH_HEADER_LEN};
+use ordbog::{Code, Dict, Mode};
+
+fn column() -> Vec<u32> {
+    common::uniform(3, 5000, 0..100_000)
+}
+
+#[test]
+fn sketch_files_round_trip() {
+    let values = column();
+    for mode in [Mode::Byte, Mode::Word] {
+        let dict = Dict::new(mode, values.clone());
+        let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+        let sketch = SketchFile::new(dict, Sketch::from_codes(mode, &codes), 512);
+        assert_eq!(sketch.zones.zones.len(), 10);
+
+        let mut bytes = Vec::new();
+        sketch.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, sketch.to_bytes());
+        let header = SketchHeader::read(&bytes).unwrap();
+        assert_eq!(header.mode, mode);
+        assert_eq!(header.rows, 5000);
+        assert_eq!(header.dict.0, SKETCH_HEADER_LEN as u64);
+        assert_eq!(header.codes.0 % 8, 0);
+        assert_eq!(header.codes.0 + header.codes.1, header.checksums.0);
+        assert_eq!(header.checksums.0 + header.checksums.1, bytes.len() as u64);
+
+        let read = SketchFile::<u32>::from_bytes(&bytes).unwrap();
+        assert_eq!(read.dict.codes, sketch.dict.codes);
+        assert_eq!(read.dict.mode, mode);
+        assert_eq!(read.block_rows, 512);
+        assert_eq!(read.zones, sketch.zones);
+        assert_eq!(read.codes.to_codes(), codes);
+    }
+}
+
+#[test]
+fn damaged_sketch_files_are_refused() {
+    let values = column();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let bytes = SketchFile::new(dict, Sketch::from_codes(Mode::Byte, &codes), 100).to_bytes();
+
+    for len in [0, 3, SKETCH_HEADER_LEN - 1, bytes.len() - 1] {
+        assert_eq!(
+            SketchFile::<u32>::from_bytes(&bytes[..len]).err(),
+            Some(FormatError::Truncated)
+        );
+    }
+    let mut bad = bytes.clone();
+    bad[0] = b'X';
+    assert_eq!(
+        SketchFile::<u32>::from_bytes(&bad).err(),
+        Some(FormatError::BadMagic)
+    );
+    // A row count that disagrees with the sections.
+    let mut bad = bytes.clone();
+    bad[16] ^= 1;
+    assert_eq!(
+        SketchFile::<u32>::from_bytes(&bad).err(),
+        Some(FormatError::BadCodes)
+    );
+    // Trailing bytes are ignored.
+    let mut extra = bytes;
+    extra.extend_from_slice(&[1, 2, 3]);
+    assert!(SketchFile::<u32>::from_bytes(&extra).is_ok());
+}
+
+#[test]
+fn checksums_catch_bitrot() {
+    // The standard CRC-32C check value.
+    assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+
+    let values = column();
+    let dict = Dict::new(Mode::Word, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let bytes = SketchFile::new(dict, Sketch::from_codes(Mode::Word, &codes), 1000).to_bytes();
+    let header = SketchHeader::read(&bytes).unwrap();
+    let skip = ReadOptions {
+        verify_checksums: false,
+    };
+
+    // Flip a bit in the codes of block 3.
+    let mut rotted = bytes.clone();
+    rotted[header.codes.0 as usize + 2 * 3500] ^= 0x10;
+    assert_eq!(
+        SketchFile::<u32>::from_bytes(&rotted).err(),
+        Some(FormatError::BadChecksum)
+    );
+    assert!(header.verify_metadata(&rotted).is_ok());
+    assert!(header.verify_block(&rotted, 2).is_ok());
+    assert_eq!(
+        header.verify_block(&rotted, 3),
+        Err(FormatError::BadChecksum)
+    );
+    let read = SketchFile::<u32>::from_bytes_with_options(&rotted, &skip).unwrap();
+    assert_ne!(read.codes.to_codes(), codes);
+
+    // And in the dictionary.
+    let mut rotted = bytes;
+    rotted[header.dict.0 as usize + header.dict.1 as usize - 1] ^= 0x01;
+    assert_eq!(
+        header.verify_metadata(&rotted),
+        Err(FormatError::BadChecksum)
+    );
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compressed_sketch_files_round_trip() {
+    use ordbog::sketchfile::FLAG_ZSTD;
+    let mut values = column();
+    values.sort_unstable();
+    for mode in [Mode::Byte, Mode::Word] {
+        let dict = Dict::new(mode, values.clone());
+        let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+        let sketch = SketchFile::new(dict, Sketch::from_codes(mode, &codes), 700);
+        let plain = sketch.to_bytes();
+        let bytes = sketch.to_bytes_compressed(3);
+        assert!(bytes.len() < plain.len());
+        let header = SketchHeader::read(&bytes).unwrap();
+        assert_eq!(header.flags, FLAG_ZSTD);
+        assert!(header.is_compressed());
+        let raw = header.block_codes(&bytes, 2).unwrap();
+        let width = if mode == Mode::Byte { 1 } else { 2 };
+        assert_eq!(raw.len(), 700 * width);
+
+        let read = SketchFile::<u32>::from_bytes(&bytes).unwrap();
+        assert_eq!(read.dict.codes, sketch.dict.codes);
+        assert_eq!(read.zones, sketch.zones);
+        assert_eq!(read.codes.to_codes(), codes);
+
+        // Corrupting a frame is caught by its checksum, or by zstd if
+        // checksums are skipped.
+        let mut rotted = bytes.clone();
+        let (at, len) = header.block_bytes(&bytes, 5);
+        rotted[(at + len / 2) as usize] ^= 0xff;
+        assert_eq!(
+            SketchFile::<u32>::from_bytes(&rotted).err(),
+            Some(FormatError::BadChecksum)
+        );
+        let skip = ReadOptions {
+            verify_checksums: false,
+        };
+        assert!(SketchFile::<u32>::from_bytes_with_options(&rotted, &skip)
+            .map(|s| s.codes.to_codes() != codes)
+            .unwrap_or(true));
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn compressed_sketch_files_need_the_feature() {
+    let values = column();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let mut bytes = SketchFile::new(dict, Sketch::from_codes(Mode::Byte, &codes), 100).to_bytes();
+    bytes[6] |= 1;
+    assert_eq!(
+        SketchFile::<u32>::from_bytes(&bytes).err(),
+        Some(FormatError::UnsupportedFlags(1))
+    );
+}
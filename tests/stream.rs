@@ -0,0 +1,74 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "futures")]
+
+mod common;
+
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::{stream, SinkExt, StreamExt};
+use ordbog::stream::{EncodeSink, EncodeStream};
+use ordbog::{Code, Dict, Mode};
+use std::sync::Arc;
+
+fn column() -> Vec<u32> {
+    common::uniform(17, 1_050, 0..10_000)
+}
+
+#[test]
+fn encode_streams_yield_chunks_of_codes() {
+    let values = column();
+    let dict = Arc::new(Dict::new(Mode::Byte, values.clone()));
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let chunks: Vec<Vec<Code>> =
+        block_on(EncodeStream::new(stream::iter(values), dict.clone(), 100).collect());
+    assert_eq!(chunks.len(), 11);
+    assert!(chunks[..10].iter().all(|c| c.len() == 100));
+    assert_eq!(chunks.concat(), codes);
+
+    let empty: Vec<Vec<Code>> =
+        block_on(EncodeStream::new(stream::iter(Vec::new()), dict, 100).collect());
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn encode_sinks_apply_backpressure() {
+    let values = column();
+    let dict = Arc::new(Dict::new(Mode::Word, values.clone()));
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+
+    // A channel with room for no more than one chunk in flight holds the
+    // sender back until the receiver catches up.
+    let (tx, rx) = mpsc::channel(0);
+    let mut sink = EncodeSink::new(tx, dict, 256);
+    let send = async {
+        for v in values {
+            sink.feed(v).await.unwrap();
+        }
+        sink.close().await.unwrap();
+    };
+    let receive = rx.collect::<Vec<Vec<Code>>>();
+    let ((), chunks) = block_on(futures::future::join(send, receive));
+    assert_eq!(
+        chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+        [256, 256, 256, 256, 26]
+    );
+    assert_eq!(chunks.concat(), codes);
+}
+
+#[test]
+fn flushing_an_encode_sink_sends_a_short_chunk() {
+    let dict = Arc::new(Dict::new(Mode::Byte, column()));
+    let (tx, mut rx) = mpsc::unbounded();
+    let mut sink = EncodeSink::new(tx, dict.clone(), 64);
+    block_on(async {
+        sink.feed(5).await.unwrap();
+        sink.feed(7000).await.unwrap();
+        sink.flush().await.unwrap();
+        assert_eq!(
+            rx.next().await.unwrap(),
+            [dict.encode(&5), dict.encode(&7000)]
+        );
+    });
+}
@@ -0,0 +1,35 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::predicate::CodePredicate;
+use ordbog::substrait::{to_substrait, SubstraitFunctions};
+use ordbog::{Dict, Mode};
+use std::ops::Bound::*;
+
+#[test]
+fn renders_candidate_and_definite_filters() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let functions = SubstraitFunctions::default();
+    let p = CodePredicate::from_range(&dict, &(Included(12), Included(20)));
+    let filter = to_substrait(&p, 3, &functions);
+    assert!(filter
+        .candidates
+        .starts_with(r#"{"scalarFunction":{"functionReference":4,"#));
+    assert!(filter.candidates.contains(r#"{"literal":{"i32":3}}"#));
+    assert!(filter.candidates.contains(r#"{"literal":{"i32":4}}"#));
+    assert!(filter.candidates.contains(r#""structField":{"field":3}"#));
+    assert!(filter
+        .definite
+        .starts_with(r#"{"scalarFunction":{"functionReference":1,"#));
+
+    let p = CodePredicate::from_range(&dict, &(Included(12), Included(18)));
+    assert_eq!(
+        to_substrait(&p, 0, &functions).definite,
+        r#"{"literal":{"boolean":false}}"#
+    );
+
+    let (uris, extensions) = functions.extensions();
+    assert!(uris.contains("functions_comparison.yaml"));
+    assert!(extensions.contains(r#""functionAnchor":3,"name":"lte:any_any""#));
+}
@@ -0,0 +1,106 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::format::FormatError;
+use ordbog::quality::Metrics;
+use ordbog::summary::SampleSummary;
+use ordbog::{BuildOptions, Dict, Mode};
+
+fn skewed_sample() -> Vec<u64> {
+    // A few very common values over a wide uniform background.
+    common::skewed(10, 50_000, 0.25)
+}
+
+#[test]
+fn summaries_are_bounded_and_keep_heavy_values() {
+    let sample = skewed_sample();
+    let summary = SampleSummary::new(sample.clone(), 500);
+    assert_eq!(summary.total(), sample.len() as u64);
+    assert!(summary.entries.len() <= 1000);
+    for heavy in common::COMMON_VALUES {
+        assert!(summary
+            .entries
+            .iter()
+            .any(|e| e.value == heavy && e.count > 1000));
+    }
+    assert!(summary.entries.windows(2).all(|w| w[0].value < w[1].value));
+}
+
+#[test]
+fn refits_from_retained_summary() {
+    let sample = skewed_sample();
+    let options = BuildOptions {
+        summary_capacity: 4000,
+        ..BuildOptions::default()
+    };
+    let word = Dict::new_with_options(Mode::Word, sample.clone(), &options);
+    let summary = word.summary().unwrap();
+    assert!(Dict::new(Mode::Word, sample.clone()).summary().is_none());
+
+    // Downgrade to byte mode without the sample.
+    let refit = Dict::from_summary(Mode::Byte, summary, &BuildOptions::default());
+    let direct = Dict::new(Mode::Byte, sample.clone());
+    let (r, d) = (
+        Metrics::measure(&refit, &sample),
+        Metrics::measure(&direct, &sample),
+    );
+    assert!(
+        r.exact_hit_rate > 0.9 * d.exact_hit_rate,
+        "{:?} vs {:?}",
+        r,
+        d
+    );
+    assert!(
+        r.max_code_mass < 1.5 * d.max_code_mass,
+        "{:?} vs {:?}",
+        r,
+        d
+    );
+
+    let empty: Dict<u64> = Dict::from_summary(Mode::Byte, &SampleSummary::default(), &options);
+    assert_eq!(empty.codes, vec![0u64]);
+}
+
+#[test]
+fn shards_merge_serialized_summaries() {
+    let sample = skewed_sample();
+    let shards: Vec<Vec<u8>> = sample
+        .chunks(7_000)
+        .map(|shard| SampleSummary::new(shard.to_vec(), 2000).to_bytes())
+        .collect();
+    let summaries: Vec<SampleSummary<u64>> = shards
+        .iter()
+        .map(|b| SampleSummary::from_bytes(b).unwrap())
+        .collect();
+    assert_eq!(
+        summaries[0],
+        SampleSummary::new(sample[..7_000].to_vec(), 2000)
+    );
+
+    // Merge in two different groupings.
+    let refs: Vec<&SampleSummary<u64>> = summaries.iter().collect();
+    let (l, r) = refs.split_at(3);
+    let (l, r) = (SampleSummary::merge(l, 2000), SampleSummary::merge(r, 2000));
+    let tree = SampleSummary::merge(&[&l, &r], 2000);
+    let flat = SampleSummary::merge(&refs, 2000);
+    for merged in [&tree, &flat] {
+        assert_eq!(merged.total(), sample.len() as u64);
+        let dict = Dict::from_summary(Mode::Byte, merged, &BuildOptions::default());
+        for heavy in common::COMMON_VALUES {
+            assert!(dict.encode(&heavy).is_exact());
+        }
+    }
+
+    let bytes = flat.to_bytes();
+    assert_eq!(SampleSummary::from_bytes(&bytes).unwrap(), flat);
+    assert_eq!(
+        SampleSummary::<u64>::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(FormatError::Truncated)
+    );
+    assert_eq!(
+        SampleSummary::<u64>::from_bytes(&Dict::new(Mode::Byte, sample).to_bytes()),
+        Err(FormatError::BadMagic)
+    );
+}
@@ -0,0 +1,45 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "swap")]
+
+use ordbog::{Code, Dict, Mode, SwappableDict};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+#[test]
+fn publish_replaces_and_returns_old() {
+    let slot = SwappableDict::new(Dict::new(Mode::Byte, vec![10, 20, 30]));
+    assert_eq!(slot.encode(&20), Code(4));
+    let snapshot = slot.load_full();
+    let (old, seen) = slot.publish_with(Dict::new(Mode::Byte, vec![20, 30]), |old, new| {
+        (old.codes.clone(), new.codes.clone())
+    });
+    assert_eq!(old.codes, vec![10, 20, 30]);
+    assert_eq!(seen, (vec![10, 20, 30], vec![20, 30]));
+    assert_eq!(slot.encode(&20), Code(2));
+    // Snapshots taken before the swap keep their dictionary.
+    assert_eq!(snapshot.encode(&20), Code(4));
+}
+
+#[test]
+fn readers_run_concurrently_with_publishes() {
+    let slot = SwappableDict::new(Dict::new(Mode::Word, (0..1000).collect()));
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let d = slot.load();
+                    let (a, b) = (d.encode(&100), d.encode(&200));
+                    assert!(a < b);
+                    assert!(a.is_exact() && b.is_exact());
+                }
+            });
+        }
+        for i in 1..100 {
+            slot.publish(Dict::new(Mode::Word, (0..1000).map(|x| x + i).collect()));
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
@@ -0,0 +1,43 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::column::CodeColumn;
+use ordbog::tablemetrics::{delta_stats, iceberg_metrics};
+use ordbog::{Code, Dict, Mode};
+
+#[test]
+fn iceberg_and_delta_metrics() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::new(Mode::Byte, vec![10i64, 20, 30]);
+    let mut codes: Vec<Code> = [12i64, 20, 25].iter().map(|v| dict.encode(v)).collect();
+    codes.push(Code(0));
+    let column = CodeColumn::from_codes(codes);
+
+    let m = iceberg_metrics(&dict, &column);
+    assert_eq!(m.value_count, 4);
+    assert_eq!(m.null_value_count, 1);
+    assert_eq!(m.lower_bound, Some(10i64.to_le_bytes().to_vec()));
+    assert_eq!(m.upper_bound, Some(30i64.to_le_bytes().to_vec()));
+
+    assert_eq!(
+        delta_stats(&dict, "x", &column),
+        r#"{"numRecords":4,"minValues":{"x":10},"maxValues":{"x":30},"nullCount":{"x":1}}"#
+    );
+
+    // An all-null file, and one reaching past the last exact code.
+    let nulls = CodeColumn::from_codes(vec![Code(0), Code(0)]);
+    assert_eq!(iceberg_metrics(&dict, &nulls).lower_bound, None);
+    let above = CodeColumn::from_codes(vec![dict.encode(&20), dict.encode(&99)]);
+    assert_eq!(
+        delta_stats(&dict, "x", &above),
+        r#"{"numRecords":2,"minValues":{"x":20},"maxValues":{},"nullCount":{"x":0}}"#
+    );
+}
+
+#[test]
+fn delta_strings_are_escaped() {
+    let words: Vec<String> = ["a\"b", "c"].iter().map(|s| s.to_string()).collect();
+    let dict = Dict::new(Mode::Byte, words.clone());
+    let column = CodeColumn::from_codes(words.iter().map(|w| dict.encode(w)).collect());
+    assert!(delta_stats(&dict, "s", &column).contains(r#""minValues":{"s":"a\"b"}"#));
+}
@@ -0,0 +1,40 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::time::{EpochMillis, EpochNanos, TimeBucket, Timestamp};
+use ordbog::{Dict, Mode};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn timestamps_truncate_to_buckets() {
+    let t = UNIX_EPOCH + Duration::from_millis(1_600_000_123_456);
+    assert_eq!(t.epoch_seconds(), 1_600_000_123);
+    assert_eq!(TimeBucket::Second.truncate(&t), 1_600_000_123);
+    assert_eq!(TimeBucket::Minute.truncate(&t), 1_600_000_080);
+    assert_eq!(TimeBucket::Hour.truncate(&t), 1_599_998_400);
+    assert_eq!(TimeBucket::Day.truncate(&t), 1_599_955_200);
+    assert_eq!(
+        TimeBucket::Minute.truncate(&EpochMillis(1_600_000_123_456)),
+        1_600_000_080
+    );
+
+    // Before the epoch, truncation still rounds down.
+    let t = UNIX_EPOCH - Duration::from_millis(1500);
+    assert_eq!(t.epoch_seconds(), -2);
+    assert_eq!(EpochNanos(-1).epoch_seconds(), -1);
+    assert_eq!(TimeBucket::Minute.truncate(&EpochMillis(-1500)), -60);
+}
+
+#[test]
+fn dict_over_hour_keys() {
+    let times: Vec<EpochMillis> = (0..6_000).map(|i| EpochMillis(i * 60_000)).collect();
+    let key = TimeBucket::Hour.key();
+    let dict = Dict::new_with_key(Mode::Byte, &times, &key);
+    // 6000 minutes is 100 hours, each of which gets an exact code.
+    assert_eq!(dict.codes.len(), 100);
+    assert_eq!(
+        dict.encode(&key(&EpochMillis(59 * 60_000))),
+        dict.encode(&0)
+    );
+    assert!(dict.encode(&key(&times[0])).is_exact());
+}
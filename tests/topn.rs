@@ -0,0 +1,75 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::topn::{top_n_candidate_blocks, top_n_threshold, zone_top_n_candidates, Extreme};
+use ordbog::zonemap::ZoneMap;
+use ordbog::{Code, Dict, Mode};
+
+fn column() -> Vec<u32> {
+    common::uniform(17, 20_000, 0..1_000_000)
+}
+
+// The blocks of `block_rows` rows holding the `n` rows of `values` at `end`,
+// breaking ties by row number.
+fn true_blocks(values: &[u32], block_rows: usize, n: usize, end: Extreme) -> Vec<usize> {
+    let mut rows: Vec<usize> = (0..values.len()).collect();
+    rows.sort_by_key(|r| (values[*r], *r));
+    if end == Extreme::Largest {
+        rows.reverse();
+    }
+    let mut blocks: Vec<usize> = rows[..n.min(rows.len())]
+        .iter()
+        .map(|r| r / block_rows)
+        .collect();
+    blocks.sort_unstable();
+    blocks.dedup();
+    blocks
+}
+
+#[test]
+fn candidate_blocks_hold_the_top_rows() {
+    let values = column();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let zones = ZoneMap::new(&codes, 100);
+    for end in [Extreme::Largest, Extreme::Smallest].iter().copied() {
+        for n in [1, 10, 500].iter().copied() {
+            let expected = true_blocks(&values, 100, n, end);
+            let blocks = top_n_candidate_blocks(&codes, 100, n, end);
+            assert!(expected.iter().all(|b| blocks.contains(b)));
+            assert!(blocks.len() < 200);
+            let zoned = zone_top_n_candidates(&zones, n, end);
+            assert!(blocks.iter().all(|b| zoned.contains(b)));
+        }
+    }
+}
+
+#[test]
+fn thresholds_count_from_the_end() {
+    let codes = [Code(3), Code(9), Code(5), Code(9), Code(1), Code(7)];
+    assert_eq!(top_n_threshold(&codes, 1, Extreme::Largest), Some(Code(9)));
+    assert_eq!(top_n_threshold(&codes, 2, Extreme::Largest), Some(Code(9)));
+    assert_eq!(top_n_threshold(&codes, 3, Extreme::Largest), Some(Code(7)));
+    assert_eq!(top_n_threshold(&codes, 2, Extreme::Smallest), Some(Code(3)));
+    assert_eq!(top_n_threshold(&codes, 99, Extreme::Largest), Some(Code(1)));
+    assert_eq!(top_n_threshold(&codes, 0, Extreme::Largest), None);
+    assert_eq!(top_n_threshold(&[], 1, Extreme::Smallest), None);
+
+    assert_eq!(
+        top_n_candidate_blocks(&codes, 2, 2, Extreme::Largest),
+        [0, 1]
+    );
+    assert_eq!(top_n_candidate_blocks(&codes, 2, 1, Extreme::Smallest), [2]);
+    let zones = ZoneMap::new(&codes, 2);
+    // Block 1 alone holds 2 rows reaching code 5, but every block has one.
+    assert_eq!(
+        zone_top_n_candidates(&zones, 2, Extreme::Largest),
+        [0, 1, 2]
+    );
+    assert_eq!(
+        zone_top_n_candidates(&zones, 0, Extreme::Largest),
+        Vec::<usize>::new()
+    );
+}
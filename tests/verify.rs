@@ -0,0 +1,64 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::verify::Verifier;
+use ordbog::{Code, Dict, Mode};
+
+fn sample() -> Vec<u64> {
+    common::uniform(11, 10_000, 0..5000)
+}
+
+#[test]
+fn verifier_reports_corrupt_codes() {
+    let values = sample();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let mut codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+
+    let mut verifier = Verifier::new(&dict, 10);
+    verifier.check_all(values.iter().zip(codes.iter().copied()));
+    let report = verifier.finish();
+    assert!(report.is_consistent());
+    assert_eq!(report.checked, values.len() as u64);
+
+    codes[17] = Code(codes[17].0 ^ 1);
+    codes[4000] = Code(0);
+    let mut verifier = Verifier::new(&dict, 1);
+    verifier.check_all(values.iter().zip(codes.iter().copied()));
+    let report = verifier.finish();
+    assert_eq!(report.violations, 2);
+    assert_eq!(report.samples.len(), 1);
+    assert_eq!(report.samples[0].index, 17);
+    assert_eq!(report.samples[0].value, values[17]);
+    assert_eq!(report.samples[0].expected, dict.encode(&values[17]));
+}
+
+#[test]
+fn remapped_codes_are_accepted_if_they_cover_the_value() {
+    let values = sample();
+    let fine = Dict::new(Mode::Word, values.clone());
+    // A subset of the fine dictionary's exact values.
+    let coarse = Dict::from_codes(Mode::Byte, fine.codes.iter().step_by(64).copied().collect());
+    let codes: Vec<Code> = values.iter().map(|v| coarse.encode(v)).collect();
+    let mut verifier = Verifier::remapped(&coarse, &fine, 10);
+    verifier.check_all(values.iter().zip(codes.iter().copied()));
+    assert!(verifier.report().is_consistent());
+
+    // A code for the neighbouring interval doesn't cover the value.
+    let i = values
+        .iter()
+        .position(|v| !coarse.encode(v).is_exact())
+        .unwrap();
+    let wrong = Code(coarse.encode(&values[i]).0 + 2);
+    assert!(!verifier.check(&values[i], wrong));
+    assert_eq!(verifier.report().violations, 1);
+}
+
+#[test]
+#[should_panic]
+fn remapping_needs_a_refinement() {
+    let fine = Dict::from_codes(Mode::Byte, vec![10, 20, 30]);
+    let other = Dict::from_codes(Mode::Byte, vec![15]);
+    Verifier::remapped(&other, &fine, 0);
+}
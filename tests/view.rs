@@ -0,0 +1,41 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::{Code, Dict, DictView, Mode, ValueRange};
+
+#[test]
+fn views_agree_with_their_dicts() {
+    let values: Vec<u32> = (0..10_000).map(|i| (i * 7919) % 4000).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let view = dict.view();
+    for v in values.iter() {
+        assert_eq!(view.encode(v), dict.encode(v));
+    }
+    for c in 0..=0xff {
+        assert_eq!(view.decode(Code(c)), dict.decode(Code(c)));
+        assert_eq!(view.code_bounds(Code(c)), dict.code_bounds(Code(c)));
+    }
+    assert_eq!(view.to_dict().codes, dict.codes);
+}
+
+#[test]
+fn views_borrow_values_held_elsewhere() {
+    // Values owned by something else, such as a mapped file's pages.
+    let pages: Vec<String> = ["apple", "kiwi", "pear"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let view = DictView::new(Mode::Word, &pages[..]);
+    assert_eq!(view.encode("kiwi"), Code(4));
+    assert_eq!(view.encode("fig"), Code(3));
+    assert_eq!(
+        view.decode(Code(3)),
+        Some(ValueRange::Between {
+            lower: Some("apple".to_string()),
+            upper: Some("kiwi".to_string()),
+        })
+    );
+    let (lower, _) = view.code_bounds(Code(7));
+    assert!(std::ptr::eq(lower.unwrap(), &pages[2]));
+    assert_eq!(view.decode(Code(8)), None);
+}
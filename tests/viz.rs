@@ -0,0 +1,20 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+#![cfg(feature = "viz")]
+
+use ordbog::{viz, Dict, Mode};
+
+#[test]
+fn plots_render_as_svg() {
+    let sample: Vec<u32> = (0..10000u32).map(|i| (i * i) % 7919).collect();
+    let dict = Dict::new(Mode::Byte, sample.clone());
+    for svg in [
+        viz::code_assignments(&dict, |v| *v as f64).unwrap(),
+        viz::interval_widths(&dict, |v| *v as f64).unwrap(),
+        viz::code_mass(&dict, &sample).unwrap(),
+    ] {
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::summary::SampleSummary;
+use ordbog::window::{WindowOptions, WindowedDict};
+use ordbog::{BuildOptions, Mode};
+
+// A day's worth of readings, centred on a value that drifts day by day.
+fn day(n: u64) -> Vec<u64> {
+    (0..5_000)
+        .map(|i| n * 10_000 + (i * 7919) % 2_000)
+        .collect()
+}
+
+#[test]
+fn window_forgets_expired_epochs() {
+    let options = WindowOptions {
+        epochs: 3,
+        refit_every: 2,
+        summary_capacity: 1000,
+        build: BuildOptions::default(),
+    };
+    let mut window = WindowedDict::new(Mode::Byte, options);
+    let refits: Vec<bool> = (0..10).map(|n| window.push_epoch(day(n))).collect();
+    assert_eq!(refits, [false, true].repeat(5));
+    assert_eq!(window.epochs(), 3);
+
+    // Only days 7 to 9 should get codes now.
+    let dict = window.refit();
+    assert!(dict.codes.iter().all(|v| *v >= 70_000));
+    for n in 7..10 {
+        let codes: Vec<_> = day(n).iter().map(|v| dict.encode(v)).collect();
+        let distinct = codes.windows(2).filter(|w| w[0] != w[1]).count();
+        assert!(distinct > 10);
+    }
+}
+
+#[test]
+fn merged_summaries_keep_total_mass() {
+    let a = SampleSummary::new(day(1), 100);
+    let b = SampleSummary::new(day(2), 100);
+    let c = SampleSummary::new(day(1), 100);
+    let ab = SampleSummary::merge(&[&a, &b], 100);
+    let abc = SampleSummary::merge(&[&ab, &c], 100);
+    assert_eq!(abc.total(), 15_000);
+    assert!(abc.entries.len() <= 200);
+    assert!(abc.entries.windows(2).all(|w| w[0].value < w[1].value));
+    assert_eq!(SampleSummary::merge(&[&a], 100).total(), a.total());
+}
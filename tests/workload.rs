@@ -0,0 +1,46 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::workload::{PredicateReport, Replay};
+use ordbog::{Code, Dict, Mode};
+use std::ops::Bound::*;
+
+#[test]
+fn replay_reports_skips_and_candidates() {
+    // Exact codes: 10 => 2, 20 => 4, 30 => 6.
+    let dict = Dict::new(Mode::Byte, vec![10, 20, 30]);
+    let column = [5, 10, 15, 20, 25, 30, 35, 10];
+    let codes: Vec<Code> = column.iter().map(|v| dict.encode(v)).collect();
+    let replay = Replay::new(&dict, &codes, 2, 4096);
+    let report = replay.run(&[
+        (Included(20), Included(20)),
+        (Included(12), Excluded(30)),
+        (Excluded(30), Unbounded),
+        (Included(31), Included(32)),
+        (Unbounded, Unbounded),
+    ]);
+    let r = |definite_rows, candidate_rows, blocks_read, blocks_skipped| PredicateReport {
+        definite_rows,
+        candidate_rows,
+        blocks_read,
+        blocks_skipped,
+    };
+    assert_eq!(
+        report.predicates,
+        vec![
+            // Only the row holding 20 matches, definitely.
+            r(1, 0, 1, 3),
+            // 15 might; 20, and 25 in the interval below 30, do.
+            r(2, 1, 2, 2),
+            // Everything above 30 is one open interval.
+            r(1, 0, 1, 3),
+            // So a range inside it gives only candidates.
+            r(0, 1, 1, 3),
+            r(8, 0, 4, 0),
+        ]
+    );
+    assert_eq!(report.skip_rate, 11.0 / 20.0);
+    assert_eq!(report.candidate_rate, 2.0 / 40.0);
+    assert_eq!(report.definite_rate, 12.0 / 40.0);
+    assert_eq!(report.bytes_saved, 11 * 4096);
+}
@@ -0,0 +1,82 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+mod common;
+
+use ordbog::sketch::Sketch;
+use ordbog::sketchfile::{SketchFile, SketchHeader};
+use ordbog::writer::CodeColumnWriter;
+use ordbog::zonemap::ZoneMap;
+use ordbog::{Code, Dict, Mode};
+
+fn column() -> Vec<u32> {
+    common::uniform(5, 2_500, 0..100_000)
+}
+
+#[test]
+fn streamed_columns_match_sketch_files() {
+    for mode in [Mode::Byte, Mode::Packed, Mode::Word].iter().copied() {
+        let values = column();
+        let dict = Dict::new(mode, values.clone());
+        let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+
+        // Pushed in uneven pieces, some as values, some as codes and some
+        // as a sketch.
+        let mut writer = CodeColumnWriter::new(Vec::new(), mode, 1000);
+        writer.push_values(&dict, &values[..700]).unwrap();
+        writer.push_all(&codes[700..1500]).unwrap();
+        writer
+            .push_sketch(&Sketch::from_codes(mode, &codes[1500..1999]))
+            .unwrap();
+        assert_eq!(writer.zones().zones.len(), 1);
+        for c in &codes[1999..] {
+            writer.push(*c).unwrap();
+        }
+        assert_eq!(writer.rows(), codes.len() as u64);
+        let (bytes, zones) = writer.finish().unwrap();
+        assert_eq!(zones, ZoneMap::new(&codes, 1000));
+        assert_eq!(
+            zones,
+            ZoneMap::of_sketch(&Sketch::from_codes(mode, &codes), 1000)
+        );
+        assert_eq!(zones.zones[2].rows, 500);
+
+        let file = SketchFile::new(
+            Dict::from_codes(mode, dict.codes.clone()),
+            Sketch::from_codes(mode, &codes),
+            1000,
+        );
+        let file = file.to_bytes();
+        let header = SketchHeader::read(&file).unwrap();
+        let (at, len) = header.codes;
+        assert_eq!(bytes, &file[at as usize..(at + len) as usize]);
+    }
+}
+
+#[test]
+fn writers_only_write_whole_blocks_until_finished() {
+    let mut sink = Vec::new();
+    let mut writer = CodeColumnWriter::new(&mut sink, Mode::Word, 4);
+    writer.push_all(&[Code(3), Code(5), Code(1)]).unwrap();
+    assert!(writer.zones().zones.is_empty());
+    writer.push(Code(9)).unwrap();
+    writer.push(Code(2)).unwrap();
+    let (_, zones) = writer.finish().unwrap();
+    assert_eq!(
+        zones,
+        ZoneMap::new(&[Code(3), Code(5), Code(1), Code(9), Code(2)], 4)
+    );
+    assert_eq!(sink, [3, 0, 5, 0, 1, 0, 9, 0, 2, 0]);
+
+    let (bytes, zones) = CodeColumnWriter::new(Vec::new(), Mode::Byte, 8)
+        .finish()
+        .unwrap();
+    assert!(bytes.is_empty() && zones.zones.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn byte_writers_refuse_word_codes() {
+    let mut writer = CodeColumnWriter::new(Vec::new(), Mode::Byte, 8);
+    let _ = writer.push(Code(256));
+}
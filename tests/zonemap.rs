@@ -0,0 +1,23 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::zonemap::ZoneMap;
+use ordbog::{Code, Dict, Mode};
+
+#[test]
+fn zones_never_drop_matching_blocks() {
+    let values: Vec<u32> = (0..10_000u32).map(|i| i / 4 + (i * 7919) % 100).collect();
+    let dict = Dict::new(Mode::Byte, values.clone());
+    let codes: Vec<Code> = values.iter().map(|v| dict.encode(v)).collect();
+    let map = ZoneMap::new(&codes, 64);
+    assert_eq!(map.zones.len(), 157);
+    assert_eq!(map.zones.last().unwrap().rows, 10_000 % 64);
+    let (lo, hi) = (dict.encode(&500), dict.encode(&600));
+    let blocks = map.candidates(lo, hi);
+    for (i, c) in codes.iter().enumerate() {
+        if lo <= *c && *c <= hi {
+            assert!(blocks.contains(&(i / 64)));
+        }
+    }
+    assert!(blocks.len() < map.zones.len() / 2);
+}
@@ -0,0 +1,60 @@
+// Copyright 2021 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use ordbog::zorder::{rect_to_code_ranges, rect_to_ranges, Rect, ZKey};
+use ordbog::{Dict, Mode};
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn interleaving_roundtrips(x in any::<u32>(), y in any::<u32>()) {
+        let z = ZKey::new(x, y);
+        prop_assert_eq!((z.x(), z.y()), (x, y));
+    }
+}
+
+#[test]
+fn exact_cover_of_small_rect() {
+    let rect = Rect { x: 2..=5, y: 1..=3 };
+    let ranges = rect_to_ranges(&rect, 1000);
+    let mut covered = 0;
+    for x in 0..10u32 {
+        for y in 0..10u32 {
+            let z = ZKey::new(x, y);
+            let inside = rect.x.contains(&x) && rect.y.contains(&y);
+            let hit = ranges.iter().any(|r| r.contains(&z));
+            assert_eq!(inside, hit);
+            covered += hit as usize;
+        }
+    }
+    assert_eq!(covered, 12);
+    for w in ranges.windows(2) {
+        assert!(w[0].end().0 + 1 < w[1].start().0);
+    }
+}
+
+#[test]
+fn bounded_cover_and_code_ranges() {
+    // tenant_id in 0..64, timestamps in 0..100_000.
+    let keys: Vec<ZKey> = (0..50_000u32)
+        .map(|i| ZKey::new(i % 64, (i * 7) % 100_000))
+        .collect();
+    let dict = Dict::new(Mode::Word, keys.clone());
+    let rect = Rect {
+        x: 10..=12,
+        y: 20_000..=30_000,
+    };
+    for max in [1, 4, 16, 64] {
+        let ranges = rect_to_ranges(&rect, max);
+        assert!(ranges.len() <= max);
+        let codes = rect_to_code_ranges(&dict, &rect, max);
+        assert!(codes.len() <= ranges.len());
+        for k in keys.iter() {
+            if rect.x.contains(&k.x()) && rect.y.contains(&k.y()) {
+                assert!(ranges.iter().any(|r| r.contains(k)));
+                let c = dict.encode(k);
+                assert!(codes.iter().any(|r| r.contains(&c)));
+            }
+        }
+    }
+}